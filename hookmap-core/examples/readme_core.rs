@@ -1,4 +1,8 @@
-use hookmap_core::{button::Button, event::Event, mouse};
+use hookmap_core::{
+    button::Button,
+    event::{Delta, Event},
+    mouse,
+};
 
 fn main() {
     let rx = hookmap_core::install_hook();
@@ -21,7 +25,7 @@ fn main() {
                 native_handler.block();
 
                 // Reverses mouse cursor movement
-                let (dx, dy) = e.delta;
+                let Delta { dx, dy } = e.delta;
                 mouse::move_relative(-dx, -dy);
             }
 
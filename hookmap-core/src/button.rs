@@ -2,15 +2,70 @@
 
 use variant_count::VariantCount;
 
+use core::fmt::{self, Display};
+
+#[cfg(feature = "std")]
+use once_cell::sync::Lazy;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::str::FromStr;
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "std")]
+use std::thread::JoinHandle;
+
 /// A button input action.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ButtonAction {
     Press,
     Release,
 }
 
+impl Display for ButtonAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ButtonAction::Press => f.write_str("press"),
+            ButtonAction::Release => f.write_str("release"),
+        }
+    }
+}
+
+/// An error returned when parsing a [`ButtonAction`] fails.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseButtonActionError(String);
+
+#[cfg(feature = "std")]
+impl Display for ParseButtonActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid button action: {:?}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseButtonActionError {}
+
+#[cfg(feature = "std")]
+impl FromStr for ButtonAction {
+    type Err = ParseButtonActionError;
+
+    /// Parses `"press"` or `"release"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "press" => Ok(ButtonAction::Press),
+            "release" => Ok(ButtonAction::Release),
+            _ => Err(ParseButtonActionError(s.to_owned())),
+        }
+    }
+}
+
 /// Indicates whether the button is on the keyboard or mouse.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ButtonKind {
     /// On the keyboard
     Key,
@@ -19,8 +74,107 @@ pub enum ButtonKind {
     Mouse,
 }
 
+/// Category of the button.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum ButtonCategory {
+    /// Mouse buttons
+    Mouse,
+
+    /// Alphabet keys
+    Letter,
+
+    /// Number keys on the main row
+    Digit,
+
+    /// Function keys (F1-F24)
+    Function,
+
+    /// Shift, Ctrl, Alt and Super keys
+    Modifier,
+
+    /// Keys on the numeric keypad
+    Numpad,
+
+    /// Arrow keys, Home, End, PageUp and PageDown
+    Navigation,
+
+    /// Symbol keys
+    Punctuation,
+
+    /// Volume and media control keys
+    Media,
+
+    /// Other keys
+    Other,
+}
+
 /// Keyboard or mouse buttons.
+///
+/// New buttons may be added in minor releases, so a `match` on [`Button`] outside this crate
+/// must have a wildcard arm.
+///
+/// ```
+/// use hookmap_core::button::Button;
+///
+/// fn describe(button: Button) -> &'static str {
+///     match button {
+///         Button::LeftButton => "left click",
+///         Button::RightButton => "right click",
+///         _ => "other",
+///     }
+/// }
+/// assert_eq!(describe(Button::A), "other");
+/// ```
+///
+/// ```compile_fail,E0004
+/// use hookmap_core::button::Button;
+///
+/// // Every button is matched without a wildcard arm, which compiles only inside this crate.
+/// fn code(button: Button) -> u8 {
+///     match button {
+///         Button::LeftButton => 0,
+/// #         Button::RightButton | Button::MiddleButton | Button::SideButton1
+/// #         | Button::SideButton2 | Button::Key1 | Button::Key2 | Button::Key3 | Button::Key4
+/// #         | Button::Key5 | Button::Key6 | Button::Key7 | Button::Key8 | Button::Key9
+/// #         | Button::Key0 | Button::Minus | Button::Backspace | Button::Tab | Button::Q
+/// #         | Button::W | Button::E | Button::R | Button::T | Button::Y | Button::U | Button::I
+/// #         | Button::O | Button::P | Button::A | Button::S | Button::D | Button::F | Button::G
+/// #         | Button::H | Button::J | Button::K | Button::L | Button::Enter | Button::LShift
+/// #         | Button::Z | Button::X | Button::C | Button::V | Button::B | Button::N | Button::M
+/// #         | Button::Comma | Button::Dot | Button::Slash | Button::Oem8 | Button::RShift
+/// #         | Button::LCtrl | Button::LSuper | Button::LAlt | Button::Space | Button::RAlt
+/// #         | Button::RSuper | Button::Application | Button::RCtrl | Button::Insert
+/// #         | Button::Delete | Button::LeftArrow | Button::Home | Button::End | Button::UpArrow
+/// #         | Button::DownArrow | Button::PageUp | Button::PageDown | Button::RightArrow
+/// #         | Button::Clear | Button::Numpad1 | Button::Numpad2 | Button::Numpad3
+/// #         | Button::Numpad4 | Button::Numpad5 | Button::Numpad6 | Button::Numpad7
+/// #         | Button::Numpad8 | Button::Numpad9 | Button::Numpad0 | Button::NumpadDot
+/// #         | Button::NumpadSlash | Button::NumpadAsterisk | Button::NumpadMinus
+/// #         | Button::NumpadPlus | Button::Esc | Button::F1 | Button::F2 | Button::F3
+/// #         | Button::F4 | Button::F5 | Button::F6 | Button::F7 | Button::F8 | Button::F9
+/// #         | Button::F10 | Button::F11 | Button::F12 | Button::F13 | Button::F14 | Button::F15
+/// #         | Button::F16 | Button::F17 | Button::F18 | Button::F19 | Button::F20 | Button::F21
+/// #         | Button::F22 | Button::F23 | Button::F24 | Button::PrintScreen | Button::Pause
+/// #         | Button::VolumeMute | Button::VolumeDown | Button::VolumeUp
+/// #         | Button::MediaNextTrack | Button::MediaPrevTrack | Button::MediaStop
+/// #         | Button::MediaPlayPause | Button::Shift | Button::Ctrl | Button::Alt
+/// #         | Button::Super => 1,
+/// #         #[cfg(any(feature = "us-keyboard-layout", feature = "japanese-keyboard-layout"))]
+/// #         Button::OpenSquareBracket | Button::CloseSquareBracket | Button::BackSlash
+/// #         | Button::SemiColon => 1,
+/// #         #[cfg(feature = "us-keyboard-layout")]
+/// #         Button::Tilde | Button::Equal | Button::CapsLock | Button::SingleQuote => 1,
+/// #         #[cfg(feature = "japanese-keyboard-layout")]
+/// #         Button::HankakuZenkaku | Button::Hat | Button::Yen | Button::At | Button::Eisu
+/// #         | Button::Colon | Button::Muhenkan | Button::Henkan | Button::KatakanaHiragana => 1,
+///         // ... and all the other buttons.
+///     }
+/// }
+/// ```
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, VariantCount)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum Button {
     LeftButton,
     RightButton,
@@ -76,6 +230,9 @@ pub enum Button {
     #[cfg(feature = "japanese-keyboard-layout")]
     OpenSquareBracket,
 
+    #[cfg(feature = "us-keyboard-layout")]
+    BackSlash,
+
     #[cfg(feature = "us-keyboard-layout")]
     CapsLock,
     #[cfg(feature = "japanese-keyboard-layout")]
@@ -120,6 +277,9 @@ pub enum Button {
     #[cfg(feature = "japanese-keyboard-layout")]
     BackSlash,
 
+    /// The key of `VK_OEM_8`, which is not on US or Japanese keyboards.
+    Oem8,
+
     RShift,
     LCtrl,
     LSuper,
@@ -150,6 +310,7 @@ pub enum Button {
     PageUp,
     PageDown,
     RightArrow,
+    Clear,
     Numpad1,
     Numpad2,
     Numpad3,
@@ -191,6 +352,14 @@ pub enum Button {
     F23,
     F24,
     PrintScreen,
+    Pause,
+    VolumeMute,
+    VolumeDown,
+    VolumeUp,
+    MediaNextTrack,
+    MediaPrevTrack,
+    MediaStop,
+    MediaPlayPause,
 
     Shift,
     Ctrl,
@@ -198,7 +367,271 @@ pub enum Button {
     Super,
 }
 
+/// Names of the keys by their position on Windows, `VK_OEM_1` to `VK_OEM_8`, regardless of
+/// the labels printed on the keys.
+///
+/// The keys of the same position have different labels on keyboards for different regions.
+/// These constants refer to the variants of the enabled keyboard layout, except for
+/// [`Button::Oem8`], which is not on US or Japanese keyboards. They are also accepted by
+/// [`Button::from_str`](core::str::FromStr::from_str), e.g. `"Oem1"`.
+///
+/// # Examples
+///
+/// ```
+/// use hookmap_core::button::Button;
+///
+/// assert_eq!(Button::Oem2, Button::Slash);
+/// ```
+///
+#[allow(non_upper_case_globals)]
+impl Button {
+    /// `VK_OEM_1`
+    #[cfg(feature = "us-keyboard-layout")]
+    pub const Oem1: Button = Button::SemiColon;
+    /// `VK_OEM_1`
+    #[cfg(feature = "japanese-keyboard-layout")]
+    pub const Oem1: Button = Button::Colon;
+
+    /// `VK_OEM_2`
+    pub const Oem2: Button = Button::Slash;
+
+    /// `VK_OEM_3`
+    #[cfg(feature = "us-keyboard-layout")]
+    pub const Oem3: Button = Button::Tilde;
+    /// `VK_OEM_3`
+    #[cfg(feature = "japanese-keyboard-layout")]
+    pub const Oem3: Button = Button::At;
+
+    /// `VK_OEM_4`
+    #[cfg(any(feature = "us-keyboard-layout", feature = "japanese-keyboard-layout"))]
+    pub const Oem4: Button = Button::OpenSquareBracket;
+
+    /// `VK_OEM_5`
+    #[cfg(feature = "us-keyboard-layout")]
+    pub const Oem5: Button = Button::BackSlash;
+    /// `VK_OEM_5`
+    #[cfg(feature = "japanese-keyboard-layout")]
+    pub const Oem5: Button = Button::Yen;
+
+    /// `VK_OEM_6`
+    #[cfg(any(feature = "us-keyboard-layout", feature = "japanese-keyboard-layout"))]
+    pub const Oem6: Button = Button::CloseSquareBracket;
+
+    /// `VK_OEM_7`
+    #[cfg(feature = "us-keyboard-layout")]
+    pub const Oem7: Button = Button::SingleQuote;
+    /// `VK_OEM_7`
+    #[cfg(feature = "japanese-keyboard-layout")]
+    pub const Oem7: Button = Button::Hat;
+
+    /// The keys of `VK_OEM_1` to `VK_OEM_8` in order.
+    #[cfg(all(
+        feature = "std",
+        any(feature = "us-keyboard-layout", feature = "japanese-keyboard-layout")
+    ))]
+    const OEM: [Button; 8] = [
+        Button::Oem1,
+        Button::Oem2,
+        Button::Oem3,
+        Button::Oem4,
+        Button::Oem5,
+        Button::Oem6,
+        Button::Oem7,
+        Button::Oem8,
+    ];
+}
+
 impl Button {
+    /// The number of variants of [`Button`].
+    pub const COUNT: usize = Self::VARIANT_COUNT;
+
+    /// All variants of [`Button`] in the order of [`Button::to_index`].
+    pub const ALL: [Button; Self::COUNT] = [
+        Button::LeftButton,
+        Button::RightButton,
+        Button::MiddleButton,
+        Button::SideButton1,
+        Button::SideButton2,
+        #[cfg(feature = "us-keyboard-layout")]
+        Button::Tilde,
+        #[cfg(feature = "japanese-keyboard-layout")]
+        Button::HankakuZenkaku,
+        Button::Key1,
+        Button::Key2,
+        Button::Key3,
+        Button::Key4,
+        Button::Key5,
+        Button::Key6,
+        Button::Key7,
+        Button::Key8,
+        Button::Key9,
+        Button::Key0,
+        Button::Minus,
+        #[cfg(feature = "us-keyboard-layout")]
+        Button::Equal,
+        #[cfg(feature = "japanese-keyboard-layout")]
+        Button::Hat,
+        #[cfg(feature = "japanese-keyboard-layout")]
+        Button::Yen,
+        Button::Backspace,
+        Button::Tab,
+        Button::Q,
+        Button::W,
+        Button::E,
+        Button::R,
+        Button::T,
+        Button::Y,
+        Button::U,
+        Button::I,
+        Button::O,
+        Button::P,
+        #[cfg(feature = "us-keyboard-layout")]
+        Button::OpenSquareBracket,
+        #[cfg(feature = "japanese-keyboard-layout")]
+        Button::At,
+        #[cfg(feature = "us-keyboard-layout")]
+        Button::CloseSquareBracket,
+        #[cfg(feature = "japanese-keyboard-layout")]
+        Button::OpenSquareBracket,
+        #[cfg(feature = "us-keyboard-layout")]
+        Button::BackSlash,
+        #[cfg(feature = "us-keyboard-layout")]
+        Button::CapsLock,
+        #[cfg(feature = "japanese-keyboard-layout")]
+        Button::Eisu,
+        Button::A,
+        Button::S,
+        Button::D,
+        Button::F,
+        Button::G,
+        Button::H,
+        Button::J,
+        Button::K,
+        Button::L,
+        #[cfg(feature = "us-keyboard-layout")]
+        Button::SemiColon,
+        #[cfg(feature = "japanese-keyboard-layout")]
+        Button::SemiColon,
+        #[cfg(feature = "us-keyboard-layout")]
+        Button::SingleQuote,
+        #[cfg(feature = "japanese-keyboard-layout")]
+        Button::Colon,
+        #[cfg(feature = "japanese-keyboard-layout")]
+        Button::CloseSquareBracket,
+        Button::Enter,
+        Button::LShift,
+        Button::Z,
+        Button::X,
+        Button::C,
+        Button::V,
+        Button::B,
+        Button::N,
+        Button::M,
+        Button::Comma,
+        Button::Dot,
+        Button::Slash,
+        #[cfg(feature = "japanese-keyboard-layout")]
+        Button::BackSlash,
+        Button::Oem8,
+        Button::RShift,
+        Button::LCtrl,
+        Button::LSuper,
+        Button::LAlt,
+        #[cfg(feature = "japanese-keyboard-layout")]
+        Button::Muhenkan,
+        Button::Space,
+        #[cfg(feature = "japanese-keyboard-layout")]
+        Button::Henkan,
+        #[cfg(feature = "japanese-keyboard-layout")]
+        Button::KatakanaHiragana,
+        Button::RAlt,
+        Button::RSuper,
+        Button::Application,
+        Button::RCtrl,
+        Button::Insert,
+        Button::Delete,
+        Button::LeftArrow,
+        Button::Home,
+        Button::End,
+        Button::UpArrow,
+        Button::DownArrow,
+        Button::PageUp,
+        Button::PageDown,
+        Button::RightArrow,
+        Button::Clear,
+        Button::Numpad1,
+        Button::Numpad2,
+        Button::Numpad3,
+        Button::Numpad4,
+        Button::Numpad5,
+        Button::Numpad6,
+        Button::Numpad7,
+        Button::Numpad8,
+        Button::Numpad9,
+        Button::Numpad0,
+        Button::NumpadDot,
+        Button::NumpadSlash,
+        Button::NumpadAsterisk,
+        Button::NumpadMinus,
+        Button::NumpadPlus,
+        Button::Esc,
+        Button::F1,
+        Button::F2,
+        Button::F3,
+        Button::F4,
+        Button::F5,
+        Button::F6,
+        Button::F7,
+        Button::F8,
+        Button::F9,
+        Button::F10,
+        Button::F11,
+        Button::F12,
+        Button::F13,
+        Button::F14,
+        Button::F15,
+        Button::F16,
+        Button::F17,
+        Button::F18,
+        Button::F19,
+        Button::F20,
+        Button::F21,
+        Button::F22,
+        Button::F23,
+        Button::F24,
+        Button::PrintScreen,
+        Button::Pause,
+        Button::VolumeMute,
+        Button::VolumeDown,
+        Button::VolumeUp,
+        Button::MediaNextTrack,
+        Button::MediaPrevTrack,
+        Button::MediaStop,
+        Button::MediaPlayPause,
+        Button::Shift,
+        Button::Ctrl,
+        Button::Alt,
+        Button::Super,
+    ];
+
+    /// Returns the index of the button, which is less than [`Button::COUNT`].
+    ///
+    /// This can be used as an index of an array-backed map.
+    #[inline]
+    pub const fn to_index(self) -> usize {
+        self as usize
+    }
+
+    /// Returns the button corresponding to the index returned by [`Button::to_index`].
+    #[inline]
+    pub const fn from_index(index: usize) -> Option<Button> {
+        if index < Self::COUNT {
+            Some(Self::ALL[index])
+        } else {
+            None
+        }
+    }
+
     pub fn kind(&self) -> ButtonKind {
         match self {
             Button::LeftButton
@@ -209,4 +642,732 @@ impl Button {
             _ => ButtonKind::Key,
         }
     }
+
+    /// Returns the generic modifier of a left or right modifier, such as [`Button::Ctrl`]
+    /// for [`Button::LCtrl`] and [`Button::RCtrl`], or `None` for the other buttons.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap_core::button::Button;
+    ///
+    /// assert_eq!(Button::RShift.generic_modifier(), Some(Button::Shift));
+    /// assert_eq!(Button::Shift.generic_modifier(), None);
+    /// assert_eq!(Button::A.generic_modifier(), None);
+    /// ```
+    ///
+    pub fn generic_modifier(&self) -> Option<Button> {
+        use Button::*;
+
+        match self {
+            LShift | RShift => Some(Shift),
+            LCtrl | RCtrl => Some(Ctrl),
+            LAlt | RAlt => Some(Alt),
+            LSuper | RSuper => Some(Super),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the buttons are the same, or if one is a generic modifier such as
+    /// [`Button::Ctrl`] and the other is its left or right variant.
+    ///
+    /// The left and right variants do not match each other.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap_core::button::Button;
+    ///
+    /// assert!(Button::LCtrl.matches_sided(Button::Ctrl));
+    /// assert!(Button::RCtrl.matches_sided(Button::Ctrl));
+    /// assert!(Button::Ctrl.matches_sided(Button::Ctrl));
+    /// assert!(!Button::LCtrl.matches_sided(Button::RCtrl));
+    /// assert!(!Button::LCtrl.matches_sided(Button::Shift));
+    /// ```
+    ///
+    pub fn matches_sided(&self, other: Button) -> bool {
+        *self == other
+            || self.generic_modifier() == Some(other)
+            || other.generic_modifier() == Some(*self)
+    }
+
+    /// Returns the category of the button.
+    pub fn category(&self) -> ButtonCategory {
+        use Button::*;
+
+        match self {
+            LeftButton | RightButton | MiddleButton | SideButton1 | SideButton2 => {
+                ButtonCategory::Mouse
+            }
+
+            A | B | C | D | E | F | G | H | I | J | K | L | M | N | O | P | Q | R | S | T | U
+            | V | W | X | Y | Z => ButtonCategory::Letter,
+
+            Key1 | Key2 | Key3 | Key4 | Key5 | Key6 | Key7 | Key8 | Key9 | Key0 => {
+                ButtonCategory::Digit
+            }
+
+            F1 | F2 | F3 | F4 | F5 | F6 | F7 | F8 | F9 | F10 | F11 | F12 | F13 | F14 | F15
+            | F16 | F17 | F18 | F19 | F20 | F21 | F22 | F23 | F24 => ButtonCategory::Function,
+
+            LShift | RShift | LCtrl | RCtrl | LAlt | RAlt | LSuper | RSuper | Shift | Ctrl
+            | Alt | Super => ButtonCategory::Modifier,
+
+            Numpad1 | Numpad2 | Numpad3 | Numpad4 | Numpad5 | Numpad6 | Numpad7 | Numpad8
+            | Numpad9 | Numpad0 | NumpadDot | NumpadSlash | NumpadAsterisk | NumpadMinus
+            | NumpadPlus => ButtonCategory::Numpad,
+
+            LeftArrow | UpArrow | DownArrow | RightArrow | Home | End | PageUp | PageDown => {
+                ButtonCategory::Navigation
+            }
+
+            Minus | Comma | Dot | Slash => ButtonCategory::Punctuation,
+
+            #[cfg(feature = "us-keyboard-layout")]
+            Tilde | Equal | OpenSquareBracket | CloseSquareBracket | BackSlash | SemiColon
+            | SingleQuote => ButtonCategory::Punctuation,
+
+            #[cfg(feature = "japanese-keyboard-layout")]
+            Hat | Yen | At | OpenSquareBracket | SemiColon | Colon | CloseSquareBracket
+            | BackSlash => ButtonCategory::Punctuation,
+
+            VolumeMute | VolumeDown | VolumeUp | MediaNextTrack | MediaPrevTrack | MediaStop
+            | MediaPlayPause => ButtonCategory::Media,
+
+            Backspace | Tab | Enter | Space | Esc | Insert | Delete | Application | PrintScreen
+            | Pause | Clear | Oem8 => ButtonCategory::Other,
+
+            #[cfg(feature = "us-keyboard-layout")]
+            CapsLock => ButtonCategory::Other,
+
+            #[cfg(feature = "japanese-keyboard-layout")]
+            HankakuZenkaku | Eisu | Muhenkan | Henkan | KatakanaHiragana => ButtonCategory::Other,
+        }
+    }
+
+    /// Returns the value of a digit key of the main keyboard or the numpad, or `None` if the
+    /// button is not a digit key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap_core::button::Button;
+    ///
+    /// assert_eq!(Button::Key7.digit_value(), Some(7));
+    /// assert_eq!(Button::Numpad7.digit_value(), Some(7));
+    /// assert_eq!(Button::NumpadPlus.digit_value(), None);
+    /// ```
+    ///
+    pub fn digit_value(&self) -> Option<u8> {
+        use Button::*;
+
+        Some(match self {
+            Key0 | Numpad0 => 0,
+            Key1 | Numpad1 => 1,
+            Key2 | Numpad2 => 2,
+            Key3 | Numpad3 => 3,
+            Key4 | Numpad4 => 4,
+            Key5 | Numpad5 => 5,
+            Key6 | Numpad6 => 6,
+            Key7 | Numpad7 => 7,
+            Key8 | Numpad8 => 8,
+            Key9 | Numpad9 => 9,
+            _ => return None,
+        })
+    }
+
+    /// Returns the character typed by the button, or `None` if the button does not type a
+    /// character (e.g. [`Button::F1`]). This is intended for human-readable logs.
+    ///
+    /// `shift` indicates whether the shift key is held down.
+    /// The mapping assumes the layout selected by the feature flag (`us-keyboard-layout` or
+    /// `japanese-keyboard-layout`), not the layout active in the OS,
+    /// and ignores the state of Caps Lock and Num Lock. Without either feature, this always
+    /// returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap_core::button::Button;
+    ///
+    /// # if cfg!(not(any(feature = "us-keyboard-layout", feature = "japanese-keyboard-layout"))) {
+    /// #     return;
+    /// # }
+    /// assert_eq!(Button::A.to_char(false), Some('a'));
+    /// assert_eq!(Button::A.to_char(true), Some('A'));
+    /// assert_eq!(Button::Key1.to_char(false), Some('1'));
+    /// assert_eq!(Button::F1.to_char(false), None);
+    /// ```
+    ///
+    pub fn to_char(&self, shift: bool) -> Option<char> {
+        use Button::*;
+
+        if cfg!(not(any(
+            feature = "us-keyboard-layout",
+            feature = "japanese-keyboard-layout"
+        ))) {
+            return None;
+        }
+
+        #[cfg(feature = "japanese-keyboard-layout")]
+        if *self == Key0 && shift {
+            return None;
+        }
+
+        let (normal, shifted) = match self {
+            A => ('a', 'A'),
+            B => ('b', 'B'),
+            C => ('c', 'C'),
+            D => ('d', 'D'),
+            E => ('e', 'E'),
+            F => ('f', 'F'),
+            G => ('g', 'G'),
+            H => ('h', 'H'),
+            I => ('i', 'I'),
+            J => ('j', 'J'),
+            K => ('k', 'K'),
+            L => ('l', 'L'),
+            M => ('m', 'M'),
+            N => ('n', 'N'),
+            O => ('o', 'O'),
+            P => ('p', 'P'),
+            Q => ('q', 'Q'),
+            R => ('r', 'R'),
+            S => ('s', 'S'),
+            T => ('t', 'T'),
+            U => ('u', 'U'),
+            V => ('v', 'V'),
+            W => ('w', 'W'),
+            X => ('x', 'X'),
+            Y => ('y', 'Y'),
+            Z => ('z', 'Z'),
+
+            Key1 => ('1', '!'),
+            Key3 => ('3', '#'),
+            Key4 => ('4', '$'),
+            Key5 => ('5', '%'),
+            Key0 => ('0', ')'),
+            Comma => (',', '<'),
+            Dot => ('.', '>'),
+            Slash => ('/', '?'),
+            #[cfg(any(feature = "us-keyboard-layout", feature = "japanese-keyboard-layout"))]
+            OpenSquareBracket => ('[', '{'),
+            #[cfg(any(feature = "us-keyboard-layout", feature = "japanese-keyboard-layout"))]
+            CloseSquareBracket => (']', '}'),
+
+            #[cfg(feature = "us-keyboard-layout")]
+            Tilde => ('`', '~'),
+            #[cfg(feature = "us-keyboard-layout")]
+            Key2 => ('2', '@'),
+            #[cfg(feature = "us-keyboard-layout")]
+            Key6 => ('6', '^'),
+            #[cfg(feature = "us-keyboard-layout")]
+            Key7 => ('7', '&'),
+            #[cfg(feature = "us-keyboard-layout")]
+            Key8 => ('8', '*'),
+            #[cfg(feature = "us-keyboard-layout")]
+            Key9 => ('9', '('),
+            #[cfg(feature = "us-keyboard-layout")]
+            Minus => ('-', '_'),
+            #[cfg(feature = "us-keyboard-layout")]
+            Equal => ('=', '+'),
+            #[cfg(feature = "us-keyboard-layout")]
+            SemiColon => (';', ':'),
+            #[cfg(feature = "us-keyboard-layout")]
+            SingleQuote => ('\'', '"'),
+            #[cfg(feature = "us-keyboard-layout")]
+            BackSlash => ('\\', '|'),
+
+            #[cfg(feature = "japanese-keyboard-layout")]
+            Key2 => ('2', '"'),
+            #[cfg(feature = "japanese-keyboard-layout")]
+            Key6 => ('6', '&'),
+            #[cfg(feature = "japanese-keyboard-layout")]
+            Key7 => ('7', '\''),
+            #[cfg(feature = "japanese-keyboard-layout")]
+            Key8 => ('8', '('),
+            #[cfg(feature = "japanese-keyboard-layout")]
+            Key9 => ('9', ')'),
+            #[cfg(feature = "japanese-keyboard-layout")]
+            Minus => ('-', '='),
+            #[cfg(feature = "japanese-keyboard-layout")]
+            Hat => ('^', '~'),
+            #[cfg(feature = "japanese-keyboard-layout")]
+            Yen => ('\\', '|'),
+            #[cfg(feature = "japanese-keyboard-layout")]
+            At => ('@', '`'),
+            #[cfg(feature = "japanese-keyboard-layout")]
+            SemiColon => (';', '+'),
+            #[cfg(feature = "japanese-keyboard-layout")]
+            Colon => (':', '*'),
+            #[cfg(feature = "japanese-keyboard-layout")]
+            BackSlash => ('\\', '_'),
+
+            Numpad1 => ('1', '1'),
+            Numpad2 => ('2', '2'),
+            Numpad3 => ('3', '3'),
+            Numpad4 => ('4', '4'),
+            Numpad5 => ('5', '5'),
+            Numpad6 => ('6', '6'),
+            Numpad7 => ('7', '7'),
+            Numpad8 => ('8', '8'),
+            Numpad9 => ('9', '9'),
+            Numpad0 => ('0', '0'),
+            NumpadDot => ('.', '.'),
+            NumpadSlash => ('/', '/'),
+            NumpadAsterisk => ('*', '*'),
+            NumpadMinus => ('-', '-'),
+            NumpadPlus => ('+', '+'),
+
+            Space => (' ', ' '),
+            Tab => ('\t', '\t'),
+            Enter => ('\n', '\n'),
+
+            _ => return None,
+        };
+        Some(if shift { shifted } else { normal })
+    }
+
+    /// Returns `true` if the key is an extended key, whose scan code is prefixed with `0xE0`.
+    ///
+    /// Navigation keys share their scan codes with the numpad keys, and the right-side
+    /// modifiers share theirs with the left-side ones. When such a key is simulated without
+    /// the extended flag, an application reading scan codes may receive the numpad or
+    /// left-side key instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap_core::button::Button;
+    ///
+    /// assert!(Button::LeftArrow.requires_extended_scancode());
+    /// assert!(!Button::Numpad4.requires_extended_scancode());
+    /// ```
+    ///
+    pub fn requires_extended_scancode(&self) -> bool {
+        use Button::*;
+
+        matches!(
+            self,
+            RCtrl
+                | RAlt
+                | LSuper
+                | RSuper
+                | Application
+                | Insert
+                | Delete
+                | Home
+                | End
+                | PageUp
+                | PageDown
+                | LeftArrow
+                | UpArrow
+                | DownArrow
+                | RightArrow
+                | NumpadSlash
+                | PrintScreen
+                | VolumeMute
+                | VolumeDown
+                | VolumeUp
+                | MediaNextTrack
+                | MediaPrevTrack
+                | MediaStop
+                | MediaPlayPause
+        )
+    }
+}
+
+/// An error returned when parsing a [`Button`] fails.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseButtonError(String);
+
+#[cfg(feature = "std")]
+impl Display for ParseButtonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid button: {:?}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseButtonError {}
+
+/// An error returned when registering an alias that already refers to another button.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasConflictError {
+    alias: String,
+    button: Button,
+}
+
+#[cfg(feature = "std")]
+impl AliasConflictError {
+    /// Returns the button that the alias already refers to.
+    pub fn button(&self) -> Button {
+        self.button
+    }
+}
+
+#[cfg(feature = "std")]
+impl Display for AliasConflictError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} already refers to {:?}", self.alias, self.button)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AliasConflictError {}
+
+#[cfg(feature = "std")]
+static ALIASES: Lazy<Mutex<HashMap<String, Button>>> = Lazy::new(Mutex::default);
+
+#[cfg(feature = "std")]
+fn parse_builtin_name(s: &str) -> Option<Button> {
+    #[cfg(any(feature = "us-keyboard-layout", feature = "japanese-keyboard-layout"))]
+    {
+        const NAMES: [&str; 8] = [
+            "Oem1", "Oem2", "Oem3", "Oem4", "Oem5", "Oem6", "Oem7", "Oem8",
+        ];
+        if let Some(index) = NAMES.iter().position(|&name| name == s) {
+            return Some(Button::OEM[index]);
+        }
+    }
+    let name = match s {
+        "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9" => format!("Key{}", s),
+        _ => s.to_owned(),
+    };
+    Button::ALL
+        .into_iter()
+        .find(|button| format!("{:?}", button) == name)
+}
+
+#[cfg(feature = "std")]
+impl Button {
+    /// Registers `alias` as another name of `button` used by [`Button::from_str`].
+    ///
+    /// This allows applications to accept names that match the labels of their users'
+    /// keyboards. Aliases are case-sensitive and shared by the whole process.
+    /// Registering the same alias for the same button again has no effect.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `alias` is already a name of another button, including the
+    /// variant names.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use hookmap_core::button::Button;
+    ///
+    /// Button::register_alias("Return", Button::Enter).unwrap();
+    /// assert_eq!("Return".parse(), Ok(Button::Enter));
+    ///
+    /// assert!(Button::register_alias("Return", Button::Tab).is_err());
+    /// assert!(Button::register_alias("Esc", Button::Enter).is_err());
+    /// ```
+    ///
+    pub fn register_alias(alias: &str, button: Button) -> Result<(), AliasConflictError> {
+        let conflict = |existing| AliasConflictError {
+            alias: alias.to_owned(),
+            button: existing,
+        };
+        match parse_builtin_name(alias) {
+            Some(existing) if existing == button => return Ok(()),
+            Some(existing) => return Err(conflict(existing)),
+            None => {}
+        }
+        let mut aliases = ALIASES.lock().unwrap();
+        match aliases.get(alias) {
+            Some(&existing) if existing != button => Err(conflict(existing)),
+            _ => {
+                aliases.insert(alias.to_owned(), button);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl FromStr for Button {
+    type Err = ParseButtonError;
+
+    /// Parses a variant name of [`Button`] such as `"LCtrl"`, or an alias registered by
+    /// [`Button::register_alias`].
+    /// A single digit is parsed as the numeric key, e.g. `"1"` as [`Button::Key1`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_builtin_name(s)
+            .or_else(|| ALIASES.lock().unwrap().get(s).copied())
+            .ok_or_else(|| ParseButtonError(s.to_owned()))
+    }
+}
+
+/// Keeps a button held down with repeated press events.
+/// The button is released when this guard is dropped.
+///
+/// This is created by [`Button::hold_repeating`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct HoldGuard {
+    button: Button,
+    is_held: Arc<AtomicBool>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+#[cfg(feature = "std")]
+impl HoldGuard {
+    pub(crate) fn new(
+        button: Button,
+        is_held: Arc<AtomicBool>,
+        join_handle: JoinHandle<()>,
+    ) -> Self {
+        Self {
+            button,
+            is_held,
+            join_handle: Some(join_handle),
+        }
+    }
+
+    /// Returns the button being held down.
+    pub fn button(&self) -> Button {
+        self.button
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for HoldGuard {
+    fn drop(&mut self) {
+        self.is_held.store(false, Ordering::SeqCst);
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.thread().unpark();
+            let _ = join_handle.join();
+        }
+        self.button.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_button() {
+        for button in Button::ALL {
+            assert_eq!(format!("{:?}", button).parse(), Ok(button));
+        }
+        assert_eq!("0".parse(), Ok(Button::Key0));
+        assert_eq!("LCtrl".parse(), Ok(Button::LCtrl));
+        assert!("lctrl".parse::<Button>().is_err());
+        assert!("10".parse::<Button>().is_err());
+        assert!("".parse::<Button>().is_err());
+    }
+
+    #[cfg(any(feature = "us-keyboard-layout", feature = "japanese-keyboard-layout"))]
+    #[test]
+    #[cfg(any(feature = "us-keyboard-layout", feature = "japanese-keyboard-layout"))]
+    fn parse_oem_position() {
+        assert_eq!("Oem1".parse(), Ok(Button::Oem1));
+        assert_eq!("Oem5".parse(), Ok(Button::Oem5));
+        assert_eq!("Oem8".parse(), Ok(Button::Oem8));
+        assert!("Oem0".parse::<Button>().is_err());
+        assert!("Oem9".parse::<Button>().is_err());
+        assert!("Oem01".parse::<Button>().is_err());
+        assert!(Button::register_alias("Oem1", Button::Enter).is_err());
+    }
+
+    #[test]
+    fn alias_resolution() {
+        assert!("AliasTestEnter".parse::<Button>().is_err());
+        assert_eq!(
+            Button::register_alias("AliasTestEnter", Button::Enter),
+            Ok(())
+        );
+        assert_eq!("AliasTestEnter".parse(), Ok(Button::Enter));
+        assert!("aliastestenter".parse::<Button>().is_err());
+
+        // Variant names take precedence and keep working.
+        assert_eq!("Enter".parse(), Ok(Button::Enter));
+    }
+
+    #[test]
+    fn alias_collisions() {
+        assert_eq!(Button::register_alias("AliasTestTab", Button::Tab), Ok(()));
+        assert_eq!(Button::register_alias("AliasTestTab", Button::Tab), Ok(()));
+        let error = Button::register_alias("AliasTestTab", Button::Esc).unwrap_err();
+        assert_eq!(error.button(), Button::Tab);
+        assert_eq!("AliasTestTab".parse(), Ok(Button::Tab));
+
+        assert_eq!(Button::register_alias("Tab", Button::Tab), Ok(()));
+        let error = Button::register_alias("Tab", Button::Esc).unwrap_err();
+        assert_eq!(error.button(), Button::Tab);
+        let error = Button::register_alias("1", Button::Esc).unwrap_err();
+        assert_eq!(error.button(), Button::Key1);
+    }
+
+    #[test]
+    fn matches_sided() {
+        use Button::*;
+
+        let families = [
+            (Shift, LShift, RShift),
+            (Ctrl, LCtrl, RCtrl),
+            (Alt, LAlt, RAlt),
+            (Super, LSuper, RSuper),
+        ];
+        for (generic, left, right) in families {
+            assert_eq!(left.generic_modifier(), Some(generic));
+            assert_eq!(right.generic_modifier(), Some(generic));
+            assert_eq!(generic.generic_modifier(), None);
+            for button in [generic, left, right] {
+                assert!(button.matches_sided(generic), "{:?}", button);
+                assert!(generic.matches_sided(button), "{:?}", button);
+                assert!(button.matches_sided(button), "{:?}", button);
+            }
+            assert!(!left.matches_sided(right));
+            assert!(!right.matches_sided(left));
+            for (other, ..) in families.iter().filter(|&&(g, ..)| g != generic) {
+                assert!(!left.matches_sided(*other));
+                assert!(!generic.matches_sided(*other));
+            }
+        }
+        assert!(A.matches_sided(A));
+        assert!(!A.matches_sided(Shift));
+        assert!(!Shift.matches_sided(A));
+        assert_eq!(A.generic_modifier(), None);
+    }
+
+    #[test]
+    fn to_char() {
+        assert_eq!(Button::Z.to_char(false), Some('z'));
+        assert_eq!(Button::Z.to_char(true), Some('Z'));
+        assert_eq!(Button::Space.to_char(true), Some(' '));
+        assert_eq!(Button::Numpad5.to_char(true), Some('5'));
+        for button in [Button::F1, Button::Shift, Button::LeftButton, Button::Esc] {
+            assert_eq!(button.to_char(false), None, "{:?}", button);
+        }
+    }
+
+    #[cfg(not(any(feature = "us-keyboard-layout", feature = "japanese-keyboard-layout")))]
+    #[test]
+    fn to_char_without_layout() {
+        for button in [Button::A, Button::Key1, Button::Space, Button::F1] {
+            assert_eq!(button.to_char(false), None, "{:?}", button);
+            assert_eq!(button.to_char(true), None, "{:?}", button);
+        }
+    }
+
+    #[cfg(feature = "us-keyboard-layout")]
+    #[test]
+    fn to_char_us_layout() {
+        assert_eq!(Button::Key2.to_char(true), Some('@'));
+        assert_eq!(Button::SingleQuote.to_char(false), Some('\''));
+        assert_eq!(Button::SingleQuote.to_char(true), Some('"'));
+    }
+
+    #[cfg(feature = "japanese-keyboard-layout")]
+    #[test]
+    fn to_char_japanese_layout() {
+        assert_eq!(Button::Key2.to_char(true), Some('"'));
+        assert_eq!(Button::Key0.to_char(true), None);
+        assert_eq!(Button::Colon.to_char(true), Some('*'));
+    }
+
+    #[test]
+    fn extended_scancode() {
+        for button in [
+            Button::Insert,
+            Button::Home,
+            Button::PageDown,
+            Button::RCtrl,
+        ] {
+            assert!(button.requires_extended_scancode(), "{:?}", button);
+        }
+        for button in [Button::Numpad0, Button::LCtrl, Button::Ctrl, Button::A] {
+            assert!(!button.requires_extended_scancode(), "{:?}", button);
+        }
+    }
+
+    #[test]
+    fn button_action_string_round_trip() {
+        for action in [ButtonAction::Press, ButtonAction::Release] {
+            assert_eq!(action.to_string().parse(), Ok(action));
+        }
+        assert_eq!("press".parse(), Ok(ButtonAction::Press));
+        assert!("Press".parse::<ButtonAction>().is_err());
+    }
+
+    #[test]
+    fn button_index_round_trip() {
+        for (i, button) in Button::ALL.iter().enumerate() {
+            assert_eq!(button.to_index(), i);
+            assert_eq!(Button::from_index(i), Some(*button));
+        }
+        assert_eq!(Button::from_index(Button::COUNT), None);
+    }
+
+    #[test]
+    fn button_category() {
+        use Button::*;
+
+        let cases = [
+            (LeftButton, ButtonCategory::Mouse),
+            (SideButton2, ButtonCategory::Mouse),
+            (A, ButtonCategory::Letter),
+            (Z, ButtonCategory::Letter),
+            (Key0, ButtonCategory::Digit),
+            (F24, ButtonCategory::Function),
+            (LShift, ButtonCategory::Modifier),
+            (Super, ButtonCategory::Modifier),
+            (Numpad0, ButtonCategory::Numpad),
+            (NumpadPlus, ButtonCategory::Numpad),
+            (PageDown, ButtonCategory::Navigation),
+            (Slash, ButtonCategory::Punctuation),
+            (VolumeUp, ButtonCategory::Media),
+            (Esc, ButtonCategory::Other),
+            (Clear, ButtonCategory::Other),
+            (Pause, ButtonCategory::Other),
+        ];
+        for (button, category) in cases {
+            assert_eq!(button.category(), category, "{:?}", button);
+        }
+    }
+
+    #[test]
+    fn digit_values() {
+        use Button::*;
+
+        let keys = [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+        let numpad = [
+            Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8,
+            Numpad9,
+        ];
+        for (value, (key, numpad)) in (0..).zip(keys.into_iter().zip(numpad)) {
+            assert_eq!(key.digit_value(), Some(value));
+            assert_eq!(numpad.digit_value(), Some(value));
+        }
+        for button in [A, F1, NumpadDot, NumpadMinus, Insert, Space] {
+            assert_eq!(button.digit_value(), None, "{:?}", button);
+        }
+    }
+
+    #[test]
+    fn mouse_category_matches_kind() {
+        use Button::*;
+
+        for button in [
+            LeftButton,
+            RightButton,
+            MiddleButton,
+            SideButton1,
+            SideButton2,
+            A,
+            Space,
+        ] {
+            assert_eq!(
+                button.category() == ButtonCategory::Mouse,
+                button.kind() == ButtonKind::Mouse,
+                "{:?}",
+                button
+            );
+        }
+    }
 }
@@ -23,7 +23,13 @@
 //! }
 //! ```
 
-use super::button::{Button, ButtonAction};
+use super::button::{Button, ButtonAction, ButtonKind};
+use core::fmt::{self, Display};
+use core::time::Duration;
+
+#[cfg(feature = "std")]
+use std::str::FromStr;
+#[cfg(feature = "std")]
 use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
 
 /// Indicates whether to pass the generated event to the next program or not.
@@ -48,8 +54,51 @@ impl Default for NativeEventOperation {
     }
 }
 
+impl Display for NativeEventOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NativeEventOperation::Block => f.write_str("block"),
+            NativeEventOperation::Dispatch => f.write_str("dispatch"),
+        }
+    }
+}
+
+/// An error returned when parsing a [`NativeEventOperation`] fails.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseNativeEventOperationError(String);
+
+#[cfg(feature = "std")]
+impl Display for ParseNativeEventOperationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid native event operation: {:?}", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseNativeEventOperationError {}
+
+#[cfg(feature = "std")]
+impl FromStr for NativeEventOperation {
+    type Err = ParseNativeEventOperationError;
+
+    /// Parses `"block"` or `"dispatch"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(NativeEventOperation::Block),
+            "dispatch" => Ok(NativeEventOperation::Dispatch),
+            _ => Err(ParseNativeEventOperationError(s.to_owned())),
+        }
+    }
+}
+
 /// Indicates button event.
+///
+/// This is created with [`ButtonEvent::new`], whose fields that are not given are set by
+/// the methods of the same names.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct ButtonEvent {
     /// Target of the generated event.
     pub target: Button,
@@ -60,31 +109,352 @@ pub struct ButtonEvent {
     /// Whether this event was generated by this program.
     /// If you type on your keyboard and an event is generated, this value will be `false`.
     pub injected: bool,
+
+    /// How long the button was held down, for release events handled by `hookmap`.
+    ///
+    /// This is `None` for press events, for events received directly from the hook, and if
+    /// the press was not seen, such as when the button was held down before the hook was
+    /// installed.
+    pub held_for: Option<Duration>,
+
+    /// The hook that received the event, [`ButtonKind::Key`] for the keyboard hook and
+    /// [`ButtonKind::Mouse`] for the mouse hook.
+    ///
+    /// This is usually `target.kind()`, but the OS may report a button from the other device.
+    pub source: ButtonKind,
+
+    /// When the event occurred. See [`Event::time`].
+    pub time: Duration,
+}
+
+impl ButtonEvent {
+    /// Creates an event of `action` on `target`.
+    ///
+    /// The event is not injected, has no hold time, comes from the hook of the kind of
+    /// `target`, and occurred at zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap_core::button::{Button, ButtonAction};
+    /// use hookmap_core::event::ButtonEvent;
+    /// use std::time::Duration;
+    ///
+    /// let event = ButtonEvent::new(Button::A, ButtonAction::Release)
+    ///     .held_for(Some(Duration::from_millis(80)))
+    ///     .time(Duration::from_secs(1));
+    /// assert_eq!(event.target, Button::A);
+    /// assert!(!event.injected);
+    /// assert_eq!(event.held_for, Some(Duration::from_millis(80)));
+    /// ```
+    ///
+    pub fn new(target: Button, action: ButtonAction) -> Self {
+        Self {
+            target,
+            action,
+            injected: false,
+            held_for: None,
+            source: target.kind(),
+            time: Duration::ZERO,
+        }
+    }
+
+    /// Sets [`ButtonEvent::injected`].
+    #[must_use]
+    pub fn injected(mut self, injected: bool) -> Self {
+        self.injected = injected;
+        self
+    }
+
+    /// Sets [`ButtonEvent::held_for`].
+    #[must_use]
+    pub fn held_for(mut self, held_for: Option<Duration>) -> Self {
+        self.held_for = held_for;
+        self
+    }
+
+    /// Sets [`ButtonEvent::source`].
+    #[must_use]
+    pub fn source(mut self, source: ButtonKind) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Sets [`ButtonEvent::time`].
+    #[must_use]
+    pub fn time(mut self, time: Duration) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Returns `true` if the target is a mouse button.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap_core::button::{Button, ButtonAction};
+    /// use hookmap_core::event::ButtonEvent;
+    ///
+    /// let event = ButtonEvent::new(Button::LeftButton, ButtonAction::Press);
+    /// assert!(event.is_mouse_button());
+    /// assert!(!event.is_keyboard_key());
+    /// ```
+    ///
+    pub fn is_mouse_button(&self) -> bool {
+        self.target.kind() == ButtonKind::Mouse
+    }
+
+    /// Returns `true` if the target is a key on the keyboard.
+    pub fn is_keyboard_key(&self) -> bool {
+        self.target.kind() == ButtonKind::Key
+    }
+}
+
+#[cfg(feature = "std")]
+impl ButtonEvent {
+    /// Sends the event again with `modifiers` held down around it.
+    ///
+    /// The modifiers are pressed in order before the event and released in reverse order
+    /// after it, in a single batch. The generated events are not hooked, so blocking the
+    /// original event and calling this does not trigger the hook again.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap_core::button::{Button, ButtonAction};
+    /// use hookmap_core::event::ButtonEvent;
+    ///
+    /// let event = ButtonEvent::new(Button::Enter, ButtonAction::Press)
+    ///     .time(hookmap_core::event_time());
+    /// // Sends Ctrl+Enter.
+    /// event.resend_with_modifiers(&[Button::LCtrl]);
+    /// ```
+    ///
+    pub fn resend_with_modifiers(&self, modifiers: &[Button]) {
+        crate::send_batch(&self.with_modifiers(modifiers));
+    }
+
+    fn with_modifiers(&self, modifiers: &[Button]) -> Vec<(Button, ButtonAction)> {
+        let press = modifiers.iter().map(|&m| (m, ButtonAction::Press));
+        let release = modifiers.iter().rev().map(|&m| (m, ButtonAction::Release));
+        press
+            .chain(std::iter::once((self.target, self.action)))
+            .chain(release)
+            .collect()
+    }
+}
+
+/// A position in screen coordinates, in pixels.
+/// The origin is the upper-left corner of the primary monitor.
+///
+/// # Examples
+///
+/// ```
+/// use hookmap_core::event::Position;
+///
+/// let position = Position { x: 10, y: 20 };
+/// let (x, y) = position.into();
+/// assert_eq!((x, y), (10, 20));
+/// assert_eq!(Position::from((x, y)), position);
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl From<(i32, i32)> for Position {
+    fn from((x, y): (i32, i32)) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<Position> for (i32, i32) {
+    fn from(position: Position) -> Self {
+        (position.x, position.y)
+    }
+}
+
+/// A movement of the mouse cursor, in pixels.
+///
+/// # Examples
+///
+/// ```
+/// use hookmap_core::event::Delta;
+///
+/// let delta = Delta { dx: 3, dy: -4 };
+/// let (dx, dy) = delta.into();
+/// assert_eq!((dx, dy), (3, -4));
+/// assert_eq!(Delta::from((dx, dy)), delta);
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Delta {
+    pub dx: i32,
+    pub dy: i32,
+}
+
+impl From<(i32, i32)> for Delta {
+    fn from((dx, dy): (i32, i32)) -> Self {
+        Self { dx, dy }
+    }
+}
+
+impl From<Delta> for (i32, i32) {
+    fn from(delta: Delta) -> Self {
+        (delta.dx, delta.dy)
+    }
 }
 
 /// Indicates mouse cursor event.
+///
+/// This is created with [`CursorEvent::new`], whose fields that are not given are set by
+/// the methods of the same names.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct CursorEvent {
-    /// Mouse cursor movement `(x, y)`
-    pub delta: (i32, i32),
+    /// Mouse cursor movement from the position of the previous cursor event, or from where
+    /// the cursor was moved by this program after it.
+    /// This is zero for the first mouse cursor event after the hook is installed.
+    pub delta: Delta,
+
+    /// Mouse cursor position after the movement.
+    pub position: Position,
+
+    /// Time elapsed since the previous mouse cursor event.
+    /// This is `None` for the first mouse cursor event.
+    pub elapsed: Option<Duration>,
 
     /// Whether this event was generated by this program.
     pub injected: bool,
+
+    /// When the event occurred. See [`Event::time`].
+    pub time: Duration,
+}
+
+impl CursorEvent {
+    /// Creates an event of the cursor moved by `delta` to `position`.
+    ///
+    /// The event has no elapsed time, is not injected, and occurred at zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap_core::event::{CursorEvent, Delta, Position};
+    /// use std::time::Duration;
+    ///
+    /// let event = CursorEvent::new(Delta { dx: 3, dy: 4 }, Position { x: 10, y: 20 })
+    ///     .elapsed(Some(Duration::from_millis(10)));
+    /// assert_eq!(event.position, Position { x: 10, y: 20 });
+    /// assert!(!event.injected);
+    /// ```
+    ///
+    pub fn new(delta: Delta, position: Position) -> Self {
+        Self {
+            delta,
+            position,
+            elapsed: None,
+            injected: false,
+            time: Duration::ZERO,
+        }
+    }
+
+    /// Sets [`CursorEvent::elapsed`].
+    #[must_use]
+    pub fn elapsed(mut self, elapsed: Option<Duration>) -> Self {
+        self.elapsed = elapsed;
+        self
+    }
+
+    /// Sets [`CursorEvent::injected`].
+    #[must_use]
+    pub fn injected(mut self, injected: bool) -> Self {
+        self.injected = injected;
+        self
+    }
+
+    /// Sets [`CursorEvent::time`].
+    #[must_use]
+    pub fn time(mut self, time: Duration) -> Self {
+        self.time = time;
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl CursorEvent {
+    /// Returns the speed of the mouse cursor in pixels per second.
+    /// Returns `0.0` if the elapsed time is unknown or zero.
+    pub fn speed(&self) -> f64 {
+        match self.elapsed {
+            Some(elapsed) if !elapsed.is_zero() => {
+                let Delta { dx, dy } = self.delta;
+                f64::from(dx).hypot(f64::from(dy)) / elapsed.as_secs_f64()
+            }
+            _ => 0.0,
+        }
+    }
 }
 
 /// Indicates mouse wheel event.
+///
+/// This is generated only by rotating the wheel. Clicking the wheel is a press and release
+/// of [`Button::MiddleButton`], which is a [`ButtonEvent`].
+///
+/// This is created with [`WheelEvent::new`], whose fields that are not given are set by
+/// the methods of the same names.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct WheelEvent {
-    /// Amout of mouse wheel rotation
+    /// Amout of mouse wheel rotation in notches.
     /// Upward rotation takes a positive value, downward rotation a negative value.
+    ///
+    /// Rotations of less than a notch, such as those of precision touchpads, are carried
+    /// over to the following events, so this is `0` until they add up to a notch.
     pub delta: i32,
 
     /// Whether this event was generated by this program.
     pub injected: bool,
+
+    /// When the event occurred. See [`Event::time`].
+    pub time: Duration,
+}
+
+impl WheelEvent {
+    /// Creates an event of the wheel rotated by `delta` notches.
+    ///
+    /// The event is not injected and occurred at zero.
+    pub fn new(delta: i32) -> Self {
+        Self {
+            delta,
+            injected: false,
+            time: Duration::ZERO,
+        }
+    }
+
+    /// Sets [`WheelEvent::injected`].
+    #[must_use]
+    pub fn injected(mut self, injected: bool) -> Self {
+        self.injected = injected;
+        self
+    }
+
+    /// Sets [`WheelEvent::time`].
+    #[must_use]
+    pub fn time(mut self, time: Duration) -> Self {
+        self.time = time;
+        self
+    }
 }
 
 /// An event
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Event {
     /// Button event
     Button(ButtonEvent),
@@ -96,12 +466,69 @@ pub enum Event {
     Cursor(CursorEvent),
 }
 
+impl Event {
+    /// Returns when the event occurred.
+    ///
+    /// Button, cursor and wheel events are stamped with the same monotonic clock, the time
+    /// elapsed since the system started, so events received from different streams can be
+    /// merged into a single timeline by sorting on this value. Events received from the hook
+    /// never go back in time. Use `event_time` to stamp events created by the program.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap_core::event::{Event, WheelEvent};
+    /// use std::time::Duration;
+    ///
+    /// let wheel = |delta, millis| {
+    ///     Event::Wheel(WheelEvent::new(delta).time(Duration::from_millis(millis)))
+    /// };
+    /// let mut events = vec![wheel(1, 20), wheel(-1, 10)];
+    /// events.sort_by_key(Event::time);
+    /// assert_eq!(events, [wheel(-1, 10), wheel(1, 20)]);
+    /// ```
+    ///
+    pub fn time(&self) -> Duration {
+        match self {
+            Event::Button(event) => event.time,
+            Event::Wheel(event) => event.time,
+            Event::Cursor(event) => event.time,
+        }
+    }
+
+    /// Returns the event as [`MouseEvent`] if it is generated by the mouse.
+    pub fn to_mouse_event(&self) -> Option<MouseEvent> {
+        match *self {
+            Event::Button(event) if event.is_mouse_button() => Some(MouseEvent::Button(event)),
+            Event::Button(_) => None,
+            Event::Wheel(event) => Some(MouseEvent::Wheel(event)),
+            Event::Cursor(event) => Some(MouseEvent::Cursor(event)),
+        }
+    }
+}
+
+/// An event generated by the mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MouseEvent {
+    /// Mouse button event
+    Button(ButtonEvent),
+
+    /// Mouse wheel event
+    Wheel(WheelEvent),
+
+    /// Mouse cursor event
+    Cursor(CursorEvent),
+}
+
 /// Decide whether to notify other programs of generated events.
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct NativeEventHandler {
     tx: Option<Sender<NativeEventOperation>>,
 }
 
+#[cfg(feature = "std")]
 impl NativeEventHandler {
     fn new(tx: Sender<NativeEventOperation>) -> Self {
         Self { tx: Some(tx) }
@@ -123,6 +550,7 @@ impl NativeEventHandler {
     }
 }
 
+#[cfg(feature = "std")]
 impl Drop for NativeEventHandler {
     fn drop(&mut self) {
         if let Some(tx) = self.tx.take() {
@@ -131,11 +559,13 @@ impl Drop for NativeEventHandler {
     }
 }
 
+#[cfg(feature = "std")]
 #[derive(Debug, Clone)]
 pub(crate) struct EventSender {
     tx: SyncSender<(Event, NativeEventHandler)>,
 }
 
+#[cfg(feature = "std")]
 impl EventSender {
     pub(crate) fn new(tx: SyncSender<(Event, NativeEventHandler)>) -> Self {
         Self { tx }
@@ -145,15 +575,248 @@ impl EventSender {
         let (tx, rx) = mpsc::channel();
         let sent_data = (event, NativeEventHandler::new(tx));
 
-        self.tx.send(sent_data).unwrap();
-        rx.recv().unwrap()
+        // After the receiver is dropped, events are dispatched until the hook is uninstalled.
+        if self.tx.send(sent_data).is_err() {
+            return NativeEventOperation::default();
+        }
+        rx.recv().unwrap_or_default()
     }
 }
 
+#[cfg(feature = "std")]
 pub type EventReceiver = Receiver<(Event, NativeEventHandler)>;
 
+/// Indicates which devices are hooked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HookTarget {
+    /// Hooks both keyboard and mouse.
+    #[default]
+    All,
+
+    /// Hooks only keyboard. Mouse input is not observed and cannot be blocked.
+    Keyboard,
+
+    /// Hooks only mouse. Keyboard input is not observed and cannot be blocked.
+    Mouse,
+}
+
+impl HookTarget {
+    /// Returns `true` if the keyboard hook is installed for this target.
+    pub fn includes_keyboard(self) -> bool {
+        matches!(self, HookTarget::All | HookTarget::Keyboard)
+    }
+
+    /// Returns `true` if the mouse hook is installed for this target.
+    pub fn includes_mouse(self) -> bool {
+        matches!(self, HookTarget::All | HookTarget::Mouse)
+    }
+}
+
+/// An error returned when installing the hook fails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InstallHookError {
+    /// The hook is already installed.
+    AlreadyInstalled,
+
+    /// The OS failed to install the hook. Contains the error code of the OS.
+    Os(i32),
+
+    /// The hook is not installed, so it cannot be reinstalled.
+    NotInstalled,
+}
+
+impl Display for InstallHookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstallHookError::AlreadyInstalled => write!(f, "hooks are already installed"),
+            InstallHookError::Os(code) => write!(f, "failed to install hooks: {:#010x}", code),
+            InstallHookError::NotInstalled => write!(f, "hooks are not installed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InstallHookError {}
+
+/// How the hook classified an event by the tag of the input generated by this process.
+/// Returned by [`last_injection_verdict`](crate::last_injection_verdict).
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InjectionVerdict {
+    /// Not generated by this process. The event is delivered with `injected: false`.
+    Untagged,
+
+    /// Generated by this process for other hooks to see, such as by
+    /// [`send_batch_recursive`](crate::send_batch_recursive).
+    /// The event is delivered with `injected: true`.
+    Injected,
+
+    /// Generated by this process and skipped, so it does not trigger the hotkeys again.
+    Skipped,
+}
+
+/// An error reported by [`set_injection_diagnostics`](crate::set_injection_diagnostics)
+/// when input generated by this process does not come back to the hook as it was sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InjectionError {
+    /// The input was not seen by the hook within [`InjectionError::TIMEOUT`].
+    Lost {
+        target: Button,
+        action: ButtonAction,
+    },
+
+    /// The input was seen by the hook as a different button or action.
+    Modified {
+        expected: (Button, ButtonAction),
+        actual: (Button, ButtonAction),
+    },
+}
+
+impl InjectionError {
+    /// How long the input may take to reach the hook before it is reported as lost.
+    pub const TIMEOUT: Duration = Duration::from_secs(1);
+}
+
+impl Display for InjectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InjectionError::Lost { target, action } => {
+                write!(
+                    f,
+                    "injected {:?} {:?} did not reach the hook",
+                    target, action
+                )
+            }
+            InjectionError::Modified { expected, actual } => write!(
+                f,
+                "injected {:?} {:?} reached the hook as {:?} {:?}",
+                expected.0, expected.1, actual.0, actual.1
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InjectionError {}
+
+/// A change of the session reported by
+/// [`set_session_change_handler`](crate::set_session_change_handler).
+///
+/// After these, the buttons held before may have been released without their events
+/// reaching the hook.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SessionChange {
+    /// The system resumed from sleep or hibernation.
+    Resume,
+
+    /// The session was unlocked.
+    Unlock,
+}
+
+#[cfg(feature = "std")]
 pub(crate) fn channel() -> (EventSender, EventReceiver) {
     const BOUND: usize = 1;
     let (tx, rx) = mpsc::sync_channel(BOUND);
     (EventSender::new(tx), rx)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_speed() {
+        let event = CursorEvent::new(Delta { dx: 30, dy: 40 }, Position::default())
+            .elapsed(Some(Duration::from_millis(100)));
+        assert!((event.speed() - 500.0).abs() < 1e-9);
+
+        let first = event.elapsed(None);
+        assert_eq!(first.speed(), 0.0);
+
+        let zero = event.elapsed(Some(Duration::ZERO));
+        assert_eq!(zero.speed(), 0.0);
+    }
+
+    #[test]
+    fn button_event_with_modifiers() {
+        let event = ButtonEvent::new(Button::A, ButtonAction::Press);
+        assert_eq!(
+            event.with_modifiers(&[Button::LCtrl, Button::LShift]),
+            [
+                (Button::LCtrl, ButtonAction::Press),
+                (Button::LShift, ButtonAction::Press),
+                (Button::A, ButtonAction::Press),
+                (Button::LShift, ButtonAction::Release),
+                (Button::LCtrl, ButtonAction::Release),
+            ]
+        );
+        assert_eq!(
+            event.with_modifiers(&[]),
+            [(Button::A, ButtonAction::Press)]
+        );
+    }
+
+    #[test]
+    fn button_event_kind() {
+        let event = |target: Button| ButtonEvent::new(target, ButtonAction::Press);
+        for button in [Button::A, Button::LCtrl, Button::VolumeUp] {
+            assert!(event(button).is_keyboard_key());
+            assert!(!event(button).is_mouse_button());
+        }
+        for button in [
+            Button::LeftButton,
+            Button::MiddleButton,
+            Button::SideButton2,
+        ] {
+            assert!(event(button).is_mouse_button());
+            assert!(!event(button).is_keyboard_key());
+        }
+    }
+
+    #[test]
+    fn button_event_source_defaults_to_target_kind() {
+        let key = ButtonEvent::new(Button::A, ButtonAction::Press);
+        assert_eq!(key.source, ButtonKind::Key);
+        let mouse = ButtonEvent::new(Button::LeftButton, ButtonAction::Press);
+        assert_eq!(mouse.source, ButtonKind::Mouse);
+        let remapped = mouse.source(ButtonKind::Key);
+        assert_eq!(remapped.source, ButtonKind::Key);
+        assert_eq!(remapped.target, Button::LeftButton);
+    }
+
+    #[test]
+    fn send_after_receiver_is_dropped() {
+        let (tx, rx) = mpsc::sync_channel(1);
+        drop(rx);
+        let event = Event::Wheel(WheelEvent::new(1));
+        assert_eq!(
+            EventSender::new(tx).send(event),
+            NativeEventOperation::Dispatch
+        );
+    }
+
+    #[test]
+    fn native_event_operation_string_round_trip() {
+        for operation in [NativeEventOperation::Block, NativeEventOperation::Dispatch] {
+            assert_eq!(operation.to_string().parse(), Ok(operation));
+        }
+        assert_eq!("dispatch".parse(), Ok(NativeEventOperation::Dispatch));
+        assert!("".parse::<NativeEventOperation>().is_err());
+    }
+
+    #[test]
+    fn hook_target_devices() {
+        assert!(HookTarget::All.includes_keyboard());
+        assert!(HookTarget::All.includes_mouse());
+        assert!(HookTarget::Keyboard.includes_keyboard());
+        assert!(!HookTarget::Keyboard.includes_mouse());
+        assert!(!HookTarget::Mouse.includes_keyboard());
+        assert!(HookTarget::Mouse.includes_mouse());
+        assert_eq!(HookTarget::default(), HookTarget::All);
+    }
+}
@@ -6,15 +6,47 @@
 //!
 //! ## Feature flags
 //!
-//! * `us-keyboard-layout` (default): Use US keyboard layout. This changes the [`Button`] variant.
+//! * `us-keyboard-layout`: Use US keyboard layout. This changes the [`Button`] variant.
 //! * `japanese-keyboard-layout`: Use Japanese keyboard layout. This changes the [`Button`] variant.
+//!   Neither layout is enabled by default, since [hookmap] chooses it. Without a layout, the
+//!   buttons that differ between the layouts are not available.
+//! * `std` (default): Enables the hooks and input simulation.
+//!   Without it, this crate is `no_std` and provides only the data types of buttons and
+//!   events, such as [`Button`], [`ButtonAction`] and [`ButtonEvent`], for transporting them
+//!   to platforms that cannot hook input. Parsing from strings and the methods that send
+//!   input or read the state of the OS are not available.
+//!   The CI checks this by building for a target without `std` with
+//!   `cargo check -p hookmap-core --no-default-features --target thumbv7em-none-eabihf`.
+//! * `test-util`: Enables `last_injection_verdict` and `reset_injection_state` for
+//!   diagnosing and testing how input generated by this library is told apart from other
+//!   input.
+//! * `serde`: Implements `Serialize` and `Deserialize` of [serde] for the buttons and events,
+//!   which also works without `std`.
 //!
+//! [serde]: https://crates.io/crates/serde
 //! [`Button`]: button::Button
+//! [`ButtonAction`]: button::ButtonAction
+//! [`ButtonEvent`]: event::ButtonEvent
 //!
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
 pub mod button;
 pub mod event;
 
+#[cfg(feature = "std")]
 mod sys;
 
-pub use sys::{install_hook, mouse, uninstall_hook};
+#[cfg(feature = "std")]
+pub use sys::{
+    clear_button_filter, clear_injection_diagnostics, clear_session_change_handler, event_time,
+    hook_thread_id, injection_tag, install_hook, install_hook_for, installed_hook_target,
+    is_hook_installed, lock_keys, mouse, post_event, pressed_buttons, reinstall_hook,
+    run_on_hook_thread, send_batch, send_batch_recursive, send_text, send_text_recursive,
+    set_button_filter, set_high_priority_hook, set_injection_diagnostics, set_injection_tag,
+    set_physical_numpad, set_scancode_input, set_session_change_handler, try_install_hook_for,
+    uninstall_hook, verify_hooks, window,
+};
+
+#[cfg(all(feature = "std", any(test, feature = "test-util")))]
+pub use sys::{last_injection_verdict, reset_injection_state};
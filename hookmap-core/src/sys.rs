@@ -2,4 +2,15 @@
 mod windows;
 
 #[cfg(target_os = "windows")]
-pub use self::windows::{install_hook, mouse, uninstall_hook};
+pub use self::windows::{
+    clear_button_filter, clear_injection_diagnostics, clear_session_change_handler, event_time,
+    hook_thread_id, injection_tag, install_hook, install_hook_for, installed_hook_target,
+    is_hook_installed, lock_keys, mouse, post_event, pressed_buttons, reinstall_hook,
+    run_on_hook_thread, send_batch, send_batch_recursive, send_text, send_text_recursive,
+    set_button_filter, set_high_priority_hook, set_injection_diagnostics, set_injection_tag,
+    set_physical_numpad, set_scancode_input, set_session_change_handler, try_install_hook_for,
+    uninstall_hook, verify_hooks, window,
+};
+
+#[cfg(all(target_os = "windows", any(test, feature = "test-util")))]
+pub use self::windows::{last_injection_verdict, reset_injection_state};
@@ -1,54 +1,137 @@
+mod diagnostics;
 mod hook;
 mod input;
+pub mod lock_keys;
+mod monitor;
+mod session;
 mod vkcode;
+pub mod window;
 
 use hook::HookHandler;
 use input::Input;
 use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
 use windows::Win32::UI::WindowsAndMessaging::HHOOK;
 
-use crate::button::{Button, ButtonAction};
-use crate::event::{self, EventReceiver, NativeEventOperation};
+use crate::button::{Button, ButtonAction, ButtonKind, HoldGuard};
+use crate::event::{
+    self, Event, EventReceiver, HookTarget, InjectionError, InstallHookError, NativeEventOperation,
+    SessionChange,
+};
 
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::iter;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 use once_cell::sync::Lazy;
-use windows::Win32::UI::{HiDpi, WindowsAndMessaging};
+use windows::Win32::UI::{HiDpi, Input::KeyboardAndMouse, WindowsAndMessaging};
 
+// `dwExtraInfo` of injected input is laid out as follows, from the least significant bit:
+//
+// | Bits   | Content                                                 |
+// |--------|---------------------------------------------------------|
+// | 0      | `SHOULD_BE_IGNORED_FLAG`                                |
+// | 1      | `INJECTED_FLAG`                                         |
+// | 2..34  | The injection tag of this process                       |
+// | 34..64 | The sequence number of the injection diagnostics, or 0  |
+//
+// On 32-bit Windows, `dwExtraInfo` has only 32 bits, so the upper bits of the tag are
+// truncated and no sequence number is encoded.
 const SHOULD_BE_IGNORED_FLAG: usize = 0x1;
 const INJECTED_FLAG: usize = 0x2;
+const FLAG_MASK: usize = SHOULD_BE_IGNORED_FLAG | INJECTED_FLAG;
+const FLAG_BITS: u32 = FLAG_MASK.count_ones();
+const SEQUENCE_SHIFT: u32 = FLAG_BITS + u32::BITS;
+
+static INJECTION_TAG: Lazy<AtomicU32> = Lazy::new(|| AtomicU32::new(std::process::id()));
+static PHYSICAL_NUMPAD: AtomicBool = AtomicBool::new(false);
+static SCANCODE_INPUT: AtomicBool = AtomicBool::new(false);
+static HIGH_PRIORITY_HOOK: AtomicBool = AtomicBool::new(false);
+// The injection flags of the last event seen by the hook plus one, or zero if none.
+static LAST_INJECTION_FLAGS: AtomicU8 = AtomicU8::new(0);
+
+#[inline]
+fn encode_extra_info(tag: u32, flags: usize) -> usize {
+    (tag as usize) << FLAG_BITS | flags
+}
+
+#[inline]
+fn encode_sequence(sequence: u32) -> usize {
+    (sequence as usize).checked_shl(SEQUENCE_SHIFT).unwrap_or(0)
+}
+
+#[inline]
+fn decode_sequence(extra_info: usize) -> u32 {
+    extra_info.checked_shr(SEQUENCE_SHIFT).unwrap_or(0) as u32
+}
+
+/// Returns the flags in `extra_info` if it was tagged with `tag`, otherwise `0`.
+#[inline]
+fn decode_extra_info(tag: u32, extra_info: usize) -> usize {
+    let tagged = extra_info & !usize::MAX.checked_shl(SEQUENCE_SHIFT).unwrap_or(0);
+    if tagged & !FLAG_MASK == encode_extra_info(tag, 0) {
+        extra_info & FLAG_MASK
+    } else {
+        0
+    }
+}
+
+#[inline]
+fn injection_flags(extra_info: usize) -> usize {
+    decode_extra_info(INJECTION_TAG.load(Ordering::Relaxed), extra_info)
+}
+
+/// Records how the hook classifies the event of `extra_info` for [`last_injection_verdict`].
+#[inline]
+fn record_injection(extra_info: usize) {
+    LAST_INJECTION_FLAGS.store(injection_flags(extra_info) as u8 + 1, Ordering::Relaxed);
+}
+
+/// Returns the sequence number in `extra_info` if it was injected by this process,
+/// otherwise `0`.
+#[inline]
+fn injection_sequence(extra_info: usize) -> u32 {
+    if injection_flags(extra_info) & INJECTED_FLAG != 0 {
+        decode_sequence(extra_info)
+    } else {
+        0
+    }
+}
 
 #[derive(Debug)]
-struct ButtonState([AtomicBool; Button::VARIANT_COUNT]);
+struct ButtonState([AtomicBool; Button::COUNT]);
 
 impl ButtonState {
     const fn new() -> Self {
         let inner = unsafe {
             // AtomicBool has the same in-memory representation as a bool.
             // https://doc.rust-lang.org/std/sync/atomic/struct.AtomicBool.html
-            std::mem::transmute([false; Button::VARIANT_COUNT])
+            std::mem::transmute::<[bool; Button::COUNT], [AtomicBool; Button::COUNT]>(
+                [false; Button::COUNT],
+            )
         };
         ButtonState(inner)
     }
 
     #[inline]
     fn press(&self, button: Button, order: Ordering) {
-        self.0[button as usize].store(true, order);
+        self.0[button.to_index()].store(true, order);
     }
 
     #[inline]
     fn release(&self, button: Button, order: Ordering) {
-        self.0[button as usize].store(false, order)
+        self.0[button.to_index()].store(false, order)
     }
 
     #[inline]
     fn is_pressed(&self, button: Button, order: Ordering) -> bool {
-        self.0[button as usize].load(order)
+        self.0[button.to_index()].load(order)
     }
 
     #[inline]
     fn is_released(&self, button: Button, order: Ordering) -> bool {
-        !self.0[button as usize].load(order)
+        !self.0[button.to_index()].load(order)
     }
 }
 
@@ -56,52 +139,153 @@ static BUTTON_STATE: ButtonState = ButtonState::new();
 
 static INPUT: Lazy<Input> = Lazy::new(Input::new);
 
-#[inline]
-fn send_input(button: Button, action: ButtonAction, recursive: bool, assume: fn(Button)) {
-    let left_and_right_modifier = match button {
+fn left_and_right_modifier(button: Button) -> Option<(Button, Button)> {
+    match button {
         Button::Shift => Some((Button::LShift, Button::RShift)),
         Button::Ctrl => Some((Button::LCtrl, Button::RCtrl)),
         Button::Alt => Some((Button::LAlt, Button::RAlt)),
         Button::Super => Some((Button::LSuper, Button::RSuper)),
         _ => None,
-    };
-    if let Some((left, right)) = left_and_right_modifier {
-        assume(left);
-        assume(right);
-        assume(button);
-        INPUT.button_input(left, action, recursive);
-        INPUT.button_input(right, action, recursive);
-    } else {
-        assume(button);
-        INPUT.button_input(button, action, recursive);
     }
 }
 
+#[inline]
+fn assume(button: Button, action: ButtonAction) {
+    match action {
+        ButtonAction::Press => button.assume_pressed(),
+        ButtonAction::Release => button.assume_released(),
+    }
+}
+
+/// An input to be sent, and the generic modifier that is completed by sending it.
+type ExpandedInput = (Button, ButtonAction, Option<Button>);
+
+// Generic modifiers such as `Shift` are sent as the left and right ones.
+fn expand_inputs(inputs: &[(Button, ButtonAction)]) -> Vec<ExpandedInput> {
+    let mut expanded = Vec::with_capacity(inputs.len());
+    for &(button, action) in inputs {
+        if let Some((left, right)) = left_and_right_modifier(button) {
+            expanded.push((left, action, None));
+            expanded.push((right, action, Some(button)));
+        } else {
+            expanded.push((button, action, None));
+        }
+    }
+    expanded
+}
+
+// The state is updated before `send` inserts the inputs so that it is never behind them,
+// even for the hooks that receive them. `SendInput` inserts nothing after an input is blocked,
+// e.g. by UIPI, so the state of the inputs that were not inserted is rolled back to keep it
+// from diverging from the actual state. `send` returns the number of inserted inputs.
+fn assume_sent(expanded: &[ExpandedInput], send: impl FnOnce() -> usize) {
+    let mut previous = Vec::with_capacity(expanded.len());
+    for (i, &(button, action, generic)) in expanded.iter().enumerate() {
+        for button in iter::once(button).chain(generic) {
+            previous.push((i, button, button.is_pressed()));
+            assume(button, action);
+        }
+    }
+    let sent = send();
+    for &(_, button, was_pressed) in previous.iter().rev().take_while(|&&(i, ..)| i >= sent) {
+        if was_pressed {
+            button.assume_pressed();
+        } else {
+            button.assume_released();
+        }
+    }
+}
+
+fn send_input(button: Button, action: ButtonAction, recursive: bool) {
+    send_batch_inner(&[(button, action)], recursive, is_scancode_input());
+}
+
+fn send_batch_inner(inputs: &[(Button, ButtonAction)], recursive: bool, scancode: bool) {
+    let expanded = expand_inputs(inputs);
+    let buttons: Vec<_> = expanded
+        .iter()
+        .map(|&(button, action, _)| (button, action))
+        .collect();
+    assume_sent(&expanded, || {
+        INPUT.button_inputs(&buttons, recursive, scancode)
+    });
+}
+
+/// Simulates button inputs in a single batch.
+///
+/// Unlike calling [`Button::press`] and [`Button::release`] one by one, no other input
+/// (including the input of the user) is inserted between the inputs.
+///
+/// # Example
+///
+/// ```no_run
+/// use hookmap_core::button::{Button, ButtonAction};
+///
+/// hookmap_core::send_batch(&[
+///     (Button::LCtrl, ButtonAction::Press),
+///     (Button::C, ButtonAction::Press),
+///     (Button::C, ButtonAction::Release),
+///     (Button::LCtrl, ButtonAction::Release),
+/// ]);
+/// ```
+///
+pub fn send_batch(inputs: &[(Button, ButtonAction)]) {
+    send_batch_inner(inputs, false, is_scancode_input());
+}
+
+/// Simulates button inputs in a single batch.
+/// Events generated by this method can be hooked.
+pub fn send_batch_recursive(inputs: &[(Button, ButtonAction)]) {
+    send_batch_inner(inputs, true, is_scancode_input());
+}
+
+/// Types `text` in a single batch, regardless of the keyboard layout.
+///
+/// The characters are sent as Unicode input rather than as key strokes, so they are not
+/// affected by the modifier keys held down, and no [`Button`] event is generated for them.
+/// Applications that read the keys directly, such as games, may ignore the input.
+///
+/// # Example
+///
+/// ```no_run
+/// hookmap_core::send_text("café ☕");
+/// ```
+///
+pub fn send_text(text: &str) {
+    INPUT.text_input(text, false);
+}
+
+/// Types `text` in a single batch, regardless of the keyboard layout.
+/// Events generated by this method can be hooked.
+pub fn send_text_recursive(text: &str) {
+    INPUT.text_input(text, true);
+}
+
 impl Button {
     /// Simulates a button presses.
     #[inline]
     pub fn press(self) {
-        send_input(self, ButtonAction::Press, false, Button::assume_pressed);
+        send_input(self, ButtonAction::Press, false);
     }
 
     /// Simulates a button presses.
     /// Events generated by this method can be hooked.
     #[inline]
     pub fn press_recursive(self) {
-        send_input(self, ButtonAction::Press, true, Button::assume_pressed);
+        send_input(self, ButtonAction::Press, true);
     }
 
     /// Simulates a button releases.
     #[inline]
     pub fn release(self) {
-        send_input(self, ButtonAction::Release, false, Button::assume_released);
+        send_input(self, ButtonAction::Release, false);
     }
 
     /// Simulates a button releases.
     /// Events generated by this method can be hooked.
     #[inline]
     pub fn release_recursive(self) {
-        send_input(self, ButtonAction::Release, true, Button::assume_released);
+        send_input(self, ButtonAction::Release, true);
     }
 
     /// Simulates a button click.
@@ -119,7 +303,83 @@ impl Button {
         self.release_recursive();
     }
 
+    /// Simulates a button press with the scan code of the key instead of its virtual-key code.
+    ///
+    /// Games that read the keyboard through DirectInput or Raw Input often ignore input that
+    /// has only a virtual-key code. The scan code is looked up with `MapVirtualKey` in the
+    /// current keyboard layout. Keys without a scan code and mouse buttons are sent as usual.
+    /// See [`set_scancode_input`](crate::set_scancode_input) to send all input this way.
+    #[inline]
+    pub fn press_scancode(self) {
+        send_batch_inner(&[(self, ButtonAction::Press)], false, true);
+    }
+
+    /// Simulates a button release with the scan code of the key.
+    /// See [`Button::press_scancode`].
+    #[inline]
+    pub fn release_scancode(self) {
+        send_batch_inner(&[(self, ButtonAction::Release)], false, true);
+    }
+
+    /// Simulates a button click with the scan code of the key.
+    /// See [`Button::press_scancode`].
+    #[inline]
+    pub fn click_scancode(self) {
+        self.press_scancode();
+        self.release_scancode();
+    }
+
+    /// Simulates a button being held down by sending press events every `interval`,
+    /// like the auto-repeat of a keyboard.
+    ///
+    /// Some games only recognize a button as held down while they receive repeated press events.
+    /// The button is released when the returned [`HoldGuard`] is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap_core::button::Button;
+    /// use std::{thread, time::Duration};
+    ///
+    /// let guard = Button::W.hold_repeating(Duration::from_millis(30));
+    /// thread::sleep(Duration::from_secs(1));
+    /// drop(guard);
+    /// ```
+    ///
+    pub fn hold_repeating(self, interval: Duration) -> HoldGuard {
+        let is_held = Arc::new(AtomicBool::new(true));
+        let is_held_ = Arc::clone(&is_held);
+        let join_handle = thread::spawn(move || {
+            while is_held_.load(Ordering::SeqCst) {
+                self.press();
+                thread::park_timeout(interval);
+            }
+        });
+        HoldGuard::new(self, is_held, join_handle)
+    }
+
     /// Returns `true` if the button is pressed.
+    ///
+    /// Input simulated by this library is reflected only if it is accepted by the system.
+    /// For example, input blocked by UIPI does not change the state.
+    ///
+    /// The state is tracked by this library instead of being read with `GetKeyState`, which
+    /// lags behind until the input reaches the message queue of the thread. Input simulated by
+    /// this library is reflected as soon as the method that sent it returns, so the state is
+    /// consistent even if it is queried immediately. Input simulated by the `*_recursive`
+    /// methods is recorded again when it reaches the hook, so that the hooks see the state as
+    /// of each input, e.g. the modifiers pressed before it in the same batch. Until the hook
+    /// has received all of it, the state may briefly reflect one of the earlier inputs.
+    ///
+    /// ```no_run
+    /// use hookmap_core::button::Button;
+    ///
+    /// Button::LShift.press();
+    /// assert!(Button::LShift.is_pressed());
+    /// Button::LShift.release();
+    /// assert!(Button::LShift.is_released());
+    /// ```
+    ///
     #[inline]
     pub fn is_pressed(self) -> bool {
         BUTTON_STATE.is_pressed(self, Ordering::SeqCst)
@@ -131,6 +391,94 @@ impl Button {
         BUTTON_STATE.is_released(self, Ordering::SeqCst)
     }
 
+    /// Returns `true` if the toggle state of the button is on, such as when Caps Lock is on.
+    ///
+    /// This is reliable for lock keys. For other keys, the state flips each time the key is
+    /// pressed.
+    ///
+    /// Unlike [`Button::is_pressed`], this is read with `GetKeyState`, so it may not reflect
+    /// input simulated just before until the input reaches the message queue of the thread.
+    #[inline]
+    pub fn is_toggled(self) -> bool {
+        let state = unsafe { KeyboardAndMouse::GetKeyState(vkcode::from_button(self).0.into()) };
+        state & 1 != 0
+    }
+
+    /// Clicks the button if its toggle state is not `on`, such as to turn Caps Lock on at
+    /// startup. See [`Button::is_toggled`] for how the state is read.
+    ///
+    /// The state is changed by emulating a click, so this does nothing if the state is
+    /// already `on`. Use [`lock_keys`] for Num Lock and Scroll Lock.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap_core::button::Button;
+    ///
+    /// Button::Insert.set_toggled(false);
+    /// ```
+    ///
+    pub fn set_toggled(self, on: bool) {
+        if self.is_toggled() != on {
+            self.click();
+        }
+    }
+
+    /// Reports the keyboard events of the virtual-key code `code` as `button` from now on.
+    ///
+    /// Some keyboards, especially the function rows of laptops, send codes that do not
+    /// correspond to any [`Button`]. Their events are not hooked and pass through to the
+    /// applications, unless their codes are registered with this. A registered code takes
+    /// precedence over the button the code usually corresponds to. The codes of a key can be
+    /// found with a tool that shows the virtual-key codes of keyboard messages.
+    /// Registering the same code again replaces the mapping, and
+    /// [`Button::unregister_code_mapping`] removes it.
+    ///
+    /// This affects only the events reported by the hook. The input sent for `button`, such
+    /// as by [`Button::press`], still uses the code of `button` itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `button` is a mouse button or a generic modifier such as [`Button::Shift`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap_core::button::Button;
+    ///
+    /// // A laptop that sends `VK_LAUNCH_MEDIA_SELECT` for its play key.
+    /// Button::register_code_mapping(0xB5, Button::MediaPlayPause);
+    /// ```
+    ///
+    pub fn register_code_mapping(code: u16, button: Button) {
+        assert!(
+            button.kind() == ButtonKind::Key && left_and_right_modifier(button).is_none(),
+            "`{:?}` cannot be the button of a code mapping",
+            button
+        );
+        vkcode::register_mapping(KeyboardAndMouse::VIRTUAL_KEY(code), button);
+    }
+
+    /// Removes the mapping of `code` registered by [`Button::register_code_mapping`], so
+    /// that the code corresponds to the button it usually does again.
+    /// Returns the button `code` was mapped to, or `None` if it was not registered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap_core::button::Button;
+    ///
+    /// Button::register_code_mapping(0xB5, Button::MediaPlayPause);
+    /// assert_eq!(
+    ///     Button::unregister_code_mapping(0xB5),
+    ///     Some(Button::MediaPlayPause)
+    /// );
+    /// ```
+    ///
+    pub fn unregister_code_mapping(code: u16) -> Option<Button> {
+        vkcode::unregister_mapping(KeyboardAndMouse::VIRTUAL_KEY(code))
+    }
+
     #[inline]
     fn assume_pressed(self) {
         BUTTON_STATE.press(self, Ordering::SeqCst);
@@ -145,7 +493,10 @@ impl Button {
 pub mod mouse {
     //! Functions for mouse operations
 
-    use super::INPUT;
+    use super::{input, monitor, INPUT};
+    use crate::button::Button;
+    use crate::event::Position;
+    use std::time::Duration;
 
     /// Gets the position of the mouse cursor. `(x, y)`
     #[inline]
@@ -153,6 +504,29 @@ pub mod mouse {
         INPUT.cursor_position()
     }
 
+    /// Returns the upper-left corner and the lower-right corner of the virtual screen, the
+    /// bounding rectangle of all monitors. The lower-right corner is outside the screen.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hookmap_core::mouse;
+    ///
+    /// let (upper_left, lower_right) = mouse::screen_bounds();
+    /// println!("{}x{}", lower_right.x - upper_left.x, lower_right.y - upper_left.y);
+    /// ```
+    ///
+    pub fn screen_bounds() -> (Position, Position) {
+        let (left, top, width, height) = input::virtual_screen();
+        (
+            Position { x: left, y: top },
+            Position {
+                x: left + width,
+                y: top + height,
+            },
+        )
+    }
+
     /// Moves the mouse cursor to the specified coordinates.
     #[inline]
     pub fn move_absolute(x: i32, y: i32) {
@@ -166,6 +540,106 @@ pub mod mouse {
         INPUT.move_absolute(x, y, true);
     }
 
+    /// Moves the mouse cursor to `(x, y)` relative to the upper-left corner of a monitor.
+    ///
+    /// Monitors are numbered from `0`: the primary monitor first, then the others from left
+    /// to right. Coordinates outside the monitor are clamped into it.
+    /// Returns `false` if there is no monitor at `monitor`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hookmap_core::mouse;
+    ///
+    /// // The center of the second monitor with a resolution of 1920x1080.
+    /// mouse::move_absolute_on_monitor(1, 960, 540);
+    /// ```
+    ///
+    pub fn move_absolute_on_monitor(monitor: usize, x: i32, y: i32) -> bool {
+        move_absolute_on_monitor_inner(monitor, x, y, false)
+    }
+
+    /// Moves the mouse cursor to `(x, y)` relative to the upper-left corner of a monitor.
+    /// Events generated by this method can be hooked.
+    /// See [`move_absolute_on_monitor`].
+    pub fn move_absolute_on_monitor_recursive(monitor: usize, x: i32, y: i32) -> bool {
+        move_absolute_on_monitor_inner(monitor, x, y, true)
+    }
+
+    fn move_absolute_on_monitor_inner(monitor: usize, x: i32, y: i32, recursive: bool) -> bool {
+        match monitor::monitors().get(monitor) {
+            Some(monitor) => {
+                let (x, y) = monitor.to_virtual(x, y);
+                INPUT.move_absolute(x, y, recursive);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the mouse cursor to `(x, y)` and clicks `button` there.
+    ///
+    /// The move and the click are sent in a single batch, so no other input (including the
+    /// input of the user) is inserted between them. If `restore` is `true`, the cursor is
+    /// moved back to where it was in the same batch. The coordinates are those of
+    /// [`move_absolute`], and may be on any monitor.
+    ///
+    /// Returns `false` if the click was not sent, e.g. because it was blocked by UIPI.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hookmap_core::button::Button;
+    /// use hookmap_core::mouse;
+    ///
+    /// mouse::click_at(100, 200, Button::LeftButton, true);
+    /// ```
+    ///
+    pub fn click_at(x: i32, y: i32, button: Button, restore: bool) -> bool {
+        click_at_inner(x, y, button, restore, false)
+    }
+
+    /// Moves the mouse cursor to `(x, y)` and clicks `button` there.
+    /// Events generated by this method can be hooked.
+    /// See [`click_at`].
+    pub fn click_at_recursive(x: i32, y: i32, button: Button, restore: bool) -> bool {
+        click_at_inner(x, y, button, restore, true)
+    }
+
+    fn click_at_inner(x: i32, y: i32, button: Button, restore: bool, recursive: bool) -> bool {
+        let is_sent = INPUT.click_at((x, y), button, restore, recursive);
+        if is_sent {
+            button.assume_pressed();
+            button.assume_released();
+        }
+        is_sent
+    }
+
+    /// Moves the mouse cursor to the specified coordinates in `steps` steps,
+    /// waiting `interval` between each step.
+    ///
+    /// This blocks the current thread until the cursor reaches the coordinates.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use hookmap_core::mouse;
+    /// use std::time::Duration;
+    ///
+    /// mouse::move_absolute_smooth(100, 200, 20, Duration::from_millis(10));
+    /// ```
+    ///
+    pub fn move_absolute_smooth(x: i32, y: i32, steps: u32, interval: Duration) {
+        INPUT.move_absolute_smooth((x, y), steps, interval, false);
+    }
+
+    /// Moves the mouse cursor to the specified coordinates in `steps` steps,
+    /// waiting `interval` between each step.
+    /// Events generated by this method can be hooked.
+    pub fn move_absolute_smooth_recursive(x: i32, y: i32, steps: u32, interval: Duration) {
+        INPUT.move_absolute_smooth((x, y), steps, interval, true);
+    }
+
     /// Moves the mouse cursor a specified distance.
     #[inline]
     pub fn move_relative(dx: i32, dy: i32) {
@@ -217,7 +691,7 @@ extern "system" fn mouse_hook_proc(n_code: i32, w_param: WPARAM, l_param: LPARAM
 ///
 /// # Panics
 ///
-/// Panics if other hooks are already installed.
+/// Panics if other hooks are already installed or the OS fails to install the hooks.
 ///
 /// # Example
 ///
@@ -226,17 +700,62 @@ extern "system" fn mouse_hook_proc(n_code: i32, w_param: WPARAM, l_param: LPARAM
 /// ```
 ///
 pub fn install_hook() -> EventReceiver {
+    install_hook_for(HookTarget::All)
+}
+
+/// Installs only the hooks for the devices specified by `target` and returns a receiver to
+/// receive the generated event.
+///
+/// Input from devices that are not hooked never reaches the receiver and is always passed
+/// through to other applications.
+///
+/// # Panics
+///
+/// Panics if other hooks are already installed or the OS fails to install the hooks.
+///
+/// # Example
+///
+/// ```no_run
+/// use hookmap_core::event::HookTarget;
+///
+/// let rx = hookmap_core::install_hook_for(HookTarget::Keyboard);
+/// ```
+///
+pub fn install_hook_for(target: HookTarget) -> EventReceiver {
+    try_install_hook_for(target).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Same as [`install_hook_for`], but returns an error instead of panicking.
+///
+/// # Errors
+///
+/// Returns [`InstallHookError::AlreadyInstalled`] if other hooks are already installed,
+/// or [`InstallHookError::Os`] if the OS fails to install the hooks.
+///
+/// # Example
+///
+/// ```no_run
+/// use hookmap_core::event::{HookTarget, InstallHookError};
+///
+/// let rx = hookmap_core::try_install_hook_for(HookTarget::All).unwrap();
+/// assert_eq!(
+///     hookmap_core::try_install_hook_for(HookTarget::All).unwrap_err(),
+///     InstallHookError::AlreadyInstalled
+/// );
+/// ```
+///
+pub fn try_install_hook_for(target: HookTarget) -> Result<EventReceiver, InstallHookError> {
     unsafe {
         // If this is not executed, the GetCursorPos function returns an invalid cursor position.
         HiDpi::SetProcessDpiAwarenessContext(HiDpi::DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE);
     }
 
-    INPUT.update_cursor_position();
+    INPUT.reset_cursor();
 
     let (tx, rx) = event::channel();
-    HOOK_HANDLER.install(tx, keyboard_hook_proc, mouse_hook_proc);
+    HOOK_HANDLER.install(tx, target, keyboard_hook_proc, mouse_hook_proc)?;
 
-    rx
+    Ok(rx)
 }
 
 /// Uninstalls a hook.
@@ -260,3 +779,622 @@ pub fn install_hook() -> EventReceiver {
 pub fn uninstall_hook() {
     HOOK_HANDLER.uninstall();
 }
+
+/// Checks that the installed hooks still receive input.
+///
+/// Windows removes a hook without notice when its procedure takes longer than the
+/// `LowLevelHooksTimeout` of the registry, and some system events remove the hooks too.
+/// This returns `true` if the hooks see the last input the system received within `timeout`,
+/// so nothing is sent while the user is idle. Only when just one of the hooks is installed
+/// and the last input may have come from the other device, an input that applications
+/// ignore is sent to check it, a click of an unassigned key for the keyboard hook and a mouse
+/// move by zero for the mouse hook. Returns `false` if the hook is not installed.
+///
+/// The hook thread must not be blocked by the receiver, so this should not be called on the
+/// thread that receives the events.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// let rx = hookmap_core::install_hook();
+/// std::thread::spawn(|| {
+///     if !hookmap_core::verify_hooks(Duration::from_millis(500)) {
+///         hookmap_core::reinstall_hook().unwrap();
+///     }
+/// });
+/// ```
+///
+pub fn verify_hooks(timeout: Duration) -> bool {
+    HOOK_HANDLER.verify(timeout)
+}
+
+/// Installs the hooks again on the hook thread, such as after [`verify_hooks`] found that
+/// Windows removed them. The events are still sent to the receiver returned by
+/// [`install_hook`].
+///
+/// # Errors
+///
+/// Returns [`InstallHookError::NotInstalled`] if the hook is not installed,
+/// or [`InstallHookError::Os`] if the OS fails to install the hooks. In the latter case the
+/// hooks stay uninstalled until this succeeds or [`uninstall_hook`] is called.
+///
+pub fn reinstall_hook() -> Result<(), InstallHookError> {
+    HOOK_HANDLER.reinstall()
+}
+
+/// Sets a predicate that decides which button events are sent to the receiver.
+///
+/// Button events for which `filter` returns `false` are dispatched immediately on the hook
+/// thread without being sent to the receiver, which reduces the latency of input that is
+/// not handled. Cursor and wheel events are not affected.
+/// Since `filter` is called on the hook thread for every button event, it should be cheap.
+///
+/// The state returned by [`Button::is_pressed`] is updated even for filtered events.
+///
+/// # Example
+///
+/// ```no_run
+/// use hookmap_core::button::Button;
+///
+/// hookmap_core::set_button_filter(|button| button == Button::A);
+/// let rx = hookmap_core::install_hook();
+/// ```
+///
+pub fn set_button_filter(filter: impl Fn(Button) -> bool + Send + Sync + 'static) {
+    HOOK_HANDLER.set_button_filter(Some(Arc::new(filter)));
+}
+
+/// Removes the predicate set by [`set_button_filter`].
+/// After this call, all button events are sent to the receiver.
+pub fn clear_button_filter() {
+    HOOK_HANDLER.set_button_filter(None);
+}
+
+/// Sets the tag that marks input generated by this process.
+///
+/// Input generated by this library is tagged so that the hook can skip it or report it as
+/// injected. Only input bearing the tag of this process is treated that way, so multiple
+/// programs using this library can run at the same time, and each of them sees the input
+/// generated by the others as ordinary input.
+///
+/// The process ID is used by default, which is unique among running processes.
+/// This only needs to be set when the tag has to be shared or distinguished differently.
+///
+/// # Example
+///
+/// ```no_run
+/// hookmap_core::set_injection_tag(0x686d);
+/// assert_eq!(hookmap_core::injection_tag(), 0x686d);
+/// ```
+///
+pub fn set_injection_tag(tag: u32) {
+    INJECTION_TAG.store(tag, Ordering::Relaxed);
+}
+
+/// Returns the tag set by [`set_injection_tag`].
+pub fn injection_tag() -> u32 {
+    INJECTION_TAG.load(Ordering::Relaxed)
+}
+
+/// Returns how the hook classified the last event it saw by the tag of [`set_injection_tag`],
+/// or `None` if it has seen no event since it was installed or [`reset_injection_state`]
+/// was called.
+///
+/// This is for diagnostics and tests of the feedback-loop prevention, e.g. to check that
+/// input sent by a hotkey is skipped instead of triggering the hotkey again.
+/// It is available with the `test-util` feature.
+///
+/// # Example
+///
+/// ```no_run
+/// use hookmap_core::button::Button;
+/// use hookmap_core::event::InjectionVerdict;
+///
+/// let rx = hookmap_core::install_hook();
+/// Button::A.click();
+/// # std::thread::sleep(std::time::Duration::from_millis(50));
+/// assert_eq!(
+///     hookmap_core::last_injection_verdict(),
+///     Some(InjectionVerdict::Skipped)
+/// );
+/// ```
+///
+#[cfg(any(test, feature = "test-util"))]
+pub fn last_injection_verdict() -> Option<event::InjectionVerdict> {
+    use event::InjectionVerdict;
+
+    let flags = usize::from(LAST_INJECTION_FLAGS.load(Ordering::Relaxed)).checked_sub(1)?;
+    Some(if flags & SHOULD_BE_IGNORED_FLAG != 0 {
+        InjectionVerdict::Skipped
+    } else if flags & INJECTED_FLAG != 0 {
+        InjectionVerdict::Injected
+    } else {
+        InjectionVerdict::Untagged
+    })
+}
+
+/// Forgets the last event returned by [`last_injection_verdict`] and restarts the sequence
+/// numbers and the pending input of [`set_injection_diagnostics`].
+///
+/// This is for diagnostics and tests, and is available with the `test-util` feature.
+#[cfg(any(test, feature = "test-util"))]
+pub fn reset_injection_state() {
+    LAST_INJECTION_FLAGS.store(0, Ordering::Relaxed);
+    diagnostics::reset();
+}
+
+/// Starts checking that the button input generated by this process comes back to the hook
+/// unmodified, and calls `on_error` when it does not.
+///
+/// This is a diagnostic for running alongside other programs that hook input. Each button
+/// input is tagged with a sequence number, and [`InjectionError::Lost`] is reported if it is
+/// not seen by the hook within [`InjectionError::TIMEOUT`], for example because a hook installed
+/// later by another program blocked or replaced it. [`InjectionError::Modified`] is reported
+/// if it is seen as a different button or action. Lost input is reported when the hook
+/// receives the next event or the next input is generated after the timeout.
+///
+/// Input from hooks installed earlier than this one cannot be detected, since those hooks
+/// see the input after this one. The sequence number is stored in the upper 30 bits of
+/// `dwExtraInfo`, so this has no effect on 32-bit Windows.
+/// `on_error` is called on the hook thread when the hook receives an event, and on the thread
+/// generating input when lost input is found then, so it should return quickly.
+///
+/// # Example
+///
+/// ```no_run
+/// hookmap_core::set_injection_diagnostics(|e| eprintln!("{}", e));
+/// ```
+///
+pub fn set_injection_diagnostics(on_error: impl Fn(InjectionError) + Send + Sync + 'static) {
+    diagnostics::enable(Arc::new(on_error));
+}
+
+/// Stops the diagnostics started by [`set_injection_diagnostics`].
+pub fn clear_injection_diagnostics() {
+    diagnostics::disable();
+}
+
+/// Sets `handler` to be called when the system resumes from sleep or the session is unlocked.
+///
+/// The buttons held before these may have been released while the hook could not see them,
+/// so this is for resetting the state that depends on the held buttons.
+/// The changes are notified only while the hook is installed. `handler` is called on the hook
+/// thread, so it should return quickly.
+///
+/// # Example
+///
+/// ```no_run
+/// hookmap_core::set_session_change_handler(|change| eprintln!("{:?}", change));
+/// ```
+///
+pub fn set_session_change_handler(handler: impl Fn(SessionChange) + Send + Sync + 'static) {
+    session::set_handler(Some(Arc::new(handler)));
+}
+
+/// Removes the handler set by [`set_session_change_handler`].
+pub fn clear_session_change_handler() {
+    session::set_handler(None);
+}
+
+/// Sets whether the numpad keys are reported as the numpad buttons regardless of Num Lock.
+///
+/// While Num Lock is off, the numpad keys act as the navigation keys, and their events are
+/// reported as [`Button::Home`], [`Button::Clear`] and so on, the same as the dedicated
+/// navigation keys. If this is set to `true`, the numpad keys are distinguished by the
+/// extended flag of their scan codes and reported as [`Button::Numpad7`], [`Button::Numpad5`]
+/// and so on, so they can be remapped by their physical position.
+/// The dedicated navigation keys are not affected.
+///
+/// This is `false` by default.
+///
+/// # Example
+///
+/// ```no_run
+/// hookmap_core::set_physical_numpad(true);
+/// ```
+///
+pub fn set_physical_numpad(enabled: bool) {
+    PHYSICAL_NUMPAD.store(enabled, Ordering::Relaxed);
+}
+
+#[inline]
+fn is_physical_numpad() -> bool {
+    PHYSICAL_NUMPAD.load(Ordering::Relaxed)
+}
+
+/// Sets whether keyboard input is simulated with scan codes instead of virtual-key codes.
+///
+/// If this is set to `true`, all keyboard input generated by this library, such as
+/// [`Button::press`] and [`send_batch`], is sent as by [`Button::press_scancode`].
+/// This makes the input recognized by games that read the keyboard through DirectInput or
+/// Raw Input. Since the scan codes depend on the keyboard layout, this is `false` by default.
+///
+/// # Example
+///
+/// ```no_run
+/// hookmap_core::set_scancode_input(true);
+/// ```
+///
+pub fn set_scancode_input(enabled: bool) {
+    SCANCODE_INPUT.store(enabled, Ordering::Relaxed);
+}
+
+#[inline]
+fn is_scancode_input() -> bool {
+    SCANCODE_INPUT.load(Ordering::Relaxed)
+}
+
+/// Sets whether the thread running the hooks is given a higher scheduling priority.
+///
+/// Windows silently removes a low-level hook whose callback does not return in time, and a
+/// busy system can delay the callback long enough for that to happen. Running the hook
+/// thread above the normal priority makes this less likely and lowers the input latency,
+/// at the cost of taking the CPU from other threads while the hook is busy. Only the hook
+/// thread itself is affected; the thread receiving the events keeps its priority.
+///
+/// This takes effect the next time the hook is installed, and is `false` by default.
+///
+/// # Example
+///
+/// ```no_run
+/// hookmap_core::set_high_priority_hook(true);
+/// let rx = hookmap_core::install_hook();
+/// ```
+///
+pub fn set_high_priority_hook(enabled: bool) {
+    HIGH_PRIORITY_HOOK.store(enabled, Ordering::Relaxed);
+}
+
+#[inline]
+fn is_high_priority_hook() -> bool {
+    HIGH_PRIORITY_HOOK.load(Ordering::Relaxed)
+}
+
+/// Returns the devices hooked by [`install_hook`] or [`install_hook_for`],
+/// or `None` if the hook is not installed.
+///
+/// # Example
+///
+/// ```no_run
+/// use hookmap_core::event::HookTarget;
+///
+/// assert_eq!(hookmap_core::installed_hook_target(), None);
+/// let rx = hookmap_core::install_hook_for(HookTarget::Mouse);
+/// assert_eq!(hookmap_core::installed_hook_target(), Some(HookTarget::Mouse));
+/// ```
+///
+pub fn installed_hook_target() -> Option<HookTarget> {
+    HOOK_HANDLER.installed_target()
+}
+
+/// Returns the ID of the thread that owns the hooks, or `None` if the hook is not installed.
+///
+/// Low-level hooks are called on the thread that installed them, and only while that thread
+/// is pumping messages. The hooks are installed on a dedicated thread that runs a message
+/// loop, so the thread calling [`install_hook`] does not need to pump messages.
+/// The ID can be passed to `PostThreadMessageW` to wake the message loop, but do not post
+/// `WM_QUIT` to it; use [`uninstall_hook`] to stop the hooks and [`run_on_hook_thread`] to
+/// run code on the thread.
+///
+/// # Example
+///
+/// ```no_run
+/// assert_eq!(hookmap_core::hook_thread_id(), None);
+/// let rx = hookmap_core::install_hook();
+/// println!("{:?}", hookmap_core::hook_thread_id());
+/// ```
+///
+pub fn hook_thread_id() -> Option<u32> {
+    HOOK_HANDLER.thread_id()
+}
+
+/// Returns the current time on the clock that the events are stamped with.
+///
+/// This can be compared with [`Event::time`] and is used to stamp events created by the
+/// program, such as the ones given to [`post_event`].
+///
+/// # Example
+///
+/// ```
+/// let before = hookmap_core::event_time();
+/// let after = hookmap_core::event_time();
+/// assert!(before <= after);
+/// ```
+///
+/// [`Event::time`]: crate::event::Event::time
+///
+pub fn event_time() -> Duration {
+    Duration::from_millis(hook::current_tick_count())
+}
+
+/// Returns `true` if the hook is installed.
+///
+/// Since installing a hook twice panics, this can be used to check before installing.
+///
+/// # Example
+///
+/// ```no_run
+/// if !hookmap_core::is_hook_installed() {
+///     let rx = hookmap_core::install_hook();
+/// }
+/// ```
+///
+pub fn is_hook_installed() -> bool {
+    installed_hook_target().is_some()
+}
+
+/// Returns the buttons that are currently pressed, in the order of [`Button::ALL`].
+///
+/// This is the state returned by [`Button::is_pressed`], so it includes the input simulated
+/// by this library. Generic modifiers such as [`Button::Shift`] are not included;
+/// the left and right ones are.
+///
+/// # Example
+///
+/// ```no_run
+/// let rx = hookmap_core::install_hook();
+/// println!("Held: {:?}", hookmap_core::pressed_buttons());
+/// ```
+///
+pub fn pressed_buttons() -> Vec<Button> {
+    Button::ALL
+        .into_iter()
+        .filter(|&button| left_and_right_modifier(button).is_none() && button.is_pressed())
+        .collect()
+}
+
+/// Sends a synthetic event to the receiver returned by [`install_hook`] and waits for it to be
+/// handled.
+///
+/// Unlike input simulation such as [`Button::press`], the event is not sent to the OS.
+/// It is only received in the same way as hooked events, so it can be used to drive the
+/// handler of the receiver. Returns the operation decided by [`NativeEventHandler`],
+/// which has no effect since there is no native event.
+///
+/// The state returned by [`Button::is_pressed`] is not updated by synthetic events.
+///
+/// # Deadlock
+///
+/// This blocks until the event is handled, so it must not be called on the thread that
+/// receives the events.
+///
+/// # Panics
+///
+/// Panics if the hook is not installed.
+///
+/// # Example
+///
+/// ```no_run
+/// use hookmap_core::button::{Button, ButtonAction};
+/// use hookmap_core::event::{ButtonEvent, Event};
+///
+/// let rx = hookmap_core::install_hook();
+/// std::thread::spawn(|| {
+///     let event = ButtonEvent::new(Button::A, ButtonAction::Press)
+///         .injected(true)
+///         .time(hookmap_core::event_time());
+///     hookmap_core::post_event(Event::Button(event));
+/// });
+/// let (event, native_handler) = rx.recv().unwrap();
+/// native_handler.dispatch();
+/// ```
+///
+/// [`NativeEventHandler`]: crate::event::NativeEventHandler
+///
+pub fn post_event(event: Event) -> NativeEventOperation {
+    HOOK_HANDLER.post_event(event)
+}
+
+/// Runs `task` on the thread that owns the hooks.
+///
+/// Some Windows APIs need to be called on the thread that installed the hooks.
+/// `task` is queued and run by the message loop of the hook thread, and this function returns
+/// without waiting for it to finish.
+///
+/// # Deadlock
+///
+/// While `task` is running, the hook thread cannot process any input events, so every keyboard
+/// and mouse input in the system is delayed. Do not block in `task`, especially do not wait for
+/// an input event or for a hook callback to finish, as this will never be resolved.
+///
+/// # Panics
+///
+/// Panics if the hook is not installed.
+///
+/// # Example
+///
+/// ```no_run
+/// let rx = hookmap_core::install_hook();
+/// hookmap_core::run_on_hook_thread(|| println!("{:?}", std::thread::current().id()));
+/// ```
+///
+pub fn run_on_hook_thread(task: impl FnOnce() + Send + 'static) {
+    HOOK_HANDLER.run_on_hook_thread(Box::new(task));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "installs real hooks and sends real input"]
+    fn click_at_events_arrive_in_order() {
+        let origin = mouse::get_position();
+        let target = (origin.0 + 10, origin.1 + 10);
+        let rx = try_install_hook_for(HookTarget::Mouse).unwrap();
+        assert!(mouse::click_at_recursive(
+            target.0,
+            target.1,
+            Button::SideButton2,
+            true
+        ));
+        let events: Vec<_> = (0..4)
+            .map(|_| {
+                let (event, native_handler) = rx.recv().unwrap();
+                native_handler.dispatch();
+                event
+            })
+            .collect();
+        uninstall_hook();
+
+        let position = |event: &Event| match event {
+            Event::Cursor(event) => Some(event.position.into()),
+            _ => None,
+        };
+        let action = |event: &Event| match event {
+            Event::Button(event) if event.target == Button::SideButton2 => Some(event.action),
+            _ => None,
+        };
+        assert_eq!(position(&events[0]), Some(target));
+        assert_eq!(action(&events[1]), Some(ButtonAction::Press));
+        assert_eq!(action(&events[2]), Some(ButtonAction::Release));
+        assert_eq!(position(&events[3]), Some(origin));
+    }
+
+    #[test]
+    #[ignore = "installs real hooks"]
+    fn hook_thread_id_is_that_of_the_hook_thread() {
+        use std::sync::mpsc;
+        use windows::Win32::System::Threading;
+
+        assert_eq!(hook_thread_id(), None);
+        let _rx = try_install_hook_for(HookTarget::Keyboard).unwrap();
+        let thread_id = hook_thread_id();
+        let (tx, rx) = mpsc::channel();
+        run_on_hook_thread(move || tx.send(unsafe { Threading::GetCurrentThreadId() }).unwrap());
+        let on_hook_thread = rx.recv_timeout(Duration::from_secs(1));
+        uninstall_hook();
+
+        assert!(thread_id.is_some_and(|id| id != 0));
+        assert_ne!(thread_id, Some(unsafe { Threading::GetCurrentThreadId() }));
+        assert_eq!(on_hook_thread.ok(), thread_id);
+        assert_eq!(hook_thread_id(), None);
+    }
+
+    #[test]
+    #[ignore = "installs real hooks and sends real input"]
+    fn reinstalled_hooks_still_receive_input() {
+        const TIMEOUT: Duration = Duration::from_secs(1);
+
+        let _rx = try_install_hook_for(HookTarget::All).unwrap();
+        let before = verify_hooks(TIMEOUT);
+        let reinstalled = reinstall_hook();
+        let after = verify_hooks(TIMEOUT);
+        uninstall_hook();
+
+        assert!(before);
+        assert_eq!(reinstalled, Ok(()));
+        assert!(after);
+        assert!(!verify_hooks(Duration::ZERO));
+        assert_eq!(reinstall_hook(), Err(InstallHookError::NotInstalled));
+    }
+
+    #[test]
+    fn generic_modifiers_are_expanded() {
+        let expanded = expand_inputs(&[
+            (Button::Shift, ButtonAction::Press),
+            (Button::A, ButtonAction::Press),
+        ]);
+        assert_eq!(
+            expanded,
+            [
+                (Button::LShift, ButtonAction::Press, None),
+                (Button::RShift, ButtonAction::Press, Some(Button::Shift)),
+                (Button::A, ButtonAction::Press, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn state_is_updated_only_for_sent_inputs() {
+        let expanded = expand_inputs(&[
+            (Button::F21, ButtonAction::Press),
+            (Button::F22, ButtonAction::Press),
+        ]);
+        assume_sent(&expanded, || 1);
+        assert!(Button::F21.is_pressed());
+        assert!(Button::F22.is_released());
+
+        assume_sent(
+            &expand_inputs(&[(Button::F21, ButtonAction::Release)]),
+            || 0,
+        );
+        assert!(Button::F21.is_pressed());
+        assume_sent(
+            &expand_inputs(&[(Button::F21, ButtonAction::Release)]),
+            || 1,
+        );
+        assert!(Button::F21.is_released());
+    }
+
+    #[test]
+    fn state_is_updated_before_sending() {
+        let expanded = expand_inputs(&[
+            (Button::Ctrl, ButtonAction::Press),
+            (Button::F20, ButtonAction::Press),
+            (Button::F20, ButtonAction::Release),
+        ]);
+        assume_sent(&expanded, || {
+            assert!(Button::LCtrl.is_pressed());
+            assert!(Button::Ctrl.is_pressed());
+            assert!(Button::F20.is_released());
+            // Only the press of `LCtrl` is inserted.
+            1
+        });
+        assert!(Button::LCtrl.is_pressed());
+        assert!(Button::RCtrl.is_released());
+        assert!(Button::F20.is_released());
+
+        // A button sent twice in a batch is rolled back to the state before the batch.
+        let expanded = expand_inputs(&[
+            (Button::LCtrl, ButtonAction::Release),
+            (Button::LCtrl, ButtonAction::Press),
+            (Button::LCtrl, ButtonAction::Release),
+        ]);
+        assume_sent(&expanded, || 0);
+        assert!(Button::LCtrl.is_pressed());
+        assume_sent(&expanded, || 3);
+        assert!(Button::LCtrl.is_released());
+    }
+
+    #[test]
+    fn pressed_buttons_reflect_sent_inputs() {
+        let press = [(Button::F24, ButtonAction::Press)];
+        assume_sent(&expand_inputs(&press), || press.len());
+        assert!(pressed_buttons().contains(&Button::F24));
+
+        let release = [(Button::F24, ButtonAction::Release)];
+        assume_sent(&expand_inputs(&release), || release.len());
+        assert!(!pressed_buttons().contains(&Button::F24));
+    }
+
+    #[test]
+    fn injection_tags_are_distinguished() {
+        let (first, second) = (1, u32::MAX);
+        for flags in [0, SHOULD_BE_IGNORED_FLAG, INJECTED_FLAG, FLAG_MASK] {
+            let extra_info = encode_extra_info(first, flags);
+            assert_eq!(decode_extra_info(first, extra_info), flags);
+            assert_eq!(decode_extra_info(second, extra_info), 0);
+
+            let extra_info = encode_extra_info(second, flags);
+            assert_eq!(decode_extra_info(second, extra_info), flags);
+            assert_eq!(decode_extra_info(first, extra_info), 0);
+        }
+        // Values set by other software are not mistaken for flags.
+        assert_eq!(decode_extra_info(first, 0xFF51_5700 | INJECTED_FLAG), 0);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn sequence_numbers_do_not_affect_tags() {
+        let tag = u32::MAX;
+        for sequence in [0, 1, (1 << 30) - 1] {
+            let extra_info = encode_extra_info(tag, INJECTED_FLAG) | encode_sequence(sequence);
+            assert_eq!(decode_extra_info(tag, extra_info), INJECTED_FLAG);
+            assert_eq!(decode_extra_info(tag - 1, extra_info), 0);
+            assert_eq!(decode_sequence(extra_info), sequence);
+        }
+    }
+}
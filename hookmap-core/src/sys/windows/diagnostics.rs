@@ -0,0 +1,250 @@
+use super::SEQUENCE_SHIFT;
+use crate::button::{Button, ButtonAction};
+use crate::event::InjectionError;
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+type OnError = Arc<dyn Fn(InjectionError) + Send + Sync>;
+
+// The largest sequence number that fits in `dwExtraInfo`. `0` means untracked.
+const MAX_SEQUENCE: u32 = match usize::BITS.checked_sub(SEQUENCE_SHIFT) {
+    Some(bits) if bits > 0 => u32::MAX >> (u32::BITS - bits),
+    _ => 0,
+};
+
+#[derive(Debug)]
+struct Sent {
+    sequence: u32,
+    input: (Button, ButtonAction),
+    sent_at: Instant,
+}
+
+/// Matches the input sent by this process with the events seen by the hook.
+#[derive(Default)]
+struct Tracker {
+    next_sequence: u32,
+    // In the order of `sent_at`.
+    pending: VecDeque<Sent>,
+}
+
+impl Tracker {
+    fn send(&mut self, input: (Button, ButtonAction), now: Instant) -> u32 {
+        self.next_sequence = self.next_sequence % MAX_SEQUENCE + 1;
+        self.pending.push_back(Sent {
+            sequence: self.next_sequence,
+            input,
+            sent_at: now,
+        });
+        self.next_sequence
+    }
+
+    fn receive(
+        &mut self,
+        sequence: u32,
+        actual: Option<(Button, ButtonAction)>,
+        errors: &mut Vec<InjectionError>,
+    ) {
+        if sequence == 0 {
+            return;
+        }
+        let Some(index) = self
+            .pending
+            .iter()
+            .position(|sent| sent.sequence == sequence)
+        else {
+            return;
+        };
+        let expected = self.pending.remove(index).unwrap().input;
+        match actual {
+            Some(actual) if actual == expected => {}
+            Some(actual) => errors.push(InjectionError::Modified { expected, actual }),
+            None => errors.push(InjectionError::Lost {
+                target: expected.0,
+                action: expected.1,
+            }),
+        }
+    }
+
+    fn expire(&mut self, now: Instant, timeout: Duration, errors: &mut Vec<InjectionError>) {
+        while let Some(sent) = self.pending.front() {
+            if now.duration_since(sent.sent_at) <= timeout {
+                break;
+            }
+            let (target, action) = self.pending.pop_front().unwrap().input;
+            errors.push(InjectionError::Lost { target, action });
+        }
+    }
+}
+
+struct Diagnostics {
+    on_error: OnError,
+    tracker: Tracker,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static DIAGNOSTICS: Mutex<Option<Diagnostics>> = Mutex::new(None);
+
+fn lock() -> MutexGuard<'static, Option<Diagnostics>> {
+    DIAGNOSTICS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(super) fn enable(on_error: OnError) {
+    *lock() = Some(Diagnostics {
+        on_error,
+        tracker: Tracker::default(),
+    });
+    ENABLED.store(MAX_SEQUENCE != 0, Ordering::Relaxed);
+}
+
+pub(super) fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+    *lock() = None;
+}
+
+/// Discards the input waiting to be seen by the hook and restarts the sequence numbers.
+#[cfg(any(test, feature = "test-util"))]
+pub(super) fn reset() {
+    if let Some(diagnostics) = lock().as_mut() {
+        diagnostics.tracker = Tracker::default();
+    }
+}
+
+fn with_tracker<R>(f: impl FnOnce(&mut Tracker, &mut Vec<InjectionError>) -> R) -> Option<R> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let mut errors = Vec::new();
+    let (result, on_error) = {
+        let mut diagnostics = lock();
+        let diagnostics = diagnostics.as_mut()?;
+        let result = f(&mut diagnostics.tracker, &mut errors);
+        (result, Arc::clone(&diagnostics.on_error))
+    };
+    // Called after releasing the lock, since `on_error` may send input, which is tracked.
+    errors.into_iter().for_each(|error| on_error(error));
+    Some(result)
+}
+
+/// Returns the sequence number to tag the input with, or `0` if the diagnostics are disabled.
+pub(super) fn track(target: Button, action: ButtonAction) -> u32 {
+    with_tracker(|tracker, errors| {
+        let now = Instant::now();
+        tracker.expire(now, InjectionError::TIMEOUT, errors);
+        tracker.send((target, action), now)
+    })
+    .unwrap_or(0)
+}
+
+/// Checks an event seen by the hook. `sequence` is the one decoded from `dwExtraInfo`,
+/// and `actual` is the button event it represents, if any.
+pub(super) fn check(sequence: u32, actual: Option<(Button, ButtonAction)>) {
+    with_tracker(|tracker, errors| {
+        tracker.receive(sequence, actual, errors);
+        tracker.expire(Instant::now(), InjectionError::TIMEOUT, errors);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    fn press(target: Button) -> (Button, ButtonAction) {
+        (target, ButtonAction::Press)
+    }
+
+    #[test]
+    fn unmodified_input_is_not_reported() {
+        let now = Instant::now();
+        let mut tracker = Tracker::default();
+        let mut errors = Vec::new();
+        let first = tracker.send(press(Button::A), now);
+        let second = tracker.send(press(Button::B), now);
+        assert_ne!(first, second);
+
+        tracker.receive(second, Some(press(Button::B)), &mut errors);
+        tracker.receive(first, Some(press(Button::A)), &mut errors);
+        tracker.receive(0, Some(press(Button::C)), &mut errors);
+        tracker.expire(now + TIMEOUT * 2, TIMEOUT, &mut errors);
+        assert_eq!(errors, []);
+    }
+
+    #[test]
+    fn modified_input_is_reported() {
+        let now = Instant::now();
+        let mut tracker = Tracker::default();
+        let mut errors = Vec::new();
+        let sequence = tracker.send(press(Button::A), now);
+        tracker.receive(sequence, Some(press(Button::B)), &mut errors);
+        tracker.receive(sequence, Some(press(Button::A)), &mut errors);
+        assert_eq!(
+            errors,
+            [InjectionError::Modified {
+                expected: press(Button::A),
+                actual: press(Button::B),
+            }]
+        );
+    }
+
+    #[test]
+    fn lost_input_is_reported_after_timeout() {
+        let now = Instant::now();
+        let mut tracker = Tracker::default();
+        let mut errors = Vec::new();
+        tracker.send(press(Button::A), now);
+        let late = tracker.send(press(Button::B), now + TIMEOUT);
+
+        tracker.expire(now + TIMEOUT, TIMEOUT, &mut errors);
+        assert_eq!(errors, []);
+        tracker.expire(now + TIMEOUT * 3 / 2, TIMEOUT, &mut errors);
+        assert_eq!(
+            errors,
+            [InjectionError::Lost {
+                target: Button::A,
+                action: ButtonAction::Press,
+            }]
+        );
+        tracker.receive(late, Some(press(Button::B)), &mut errors);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn sequence_numbers_wrap_around() {
+        let now = Instant::now();
+        let mut tracker = Tracker {
+            next_sequence: MAX_SEQUENCE - 1,
+            ..Default::default()
+        };
+        assert_eq!(tracker.send(press(Button::A), now), MAX_SEQUENCE);
+        assert_eq!(tracker.send(press(Button::A), now), 1);
+    }
+
+    #[test]
+    fn on_error_can_send_input() {
+        if MAX_SEQUENCE == 0 {
+            return;
+        }
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        enable(Arc::new(move |error| {
+            // Stands in for sending input, which is tracked.
+            track(Button::F18, ButtonAction::Release);
+            let _ = tx.lock().unwrap().send(error);
+        }));
+
+        let sequence = track(Button::F17, ButtonAction::Press);
+        check(sequence, Some((Button::F18, ButtonAction::Press)));
+        disable();
+
+        let errors: Vec<_> = rx.try_iter().collect();
+        assert!(errors.contains(&InjectionError::Modified {
+            expected: (Button::F17, ButtonAction::Press),
+            actual: (Button::F18, ButtonAction::Press),
+        }));
+    }
+}
@@ -1,18 +1,27 @@
-use super::input::Input;
-use super::{vkcode, INJECTED_FLAG, SHOULD_BE_IGNORED_FLAG};
-use crate::button::{Button, ButtonAction};
+use super::input::{self, Input};
+use super::session::SessionWindow;
+use super::{
+    diagnostics, injection_flags, injection_sequence, is_high_priority_hook, is_physical_numpad,
+    record_injection, vkcode, INJECTED_FLAG, SHOULD_BE_IGNORED_FLAG,
+};
+use crate::button::{Button, ButtonAction, ButtonKind};
 use crate::event::{
-    ButtonEvent, CursorEvent, Event, EventSender, NativeEventOperation, WheelEvent,
+    ButtonEvent, CursorEvent, Event, EventSender, HookTarget, InstallHookError,
+    NativeEventOperation, WheelEvent,
 };
 
 use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::mpsc::Sender;
-use std::sync::{mpsc, Mutex};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard, PoisonError, RwLock};
 use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
-use windows::Win32::System::Threading;
-use windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY;
+use windows::Win32::System::{SystemInformation, Threading};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    self, VIRTUAL_KEY, VK_LSHIFT, VK_NUMLOCK, VK_PAUSE, VK_PROCESSKEY, VK_RSHIFT,
+};
 use windows::Win32::UI::WindowsAndMessaging;
 
 // For many constants.
@@ -20,78 +29,278 @@ use windows::Win32::UI::WindowsAndMessaging::*;
 
 type HookProc = unsafe extern "system" fn(code: i32, WPARAM, LPARAM) -> LRESULT;
 
+type Task = Box<dyn FnOnce() + Send>;
+
+/// Handles of the keyboard and mouse hooks.
+type HookHandles = (Option<HHOOK>, Option<HHOOK>);
+
+/// Hook handles and the id of the thread that installed them.
+type InstalledHooks = (Option<HHOOK>, Option<HHOOK>, u32);
+
+// A message posted to the hook thread to run the queued tasks.
+const WM_RUN_TASKS: u32 = WM_APP;
+
+#[derive(Default)]
+struct TaskQueue(Mutex<Vec<Task>>);
+
+impl TaskQueue {
+    fn push(&self, task: Task) {
+        self.0.lock().unwrap().push(task);
+    }
+
+    fn run_all(&self) {
+        let tasks = std::mem::take(&mut *self.0.lock().unwrap());
+        tasks.into_iter().for_each(|task| task());
+    }
+}
+
+impl std::fmt::Debug for TaskQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TaskQueue")
+    }
+}
+
+type ButtonFilter = Arc<dyn Fn(Button) -> bool + Send + Sync>;
+
+#[derive(Default)]
+struct ButtonFilterSlot {
+    filter: RwLock<Option<ButtonFilter>>,
+    // Whether `filter` is set, so that the hook procedure does not lock it while it is not.
+    is_set: AtomicBool,
+}
+
+impl std::fmt::Debug for ButtonFilterSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ButtonFilterSlot")
+    }
+}
+
+/// Installs the hooks of `target` on the current thread. If one of them fails, the other is
+/// uninstalled.
+unsafe fn set_hooks(
+    target: HookTarget,
+    keyboard_hook_proc: HookProc,
+    mouse_hook_proc: HookProc,
+) -> Result<HookHandles, InstallHookError> {
+    let install = |id, hook_proc: HookProc| {
+        WindowsAndMessaging::SetWindowsHookExW(id, Some(hook_proc), HINSTANCE(0), 0)
+            .map_err(|e| InstallHookError::Os(e.code().0))
+    };
+
+    let keyboard_hook_handler = target
+        .includes_keyboard()
+        .then(|| install(WH_KEYBOARD_LL, keyboard_hook_proc))
+        .transpose()?;
+
+    let mouse_hook_handler = match target
+        .includes_mouse()
+        .then(|| install(WH_MOUSE_LL, mouse_hook_proc))
+        .transpose()
+    {
+        Ok(handler) => handler,
+        Err(e) => {
+            if let Some(keyboard_hook_handler) = keyboard_hook_handler {
+                WindowsAndMessaging::UnhookWindowsHookEx(keyboard_hook_handler);
+            }
+            return Err(e);
+        }
+    };
+    Ok((keyboard_hook_handler, mouse_hook_handler))
+}
+
+/// Counts the calls of each hook procedure and records the tick count of the last one, which
+/// show that the hooks are alive.
+#[derive(Debug, Default)]
+struct HookCalls {
+    keyboard: AtomicU32,
+    mouse: AtomicU32,
+    last_tick: AtomicU32,
+}
+
+impl HookCalls {
+    fn get(&self) -> (u32, u32) {
+        (
+            self.keyboard.load(Ordering::Relaxed),
+            self.mouse.load(Ordering::Relaxed),
+        )
+    }
+
+    fn keyboard_called(&self) {
+        self.keyboard.fetch_add(1, Ordering::Relaxed);
+        self.touch();
+    }
+
+    fn mouse_called(&self) {
+        self.mouse.fetch_add(1, Ordering::Relaxed);
+        self.touch();
+    }
+
+    /// Records the current tick count as the time of the last call. This is also done when
+    /// the hooks are installed, so that the input before it is not mistaken for missed input.
+    fn touch(&self) {
+        let tick = unsafe { SystemInformation::GetTickCount() };
+        self.last_tick.store(tick, Ordering::Relaxed);
+    }
+
+    /// Returns `true` if a hook procedure was called at or after the tick count `tick`.
+    fn has_seen(&self, tick: u32) -> bool {
+        is_at_or_after(self.last_tick.load(Ordering::Relaxed), tick)
+    }
+
+    /// Returns `true` if a hook of `target` is called within `timeout` for the input the
+    /// system received at the tick count `last_input`.
+    ///
+    /// The hooks are called after the system records the input, so they are alive if one of
+    /// them was called since then. With both hooks installed, no input is sent. With only
+    /// one, the input may have come from the other device, so the hook is checked with
+    /// `probe` if it has not seen it. The probe is only sent while the user is active, so it
+    /// does not keep an idle system awake.
+    fn verify(
+        &self,
+        target: HookTarget,
+        last_input: Option<u32>,
+        timeout: Duration,
+        probe: impl FnOnce() -> bool,
+    ) -> bool {
+        // The check is skipped if the system does not tell.
+        let Some(last_input) = last_input else {
+            return true;
+        };
+        let deadline = Instant::now() + timeout;
+        while !self.has_seen(last_input) {
+            if Instant::now() >= deadline {
+                return target != HookTarget::All && probe();
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        true
+    }
+}
+
+/// Compares 32-bit tick counts, which wrap around after about 49.7 days.
+fn is_at_or_after(tick: u32, since: u32) -> bool {
+    tick.wrapping_sub(since) as i32 >= 0
+}
+
 #[derive(Debug)]
 struct Inner {
-    keyboard_hook_handler: HHOOK,
-    mouse_hook_handler: HHOOK,
+    target: HookTarget,
+    keyboard_hook_handler: Option<HHOOK>,
+    mouse_hook_handler: Option<HHOOK>,
+    keyboard_hook_proc: HookProc,
+    mouse_hook_proc: HookProc,
     event_sender: EventSender,
     join_handle: JoinHandle<()>,
     thread_id: u32,
+    tasks: Arc<TaskQueue>,
 }
 
 impl Inner {
     fn spawn_thread(
-        tx: Sender<(HHOOK, HHOOK, u32)>,
+        tx: Sender<Result<InstalledHooks, InstallHookError>>,
+        target: HookTarget,
         keyboard_hook_proc: HookProc,
         mouse_hook_proc: HookProc,
+        tasks: Arc<TaskQueue>,
     ) -> JoinHandle<()> {
         thread::spawn(move || unsafe {
-            let keyboard_hook_handler = WindowsAndMessaging::SetWindowsHookExW(
-                WH_KEYBOARD_LL,
-                Some(keyboard_hook_proc),
-                HINSTANCE(0),
-                0,
-            )
-            .expect("Failed to install keyboard hook.");
+            if is_high_priority_hook() {
+                // Failing to raise the priority is not fatal, so the result is ignored.
+                Threading::SetThreadPriority(
+                    Threading::GetCurrentThread(),
+                    Threading::THREAD_PRIORITY_HIGHEST,
+                );
+            }
 
-            let mouse_hook_handler = WindowsAndMessaging::SetWindowsHookExW(
-                WH_MOUSE_LL,
-                Some(mouse_hook_proc),
-                HINSTANCE(0),
-                0,
-            )
-            .expect("Failed to install mouse hook.");
+            let (keyboard_hook_handler, mouse_hook_handler) =
+                match set_hooks(target, keyboard_hook_proc, mouse_hook_proc) {
+                    Ok(handlers) => handlers,
+                    Err(e) => {
+                        let _ = tx.send(Err(e));
+                        return;
+                    }
+                };
 
             let thread_id = Threading::GetCurrentThreadId();
 
-            tx.send((keyboard_hook_handler, mouse_hook_handler, thread_id))
+            tx.send(Ok((keyboard_hook_handler, mouse_hook_handler, thread_id)))
                 .unwrap();
 
-            WindowsAndMessaging::GetMessageW(
-                &mut MaybeUninit::zeroed().assume_init(),
-                HWND(0),
-                0,
-                0,
-            );
+            // Destroyed when the message loop ends.
+            let _session_window = SessionWindow::create();
+
+            let mut msg = MaybeUninit::zeroed().assume_init();
+            while WindowsAndMessaging::GetMessageW(&mut msg, HWND(0), 0, 0).0 > 0 {
+                if msg.message == WM_RUN_TASKS {
+                    tasks.run_all();
+                } else {
+                    WindowsAndMessaging::DispatchMessageW(&msg);
+                }
+            }
         })
     }
 
     fn new(
         event_sender: EventSender,
+        target: HookTarget,
         keyboard_hook_proc: HookProc,
         mouse_hook_proc: HookProc,
-    ) -> Self {
+    ) -> Result<Self, InstallHookError> {
         let (tx, rx) = mpsc::channel();
+        let tasks = Arc::default();
 
-        let join_handle = Self::spawn_thread(tx, keyboard_hook_proc, mouse_hook_proc);
-        let (keyboard_hook_handler, mouse_hook_handler, thread_id) = rx.recv().unwrap();
+        let join_handle = Self::spawn_thread(
+            tx,
+            target,
+            keyboard_hook_proc,
+            mouse_hook_proc,
+            Arc::clone(&tasks),
+        );
+        let (keyboard_hook_handler, mouse_hook_handler, thread_id) = match rx.recv().unwrap() {
+            Ok(handlers) => handlers,
+            Err(e) => {
+                join_handle.join().unwrap();
+                return Err(e);
+            }
+        };
 
-        Inner {
+        Ok(Inner {
+            target,
             keyboard_hook_handler,
             mouse_hook_handler,
+            keyboard_hook_proc,
+            mouse_hook_proc,
             event_sender,
             join_handle,
             thread_id,
+            tasks,
+        })
+    }
+
+    fn run_on_hook_thread(&self, task: Task) {
+        self.tasks.push(task);
+        unsafe {
+            WindowsAndMessaging::PostThreadMessageW(
+                self.thread_id,
+                WM_RUN_TASKS,
+                WPARAM(0),
+                LPARAM(0),
+            )
+            .unwrap();
         }
     }
 
     fn uninstall(self) {
         unsafe {
-            WindowsAndMessaging::UnhookWindowsHookEx(self.keyboard_hook_handler)
-                .expect("Failed to uninstall keyboard hook.");
+            if let Some(keyboard_hook_handler) = self.keyboard_hook_handler {
+                WindowsAndMessaging::UnhookWindowsHookEx(keyboard_hook_handler)
+                    .expect("Failed to uninstall keyboard hook.");
+            }
 
-            WindowsAndMessaging::UnhookWindowsHookEx(self.mouse_hook_handler)
-                .expect("Failed to uninstall mouse hook.");
+            if let Some(mouse_hook_handler) = self.mouse_hook_handler {
+                WindowsAndMessaging::UnhookWindowsHookEx(mouse_hook_handler)
+                    .expect("Failed to uninstall mouse hook.");
+            }
 
             WindowsAndMessaging::PostThreadMessageW(self.thread_id, WM_QUIT, WPARAM(0), LPARAM(0))
                 .unwrap();
@@ -103,6 +312,8 @@ impl Inner {
 #[derive(Debug, Default)]
 pub(super) struct HookHandler {
     inner: Mutex<Option<Inner>>,
+    button_filter: ButtonFilterSlot,
+    calls: HookCalls,
 }
 
 impl HookHandler {
@@ -113,32 +324,153 @@ impl HookHandler {
     pub(super) fn install(
         &self,
         event_sender: EventSender,
+        target: HookTarget,
         keyboard_hook_proc: HookProc,
         mouse_hook_proc: HookProc,
-    ) {
-        let mut hook = self.inner.lock().unwrap();
-        assert!(hook.is_none(), "Hooks are already installed.");
+    ) -> Result<(), InstallHookError> {
+        let mut hook = self.lock_inner();
+        if hook.is_some() {
+            return Err(InstallHookError::AlreadyInstalled);
+        }
 
         *hook = Some(Inner::new(
             event_sender,
+            target,
             keyboard_hook_proc,
             mouse_hook_proc,
-        ));
+        )?);
+        self.calls.touch();
+        Ok(())
+    }
+
+    // `Inner` is replaced as a whole, so it is consistent even if a thread panicked
+    // while holding the lock. Recovering from poisoning keeps the hooks working.
+    fn lock_inner(&self) -> MutexGuard<'_, Option<Inner>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(super) fn set_button_filter(&self, filter: Option<ButtonFilter>) {
+        let mut slot = self.button_filter.filter.write().unwrap();
+        self.button_filter
+            .is_set
+            .store(filter.is_some(), Ordering::Release);
+        *slot = filter;
+    }
+
+    fn is_filtered_out(&self, button: Button) -> bool {
+        if !self.button_filter.is_set.load(Ordering::Acquire) {
+            return false;
+        }
+        match &*self.button_filter.filter.read().unwrap() {
+            Some(filter) => !filter(button),
+            None => false,
+        }
+    }
+
+    pub(super) fn installed_target(&self) -> Option<HookTarget> {
+        self.lock_inner().as_ref().map(|inner| inner.target)
+    }
+
+    pub(super) fn thread_id(&self) -> Option<u32> {
+        self.lock_inner().as_ref().map(|inner| inner.thread_id)
     }
 
     pub(super) fn uninstall(&self) {
-        self.inner
-            .lock()
-            .unwrap()
+        self.lock_inner()
             .take()
             .expect("Hooks are not installed.")
             .uninstall();
     }
 
+    /// Returns `true` if the hooks are called within `timeout` for the last input the system
+    /// received. See [`HookCalls::verify`].
+    pub(super) fn verify(&self, timeout: Duration) -> bool {
+        let Some(target) = self.installed_target() else {
+            return false;
+        };
+        self.calls
+            .verify(target, input::last_input_tick(), timeout, || {
+                self.probe(target, timeout)
+            })
+    }
+
+    /// Sends input that only the hooks see, and returns `true` if each hook of `target` is
+    /// called within `timeout`.
+    fn probe(&self, target: HookTarget, timeout: Duration) -> bool {
+        let (keyboard, mouse) = (target.includes_keyboard(), target.includes_mouse());
+        let before = self.calls.get();
+        input::send_probe(keyboard, mouse);
+        let deadline = Instant::now() + timeout;
+        loop {
+            let after = self.calls.get();
+            if (!keyboard || after.0 != before.0) && (!mouse || after.1 != before.1) {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Uninstalls the hooks and installs them again on the hook thread, keeping the events
+    /// sent to the same receiver.
+    pub(super) fn reinstall(&self) -> Result<(), InstallHookError> {
+        let (tx, rx) = mpsc::channel();
+        let thread_id = {
+            let inner = self.lock_inner();
+            let inner = inner.as_ref().ok_or(InstallHookError::NotInstalled)?;
+            let (target, keyboard_hook_proc, mouse_hook_proc) = (
+                inner.target,
+                inner.keyboard_hook_proc,
+                inner.mouse_hook_proc,
+            );
+            let old = (inner.keyboard_hook_handler, inner.mouse_hook_handler);
+            // The old hooks are uninstalled first, so that no event is hooked twice. They may
+            // have been removed by the OS already, so failing to uninstall them is ignored.
+            // Posted while holding the lock, so that this runs before uninstalling.
+            inner.run_on_hook_thread(Box::new(move || unsafe {
+                [old.0, old.1].into_iter().flatten().for_each(|handler| {
+                    WindowsAndMessaging::UnhookWindowsHookEx(handler);
+                });
+                let _ = tx.send(set_hooks(target, keyboard_hook_proc, mouse_hook_proc));
+            }));
+            inner.thread_id
+        };
+        // The lock is released while waiting, since the hook procedures run before the task.
+        let result = rx.recv().unwrap_or(Err(InstallHookError::NotInstalled));
+        if let Some(inner) = self.lock_inner().as_mut() {
+            if inner.thread_id == thread_id {
+                (inner.keyboard_hook_handler, inner.mouse_hook_handler) =
+                    result.unwrap_or_default();
+            }
+        }
+        if result.is_ok() {
+            self.calls.touch();
+        }
+        result.map(|_| ())
+    }
+
+    pub(super) fn run_on_hook_thread(&self, task: Task) {
+        self.lock_inner()
+            .as_ref()
+            .expect("Hooks are not installed.")
+            .run_on_hook_thread(task);
+    }
+
+    pub(super) fn post_event(&self, event: Event) -> NativeEventOperation {
+        // The lock is released before waiting so that hooked events are not delayed.
+        let event_sender = self
+            .lock_inner()
+            .as_ref()
+            .expect("Hooks are not installed.")
+            .event_sender
+            .clone();
+        event_sender.send(event)
+    }
+
     fn send_event(&self, event: Event) -> NativeEventOperation {
-        self.inner
-            .lock()
-            .unwrap()
+        self.lock_inner()
             .as_ref()
             .expect("Hooks are not installed.")
             .event_sender
@@ -146,49 +478,194 @@ impl HookHandler {
     }
 }
 
-pub(super) fn create_keyboard_event(hook: &KBDLLHOOKSTRUCT) -> Option<ButtonEvent> {
-    if hook.dwExtraInfo & SHOULD_BE_IGNORED_FLAG != 0 {
+/// Returns the virtual-key code of the physical key that sent a keyboard event,
+/// or `None` if the event is a phantom sent along with another key.
+fn physical_key(hook: &KBDLLHOOKSTRUCT) -> Option<VIRTUAL_KEY> {
+    let vk = VIRTUAL_KEY(hook.vkCode as u16);
+    if hook.flags.0 & LLKHF_INJECTED.0 != 0 {
+        return Some(vk);
+    }
+    let extended = hook.flags.0 & LLKHF_EXTENDED.0 != 0;
+    match (vk, hook.scanCode, extended) {
+        // Keyboards wrap PrintScreen, and the navigation keys pressed while Num Lock is on,
+        // in a fake Shift press and release (`E0 2A`, `E0 AA`). Unlike the real Shift keys,
+        // it has the extended flag.
+        (VK_LSHIFT | VK_RSHIFT, _, true) => None,
+        // Pause and Num Lock share the scan code `45`, and their virtual-key codes swap while
+        // Ctrl is held down, so they are told apart by the extended flag instead.
+        // Pause with Ctrl is reported as Break (`VK_CANCEL`) with the scan code `E0 46`.
+        (_, 0x45, false) | (_, 0x46, true) => Some(VK_PAUSE),
+        (_, 0x45, true) => Some(VK_NUMLOCK),
+        // Some keys of unusual keyboards have only a scan code and are reported as `0` or
+        // `0xFF`, and keys processed by an IME may be reported as `VK_PROCESSKEY`.
+        (VIRTUAL_KEY(0) | VIRTUAL_KEY(0xFF) | VK_PROCESSKEY, scan_code, extended) => {
+            scan_code_to_vk(scan_code, extended)
+        }
+        _ => Some(vk),
+    }
+}
+
+/// Looks up the virtual-key code of a scan code in the current keyboard layout.
+/// Returns `None` if the layout does not map it, so that the key is not mistaken for another.
+fn scan_code_to_vk(scan_code: u32, extended: bool) -> Option<VIRTUAL_KEY> {
+    if scan_code == 0 {
         return None;
     }
+    let scan_code = if extended {
+        0xE000 | scan_code
+    } else {
+        scan_code
+    };
+    let vk = unsafe { KeyboardAndMouse::MapVirtualKeyW(scan_code, MAPVK_VSC_TO_VK_EX) };
+    match VIRTUAL_KEY(vk as u16) {
+        VIRTUAL_KEY(0) | VIRTUAL_KEY(0xFF) | VK_PROCESSKEY => None,
+        vk => Some(vk),
+    }
+}
+
+pub(super) fn create_keyboard_event(hook: &KBDLLHOOKSTRUCT, time: Duration) -> Option<ButtonEvent> {
+    let flags = injection_flags(hook.dwExtraInfo);
     let action = if hook.flags.0 >> 7 == 0 {
         ButtonAction::Press
     } else {
         ButtonAction::Release
     };
-    Some(ButtonEvent {
-        target: vkcode::into_button(VIRTUAL_KEY(hook.vkCode as u16))?,
-        injected: hook.dwExtraInfo & INJECTED_FLAG != 0,
-        action,
-    })
+    let extended = hook.flags.0 & LLKHF_EXTENDED.0 != 0;
+    let target = physical_key(hook)
+        .and_then(|vk| vkcode::into_key_button(vk, extended, is_physical_numpad()));
+    diagnostics::check(
+        injection_sequence(hook.dwExtraInfo),
+        target.map(|target| (target, action)),
+    );
+    if flags & SHOULD_BE_IGNORED_FLAG != 0 {
+        return None;
+    }
+    Some(
+        ButtonEvent::new(target?, action)
+            .injected(flags & INJECTED_FLAG != 0)
+            .source(ButtonKind::Key)
+            .time(time),
+    )
+}
+
+// A hook procedure must pass the message to `CallNextHookEx` without processing it
+// unless `n_code` is `HC_ACTION`. `l_param` may not point to a valid struct in that case.
+#[inline]
+fn should_process(n_code: i32) -> bool {
+    n_code == HC_ACTION as i32
+}
+
+// Input sent by this process is recorded when it is sent, and recorded again when it comes
+// back through the hook so that the hooks see the state as of the event. Otherwise, a hotkey
+// fired by a recursive batch would see the state after the whole batch, e.g. `Shift` released
+// when the batch presses `Shift`, clicks `A` and releases `Shift`.
+#[inline]
+fn record_button_state(event: ButtonEvent) {
+    match event.action {
+        ButtonAction::Press => event.target.assume_pressed(),
+        ButtonAction::Release => event.target.assume_released(),
+    }
 }
 
 #[inline]
 fn common_hook_proc_inner(hook_handler: &HookHandler, event: Event) -> NativeEventOperation {
-    if let Event::Button(ButtonEvent { target, action, .. }) = event {
-        match action {
-            ButtonAction::Press => target.assume_pressed(),
-            ButtonAction::Release => target.assume_released(),
+    if let Event::Button(button_event) = event {
+        record_button_state(button_event);
+        if hook_handler.is_filtered_out(button_event.target) {
+            return NativeEventOperation::Dispatch;
         }
     }
     hook_handler.send_event(event)
 }
 
+/// Windows opens the Start menu when the Windows key is released without any other key seen
+/// since it was pressed. When the other key of a combination is blocked, an unassigned key is
+/// clicked instead, once per press of the Windows key.
+#[derive(Debug, Default)]
+struct StartMenuMask {
+    is_masked: AtomicBool,
+}
+
+impl StartMenuMask {
+    /// Returns `true` if the mask key must be clicked after `event` is handled with `operation`.
+    fn needs_mask(
+        &self,
+        event: ButtonEvent,
+        operation: NativeEventOperation,
+        is_windows_key_held: bool,
+    ) -> bool {
+        if event.action == ButtonAction::Release {
+            return false;
+        }
+        if matches!(event.target, Button::LSuper | Button::RSuper) {
+            self.is_masked.store(false, Ordering::Relaxed);
+            return false;
+        }
+        is_windows_key_held
+            && operation == NativeEventOperation::Block
+            && !self.is_masked.swap(true, Ordering::Relaxed)
+    }
+}
+
+static START_MENU_MASK: StartMenuMask = StartMenuMask {
+    is_masked: AtomicBool::new(false),
+};
+
+/// Returns the number of milliseconds elapsed since the system started.
+pub(super) fn current_tick_count() -> u64 {
+    unsafe { SystemInformation::GetTickCount64() }
+}
+
+/// The hooks report the time of an event as the 32-bit tick count, which wraps around after
+/// about 49.7 days. The time is extended to the 64-bit tick count of `GetTickCount64`, which
+/// counts from the same origin, and kept from going backward between the keyboard and mouse
+/// hooks so that the events of both can be ordered by it.
+#[derive(Debug, Default)]
+struct EventClock {
+    last: AtomicU64,
+}
+
+impl EventClock {
+    /// Returns the time of an event stamped with `tick`, given the current 64-bit tick count.
+    fn stamp(&self, tick: u32, now: u64) -> Duration {
+        const WRAP: u64 = 1 << 32;
+        let mut time = now / WRAP * WRAP + u64::from(tick);
+        // The event was stamped before the tick count last wrapped around.
+        if time > now {
+            time = time.saturating_sub(WRAP);
+        }
+        let time = self.last.fetch_max(time, Ordering::Relaxed).max(time);
+        Duration::from_millis(time)
+    }
+}
+
+static EVENT_CLOCK: EventClock = EventClock {
+    last: AtomicU64::new(0),
+};
+
 #[inline]
 pub(super) fn keyboard_hook_proc_inner(
     hook_handler: &HookHandler,
     n_code: i32,
     l_param: LPARAM,
 ) -> NativeEventOperation {
-    if n_code < 0 {
+    hook_handler.calls.keyboard_called();
+    if !should_process(n_code) {
         return NativeEventOperation::Dispatch;
     }
     let hook_struct = unsafe { *(l_param.0 as *const KBDLLHOOKSTRUCT) };
-    let event = match create_keyboard_event(&hook_struct) {
+    record_injection(hook_struct.dwExtraInfo);
+    let time = EVENT_CLOCK.stamp(hook_struct.time, current_tick_count());
+    let event = match create_keyboard_event(&hook_struct, time) {
         None => return NativeEventOperation::Dispatch,
         Some(event) => event,
     };
 
     let native_operation = common_hook_proc_inner(hook_handler, Event::Button(event));
+    let is_windows_key_held = Button::LSuper.is_pressed() || Button::RSuper.is_pressed();
+    if START_MENU_MASK.needs_mask(event, native_operation, is_windows_key_held) {
+        input::send_mask_key();
+    }
     if event.action == ButtonAction::Release {
         return NativeEventOperation::Dispatch;
     }
@@ -202,6 +679,13 @@ enum MouseEventTarget {
     Wheel,
 }
 
+// The high-order word of `mouseData` specifies which X button was pressed or released.
+fn x_button(hook: &MSLLHOOKSTRUCT) -> MOUSEHOOKSTRUCTEX_MOUSE_DATA {
+    MOUSEHOOKSTRUCTEX_MOUSE_DATA(hook.mouseData.0 >> 16)
+}
+
+// Rotating the wheel is reported as `WM_MOUSEWHEEL`, while clicking it is reported as
+// `WM_MBUTTONDOWN` and `WM_MBUTTONUP` like the other buttons.
 fn into_mouse_event_target(w_param: WPARAM, hook: &MSLLHOOKSTRUCT) -> Option<MouseEventTarget> {
     let mouse_button = match w_param.0 as u32 {
         WM_MOUSEWHEEL => return Some(MouseEventTarget::Wheel),
@@ -209,8 +693,11 @@ fn into_mouse_event_target(w_param: WPARAM, hook: &MSLLHOOKSTRUCT) -> Option<Mou
         WM_LBUTTONDOWN | WM_LBUTTONUP => Button::LeftButton,
         WM_RBUTTONDOWN | WM_RBUTTONUP => Button::RightButton,
         WM_MBUTTONDOWN | WM_MBUTTONUP => Button::MiddleButton,
-        WM_XBUTTONDOWN | WM_XBUTTONUP if hook.mouseData == XBUTTON1 => Button::SideButton1,
-        WM_XBUTTONDOWN | WM_XBUTTONUP if hook.mouseData == XBUTTON2 => Button::SideButton2,
+        WM_XBUTTONDOWN | WM_XBUTTONUP => match x_button(hook) {
+            XBUTTON1 => Button::SideButton1,
+            XBUTTON2 => Button::SideButton2,
+            _ => return None,
+        },
         _ => return None,
     };
     Some(MouseEventTarget::Button(mouse_button))
@@ -226,27 +713,64 @@ fn into_mouse_button_action(w_param: WPARAM) -> Option<ButtonAction> {
     }
 }
 
-fn create_mouse_event(input: &Input, w_param: WPARAM, hook: MSLLHOOKSTRUCT) -> Option<Event> {
-    if hook.dwExtraInfo & SHOULD_BE_IGNORED_FLAG != 0 {
+/// `prev` is the position of the previous cursor event, which is `None` for the first one.
+fn create_cursor_event(
+    prev: Option<(i32, i32)>,
+    current: (i32, i32),
+    elapsed: Option<Duration>,
+    injected: bool,
+    time: Duration,
+) -> CursorEvent {
+    CursorEvent::new(
+        prev.map_or((0, 0), |prev| (current.0 - prev.0, current.1 - prev.1))
+            .into(),
+        current.into(),
+    )
+    .elapsed(elapsed)
+    .injected(injected)
+    .time(time)
+}
+
+fn create_mouse_event(
+    input: &Input,
+    w_param: WPARAM,
+    hook: MSLLHOOKSTRUCT,
+    time: Duration,
+) -> Option<Event> {
+    let flags = injection_flags(hook.dwExtraInfo);
+    let target = into_mouse_event_target(w_param, &hook);
+    let button_event = match target {
+        Some(MouseEventTarget::Button(button)) => {
+            into_mouse_button_action(w_param).map(|action| (button, action))
+        }
+        _ => None,
+    };
+    diagnostics::check(injection_sequence(hook.dwExtraInfo), button_event);
+    if flags & SHOULD_BE_IGNORED_FLAG != 0 {
         return None;
     }
-    let injected = hook.dwExtraInfo & INJECTED_FLAG != 0;
-    let event = match into_mouse_event_target(w_param, &hook)? {
+    let injected = flags & INJECTED_FLAG != 0;
+    let event = match target? {
         MouseEventTarget::Wheel => {
-            let delta = (hook.mouseData.0 as i32 >> 16) / WHEEL_DELTA as i32;
-            Event::Wheel(WheelEvent { delta, injected })
+            let delta = input.accumulate_wheel(hook.mouseData.0 as i32 >> 16);
+            Event::Wheel(WheelEvent::new(delta).injected(injected).time(time))
         }
         MouseEventTarget::Cursor => {
-            let prev = input.cursor_position();
-            let current = hook.pt;
-            let delta = (current.x - prev.0, current.y - prev.1);
-            Event::Cursor(CursorEvent { delta, injected })
+            // The delta is computed from `pt` of the events rather than `GetCursorPos`, which
+            // may not have been updated yet, so that the deltas add up to the positions.
+            let current = (hook.pt.x, hook.pt.y);
+            let prev = input.swap_cursor_position(current);
+            let elapsed = input
+                .swap_cursor_time(hook.time)
+                .map(|prev_time| Duration::from_millis(hook.time.wrapping_sub(prev_time).into()));
+            Event::Cursor(create_cursor_event(prev, current, elapsed, injected, time))
         }
-        MouseEventTarget::Button(button) => Event::Button(ButtonEvent {
-            target: button,
-            action: into_mouse_button_action(w_param)?,
-            injected,
-        }),
+        MouseEventTarget::Button(button) => Event::Button(
+            ButtonEvent::new(button, into_mouse_button_action(w_param)?)
+                .injected(injected)
+                .source(ButtonKind::Mouse)
+                .time(time),
+        ),
     };
     Some(event)
 }
@@ -259,13 +783,552 @@ pub(super) fn mouse_hook_proc_inner(
     w_param: WPARAM,
     l_param: LPARAM,
 ) -> NativeEventOperation {
-    if n_code < 0 {
+    hook_handler.calls.mouse_called();
+    if !should_process(n_code) {
         return NativeEventOperation::Dispatch;
     }
     let hook_struct = unsafe { *(l_param.0 as *const MSLLHOOKSTRUCT) };
-    let event = match create_mouse_event(input, w_param, hook_struct) {
+    record_injection(hook_struct.dwExtraInfo);
+    let time = EVENT_CLOCK.stamp(hook_struct.time, current_tick_count());
+    let event = match create_mouse_event(input, w_param, hook_struct, time) {
         None => return NativeEventOperation::Dispatch,
         Some(event) => event,
     };
     common_hook_proc_inner(hook_handler, event)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        assume_sent, encode_extra_info, expand_inputs, injection_tag, last_injection_verdict,
+        reset_injection_state,
+    };
+    use super::*;
+    use crate::event::{Delta, Position};
+    use windows::Win32::Foundation::POINT;
+
+    unsafe extern "system" fn pass_through(
+        n_code: i32,
+        w_param: WPARAM,
+        l_param: LPARAM,
+    ) -> LRESULT {
+        WindowsAndMessaging::CallNextHookEx(HHOOK(0), n_code, w_param, l_param)
+    }
+
+    #[test]
+    fn hook_procs_pass_through_negative_codes() {
+        // `l_param` is not dereferenced, and the uninstalled handler is not accessed.
+        let handler = HookHandler::new();
+        assert_eq!(
+            keyboard_hook_proc_inner(&handler, -1, LPARAM(0)),
+            NativeEventOperation::Dispatch
+        );
+        assert_eq!(
+            mouse_hook_proc_inner(&handler, &Input::new(), -1, WPARAM(0), LPARAM(0)),
+            NativeEventOperation::Dispatch
+        );
+        assert!(should_process(HC_ACTION as i32));
+    }
+
+    fn own_extra_info(flags: usize) -> usize {
+        encode_extra_info(injection_tag(), flags)
+    }
+
+    fn create_hook_struct(mouse_data: u32, dw_extra_info: usize) -> MSLLHOOKSTRUCT {
+        MSLLHOOKSTRUCT {
+            pt: POINT { x: 0, y: 0 },
+            mouseData: MOUSEHOOKSTRUCTEX_MOUSE_DATA(mouse_data),
+            flags: 0,
+            time: 0,
+            dwExtraInfo: dw_extra_info,
+        }
+    }
+
+    fn create_keyboard_hook_struct(vk: VIRTUAL_KEY, scan_code: u32, flags: u32) -> KBDLLHOOKSTRUCT {
+        KBDLLHOOKSTRUCT {
+            vkCode: vk.0.into(),
+            scanCode: scan_code,
+            flags: KBDLLHOOKSTRUCT_FLAGS(flags),
+            time: 0,
+            dwExtraInfo: 0,
+        }
+    }
+
+    fn key_target(vk: VIRTUAL_KEY, scan_code: u32, flags: u32) -> Option<Button> {
+        [flags, flags | LLKHF_UP.0]
+            .map(|flags| {
+                create_keyboard_event(
+                    &create_keyboard_hook_struct(vk, scan_code, flags),
+                    Duration::ZERO,
+                )
+                .map(|event| event.target)
+            })
+            .into_iter()
+            .reduce(|press, release| {
+                assert_eq!(press, release);
+                press
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn keyboard_events_are_tagged_with_their_hook() {
+        let hook = create_keyboard_hook_struct(VK_PAUSE, 0x45, 0);
+        let event = create_keyboard_event(&hook, Duration::ZERO).unwrap();
+        assert_eq!(event.source, ButtonKind::Key);
+        assert!(event.is_keyboard_key());
+    }
+
+    #[test]
+    fn pause_is_coalesced() {
+        use windows::Win32::UI::Input::KeyboardAndMouse::VK_CANCEL;
+
+        let extended = LLKHF_EXTENDED.0;
+        assert_eq!(key_target(VK_PAUSE, 0x45, 0), Some(Button::Pause));
+        // Ctrl+Pause
+        assert_eq!(key_target(VK_CANCEL, 0x46, extended), Some(Button::Pause));
+        // Pause encoded as Num Lock without the extended prefix.
+        assert_eq!(key_target(VK_NUMLOCK, 0x45, 0), Some(Button::Pause));
+        // Ctrl+Num Lock is reported as `VK_PAUSE`, but it is not Pause.
+        assert_eq!(key_target(VK_PAUSE, 0x45, extended), None);
+
+        // Input injected by software is taken as is.
+        let injected = LLKHF_INJECTED.0;
+        assert_eq!(key_target(VK_PAUSE, 0, injected), Some(Button::Pause));
+        assert_eq!(key_target(VK_NUMLOCK, 0x45, injected), None);
+    }
+
+    #[test]
+    fn own_input_is_skipped_and_reported() {
+        use crate::event::InjectionVerdict;
+
+        let skipped = own_extra_info(SHOULD_BE_IGNORED_FLAG | INJECTED_FLAG);
+        let mut pause = create_keyboard_hook_struct(VK_PAUSE, 0x45, 0);
+        pause.dwExtraInfo = skipped;
+        assert_eq!(create_keyboard_event(&pause, Duration::ZERO), None);
+
+        // Neither this nor the keys without a button reach the uninstalled handler.
+        let handler = HookHandler::new();
+        let mut unknown = create_keyboard_hook_struct(VIRTUAL_KEY(0), 0, 0);
+        let hook_proc = |hook: &KBDLLHOOKSTRUCT| {
+            let l_param = LPARAM(hook as *const KBDLLHOOKSTRUCT as isize);
+            keyboard_hook_proc_inner(&handler, HC_ACTION as i32, l_param)
+        };
+
+        reset_injection_state();
+        assert_eq!(last_injection_verdict(), None);
+        assert_eq!(hook_proc(&pause), NativeEventOperation::Dispatch);
+        assert_eq!(last_injection_verdict(), Some(InjectionVerdict::Skipped));
+        for (extra_info, verdict) in [
+            (own_extra_info(INJECTED_FLAG), InjectionVerdict::Injected),
+            (
+                encode_extra_info(injection_tag() ^ 1, SHOULD_BE_IGNORED_FLAG),
+                InjectionVerdict::Untagged,
+            ),
+            (0, InjectionVerdict::Untagged),
+        ] {
+            unknown.dwExtraInfo = extra_info;
+            hook_proc(&unknown);
+            assert_eq!(last_injection_verdict(), Some(verdict));
+        }
+        reset_injection_state();
+        assert_eq!(last_injection_verdict(), None);
+    }
+
+    #[test]
+    fn keys_without_virtual_key_code_are_not_misidentified() {
+        let extended = LLKHF_EXTENDED.0;
+        for vk in [VIRTUAL_KEY(0), VIRTUAL_KEY(0xFF), VK_PROCESSKEY] {
+            assert_eq!(key_target(vk, 0, 0), None);
+            assert_eq!(key_target(vk, 0, extended), None);
+            // Input injected by software is taken as is.
+            assert_eq!(key_target(vk, 0x1E, LLKHF_INJECTED.0), None);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "us-keyboard-layout")]
+    fn keys_without_virtual_key_code_are_identified_by_scan_code() {
+        let extended = LLKHF_EXTENDED.0;
+        assert_eq!(key_target(VK_PROCESSKEY, 0x1E, 0), Some(Button::A));
+        assert_eq!(key_target(VIRTUAL_KEY(0xFF), 0x1D, 0), Some(Button::LCtrl));
+        assert_eq!(
+            key_target(VIRTUAL_KEY(0), 0x1D, extended),
+            Some(Button::RCtrl)
+        );
+    }
+
+    #[test]
+    fn print_screen_fake_shift_is_coalesced() {
+        use windows::Win32::UI::Input::KeyboardAndMouse::VK_SNAPSHOT;
+
+        let extended = LLKHF_EXTENDED.0;
+        assert_eq!(key_target(VK_LSHIFT, 0x2A, extended), None);
+        assert_eq!(
+            key_target(VK_SNAPSHOT, 0x37, extended),
+            Some(Button::PrintScreen)
+        );
+        // Alt+PrintScreen sends SysRq.
+        assert_eq!(key_target(VK_SNAPSHOT, 0x54, 0), Some(Button::PrintScreen));
+
+        assert_eq!(key_target(VK_LSHIFT, 0x2A, 0), Some(Button::LShift));
+        assert_eq!(key_target(VK_RSHIFT, 0x36, 0), Some(Button::RShift));
+        assert_eq!(
+            key_target(VK_LSHIFT, 0x2A, extended | LLKHF_INJECTED.0),
+            Some(Button::LShift)
+        );
+    }
+
+    #[test]
+    fn own_input_round_trip_restores_state() {
+        let click = [
+            (Button::F23, ButtonAction::Press),
+            (Button::F23, ButtonAction::Release),
+        ];
+        assume_sent(&expand_inputs(&click), || click.len());
+        assert!(Button::F23.is_released());
+
+        for (target, action) in click {
+            record_button_state(ButtonEvent::new(target, action).injected(true));
+        }
+        assert!(Button::F23.is_released());
+
+        // Input from devices and other software is recorded when it is hooked.
+        let mut event = ButtonEvent::new(Button::F23, ButtonAction::Press);
+        record_button_state(event);
+        assert!(Button::F23.is_pressed());
+        event.action = ButtonAction::Release;
+        record_button_state(event);
+        assert!(Button::F23.is_released());
+    }
+
+    #[test]
+    fn recursive_modifier_is_visible_to_hooks_of_the_same_batch() {
+        let batch = [
+            (Button::LShift, ButtonAction::Press),
+            (Button::F19, ButtonAction::Press),
+            (Button::F19, ButtonAction::Release),
+            (Button::LShift, ButtonAction::Release),
+        ];
+        assume_sent(&expand_inputs(&batch), || batch.len());
+
+        // Stands in for the hook receiving the batch, where a hotkey of `Shift + F19` checks
+        // the state of `LShift` when `F19` is pressed.
+        let mut is_shift_pressed_at_f19 = None;
+        for (target, action) in batch {
+            record_button_state(ButtonEvent::new(target, action).injected(true));
+            if (target, action) == (Button::F19, ButtonAction::Press) {
+                is_shift_pressed_at_f19 = Some(Button::LShift.is_pressed());
+            }
+        }
+        assert_eq!(is_shift_pressed_at_f19, Some(true));
+        assert!(Button::LShift.is_released());
+        assert!(Button::F19.is_released());
+    }
+
+    #[test]
+    fn blocked_windows_key_combination_is_masked_once() {
+        let mask = StartMenuMask::default();
+        let event = |target, action| ButtonEvent::new(target, action);
+        let press = |target| event(target, ButtonAction::Press);
+        let release = |target| event(target, ButtonAction::Release);
+        let (block, dispatch) = (NativeEventOperation::Block, NativeEventOperation::Dispatch);
+
+        assert!(!mask.needs_mask(press(Button::D), block, false));
+        assert!(!mask.needs_mask(press(Button::LSuper), dispatch, false));
+        assert!(!mask.needs_mask(press(Button::E), dispatch, true));
+        assert!(mask.needs_mask(press(Button::D), block, true));
+        assert!(!mask.needs_mask(press(Button::D), block, true));
+        assert!(!mask.needs_mask(release(Button::D), block, true));
+        assert!(!mask.needs_mask(release(Button::LSuper), dispatch, true));
+
+        assert!(!mask.needs_mask(press(Button::RSuper), dispatch, false));
+        assert!(mask.needs_mask(press(Button::D), block, true));
+    }
+
+    #[test]
+    fn mouse_button_events() {
+        let input = Input::new();
+        let cases = [
+            (WM_LBUTTONDOWN, WM_LBUTTONUP, 0, Button::LeftButton),
+            (WM_RBUTTONDOWN, WM_RBUTTONUP, 0, Button::RightButton),
+            (WM_MBUTTONDOWN, WM_MBUTTONUP, 0, Button::MiddleButton),
+            (
+                WM_XBUTTONDOWN,
+                WM_XBUTTONUP,
+                XBUTTON1.0 << 16,
+                Button::SideButton1,
+            ),
+            (
+                WM_XBUTTONDOWN,
+                WM_XBUTTONUP,
+                XBUTTON2.0 << 16,
+                Button::SideButton2,
+            ),
+        ];
+        for (down, up, mouse_data, target) in cases {
+            for (message, action) in [(down, ButtonAction::Press), (up, ButtonAction::Release)] {
+                let hook = create_hook_struct(mouse_data, own_extra_info(INJECTED_FLAG));
+                let event =
+                    create_mouse_event(&input, WPARAM(message as usize), hook, Duration::ZERO);
+                let expected = Event::Button(ButtonEvent::new(target, action).injected(true));
+                assert_eq!(event, Some(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn mouse_wheel_event() {
+        let input = Input::new();
+        let hook = create_hook_struct((-(WHEEL_DELTA as i32) as u32) << 16, 0);
+        let event =
+            create_mouse_event(&input, WPARAM(WM_MOUSEWHEEL as usize), hook, Duration::ZERO);
+        let expected = Event::Wheel(WheelEvent::new(-1));
+        assert_eq!(event, Some(expected));
+    }
+
+    #[test]
+    fn middle_click_and_wheel_rotation_are_distinguished() {
+        let input = Input::new();
+        let notch = WHEEL_DELTA << 16;
+        for message in [WM_MBUTTONDOWN, WM_MBUTTONUP] {
+            // `mouseData` is not used for the middle button even if it is not zero.
+            for mouse_data in [0, notch] {
+                let hook = create_hook_struct(mouse_data, 0);
+                let event =
+                    create_mouse_event(&input, WPARAM(message as usize), hook, Duration::ZERO);
+                assert!(matches!(
+                    event,
+                    Some(Event::Button(ButtonEvent {
+                        target: Button::MiddleButton,
+                        ..
+                    }))
+                ));
+            }
+        }
+        for mouse_data in [notch, (-(WHEEL_DELTA as i32) as u32) << 16] {
+            let hook = create_hook_struct(mouse_data, 0);
+            let event =
+                create_mouse_event(&input, WPARAM(WM_MOUSEWHEEL as usize), hook, Duration::ZERO);
+            assert!(matches!(event, Some(Event::Wheel(_))));
+        }
+    }
+
+    #[test]
+    fn ignored_mouse_events() {
+        let input = Input::new();
+        let hook = create_hook_struct(0, own_extra_info(SHOULD_BE_IGNORED_FLAG));
+        let event = create_mouse_event(
+            &input,
+            WPARAM(WM_LBUTTONDOWN as usize),
+            hook,
+            Duration::ZERO,
+        );
+        assert_eq!(event, None);
+    }
+
+    #[test]
+    fn event_clock_extends_and_orders_tick_counts() {
+        const WRAP: u64 = 1 << 32;
+        let clock = EventClock::default();
+        let millis = |time: Duration| time.as_millis() as u64;
+        assert_eq!(millis(clock.stamp(100, 150)), 100);
+        // Stamped just before the tick count wrapped around.
+        let before_wrap = clock.stamp(u32::MAX - 10, WRAP + 5);
+        assert_eq!(millis(before_wrap), WRAP - 11);
+        assert_eq!(millis(clock.stamp(3, WRAP + 5)), WRAP + 3);
+        // An event reported with an earlier tick does not go back in time.
+        assert_eq!(millis(clock.stamp(1, WRAP + 6)), WRAP + 3);
+    }
+
+    #[test]
+    fn hook_calls_are_compared_with_wrapping_tick_counts() {
+        assert!(is_at_or_after(100, 100));
+        assert!(is_at_or_after(101, 100));
+        assert!(!is_at_or_after(99, 100));
+        // The tick count wrapped around between the input and the call.
+        assert!(is_at_or_after(5, u32::MAX - 5));
+        assert!(!is_at_or_after(u32::MAX - 5, 5));
+    }
+
+    #[test]
+    fn hooks_are_probed_only_for_input_they_may_not_have_seen() {
+        let calls = HookCalls::default();
+        calls.last_tick.store(100, Ordering::Relaxed);
+        let probes = AtomicU32::new(0);
+        let verify = |target, last_input| {
+            calls.verify(target, last_input, Duration::ZERO, || {
+                probes.fetch_add(1, Ordering::Relaxed);
+                true
+            })
+        };
+        assert!(verify(HookTarget::All, Some(100)));
+        assert!(verify(HookTarget::Keyboard, Some(90)));
+        assert!(verify(HookTarget::All, None));
+        assert_eq!(probes.load(Ordering::Relaxed), 0);
+        // Any input reaches one of the hooks, so they missed it.
+        assert!(!verify(HookTarget::All, Some(101)));
+        assert_eq!(probes.load(Ordering::Relaxed), 0);
+        // The input may have come from the mouse.
+        assert!(verify(HookTarget::Keyboard, Some(101)));
+        assert_eq!(probes.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn interleaved_keyboard_and_mouse_events_are_ordered_by_time() {
+        let input = Input::new();
+        let clock = EventClock::default();
+        let now = (1 << 32) + 500;
+        let mut events = vec![];
+        for (i, tick) in [u32::MAX - 20, u32::MAX - 5, 10, 10, 8, 40]
+            .into_iter()
+            .enumerate()
+        {
+            let event = if i % 2 == 0 {
+                let mut hook = create_keyboard_hook_struct(VK_PAUSE, 0x45, 0);
+                hook.time = tick;
+                Event::Button(create_keyboard_event(&hook, clock.stamp(tick, now)).unwrap())
+            } else {
+                let mut hook = create_hook_struct(0, 0);
+                hook.time = tick;
+                let message = if i % 3 == 0 {
+                    WM_MOUSEMOVE
+                } else {
+                    WM_LBUTTONDOWN
+                };
+                let time = clock.stamp(tick, now);
+                create_mouse_event(&input, WPARAM(message as usize), hook, time).unwrap()
+            };
+            events.push(event);
+        }
+
+        let times: Vec<_> = events.iter().map(Event::time).collect();
+        assert!(times.windows(2).all(|pair| pair[0] <= pair[1]));
+        let mut merged = events.clone();
+        merged.sort_by_key(Event::time);
+        assert_eq!(merged, events);
+    }
+
+    #[test]
+    fn events_injected_by_other_instances() {
+        let input = Input::new();
+        let other_tag = injection_tag().wrapping_add(1);
+        for flags in [SHOULD_BE_IGNORED_FLAG, INJECTED_FLAG] {
+            let hook = create_hook_struct(0, encode_extra_info(other_tag, flags));
+            let event = create_mouse_event(
+                &input,
+                WPARAM(WM_LBUTTONDOWN as usize),
+                hook,
+                Duration::ZERO,
+            );
+            let expected = Event::Button(ButtonEvent::new(Button::LeftButton, ButtonAction::Press));
+            assert_eq!(event, Some(expected));
+        }
+    }
+
+    #[test]
+    fn button_filter() {
+        let handler = HookHandler::new();
+        assert!(!handler.is_filtered_out(Button::A));
+
+        handler.set_button_filter(Some(Arc::new(|button| button == Button::A)));
+        assert!(!handler.is_filtered_out(Button::A));
+        assert!(handler.is_filtered_out(Button::B));
+
+        handler.set_button_filter(None);
+        assert!(!handler.is_filtered_out(Button::B));
+    }
+
+    #[test]
+    fn new_handler_is_not_installed() {
+        assert_eq!(HookHandler::new().installed_target(), None);
+    }
+
+    #[test]
+    fn poisoned_handler_is_recovered() {
+        let handler = HookHandler::new();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _guard = handler.inner.lock().unwrap();
+            panic!("poisons the lock");
+        }));
+        assert!(result.is_err());
+        assert!(handler.inner.is_poisoned());
+        assert_eq!(handler.installed_target(), None);
+    }
+
+    #[test]
+    #[ignore = "installs real hooks"]
+    fn install_twice_fails_without_leaking() {
+        let handler = HookHandler::new();
+        let (tx, _rx) = crate::event::channel();
+        let result = handler.install(tx.clone(), HookTarget::Keyboard, pass_through, pass_through);
+        assert_eq!(result, Ok(()));
+        assert_eq!(handler.installed_target(), Some(HookTarget::Keyboard));
+
+        let result = handler.install(tx, HookTarget::All, pass_through, pass_through);
+        assert_eq!(result, Err(InstallHookError::AlreadyInstalled));
+        assert_eq!(handler.installed_target(), Some(HookTarget::Keyboard));
+
+        handler.uninstall();
+        assert_eq!(handler.installed_target(), None);
+    }
+
+    #[test]
+    fn cursor_event_has_the_hook_position_and_the_delta_from_the_previous_one() {
+        let origin = (100, 50);
+        let moves = [(110, 55), (107, 70), (-3, 70)];
+        let expected = [
+            (Delta { dx: 10, dy: 5 }, Position { x: 110, y: 55 }),
+            (Delta { dx: -3, dy: 15 }, Position { x: 107, y: 70 }),
+            (Delta { dx: -110, dy: 0 }, Position { x: -3, y: 70 }),
+        ];
+
+        let mut prev = origin;
+        for (current, (delta, position)) in moves.into_iter().zip(expected) {
+            let event = create_cursor_event(Some(prev), current, None, false, Duration::ZERO);
+            assert_eq!((event.delta, event.position), (delta, position));
+            prev = current;
+        }
+    }
+
+    #[test]
+    fn sub_notch_wheel_rotations_are_accumulated() {
+        let input = Input::new();
+        let step = WHEEL_DELTA as i32 / 6;
+
+        // A precision touchpad rotates a sixth of a notch per event.
+        let deltas: Vec<_> = (0..12).map(|_| input.accumulate_wheel(step)).collect();
+        assert_eq!(deltas, [0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1]);
+
+        // The fraction is discarded when the direction changes.
+        assert_eq!(input.accumulate_wheel(step * 5), 0);
+        assert_eq!(input.accumulate_wheel(-step), 0);
+        assert_eq!(input.accumulate_wheel(-step * 5), -1);
+        assert_eq!(input.accumulate_wheel(-(WHEEL_DELTA as i32) * 2), -2);
+    }
+
+    #[test]
+    fn cursor_delta_is_computed_from_the_previous_event() {
+        let input = Input::new();
+        let cursor = |x, y| {
+            let mut hook = create_hook_struct(0, 0);
+            hook.pt = POINT { x, y };
+            match create_mouse_event(&input, WPARAM(WM_MOUSEMOVE as usize), hook, Duration::ZERO) {
+                Some(Event::Cursor(event)) => event,
+                event => panic!("{event:?}"),
+            }
+        };
+
+        let first = cursor(100, 50);
+        assert_eq!(first.position, Position { x: 100, y: 50 });
+        assert_eq!(first.delta, Delta { dx: 0, dy: 0 });
+        assert_eq!(first.elapsed, None);
+
+        let second = cursor(90, 62);
+        assert_eq!(second.position, Position { x: 90, y: 62 });
+        assert_eq!(second.delta, Delta { dx: -10, dy: 12 });
+
+        input.reset_cursor();
+        assert_eq!(cursor(0, 0).delta, Delta { dx: 0, dy: 0 });
+    }
+}
@@ -1,6 +1,12 @@
-use super::{vkcode, INJECTED_FLAG, SHOULD_BE_IGNORED_FLAG};
+use super::{
+    diagnostics, encode_extra_info, encode_sequence, vkcode, INJECTED_FLAG, INJECTION_TAG,
+    SHOULD_BE_IGNORED_FLAG,
+};
 use crate::button::{Button, ButtonAction, ButtonKind};
 
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
 use std::{mem::MaybeUninit, sync::Mutex};
 
 use windows::Win32::UI::Input::KeyboardAndMouse;
@@ -11,19 +17,23 @@ use windows::Win32::UI::WindowsAndMessaging::*;
 
 const INPUT_MEM_SIZE: i32 = std::mem::size_of::<INPUT>() as i32;
 
+// An unassigned virtual-key code, which no application responds to.
+const VK_MASK: VIRTUAL_KEY = VIRTUAL_KEY(0xE8);
+
 #[inline]
 fn create_dw_extra_info(recursive: bool) -> usize {
-    INJECTED_FLAG | if recursive { 0 } else { SHOULD_BE_IGNORED_FLAG }
+    let flags = INJECTED_FLAG | if recursive { 0 } else { SHOULD_BE_IGNORED_FLAG };
+    encode_extra_info(INJECTION_TAG.load(Ordering::Relaxed), flags)
 }
 
-fn create_mouse_input(mouse_data: i32, dw_flags: MOUSE_EVENT_FLAGS, recursive: bool) -> INPUT {
+fn create_mouse_input(mouse_data: i32, dw_flags: MOUSE_EVENT_FLAGS, extra_info: usize) -> INPUT {
     let input = MOUSEINPUT {
         dx: 0,
         dy: 0,
         mouseData: mouse_data,
         dwFlags: dw_flags,
         time: 0,
-        dwExtraInfo: create_dw_extra_info(recursive),
+        dwExtraInfo: extra_info,
     };
     INPUT {
         r#type: INPUT_MOUSE,
@@ -31,20 +41,130 @@ fn create_mouse_input(mouse_data: i32, dw_flags: MOUSE_EVENT_FLAGS, recursive: b
     }
 }
 
-fn create_input_struct(button: Button, action: ButtonAction, recursive: bool) -> INPUT {
+// Converts a coordinate in pixels into the normalized absolute coordinate (0 to 65535)
+// on the virtual desktop, which spans all monitors.
+fn normalize_absolute(position: i32, origin: i32, size: i32) -> i32 {
+    // Windows maps a normalized coordinate `n` to the pixel `n * size / 65536`,
+    // so rounding up maps it back to exactly `position`.
+    let size = size.max(1);
+    let offset = (position - origin).clamp(0, size - 1) as u64;
+    (offset * 65536).div_ceil(size as u64) as i32
+}
+
+/// Returns the left, top, width and height of the virtual desktop, which spans all monitors.
+pub(super) fn virtual_screen() -> (i32, i32, i32, i32) {
+    unsafe {
+        (
+            WindowsAndMessaging::GetSystemMetrics(SM_XVIRTUALSCREEN),
+            WindowsAndMessaging::GetSystemMetrics(SM_YVIRTUALSCREEN),
+            WindowsAndMessaging::GetSystemMetrics(SM_CXVIRTUALSCREEN),
+            WindowsAndMessaging::GetSystemMetrics(SM_CYVIRTUALSCREEN),
+        )
+    }
+}
+
+fn create_absolute_move_input(x: i32, y: i32, recursive: bool) -> INPUT {
+    let (left, top, width, height) = virtual_screen();
+    let mut input = create_mouse_input(
+        0,
+        MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK,
+        create_dw_extra_info(recursive),
+    );
+    input.Anonymous.mi.dx = normalize_absolute(x, left, width);
+    input.Anonymous.mi.dy = normalize_absolute(y, top, height);
+    input
+}
+
+// Moving with `SendInput` instead of `SetCursorPos` keeps the move and the click in one batch.
+fn create_click_at_inputs(
+    (x, y): (i32, i32),
+    button: Button,
+    restore: Option<(i32, i32)>,
+    recursive: bool,
+) -> Vec<INPUT> {
+    let mut inputs = vec![
+        create_absolute_move_input(x, y, recursive),
+        create_input_struct(button, ButtonAction::Press, recursive, false),
+        create_input_struct(button, ButtonAction::Release, recursive, false),
+    ];
+    if let Some((x, y)) = restore {
+        inputs.push(create_absolute_move_input(x, y, recursive));
+    }
+    inputs
+}
+
+/// Returns the positions of each step when moving from `from` to `to` in `steps` steps.
+/// The last position is always `to`.
+pub(super) fn smooth_path(
+    from: (i32, i32),
+    to: (i32, i32),
+    steps: u32,
+) -> impl Iterator<Item = (i32, i32)> {
+    let steps = i64::from(steps.max(1));
+    let interpolate = move |from: i32, to: i32, i: i64| {
+        from + ((i64::from(to) - i64::from(from)) * i / steps) as i32
+    };
+    (1..=steps).map(move |i| (interpolate(from.0, to.0, i), interpolate(from.1, to.1, i)))
+}
+
+// The scan code is left to the OS except for the keys that need it explicitly.
+fn scan_code(button: Button) -> u16 {
+    match button {
+        // Without its scan code (`E0 37`), `VK_SNAPSHOT` is seen by the hooks and applications
+        // but does not trigger a screenshot.
+        Button::PrintScreen => 0x37,
+        _ => 0,
+    }
+}
+
+// Returns the scan code to send instead of the virtual-key code, or `None` if the key has none.
+fn physical_scan_code(button: Button) -> Option<u16> {
+    match scan_code(button) {
+        // Pause sends `E1 1D 45`, which cannot be expressed in `wScan`.
+        // `45` alone is Num Lock.
+        0 if button == Button::Pause => None,
+        0 => {
+            let vk = vkcode::from_button(button);
+            let scan_code =
+                unsafe { KeyboardAndMouse::MapVirtualKeyW(vk.0.into(), MAPVK_VK_TO_VSC) };
+            (scan_code != 0).then_some(scan_code as u16)
+        }
+        scan_code => Some(scan_code),
+    }
+}
+
+fn create_input_struct(
+    button: Button,
+    action: ButtonAction,
+    recursive: bool,
+    scancode: bool,
+) -> INPUT {
+    let extra_info =
+        create_dw_extra_info(recursive) | encode_sequence(diagnostics::track(button, action));
     match button.kind() {
         ButtonKind::Key => {
-            let flags = match action {
+            let mut flags = match action {
                 ButtonAction::Press => KEYBD_EVENT_FLAGS(0),
                 ButtonAction::Release => KEYEVENTF_KEYUP,
             };
-            let keybd_input = KEYBDINPUT {
+            if button.requires_extended_scancode() {
+                // Without this flag, navigation keys may be sent as the numpad keys.
+                flags |= KEYEVENTF_EXTENDEDKEY;
+            }
+            let mut keybd_input = KEYBDINPUT {
                 wVk: vkcode::from_button(button),
-                wScan: 0,
+                wScan: scan_code(button),
                 dwFlags: flags,
                 time: 0,
-                dwExtraInfo: create_dw_extra_info(recursive),
+                dwExtraInfo: extra_info,
             };
+            if let Some(scan_code) = physical_scan_code(button).filter(|_| scancode) {
+                // Games that read DirectInput or Raw Input identify keys by their scan codes
+                // and ignore the virtual-key code.
+                keybd_input.wVk = VIRTUAL_KEY(0);
+                keybd_input.wScan = scan_code;
+                keybd_input.dwFlags |= KEYEVENTF_SCANCODE;
+            }
             INPUT {
                 r#type: INPUT_KEYBOARD,
                 Anonymous: INPUT_0 { ki: keybd_input },
@@ -71,11 +191,99 @@ fn create_input_struct(button: Button, action: ButtonAction, recursive: bool) ->
                     _ => unreachable!(),
                 },
             };
-            create_mouse_input(mouse_data.0 as i32, dw_flags, recursive)
+            create_mouse_input(mouse_data.0 as i32, dw_flags, extra_info)
         }
     }
 }
 
+fn create_virtual_key_input(vk: VIRTUAL_KEY, action: ButtonAction) -> INPUT {
+    let mut flags = match action {
+        ButtonAction::Press => KEYBD_EVENT_FLAGS(0),
+        ButtonAction::Release => KEYEVENTF_KEYUP,
+    };
+    if vk == VK_NUMLOCK {
+        flags |= KEYEVENTF_EXTENDEDKEY;
+    }
+    INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: vk,
+                wScan: 0,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: create_dw_extra_info(false),
+            },
+        },
+    }
+}
+
+// Each UTF-16 code unit is sent as a packet, which applications receive as a character
+// regardless of the keyboard layout and the state of the modifier keys.
+fn create_text_inputs(text: &str, recursive: bool) -> Vec<INPUT> {
+    let extra_info = create_dw_extra_info(recursive);
+    let input = |unit, flags| INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: VIRTUAL_KEY(0),
+                wScan: unit,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: extra_info,
+            },
+        },
+    };
+    text.encode_utf16()
+        .flat_map(|unit| {
+            [
+                input(unit, KEYEVENTF_UNICODE),
+                input(unit, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP),
+            ]
+        })
+        .collect()
+}
+
+/// Clicks the key of `vk`, which may not be a [`Button`]. The input is not hooked.
+pub(super) fn click_virtual_key(vk: VIRTUAL_KEY) -> bool {
+    let inputs = [
+        create_virtual_key_input(vk, ButtonAction::Press),
+        create_virtual_key_input(vk, ButtonAction::Release),
+    ];
+    let sent = unsafe { KeyboardAndMouse::SendInput(&inputs, INPUT_MEM_SIZE) };
+    sent as usize == inputs.len()
+}
+
+/// Clicks an unassigned key, so that the system sees a key between the press and the release
+/// of the Windows key and does not open the Start menu.
+pub(super) fn send_mask_key() {
+    click_virtual_key(VK_MASK);
+}
+
+/// Sends input that applications ignore, for checking that the hooks still see input:
+/// a click of an unassigned key for the keyboard, and a move by zero for the mouse.
+/// The input is not hooked.
+pub(super) fn send_probe(keyboard: bool, mouse: bool) {
+    if keyboard {
+        click_virtual_key(VK_MASK);
+    }
+    if mouse {
+        let input = create_mouse_input(0, MOUSEEVENTF_MOVE, create_dw_extra_info(false));
+        unsafe { KeyboardAndMouse::SendInput(&[input], INPUT_MEM_SIZE) };
+    }
+}
+
+/// Returns the tick count of the last input the system received, or `None` if it fails.
+pub(super) fn last_input_tick() -> Option<u32> {
+    let mut info = LASTINPUTINFO {
+        cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+        dwTime: 0,
+    };
+    unsafe { KeyboardAndMouse::GetLastInputInfo(&mut info) }
+        .as_bool()
+        .then_some(info.dwTime)
+}
+
 #[inline]
 fn get_cursor_position() -> (i32, i32) {
     unsafe {
@@ -87,39 +295,104 @@ fn get_cursor_position() -> (i32, i32) {
 
 #[derive(Debug)]
 pub(super) struct Input {
-    cursor_position: Mutex<(i32, i32)>,
+    // The position of the last cursor event, or where the program moved the cursor to.
+    cursor_position: Mutex<Option<(i32, i32)>>,
+    cursor_time: Mutex<Option<u32>>,
+    // The rotation of the wheel less than a notch, which is carried over to the next event.
+    wheel_remainder: Mutex<i32>,
 }
 
 impl Input {
     pub(super) fn new() -> Self {
         Self {
-            cursor_position: Mutex::new(get_cursor_position()),
+            cursor_position: Mutex::default(),
+            cursor_time: Mutex::default(),
+            wheel_remainder: Mutex::default(),
         }
     }
 
-    pub(super) fn button_input(&self, button: Button, action: ButtonAction, recursive: bool) {
-        unsafe {
-            KeyboardAndMouse::SendInput(
-                &[create_input_struct(button, action, recursive)],
-                INPUT_MEM_SIZE,
-            );
-        }
+    /// Stores the position of the cursor event and returns the previous one.
+    pub(super) fn swap_cursor_position(&self, position: (i32, i32)) -> Option<(i32, i32)> {
+        self.cursor_position.lock().unwrap().replace(position)
+    }
+
+    /// Stores the time stamp of the cursor event and returns the previous one.
+    pub(super) fn swap_cursor_time(&self, time: u32) -> Option<u32> {
+        self.cursor_time.lock().unwrap().replace(time)
+    }
+
+    /// Returns the number of inputs inserted into the input stream.
+    pub(super) fn button_inputs(
+        &self,
+        inputs: &[(Button, ButtonAction)],
+        recursive: bool,
+        scancode: bool,
+    ) -> usize {
+        let inputs: Vec<_> = inputs
+            .iter()
+            .map(|&(button, action)| create_input_struct(button, action, recursive, scancode))
+            .collect();
+        unsafe { KeyboardAndMouse::SendInput(&inputs, INPUT_MEM_SIZE) as usize }
+    }
+
+    /// Types `text` in a single batch. Returns the number of inputs inserted into the
+    /// input stream.
+    pub(super) fn text_input(&self, text: &str, recursive: bool) -> usize {
+        let inputs = create_text_inputs(text, recursive);
+        unsafe { KeyboardAndMouse::SendInput(&inputs, INPUT_MEM_SIZE) as usize }
+    }
+
+    /// Moves the cursor to `position` and clicks `button` there in a single batch, then moves
+    /// it back if `restore` is `true`. Returns whether the click was inserted.
+    pub(super) fn click_at(
+        &self,
+        position: (i32, i32),
+        button: Button,
+        restore: bool,
+        recursive: bool,
+    ) -> bool {
+        let restore = restore.then(get_cursor_position);
+        let inputs = create_click_at_inputs(position, button, restore, recursive);
+        let sent = unsafe { KeyboardAndMouse::SendInput(&inputs, INPUT_MEM_SIZE) as usize };
+        sent >= 3
     }
 
     pub(super) fn rotate_wheel(&self, speed: i32, recursive: bool) {
         let speed = speed * WHEEL_DELTA as i32;
-        let input = create_mouse_input(speed, MOUSEEVENTF_WHEEL, recursive);
+        let input = create_mouse_input(speed, MOUSEEVENTF_WHEEL, create_dw_extra_info(recursive));
         unsafe {
             KeyboardAndMouse::SendInput(&[input], INPUT_MEM_SIZE);
         }
     }
 
+    /// Adds the rotation of a wheel event in the units of `WHEEL_DELTA`, and returns the
+    /// number of notches completed by it.
+    ///
+    /// Precision touchpads rotate the wheel by a fraction of a notch per event, which is
+    /// carried over to the following events instead of being truncated. The fraction is
+    /// discarded when the wheel is rotated in the other direction.
+    pub(super) fn accumulate_wheel(&self, delta: i32) -> i32 {
+        let mut remainder = self.wheel_remainder.lock().unwrap();
+        if remainder.signum() * delta.signum() < 0 {
+            *remainder = 0;
+        }
+        let total = *remainder + delta;
+        *remainder = total % WHEEL_DELTA as i32;
+        total / WHEEL_DELTA as i32
+    }
+
     pub(super) fn cursor_position(&self) -> (i32, i32) {
         get_cursor_position()
     }
 
     pub(super) fn update_cursor_position(&self) {
-        *self.cursor_position.lock().unwrap() = get_cursor_position();
+        *self.cursor_position.lock().unwrap() = Some(get_cursor_position());
+    }
+
+    /// Forgets the previous cursor event, so that the next one is treated as the first.
+    pub(super) fn reset_cursor(&self) {
+        *self.cursor_position.lock().unwrap() = None;
+        *self.cursor_time.lock().unwrap() = None;
     }
 
     // FIXME: Since the cursor is moved with SetCursorPos, it cannot be hooked
@@ -134,15 +407,223 @@ impl Input {
 
             // In some applications, the SetCursorPos function alone is not enough
             // to notice the cursor move, so the SendInput function is used to move
-            // the cursor by 0.
-            let input = create_mouse_input(0, MOUSEEVENTF_MOVE, recursive);
+            // the cursor to the same position. The coordinates are normalized to the
+            // virtual desktop so that this works on any monitor.
+            let input = create_absolute_move_input(x, y, recursive);
             KeyboardAndMouse::SendInput(&[input], INPUT_MEM_SIZE);
         }
     }
 
+    pub(super) fn move_absolute_smooth(
+        &self,
+        (x, y): (i32, i32),
+        steps: u32,
+        interval: Duration,
+        recursive: bool,
+    ) {
+        let path = smooth_path(get_cursor_position(), (x, y), steps);
+        for (i, (x, y)) in path.enumerate() {
+            if i != 0 {
+                thread::sleep(interval);
+            }
+            self.move_absolute(x, y, recursive);
+        }
+    }
+
     pub(super) fn move_relative(&self, dx: i32, dy: i32, recursive: bool) {
         let current_pos = get_cursor_position();
         let (x, y) = (current_pos.0 + dx, current_pos.1 + dy);
         self.move_absolute(x, y, recursive);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::injection_flags;
+    use super::*;
+
+    fn keyboard_flags(button: Button, action: ButtonAction) -> KEYBD_EVENT_FLAGS {
+        let input = create_input_struct(button, action, false, false);
+        unsafe { input.Anonymous.ki.dwFlags }
+    }
+
+    #[test]
+    fn normalized_coordinates_map_back_to_pixels() {
+        for (origin, size) in [(0, 1920), (-1280, 3200), (0, 1), (-1080, 2160)] {
+            for position in origin..origin + size {
+                let normalized = normalize_absolute(position, origin, size);
+                assert!((0..=65535).contains(&normalized));
+                let pixel = (i64::from(normalized) * i64::from(size) / 65536) as i32 + origin;
+                assert_eq!(pixel, position);
+            }
+        }
+        assert_eq!(normalize_absolute(-5000, -1280, 3200), 0);
+        assert_eq!(normalize_absolute(5000, -1280, 3200), 65516);
+    }
+
+    #[test]
+    fn smooth_path_ends_at_destination() {
+        let path: Vec<_> = smooth_path((0, 0), (10, -5), 5).collect();
+        assert_eq!(path, vec![(2, -1), (4, -2), (6, -3), (8, -4), (10, -5)]);
+
+        let path: Vec<_> = smooth_path((3, 3), (7, 9), 0).collect();
+        assert_eq!(path, vec![(7, 9)]);
+    }
+
+    #[test]
+    fn text_is_sent_as_utf16_packets() {
+        let inputs = create_text_inputs("é😀", false);
+        let keys: Vec<_> = inputs
+            .iter()
+            .map(|input| unsafe { (input.Anonymous.ki.wVk, input.Anonymous.ki.wScan) })
+            .collect();
+        let units: Vec<_> = "é😀".encode_utf16().collect();
+        assert_eq!(units.len(), 3);
+        assert_eq!(
+            keys,
+            units
+                .iter()
+                .flat_map(|&unit| [(VIRTUAL_KEY(0), unit); 2])
+                .collect::<Vec<_>>()
+        );
+        for (i, input) in inputs.iter().enumerate() {
+            let flags = unsafe { input.Anonymous.ki.dwFlags };
+            let up = if i % 2 == 0 {
+                KEYBD_EVENT_FLAGS(0)
+            } else {
+                KEYEVENTF_KEYUP
+            };
+            assert_eq!(flags, KEYEVENTF_UNICODE | up);
+        }
+        assert!(create_text_inputs("", false).is_empty());
+    }
+
+    #[test]
+    fn click_at_moves_then_clicks() {
+        let inputs = create_click_at_inputs((10, 20), Button::RightButton, Some((3, 4)), false);
+        let flags: Vec<_> = inputs
+            .iter()
+            .map(|input| unsafe { input.Anonymous.mi.dwFlags })
+            .collect();
+        let absolute_move = MOUSEEVENTF_MOVE | MOUSEEVENTF_ABSOLUTE | MOUSEEVENTF_VIRTUALDESK;
+        assert_eq!(
+            flags,
+            [
+                absolute_move,
+                MOUSEEVENTF_RIGHTDOWN,
+                MOUSEEVENTF_RIGHTUP,
+                absolute_move
+            ]
+        );
+        for (input, (x, y)) in [(inputs[0], (10, 20)), (inputs[3], (3, 4))] {
+            let actual = unsafe { input.Anonymous.mi };
+            let expected = unsafe { create_absolute_move_input(x, y, false).Anonymous.mi };
+            assert_eq!((actual.dx, actual.dy), (expected.dx, expected.dy));
+        }
+
+        let inputs = create_click_at_inputs((10, 20), Button::LeftButton, None, false);
+        assert_eq!(inputs.len(), 3);
+    }
+
+    #[test]
+    fn extended_flag_is_set_for_navigation_keys() {
+        for action in [ButtonAction::Press, ButtonAction::Release] {
+            let flags = keyboard_flags(Button::LeftArrow, action);
+            assert_eq!(flags & KEYEVENTF_EXTENDEDKEY, KEYEVENTF_EXTENDEDKEY);
+
+            let flags = keyboard_flags(Button::Numpad4, action);
+            assert_eq!(flags & KEYEVENTF_EXTENDEDKEY, KEYBD_EVENT_FLAGS(0));
+        }
+        let flags = keyboard_flags(Button::Delete, ButtonAction::Release);
+        assert_eq!(flags, KEYEVENTF_KEYUP | KEYEVENTF_EXTENDEDKEY);
+    }
+
+    #[test]
+    fn print_screen_has_scan_code() {
+        for action in [ButtonAction::Press, ButtonAction::Release] {
+            let input = create_input_struct(Button::PrintScreen, action, false, false);
+            let ki = unsafe { input.Anonymous.ki };
+            assert_eq!(ki.wVk, VK_SNAPSHOT);
+            assert_eq!(ki.wScan, 0x37);
+            assert_eq!(ki.dwFlags & KEYEVENTF_EXTENDEDKEY, KEYEVENTF_EXTENDEDKEY);
+        }
+        let input = create_input_struct(Button::A, ButtonAction::Press, false, false);
+        assert_eq!(unsafe { input.Anonymous.ki.wScan }, 0);
+    }
+
+    #[test]
+    fn scan_codes_replace_virtual_key_codes() {
+        for (button, extended) in [(Button::A, false), (Button::LeftArrow, true)] {
+            let input = create_input_struct(button, ButtonAction::Release, false, true);
+            let ki = unsafe { input.Anonymous.ki };
+            assert_eq!(ki.wVk, VIRTUAL_KEY(0));
+            assert_eq!(Some(ki.wScan), physical_scan_code(button));
+            assert_eq!(ki.dwFlags & KEYEVENTF_SCANCODE, KEYEVENTF_SCANCODE);
+            assert_eq!(ki.dwFlags & KEYEVENTF_KEYUP, KEYEVENTF_KEYUP);
+            assert_eq!(ki.dwFlags.0 & KEYEVENTF_EXTENDEDKEY.0 != 0, extended);
+        }
+        let input = create_input_struct(Button::PrintScreen, ButtonAction::Press, false, true);
+        assert_eq!(unsafe { input.Anonymous.ki.wScan }, 0x37);
+    }
+
+    #[test]
+    fn windows_keys_are_extended() {
+        for (button, vk) in [
+            (Button::LSuper, VK_LWIN),
+            (Button::RSuper, VK_RWIN),
+            (Button::Application, VK_APPS),
+        ] {
+            for action in [ButtonAction::Press, ButtonAction::Release] {
+                let input = create_input_struct(button, action, false, false);
+                let ki = unsafe { input.Anonymous.ki };
+                assert_eq!(ki.wVk, vk);
+                assert_eq!(ki.dwFlags & KEYEVENTF_EXTENDEDKEY, KEYEVENTF_EXTENDEDKEY);
+            }
+        }
+    }
+
+    #[test]
+    fn mask_key_is_ignored_by_hooks() {
+        let input = create_virtual_key_input(VK_MASK, ButtonAction::Release);
+        let ki = unsafe { input.Anonymous.ki };
+        assert_eq!(ki.wVk, VK_MASK);
+        assert_eq!(ki.dwFlags, KEYEVENTF_KEYUP);
+        assert_ne!(injection_flags(ki.dwExtraInfo) & SHOULD_BE_IGNORED_FLAG, 0);
+    }
+
+    #[test]
+    fn num_lock_is_extended() {
+        let input = create_virtual_key_input(VK_NUMLOCK, ButtonAction::Press);
+        let ki = unsafe { input.Anonymous.ki };
+        assert_eq!(ki.dwFlags, KEYEVENTF_EXTENDEDKEY);
+        let input = create_virtual_key_input(VK_CAPITAL, ButtonAction::Press);
+        let ki = unsafe { input.Anonymous.ki };
+        assert_eq!(ki.dwFlags, KEYBD_EVENT_FLAGS(0));
+    }
+
+    #[test]
+    #[ignore = "sends real input; check that the desktop is shown"]
+    fn windows_key_combination_is_emulated() {
+        Button::LSuper.press();
+        Button::D.click();
+        Button::LSuper.release();
+    }
+
+    #[test]
+    #[ignore = "sends real input; check that the context menu opens"]
+    fn application_key_opens_context_menu() {
+        Button::Application.click();
+    }
+
+    #[test]
+    #[ignore = "sends real input; check that a game reading DirectInput receives W"]
+    fn scan_code_input_reaches_games() {
+        Button::W.click_scancode();
+    }
+
+    #[test]
+    #[ignore = "sends real input; check that a screenshot is copied to the clipboard"]
+    fn print_screen_takes_screenshot() {
+        Button::PrintScreen.click();
+    }
+}
@@ -0,0 +1,122 @@
+//! Functions for reading and setting the state of the lock keys, such as for showing it in
+//! a status indicator.
+//!
+//! The state is read with `GetKeyState`, like [`Button::is_toggled`], so it may not reflect
+//! input simulated just before until the input reaches the message queue of the thread.
+//! Unlike [`Button::is_toggled`], these do not depend on the keyboard layout.
+//!
+//! The state is set by emulating a click of the lock key, which is not hooked. The key is
+//! clicked only if the state differs, so the LED does not flicker.
+//!
+//! [`Button::is_toggled`]: crate::button::Button::is_toggled
+
+use super::input;
+
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    self, VIRTUAL_KEY, VK_CAPITAL, VK_NUMLOCK, VK_SCROLL,
+};
+
+fn is_on(key: VIRTUAL_KEY) -> bool {
+    let state = unsafe { KeyboardAndMouse::GetKeyState(key.0.into()) };
+    state & 1 != 0
+}
+
+// Returns `false` if the click could not be sent.
+fn set(key: VIRTUAL_KEY, on: bool) -> bool {
+    is_on(key) == on || input::click_virtual_key(key)
+}
+
+/// Returns `true` if Caps Lock is on.
+#[inline]
+pub fn caps_lock_on() -> bool {
+    is_on(VK_CAPITAL)
+}
+
+/// Returns `true` if Num Lock is on.
+#[inline]
+pub fn num_lock_on() -> bool {
+    is_on(VK_NUMLOCK)
+}
+
+/// Returns `true` if Scroll Lock is on.
+#[inline]
+pub fn scroll_lock_on() -> bool {
+    is_on(VK_SCROLL)
+}
+
+/// Turns Caps Lock on or off. Returns `false` if the input could not be sent.
+///
+/// # Examples
+///
+/// ```no_run
+/// use hookmap_core::lock_keys;
+///
+/// lock_keys::set_caps_lock(false);
+/// ```
+///
+#[inline]
+pub fn set_caps_lock(on: bool) -> bool {
+    set(VK_CAPITAL, on)
+}
+
+/// Turns Num Lock on or off. Returns `false` if the input could not be sent.
+///
+/// # Examples
+///
+/// ```no_run
+/// use hookmap_core::lock_keys;
+///
+/// lock_keys::set_num_lock(true);
+/// ```
+///
+#[inline]
+pub fn set_num_lock(on: bool) -> bool {
+    set(VK_NUMLOCK, on)
+}
+
+/// Turns Scroll Lock on or off. Returns `false` if the input could not be sent.
+#[inline]
+pub fn set_scroll_lock(on: bool) -> bool {
+    set(VK_SCROLL, on)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "us-keyboard-layout")]
+    fn caps_lock_agrees_with_button_state() {
+        use crate::button::Button;
+        assert_eq!(caps_lock_on(), Button::CapsLock.is_toggled());
+    }
+
+    #[test]
+    #[cfg(feature = "us-keyboard-layout")]
+    #[ignore = "sends real input; toggles Caps Lock twice"]
+    fn caps_lock_state_follows_input() {
+        use crate::button::Button;
+        use std::{thread, time::Duration};
+
+        let before = caps_lock_on();
+        Button::CapsLock.click();
+        thread::sleep(Duration::from_millis(50));
+        assert_ne!(caps_lock_on(), before);
+        Button::CapsLock.click();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(caps_lock_on(), before);
+    }
+
+    #[test]
+    #[ignore = "sends real input; restores Num Lock afterwards"]
+    fn num_lock_is_set_only_when_needed() {
+        use std::{thread, time::Duration};
+
+        let before = num_lock_on();
+        for on in [!before, !before, before] {
+            assert!(set_num_lock(on));
+            thread::sleep(Duration::from_millis(50));
+            assert_eq!(num_lock_on(), on);
+        }
+    }
+}
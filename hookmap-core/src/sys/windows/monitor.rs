@@ -0,0 +1,113 @@
+use windows::Win32::Foundation::{BOOL, LPARAM, RECT};
+use windows::Win32::Graphics::Gdi::{self, HDC, HMONITOR, MONITORINFO};
+use windows::Win32::UI::WindowsAndMessaging::MONITORINFOF_PRIMARY;
+
+/// The bounds of a monitor on the virtual desktop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct MonitorRect {
+    pub(super) left: i32,
+    pub(super) top: i32,
+    pub(super) right: i32,
+    pub(super) bottom: i32,
+    pub(super) is_primary: bool,
+}
+
+impl MonitorRect {
+    /// Converts `(x, y)` relative to the upper-left corner of this monitor into the
+    /// coordinates on the virtual desktop, clamped into this monitor.
+    pub(super) fn to_virtual(self, x: i32, y: i32) -> (i32, i32) {
+        let x = self.left.saturating_add(x).clamp(self.left, self.right - 1);
+        let y = self.top.saturating_add(y).clamp(self.top, self.bottom - 1);
+        (x, y)
+    }
+}
+
+/// Orders monitors as the primary monitor first, then the others from left to right,
+/// and from top to bottom among those with the same left edge.
+pub(super) fn sort_monitors(monitors: &mut [MonitorRect]) {
+    monitors.sort_by_key(|monitor| (!monitor.is_primary, monitor.left, monitor.top));
+}
+
+unsafe extern "system" fn push_monitor(
+    monitor: HMONITOR,
+    _: HDC,
+    _: *mut RECT,
+    data: LPARAM,
+) -> BOOL {
+    let monitors = &mut *(data.0 as *mut Vec<MonitorRect>);
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    if Gdi::GetMonitorInfoW(monitor, &mut info).as_bool() {
+        let rect = info.rcMonitor;
+        monitors.push(MonitorRect {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+            is_primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+        });
+    }
+    true.into()
+}
+
+/// Returns the monitors in the order of [`sort_monitors`].
+pub(super) fn monitors() -> Vec<MonitorRect> {
+    let mut monitors = Vec::new();
+    unsafe {
+        Gdi::EnumDisplayMonitors(
+            HDC(0),
+            std::ptr::null(),
+            Some(push_monitor),
+            LPARAM(&mut monitors as *mut Vec<MonitorRect> as isize),
+        );
+    }
+    sort_monitors(&mut monitors);
+    monitors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(left: i32, top: i32, width: i32, height: i32, is_primary: bool) -> MonitorRect {
+        MonitorRect {
+            left,
+            top,
+            right: left + width,
+            bottom: top + height,
+            is_primary,
+        }
+    }
+
+    #[test]
+    fn primary_monitor_comes_first() {
+        let mut monitors = [
+            rect(1920, 0, 2560, 1440, false),
+            rect(-1280, 200, 1280, 1024, false),
+            rect(0, 0, 1920, 1080, true),
+        ];
+        sort_monitors(&mut monitors);
+        assert!(monitors[0].is_primary);
+        assert_eq!(monitors[1].left, -1280);
+        assert_eq!(monitors[2].left, 1920);
+    }
+
+    #[test]
+    fn coordinates_are_relative_to_monitor() {
+        let second = rect(1920, -360, 2560, 1440, false);
+        assert_eq!(second.to_virtual(1280, 720), (3200, 360));
+        assert_eq!(second.to_virtual(0, 0), (1920, -360));
+
+        let left = rect(-1280, 200, 1280, 1024, false);
+        assert_eq!(left.to_virtual(640, 512), (-640, 712));
+    }
+
+    #[test]
+    fn coordinates_are_clamped_into_monitor() {
+        let monitor = rect(1920, 0, 2560, 1440, false);
+        assert_eq!(monitor.to_virtual(-10, 5000), (1920, 1439));
+        assert_eq!(monitor.to_virtual(i32::MAX, i32::MIN), (4479, 0));
+    }
+}
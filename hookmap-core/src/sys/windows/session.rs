@@ -0,0 +1,169 @@
+//! Notifications of resuming from sleep and unlocking the session.
+//!
+//! They are received by a message-only window created on the hook thread, whose message loop
+//! dispatches them to the window procedure.
+
+use crate::event::SessionChange;
+
+use std::sync::{Arc, RwLock};
+
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{HANDLE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::Power::{
+    RegisterSuspendResumeNotification, UnregisterSuspendResumeNotification,
+    DEVICE_NOTIFY_WINDOW_HANDLE, HPOWERNOTIFY,
+};
+use windows::Win32::System::RemoteDesktop::{
+    WTSRegisterSessionNotification, WTSUnRegisterSessionNotification, NOTIFY_FOR_THIS_SESSION,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateWindowExW, DefWindowProcW, DestroyWindow, RegisterClassW, HMENU, HWND_MESSAGE,
+    PBT_APMRESUMEAUTOMATIC, WINDOW_EX_STYLE, WINDOW_STYLE, WM_POWERBROADCAST, WM_WTSSESSION_CHANGE,
+    WNDCLASSW, WTS_SESSION_UNLOCK,
+};
+
+type Handler = Arc<dyn Fn(SessionChange) + Send + Sync>;
+
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+
+pub(super) fn set_handler(handler: Option<Handler>) {
+    *HANDLER.write().unwrap() = handler;
+}
+
+/// Returns the change notified by a window message, if any.
+fn session_change(message: u32, w_param: WPARAM) -> Option<SessionChange> {
+    match (message, w_param.0 as u32) {
+        (WM_WTSSESSION_CHANGE, WTS_SESSION_UNLOCK) => Some(SessionChange::Unlock),
+        (WM_POWERBROADCAST, PBT_APMRESUMEAUTOMATIC) => Some(SessionChange::Resume),
+        _ => None,
+    }
+}
+
+/// Calls the handler if the message notifies a change.
+fn notify(message: u32, w_param: WPARAM) {
+    let Some(change) = session_change(message, w_param) else {
+        return;
+    };
+    // Cloned so that the handler can replace itself.
+    let handler = HANDLER.read().unwrap().clone();
+    if let Some(handler) = handler {
+        handler(change);
+    }
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    message: u32,
+    w_param: WPARAM,
+    l_param: LPARAM,
+) -> LRESULT {
+    notify(message, w_param);
+    DefWindowProcW(hwnd, message, w_param, l_param)
+}
+
+/// A message-only window registered for the notifications.
+///
+/// The notifications are delivered only while the thread that created it dispatches messages.
+#[derive(Debug)]
+pub(super) struct SessionWindow {
+    hwnd: HWND,
+    power_notify: Option<HPOWERNOTIFY>,
+}
+
+impl SessionWindow {
+    /// Creates the window on the current thread.
+    ///
+    /// Returns `None` if the window cannot be created, in which case no change is notified.
+    pub(super) fn create() -> Option<Self> {
+        let class_name: Vec<u16> = "hookmap-session".encode_utf16().chain(Some(0)).collect();
+        unsafe {
+            let instance = GetModuleHandleW(PCWSTR::default()).ok()?;
+            let class = WNDCLASSW {
+                lpfnWndProc: Some(window_proc),
+                hInstance: instance,
+                lpszClassName: PCWSTR(class_name.as_ptr()),
+                ..Default::default()
+            };
+            // Fails if the class has been registered by the previous hook thread, which is fine.
+            RegisterClassW(&class);
+
+            let hwnd = CreateWindowExW(
+                WINDOW_EX_STYLE::default(),
+                PCWSTR(class_name.as_ptr()),
+                PCWSTR::default(),
+                WINDOW_STYLE::default(),
+                0,
+                0,
+                0,
+                0,
+                HWND_MESSAGE,
+                HMENU(0),
+                instance,
+                std::ptr::null(),
+            );
+            if hwnd.0 == 0 {
+                return None;
+            }
+
+            // Either of the notifications may be unavailable, such as the session notification
+            // while the Terminal Services are stopped, so the results are not fatal.
+            WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_THIS_SESSION);
+            let power_notify =
+                RegisterSuspendResumeNotification(HANDLE(hwnd.0), DEVICE_NOTIFY_WINDOW_HANDLE.0)
+                    .ok();
+
+            Some(Self { hwnd, power_notify })
+        }
+    }
+}
+
+impl Drop for SessionWindow {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(power_notify) = self.power_notify {
+                UnregisterSuspendResumeNotification(power_notify);
+            }
+            WTSUnRegisterSessionNotification(self.hwnd);
+            DestroyWindow(self.hwnd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use windows::Win32::UI::WindowsAndMessaging::{
+        PBT_APMSUSPEND, WM_APP, WTS_SESSION_LOCK, WTS_SESSION_LOGON,
+    };
+
+    #[test]
+    fn notified_changes_are_passed_to_the_handler() {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let changes_ = Arc::clone(&changes);
+        set_handler(Some(Arc::new(move |change| {
+            changes_.lock().unwrap().push(change)
+        })));
+
+        // Stands in for the system, which sends these to the window.
+        let messages = [
+            (WM_WTSSESSION_CHANGE, WTS_SESSION_LOCK),
+            (WM_POWERBROADCAST, PBT_APMSUSPEND),
+            (WM_POWERBROADCAST, PBT_APMRESUMEAUTOMATIC),
+            (WM_WTSSESSION_CHANGE, WTS_SESSION_LOGON),
+            (WM_WTSSESSION_CHANGE, WTS_SESSION_UNLOCK),
+            (WM_APP, WTS_SESSION_UNLOCK),
+        ];
+        for (message, w_param) in messages {
+            notify(message, WPARAM(w_param as usize));
+        }
+        set_handler(None);
+        notify(WM_WTSSESSION_CHANGE, WPARAM(WTS_SESSION_UNLOCK as usize));
+
+        assert_eq!(
+            *changes.lock().unwrap(),
+            [SessionChange::Resume, SessionChange::Unlock]
+        );
+    }
+}
@@ -1,7 +1,37 @@
 use crate::button::Button;
 
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
 use windows::Win32::UI::Input::KeyboardAndMouse::VIRTUAL_KEY;
 
+// Codes registered by `Button::register_code_mapping`, which take precedence over `into_button`.
+static CODE_MAPPING: RwLock<BTreeMap<u16, Button>> = RwLock::new(BTreeMap::new());
+
+// Whether `CODE_MAPPING` is not empty, so that the hook procedure does not lock it while it is.
+static HAS_MAPPING: AtomicBool = AtomicBool::new(false);
+
+pub(super) fn register_mapping(vkcode: VIRTUAL_KEY, button: Button) {
+    let mut mapping = CODE_MAPPING.write().unwrap();
+    mapping.insert(vkcode.0, button);
+    HAS_MAPPING.store(true, Ordering::Release);
+}
+
+pub(super) fn unregister_mapping(vkcode: VIRTUAL_KEY) -> Option<Button> {
+    let mut mapping = CODE_MAPPING.write().unwrap();
+    let button = mapping.remove(&vkcode.0);
+    HAS_MAPPING.store(!mapping.is_empty(), Ordering::Release);
+    button
+}
+
+fn mapped_button(vkcode: VIRTUAL_KEY) -> Option<Button> {
+    if !HAS_MAPPING.load(Ordering::Acquire) {
+        return None;
+    }
+    CODE_MAPPING.read().unwrap().get(&vkcode.0).copied()
+}
+
 pub(super) const fn into_button(vkcode: VIRTUAL_KEY) -> Option<Button> {
     use windows::Win32::UI::Input::KeyboardAndMouse::*;
     use Button::*;
@@ -61,6 +91,9 @@ pub(super) const fn into_button(vkcode: VIRTUAL_KEY) -> Option<Button> {
         #[cfg(feature = "japanese-keyboard-layout")]
         VK_OEM_4 => OpenSquareBracket,
 
+        #[cfg(feature = "us-keyboard-layout")]
+        VK_OEM_5 => BackSlash,
+
         #[cfg(feature = "us-keyboard-layout")]
         VK_CAPITAL => CapsLock,
         #[cfg(feature = "japanese-keyboard-layout")]
@@ -105,6 +138,8 @@ pub(super) const fn into_button(vkcode: VIRTUAL_KEY) -> Option<Button> {
         #[cfg(feature = "japanese-keyboard-layout")]
         VK_OEM_102 => BackSlash,
 
+        VK_OEM_8 => Oem8,
+
         VK_RSHIFT => RShift,
         VK_LCONTROL => LCtrl,
         VK_LWIN => LSuper,
@@ -135,6 +170,7 @@ pub(super) const fn into_button(vkcode: VIRTUAL_KEY) -> Option<Button> {
         VK_PRIOR => PageUp,
         VK_NEXT => PageDown,
         VK_RIGHT => RightArrow,
+        VK_CLEAR => Clear,
         VK_NUMPAD1 => Numpad1,
         VK_NUMPAD2 => Numpad2,
         VK_NUMPAD3 => Numpad3,
@@ -176,10 +212,62 @@ pub(super) const fn into_button(vkcode: VIRTUAL_KEY) -> Option<Button> {
         VK_F23 => F23,
         VK_F24 => F24,
         VK_SNAPSHOT => PrintScreen,
+        VK_PAUSE => Pause,
+        VK_VOLUME_MUTE => VolumeMute,
+        VK_VOLUME_DOWN => VolumeDown,
+        VK_VOLUME_UP => VolumeUp,
+        VK_MEDIA_NEXT_TRACK => MediaNextTrack,
+        VK_MEDIA_PREV_TRACK => MediaPrevTrack,
+        VK_MEDIA_STOP => MediaStop,
+        VK_MEDIA_PLAY_PAUSE => MediaPlayPause,
+        _ => return None,
+    })
+}
+
+/// Returns the numpad key at the same position as `button`, which the numpad sends instead
+/// while Num Lock is off.
+const fn numpad_position(button: Button) -> Option<Button> {
+    use Button::*;
+
+    Some(match button {
+        Insert => Numpad0,
+        End => Numpad1,
+        DownArrow => Numpad2,
+        PageDown => Numpad3,
+        LeftArrow => Numpad4,
+        Clear => Numpad5,
+        RightArrow => Numpad6,
+        Home => Numpad7,
+        UpArrow => Numpad8,
+        PageUp => Numpad9,
+        Delete => NumpadDot,
         _ => return None,
     })
 }
 
+/// Converts a virtual key code of a keyboard event to a button.
+///
+/// With Num Lock off, the numpad keys send the virtual key codes of the navigation keys
+/// without the extended flag. If `physical_numpad` is `true`, they are converted to the
+/// numpad buttons regardless of Num Lock. Registered codes are converted as registered.
+pub(super) fn into_key_button(
+    vkcode: VIRTUAL_KEY,
+    extended: bool,
+    physical_numpad: bool,
+) -> Option<Button> {
+    if let Some(button) = mapped_button(vkcode) {
+        return Some(button);
+    }
+    let button = into_button(vkcode)?;
+    if extended || !physical_numpad {
+        return Some(button);
+    }
+    match numpad_position(button) {
+        Some(numpad) => Some(numpad),
+        None => Some(button),
+    }
+}
+
 pub(super) const fn from_button(button: Button) -> VIRTUAL_KEY {
     use windows::Win32::UI::Input::KeyboardAndMouse::*;
     use Button::*;
@@ -239,6 +327,9 @@ pub(super) const fn from_button(button: Button) -> VIRTUAL_KEY {
         #[cfg(feature = "japanese-keyboard-layout")]
         OpenSquareBracket => VK_OEM_4,
 
+        #[cfg(feature = "us-keyboard-layout")]
+        BackSlash => VK_OEM_5,
+
         #[cfg(feature = "us-keyboard-layout")]
         CapsLock => VK_CAPITAL,
         #[cfg(feature = "japanese-keyboard-layout")]
@@ -283,6 +374,8 @@ pub(super) const fn from_button(button: Button) -> VIRTUAL_KEY {
         #[cfg(feature = "japanese-keyboard-layout")]
         BackSlash => VK_OEM_102,
 
+        Oem8 => VK_OEM_8,
+
         RShift => VK_RSHIFT,
         LCtrl => VK_LCONTROL,
         LSuper => VK_LWIN,
@@ -303,7 +396,7 @@ pub(super) const fn from_button(button: Button) -> VIRTUAL_KEY {
         RSuper => VK_RWIN,
         Application => VK_APPS,
         RCtrl => VK_RCONTROL,
-        Insert => VK_RCONTROL,
+        Insert => VK_INSERT,
         Delete => VK_DELETE,
         LeftArrow => VK_LEFT,
         Home => VK_HOME,
@@ -313,6 +406,7 @@ pub(super) const fn from_button(button: Button) -> VIRTUAL_KEY {
         PageUp => VK_PRIOR,
         PageDown => VK_NEXT,
         RightArrow => VK_RIGHT,
+        Clear => VK_CLEAR,
         Numpad1 => VK_NUMPAD1,
         Numpad2 => VK_NUMPAD2,
         Numpad3 => VK_NUMPAD3,
@@ -354,7 +448,133 @@ pub(super) const fn from_button(button: Button) -> VIRTUAL_KEY {
         F23 => VK_F23,
         F24 => VK_F24,
         PrintScreen => VK_SNAPSHOT,
+        Pause => VK_PAUSE,
+        VolumeMute => VK_VOLUME_MUTE,
+        VolumeDown => VK_VOLUME_DOWN,
+        VolumeUp => VK_VOLUME_UP,
+        MediaNextTrack => VK_MEDIA_NEXT_TRACK,
+        MediaPrevTrack => VK_MEDIA_PREV_TRACK,
+        MediaStop => VK_MEDIA_STOP,
+        MediaPlayPause => VK_MEDIA_PLAY_PAUSE,
 
         Shift | Ctrl | Alt | Super => unreachable!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::button::ButtonKind;
+    use windows::Win32::UI::Input::KeyboardAndMouse::*;
+
+    #[test]
+    fn vkcode_round_trip() {
+        for button in Button::ALL {
+            if button.kind() == ButtonKind::Mouse
+                || matches!(
+                    button,
+                    Button::Shift | Button::Ctrl | Button::Alt | Button::Super
+                )
+            {
+                continue;
+            }
+            assert_eq!(into_button(from_button(button)), Some(button));
+        }
+    }
+
+    #[test]
+    #[cfg(any(feature = "us-keyboard-layout", feature = "japanese-keyboard-layout"))]
+    fn oem_keys_by_position() {
+        let oem = [
+            (VK_OEM_1, Button::Oem1),
+            (VK_OEM_2, Button::Oem2),
+            (VK_OEM_3, Button::Oem3),
+            (VK_OEM_4, Button::Oem4),
+            (VK_OEM_5, Button::Oem5),
+            (VK_OEM_6, Button::Oem6),
+            (VK_OEM_7, Button::Oem7),
+            (VK_OEM_8, Button::Oem8),
+        ];
+        for (i, (vk, button)) in oem.into_iter().enumerate() {
+            assert_eq!(into_button(vk), Some(button));
+            assert_eq!(from_button(button), vk);
+            assert_eq!(format!("Oem{}", i + 1).parse(), Ok(button));
+        }
+    }
+
+    #[test]
+    fn numpad_with_num_lock_on() {
+        for physical_numpad in [false, true] {
+            assert_eq!(
+                into_key_button(VK_NUMPAD5, false, physical_numpad),
+                Some(Button::Numpad5)
+            );
+            assert_eq!(
+                into_key_button(VK_DECIMAL, false, physical_numpad),
+                Some(Button::NumpadDot)
+            );
+        }
+    }
+
+    #[test]
+    fn numpad_with_num_lock_off() {
+        let cases = [
+            (VK_CLEAR, Button::Clear, Button::Numpad5),
+            (VK_HOME, Button::Home, Button::Numpad7),
+            (VK_INSERT, Button::Insert, Button::Numpad0),
+            (VK_DELETE, Button::Delete, Button::NumpadDot),
+            (VK_LEFT, Button::LeftArrow, Button::Numpad4),
+        ];
+        for (vkcode, navigation, numpad) in cases {
+            assert_eq!(into_key_button(vkcode, false, false), Some(navigation));
+            assert_eq!(into_key_button(vkcode, false, true), Some(numpad));
+        }
+    }
+
+    #[test]
+    fn dedicated_navigation_keys_are_extended() {
+        for physical_numpad in [false, true] {
+            assert_eq!(
+                into_key_button(VK_HOME, true, physical_numpad),
+                Some(Button::Home)
+            );
+            assert_eq!(
+                into_key_button(VK_UP, true, physical_numpad),
+                Some(Button::UpArrow)
+            );
+        }
+        assert_eq!(into_key_button(VK_RETURN, false, true), Some(Button::Enter));
+    }
+
+    #[test]
+    fn registered_codes_are_converted() {
+        // Unassigned codes, which no other test registers.
+        let (registered, unregistered) = (VIRTUAL_KEY(0x97), VIRTUAL_KEY(0x98));
+        assert_eq!(into_key_button(registered, false, false), None);
+
+        register_mapping(registered, Button::MediaPlayPause);
+        assert_eq!(
+            into_key_button(registered, false, false),
+            Some(Button::MediaPlayPause)
+        );
+        assert_eq!(
+            into_key_button(registered, true, true),
+            Some(Button::MediaPlayPause)
+        );
+        register_mapping(registered, Button::MediaStop);
+        assert_eq!(
+            into_key_button(registered, false, false),
+            Some(Button::MediaStop)
+        );
+        // Unregistered codes fall back to the built-in table.
+        assert_eq!(into_key_button(unregistered, false, false), None);
+        assert_eq!(
+            into_key_button(VK_VOLUME_UP, false, false),
+            Some(Button::VolumeUp)
+        );
+
+        assert_eq!(unregister_mapping(registered), Some(Button::MediaStop));
+        assert_eq!(unregister_mapping(registered), None);
+        assert_eq!(into_key_button(registered, false, false), None);
+    }
+}
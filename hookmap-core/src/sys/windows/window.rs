@@ -0,0 +1,140 @@
+//! Functions for sending input to a specific window.
+//!
+//! Unlike the input simulated by [`Button::press`], the messages are posted directly to the
+//! window, so the window does not need to be in the foreground.
+//!
+//! # Caveats
+//!
+//! Some applications ignore posted keyboard messages, read the keyboard state directly,
+//! or use raw input. Input sent by this module does not work for them.
+//! Also, the posted messages are not hooked and do not change the state of the modifier keys.
+
+use super::vkcode;
+use crate::button::{Button, ButtonAction, ButtonKind};
+
+use windows::core::PCWSTR;
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::Input::KeyboardAndMouse;
+use windows::Win32::UI::WindowsAndMessaging::{
+    self, MAPVK_VK_TO_VSC, WM_CHAR, WM_KEYDOWN, WM_KEYUP,
+};
+
+/// A handle of a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Window(isize);
+
+fn to_wide(s: Option<&str>) -> Option<Vec<u16>> {
+    s.map(|s| s.encode_utf16().chain(Some(0)).collect())
+}
+
+fn as_pcwstr(s: &Option<Vec<u16>>) -> PCWSTR {
+    PCWSTR(s.as_ref().map_or(std::ptr::null(), |s| s.as_ptr()))
+}
+
+impl Window {
+    /// Finds a top-level window whose class name and title match the arguments.
+    /// `None` matches any class name or title.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap_core::window::Window;
+    ///
+    /// let notepad = Window::find(Some("Notepad"), None);
+    /// ```
+    ///
+    pub fn find(class_name: Option<&str>, title: Option<&str>) -> Option<Window> {
+        let class_name = to_wide(class_name);
+        let title = to_wide(title);
+        let hwnd =
+            unsafe { WindowsAndMessaging::FindWindowW(as_pcwstr(&class_name), as_pcwstr(&title)) };
+        (hwnd.0 != 0).then_some(Window(hwnd.0))
+    }
+
+    /// Returns the window in the foreground.
+    pub fn foreground() -> Option<Window> {
+        let hwnd = unsafe { WindowsAndMessaging::GetForegroundWindow() };
+        (hwnd.0 != 0).then_some(Window(hwnd.0))
+    }
+
+    /// Returns `true` if the window still exists.
+    pub fn exists(&self) -> bool {
+        unsafe { WindowsAndMessaging::IsWindow(HWND(self.0)).as_bool() }
+    }
+
+    /// Posts a keyboard message to the window.
+    ///
+    /// Returns `false` if `button` is a mouse button or the message could not be posted.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap_core::button::{Button, ButtonAction};
+    /// use hookmap_core::window::Window;
+    ///
+    /// if let Some(window) = Window::find(Some("Notepad"), None) {
+    ///     window.post(Button::A, ButtonAction::Press);
+    ///     window.post(Button::A, ButtonAction::Release);
+    /// }
+    /// ```
+    ///
+    pub fn post(&self, button: Button, action: ButtonAction) -> bool {
+        if button.kind() == ButtonKind::Mouse {
+            return false;
+        }
+        let vkcode = vkcode::from_button(button);
+        let scan_code =
+            unsafe { KeyboardAndMouse::MapVirtualKeyW(vkcode.0 as u32, MAPVK_VK_TO_VSC) };
+
+        // https://docs.microsoft.com/en-us/windows/win32/inputdev/wm-keydown
+        let repeat_count = 1;
+        let (message, transition_flags) = match action {
+            ButtonAction::Press => (WM_KEYDOWN, 0),
+            ButtonAction::Release => (WM_KEYUP, 0b11 << 30),
+        };
+        let l_param = repeat_count | (scan_code as isize) << 16 | transition_flags;
+
+        unsafe {
+            WindowsAndMessaging::PostMessageW(
+                HWND(self.0),
+                message,
+                WPARAM(vkcode.0 as usize),
+                LPARAM(l_param),
+            )
+            .as_bool()
+        }
+    }
+
+    /// Posts `text` to the window as character messages.
+    ///
+    /// Returns `false` if any of the messages could not be posted.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap_core::window::Window;
+    ///
+    /// if let Some(window) = Window::find(Some("Notepad"), None) {
+    ///     window.post_text("café");
+    /// }
+    /// ```
+    ///
+    pub fn post_text(&self, text: &str) -> bool {
+        text.encode_utf16().fold(true, |posted, unit| {
+            let result = unsafe {
+                WindowsAndMessaging::PostMessageW(
+                    HWND(self.0),
+                    WM_CHAR,
+                    WPARAM(unit as usize),
+                    LPARAM(1),
+                )
+            };
+            posted & result.as_bool()
+        })
+    }
+
+    /// Posts press and release messages of the button to the window.
+    pub fn click(&self, button: Button) -> bool {
+        self.post(button, ButtonAction::Press) && self.post(button, ButtonAction::Release)
+    }
+}
@@ -0,0 +1,97 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use hookmap::hotkey::bench::{self, FakeButtonState};
+use hookmap::prelude::*;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn press(target: Button) -> ButtonEvent {
+    ButtonEvent::new(target, ButtonAction::Press)
+}
+
+/// A remap-heavy configuration: every letter is remapped, and a few hotkeys use modifiers.
+fn remap_heavy_config() -> Hotkey {
+    let mut hotkey = Hotkey::new();
+    let letters = Button::ALL
+        .into_iter()
+        .filter(|button| button.category() == ButtonCategory::Letter);
+    let mut registrar = hotkey.register(Context::default());
+    for (source, target) in letters.clone().zip(letters.rev()) {
+        registrar.remap(source, target);
+    }
+    hotkey
+        .register(Context::new().modifiers(buttons!(LCtrl, LShift)))
+        .disable(Button::Key1)
+        .on_press(Button::Key2, |_| {});
+    hotkey
+}
+
+/// A group remap: every letter is remapped to one button, which is stored as a single entry.
+fn group_remap_config() -> Hotkey {
+    let mut hotkey = Hotkey::new();
+    hotkey.register(Context::default()).remap(
+        buttons!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z),
+        Button::F20,
+    );
+    hotkey
+}
+
+fn dispatch(c: &mut Criterion) {
+    let hotkey = remap_heavy_config();
+    let released = FakeButtonState::default();
+    let mut group = c.benchmark_group("dispatch");
+
+    group.bench_function("miss", |b| {
+        b.iter(|| bench::native_event_operation(&hotkey, black_box(press(Button::F21)), &released))
+    });
+    group.bench_function("remap", |b| {
+        b.iter(|| bench::native_event_operation(&hotkey, black_box(press(Button::A)), &released))
+    });
+    group.bench_function("modifiers_satisfied", |b| {
+        let held = FakeButtonState::held(&[Button::LCtrl, Button::LShift]);
+        b.iter(|| bench::native_event_operation(&hotkey, black_box(press(Button::Key1)), &held))
+    });
+    group.bench_function("modifiers_unsatisfied", |b| {
+        b.iter(|| bench::native_event_operation(&hotkey, black_box(press(Button::Key1)), &released))
+    });
+    let grouped = group_remap_config();
+    group.bench_function("group_remap", |b| {
+        b.iter(|| bench::native_event_operation(&grouped, black_box(press(Button::Z)), &released))
+    });
+    group.bench_function("group_remap_miss", |b| {
+        b.iter(|| bench::native_event_operation(&grouped, black_box(press(Button::F21)), &released))
+    });
+    group.finish();
+}
+
+/// Publishes `iters` events from each of several threads at once, as the hook thread and the
+/// threads posting events do, and returns the time until all of them are published.
+fn publish_concurrently(iters: u64) -> Duration {
+    const THREADS: usize = 4;
+    let released = FakeButtonState::default();
+    let start = Instant::now();
+    thread::scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|| {
+                for _ in 0..iters {
+                    black_box(bench::publish_event(black_box(press(Button::A)), &released));
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
+/// Compares the contention on the interceptor broker while no interceptor is waiting, when it
+/// is not locked, with while one is waiting for an event that never comes, when every event
+/// locks it.
+fn interceptor(c: &mut Criterion) {
+    let mut group = c.benchmark_group("interceptor");
+    group.bench_function("contended_idle", |b| b.iter_custom(publish_concurrently));
+    let _rx = bench::subscribe(Filter::new().target(Button::F24));
+    group.bench_function("contended_waiting", |b| b.iter_custom(publish_concurrently));
+    group.finish();
+}
+
+criterion_group!(benches, dispatch, interceptor);
+criterion_main!(benches);
@@ -0,0 +1,14 @@
+use hookmap::prelude::*;
+use hookmap::utils;
+
+// Talk in a voice chat app listening to F13 while the side button of the mouse is held down
+fn main() {
+    let mut hotkey = Hotkey::new();
+    utils::push_to_talk(
+        &mut hotkey,
+        &Context::new(),
+        Button::SideButton1,
+        Button::F13,
+    );
+    hotkey.install();
+}
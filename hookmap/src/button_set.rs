@@ -0,0 +1,76 @@
+use hookmap_core::button::Button;
+
+const WORDS: usize = Button::COUNT.div_ceil(u64::BITS as usize);
+
+/// A set of buttons represented as a bit set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ButtonSet([u64; WORDS]);
+
+impl ButtonSet {
+    pub(crate) fn insert(&mut self, button: Button) {
+        let index = button.to_index();
+        self.0[index / u64::BITS as usize] |= 1 << (index % u64::BITS as usize);
+    }
+
+    pub(crate) fn remove(&mut self, button: Button) {
+        let index = button.to_index();
+        self.0[index / u64::BITS as usize] &= !(1 << (index % u64::BITS as usize));
+    }
+
+    pub(crate) fn contains(&self, button: Button) -> bool {
+        let index = button.to_index();
+        self.0[index / u64::BITS as usize] & (1 << (index % u64::BITS as usize)) != 0
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = Button> + '_ {
+        Button::ALL
+            .into_iter()
+            .filter(move |button| self.contains(*button))
+    }
+}
+
+impl FromIterator<Button> for ButtonSet {
+    fn from_iter<I: IntoIterator<Item = Button>>(iter: I) -> Self {
+        let mut set = ButtonSet::default();
+        set.extend(iter);
+        set
+    }
+}
+
+impl Extend<Button> for ButtonSet {
+    fn extend<I: IntoIterator<Item = Button>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|button| self.insert(button));
+    }
+}
+
+impl IntoIterator for ButtonSet {
+    type Item = Button;
+    type IntoIter = std::vec::IntoIter<Button>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter().collect::<Vec<_>>().into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn button_set_contains_only_inserted_buttons() {
+        let set: ButtonSet = [Button::A, Button::RightButton, Button::Esc]
+            .into_iter()
+            .collect();
+        for button in Button::ALL {
+            let expected = matches!(button, Button::A | Button::RightButton | Button::Esc);
+            assert_eq!(set.contains(button), expected, "{:?}", button);
+        }
+    }
+
+    #[test]
+    fn button_set_extend() {
+        let mut set = ButtonSet::from_iter([Button::A]);
+        set.extend(ButtonSet::from_iter([Button::B, Button::A]));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![Button::A, Button::B]);
+    }
+}
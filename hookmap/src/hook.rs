@@ -1,26 +1,105 @@
 use hookmap_core::button::Button;
-use hookmap_core::event::{ButtonEvent, CursorEvent, NativeEventOperation, WheelEvent};
+use hookmap_core::event::{ButtonEvent, CursorEvent, MouseEvent, NativeEventOperation, WheelEvent};
+
+use crate::button_set::ButtonSet;
+
+use std::ops::Deref;
+use std::time::Instant;
+use std::{iter, option, vec};
 
 pub(crate) trait Hook<E> {
     fn native_event_operation(&self) -> NativeEventOperation;
     fn run(&self, event: E);
+
+    /// Returns `true` if the hook is run on the event loop in the order of events
+    /// instead of on a new thread. Such hooks must return quickly.
+    fn is_ordered(&self) -> bool {
+        false
+    }
+}
+
+/// Hooks fetched for an event.
+///
+/// A single hook, which is the common case for remaps, is stored without allocating.
+#[derive(Debug)]
+pub(crate) enum Hooks<H> {
+    One(H),
+    Many(Vec<H>),
+}
+
+impl<H> Default for Hooks<H> {
+    fn default() -> Self {
+        Hooks::Many(Vec::new())
+    }
+}
+
+impl<H> From<Vec<H>> for Hooks<H> {
+    fn from(hooks: Vec<H>) -> Self {
+        Hooks::Many(hooks)
+    }
+}
+
+impl<H> Deref for Hooks<H> {
+    type Target = [H];
+
+    fn deref(&self) -> &[H] {
+        match self {
+            Hooks::One(hook) => std::slice::from_ref(hook),
+            Hooks::Many(hooks) => hooks,
+        }
+    }
+}
+
+impl<H> IntoIterator for Hooks<H> {
+    type Item = H;
+    type IntoIter = iter::Chain<option::IntoIter<H>, vec::IntoIter<H>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            Hooks::One(hook) => Some(hook).into_iter().chain(Vec::new()),
+            Hooks::Many(hooks) => None.into_iter().chain(hooks),
+        }
+    }
+}
+
+/// Returns [`NativeEventOperation::Block`] if any of `hooks` blocks the event.
+pub(crate) fn native_event_operation<E>(hooks: &[impl Hook<E>]) -> NativeEventOperation {
+    if hooks
+        .iter()
+        .any(|hook| hook.native_event_operation() == NativeEventOperation::Block)
+    {
+        NativeEventOperation::Block
+    } else {
+        NativeEventOperation::Dispatch
+    }
 }
 
 pub(crate) trait ButtonState {
     fn is_pressed(&self, button: Button) -> bool;
     fn is_released(&self, button: Button) -> bool;
+    fn is_toggled(&self, button: Button) -> bool;
+
+    /// Returns when `button` was last released, or `None` if it is unknown.
+    fn released_at(&self, button: Button) -> Option<Instant>;
+
+    /// Returns when `button` was pressed if it is held down, ignoring key repeats.
+    fn pressed_at(&self, button: Button) -> Option<Instant>;
 }
 
 pub(crate) trait HookStorage {
     type ButtonHook: Hook<ButtonEvent>;
     type MouseCursorHook: Hook<CursorEvent>;
     type MouseWheelHook: Hook<WheelEvent>;
+    type MouseHook: Hook<MouseEvent>;
+
+    /// Returns the buttons for which any hook may be fetched.
+    fn handled_buttons(&self) -> ButtonSet;
 
     fn fetch_button_hook<S: ButtonState>(
         &self,
         event: ButtonEvent,
         state: &S,
-    ) -> Vec<Self::ButtonHook>;
+    ) -> Hooks<Self::ButtonHook>;
 
     fn fetch_mouse_cursor_hook<S: ButtonState>(
         &self,
@@ -33,4 +112,13 @@ pub(crate) trait HookStorage {
         event: WheelEvent,
         state: &S,
     ) -> Vec<Self::MouseWheelHook>;
+
+    fn fetch_mouse_hook<S: ButtonState>(
+        &self,
+        event: MouseEvent,
+        state: &S,
+    ) -> Vec<Self::MouseHook>;
+
+    /// Releases the buttons held down by hooks as if their sources were released.
+    fn release_held(&self);
 }
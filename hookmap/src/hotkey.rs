@@ -1,23 +1,77 @@
 //! Registering Hotkeys.
 
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench;
 mod context;
+mod cursor;
+mod dwell;
+mod edge;
+mod governor;
+mod group;
+mod handle;
+mod hold;
 mod hook;
+mod modifier_tap;
+mod panic;
+mod profile;
+mod repeat;
+mod shared;
 mod storage;
+mod tap;
+mod wheel;
 
 pub use self::context::Context;
+pub use self::edge::ScreenEdge;
+pub use self::group::HotkeyGroup;
+pub use self::handle::HotkeyHandle;
+pub use self::hook::{registration_id, Process, RemapReleasePolicy};
+pub use self::panic::CallbackPanic;
+pub use self::profile::Profile;
+pub use self::shared::context;
+pub use self::wheel::WheelDirection;
+pub use crate::runtime::{EventQueue, OverflowPolicy};
 
-use self::hook::{Condition, HotkeyAction, HotkeyHook, MouseHook, Process, RemapHook};
+use self::cursor::CursorAccumulator;
+use self::dwell::CursorDwell;
+use self::edge::EdgeDwell;
+use self::governor::{Governor, GovernorState};
+use self::hold::TimedHold;
+use self::hook::{Condition, Consumer, HotkeyAction, HotkeyHook, MouseHook, Predicate, RemapHook};
+use self::modifier_tap::ModifierTap;
+use self::repeat::AutoRepeat;
+use self::shared::ContextMap;
 use self::storage::HotkeyStorage;
+use self::tap::TapCounter;
+use self::wheel::{WheelCoalescer, WheelThreshold};
+use crate::button_set::ButtonSet;
+use crate::hook::ButtonState;
 use crate::macros::button_arg::{ButtonArg, ButtonArgUnit};
-use crate::runtime::Runtime;
+use crate::runtime::{
+    self, DisableCondition, FocusWatch, HookWatchdog, InstallRetry, KillSwitch, RealButtonState,
+    Runtime,
+};
 
-use hookmap_core::button::Button;
-use hookmap_core::event::{ButtonEvent, CursorEvent, NativeEventOperation, WheelEvent};
+use hookmap_core::button::{Button, ButtonAction, ButtonCategory};
+use hookmap_core::event::{
+    ButtonEvent, CursorEvent, HookTarget, InjectionError, InstallHookError, MouseEvent,
+    NativeEventOperation, SessionChange, WheelEvent,
+};
+use hookmap_core::mouse;
+use hookmap_core::window::Window;
 
+use std::any::TypeId;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// Registers and installs hotkeys.
 ///
+/// `Hotkey` is [`Send`] and [`Sync`]. Since registering requires `&mut self`,
+/// wrap it in a [`Mutex`](std::sync::Mutex) to register hotkeys from multiple threads.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -33,6 +87,24 @@ use std::sync::Arc;
 #[derive(Debug, Default)]
 pub struct Hotkey {
     storage: HotkeyStorage,
+    hook_target: HookTarget,
+    injection_tag: Option<u32>,
+    physical_numpad: bool,
+    scancode_input: bool,
+    high_priority_hook: bool,
+    injection_error_handler: Option<Process<InjectionError>>,
+    session_change_handler: Option<Process<SessionChange>>,
+    callback_panic_handler: Option<Process<CallbackPanic>>,
+    kill_switch: Option<KillSwitch>,
+    disable_condition: Option<DisableCondition>,
+    release_on_focus_change: bool,
+    event_queue: EventQueue,
+    install_retry: InstallRetry,
+    hook_watchdog_interval: Option<Duration>,
+    hook_reinstalled_handler: Option<Process<()>>,
+    contexts: ContextMap,
+    groups: Vec<HotkeyGroup>,
+    handle: HotkeyHandle,
 }
 
 impl Hotkey {
@@ -50,6 +122,15 @@ impl Hotkey {
         Self::default()
     }
 
+    /// Dispatches `events` on the current thread as if they were sent by the hook.
+    #[cfg(all(test, any(feature = "tokio", feature = "ipc")))]
+    pub(crate) fn dispatch_all(self, events: impl IntoIterator<Item = hookmap_core::event::Event>) {
+        let runtime = Runtime::new(self.storage);
+        for event in events {
+            let _ = runtime.dispatch(event, |_| {});
+        }
+    }
+
     /// Creates a [`Registrar`] to register hotkeys.
     ///
     /// # Examples
@@ -63,75 +144,96 @@ impl Hotkey {
     ///     .remap(Button::A, Button::B);
     /// ```
     ///
-    pub fn register(&mut self, context: Context) -> Registrar {
+    #[must_use = "hotkeys are registered by calling methods of the returned `Registrar`"]
+    pub fn register(&mut self, context: Context) -> Registrar<'_> {
+        let (id, disabled) = self.storage.begin_registration();
+        self.storage.observe(context.iter_graced());
         Registrar {
             storage: &mut self.storage,
-            context,
+            context: context.disabled_by(disabled),
+            id,
         }
     }
 
-    /// Installs hotkeys and blocks the current thread.
+    /// Removes the hotkeys registered through the [`Registrar`] identified by `id`.
+    /// Returns `false` if `id` is unknown, such as when it has already been unregistered.
     ///
     /// # Examples
     ///
-    /// ```no_run
+    /// ```
     /// use hookmap::prelude::*;
     ///
     /// let mut hotkey = Hotkey::new();
-    /// hotkey.install();
+    /// let id = hotkey
+    ///     .register(Context::default())
+    ///     .remap(Button::A, Button::B)
+    ///     .id();
+    /// assert!(hotkey.unregister(id));
+    /// assert!(!hotkey.unregister(id));
     /// ```
     ///
-    pub fn install(self) {
-        let runtime = Runtime::new(self.storage);
-        runtime.start();
+    pub fn unregister(&mut self, id: RegistrationId) -> bool {
+        self.storage.unregister(id)
     }
-}
 
-/// Register hotkeys.
-/// Calls [`Hotkey::register`] to get this instance.
-///
-/// # Examples
-///
-/// ```
-/// use hookmap::prelude::*;
-///
-/// let mut hotkey = Hotkey::new();
-/// hotkey
-///     .register(Context::default())
-///     .remap(Button::A, Button::B)
-///     .on_press(Button::C, |e| println!("{:?}", e));
-///
-/// ```
-pub struct Registrar<'a> {
-    storage: &'a mut HotkeyStorage,
-    context: Context,
-}
+    /// Returns a handle to replace the hotkeys after this is installed.
+    /// See [`HotkeyHandle::reload`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let hotkey = Hotkey::new();
+    /// let handle = hotkey.handle();
+    /// ```
+    ///
+    pub fn handle(&self) -> HotkeyHandle {
+        self.handle.clone()
+    }
 
-impl<'a> Registrar<'a> {
-    /// Makes `target` behave like a `behavior`.
+    /// Adds the remaps of `profile`, which are active only while the profile is active.
+    /// The remaps of a profile with the same name as an added one are added to it.
+    ///
+    /// No profile is active until [`Hotkey::activate_profile`] is called.
+    /// See [`HotkeyHandle::activate_profile`] to switch the profile while the hotkeys are
+    /// installed.
     ///
     /// # Examples
     ///
     /// ```
     /// use hookmap::prelude::*;
+    /// use hookmap::hotkey::Profile;
     ///
     /// let mut hotkey = Hotkey::new();
     /// hotkey
-    ///     .register(Context::default())
-    ///     .remap(Button::A, Button::B);
+    ///     .add_profile(Profile::new("default"))
+    ///     .add_profile(Profile::new("gaming").remap(Button::LSuper, Button::F24));
+    /// hotkey.activate_profile("default");
     /// ```
     ///
-    pub fn remap(&mut self, targets: impl Into<ButtonArg>, behavior: Button) -> &mut Self {
-        let targets = targets.into();
-        let hook = Arc::new(RemapHook::new(self.context.to_condition(), behavior));
-        assert!(targets.is_all_plain());
-
-        for target in targets.iter_plain() {
-            self.storage.register_remap(target, Arc::clone(&hook));
+    pub fn add_profile(&mut self, profile: Profile) -> &mut Self {
+        let profiles = Arc::clone(&self.handle.profiles);
+        let index = profiles.add(profile.name());
+        let mut registrar = self.register(Context::default());
+        for (targets, behavior) in profile.remaps {
+            let profiles = Arc::clone(&profiles);
+            registrar.remap_if(targets, behavior, move || profiles.is_active(index));
         }
         self
     }
-    /// Run `process` when `target` is pressed.
+
+    /// Activates the profile named `name` and deactivates the others.
+    /// Returns `false` if no profile named `name` has been added.
+    /// See [`HotkeyHandle::activate_profile`].
+    pub fn activate_profile(&self, name: &str) -> bool {
+        self.handle.activate_profile(name)
+    }
+
+    /// Returns the group named `name`, creating it if it does not exist.
+    ///
+    /// Hotkeys registered with [`Context::group`] are active only while the group is
+    /// enabled. See [`HotkeyGroup`].
     ///
     /// # Examples
     ///
@@ -139,35 +241,37 @@ impl<'a> Registrar<'a> {
     /// use hookmap::prelude::*;
     ///
     /// let mut hotkey = Hotkey::new();
+    /// let media = hotkey.group("media");
     /// hotkey
-    ///     .register(Context::default())
-    ///     .on_press(buttons!(A), |e| println!("Pressed: {:?}", e));
+    ///     .register(Context::new().group(&media))
+    ///     .remap(Button::F7, Button::MediaPlayPause);
+    /// hotkey
+    ///     .register(Context::new())
+    ///     .on_press(Button::F8, move |_| media.set_enabled(!media.is_enabled()));
     /// ```
     ///
-    pub fn on_press(
-        &mut self,
-        targets: impl Into<ButtonArg>,
-        process: impl Into<Process<ButtonEvent>>,
-    ) -> &mut Self {
-        let targets = targets.into();
-        let hook = Arc::new(HotkeyHook::new(
-            self.context.to_condition(),
-            HotkeyAction::Process(process.into()),
-            self.context.native_event_operation,
-        ));
-
-        for target in targets.iter_plain() {
-            self.storage
-                .register_hotkey_on_press(target, Arc::clone(&hook));
-        }
-        for target in targets.iter_not() {
-            self.storage
-                .register_hotkey_on_release(target, Arc::clone(&hook));
+    pub fn group(&mut self, name: &str) -> HotkeyGroup {
+        match self.groups.iter().find(|group| group.name() == name) {
+            Some(group) => group.clone(),
+            None => {
+                let group = HotkeyGroup::new(name);
+                self.groups.push(group.clone());
+                group
+            }
         }
-        self
     }
 
-    /// Run `process` when `target` is released.
+    /// Returns whether `event` would be blocked if it were sent now, without running any
+    /// callback or sending any input.
+    ///
+    /// The hotkeys are matched against the buttons currently held down, and the kill switch
+    /// and [`Hotkey::disable_in`] are taken into account. Predicates given to
+    /// [`Registrar::remap_if`] are evaluated. Events blocked by [`interceptor`]s are not
+    /// reported, since they are not a part of the hotkeys. The callbacks given to
+    /// [`Registrar::on_press_consuming`] are not run, and the event is assumed not to be
+    /// consumed by them.
+    ///
+    /// [`interceptor`]: crate::interceptor
     ///
     /// # Examples
     ///
@@ -175,153 +279,3952 @@ impl<'a> Registrar<'a> {
     /// use hookmap::prelude::*;
     ///
     /// let mut hotkey = Hotkey::new();
-    /// hotkey
-    ///     .register(Context::default())
-    ///     .on_release(buttons!(A), |e| println!("Released: {:?}", e));
+    /// hotkey.register(Context::default()).remap(Button::A, Button::B);
+    ///
+    /// let event = |target| {
+    ///     ButtonEvent::new(target, ButtonAction::Press).time(hookmap::event_time())
+    /// };
+    /// assert_eq!(hotkey.would_block(event(Button::A)), NativeEventOperation::Block);
+    /// assert_eq!(hotkey.would_block(event(Button::C)), NativeEventOperation::Dispatch);
     /// ```
     ///
-    pub fn on_release(
-        &mut self,
-        targets: impl Into<ButtonArg>,
-        process: impl Into<Process<ButtonEvent>>,
-    ) -> &mut Self {
-        let targets = targets.into();
-        let condition = self.context.to_condition();
-        let process = HotkeyAction::Process(process.into());
-
-        if self.context.has_no_modifiers() {
-            let hook = Arc::new(HotkeyHook::new(
-                condition,
-                process,
-                self.context.native_event_operation,
-            ));
+    pub fn would_block(&self, event: ButtonEvent) -> NativeEventOperation {
+        self.would_block_with_state(event, &RealButtonState)
+    }
 
-            for target in targets.iter_plain() {
-                self.storage
-                    .register_hotkey_on_release(target, Arc::clone(&hook));
-            }
-            for target in targets.iter_not() {
-                self.storage
-                    .register_hotkey_on_press(target, Arc::clone(&hook));
-            }
-            return self;
+    fn would_block_with_state(
+        &self,
+        event: ButtonEvent,
+        state: &impl ButtonState,
+    ) -> NativeEventOperation {
+        let is_killed = self
+            .kill_switch
+            .is_some_and(|kill_switch| kill_switch.is_triggered(event, state));
+        let is_disabled = event.action == ButtonAction::Press
+            && self
+                .disable_condition
+                .as_ref()
+                .is_some_and(DisableCondition::is_satisfied);
+        if is_killed || is_disabled {
+            NativeEventOperation::Dispatch
+        } else {
+            self.storage.would_block(event, state)
         }
+    }
 
-        for target in targets.iter() {
-            let is_active = Arc::default();
-            let inactivation_hook = Arc::new(HotkeyHook::new(
-                Condition::Activation(Arc::clone(&is_active)),
-                process.clone(),
-                self.context.native_event_operation,
-            ));
-            let activation_hook = Arc::new(HotkeyHook::new(
-                condition.clone(),
-                HotkeyAction::Activate(is_active),
-                NativeEventOperation::Dispatch,
-            ));
-
-            match target {
-                ButtonArgUnit::Plain(target) => {
-                    self.storage
-                        .register_hotkey_on_press(target, Arc::clone(&activation_hook));
-                    self.storage
-                        .register_hotkey_on_release(target, Arc::clone(&inactivation_hook));
-                }
-                ButtonArgUnit::Not(target) => {
-                    self.storage
-                        .register_hotkey_on_release(target, Arc::clone(&activation_hook));
-                    self.storage
-                        .register_hotkey_on_press(target, Arc::clone(&inactivation_hook));
-                }
-            }
-
-            for target in self.context.iter_pressed() {
-                self.storage
-                    .register_hotkey_on_release(*target, Arc::clone(&inactivation_hook));
-            }
-            for target in self.context.iter_released() {
-                self.storage
-                    .register_hotkey_on_press(*target, Arc::clone(&inactivation_hook));
-            }
-        }
+    /// Sets the policy for releasing the buttons remapped by [`Registrar::remap`].
+    /// This affects remaps registered after this call.
+    ///
+    /// With [`RemapReleasePolicy::RefCount`], when multiple buttons are remapped to the same
+    /// button, it is released only after all of them are released.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use hookmap::hotkey::RemapReleasePolicy;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.remap_release_policy(RemapReleasePolicy::RefCount);
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .remap(buttons!(A, B), Button::C);
+    /// ```
+    ///
+    pub fn remap_release_policy(&mut self, policy: RemapReleasePolicy) -> &mut Self {
+        self.storage.remap_release_policy = policy;
         self
     }
 
-    /// Run `process` when a mouse wheel is rotated.
+    /// Sets the devices to be hooked by [`Hotkey::install`].
+    /// By default, both keyboard and mouse are hooked.
+    ///
+    /// Hotkeys for devices that are not hooked never fire.
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
     /// use hookmap::prelude::*;
     ///
     /// let mut hotkey = Hotkey::new();
     /// hotkey
     ///     .register(Context::default())
-    ///     .mouse_wheel(|e: WheelEvent| println!("Delta: {}", e.delta));
+    ///     .remap(Button::A, Button::B);
+    /// hotkey.hook_target(HookTarget::Keyboard);
+    /// hotkey.install();
     /// ```
     ///
-    pub fn mouse_wheel(&mut self, process: impl Into<Process<WheelEvent>>) -> &mut Self {
-        let hook = Arc::new(MouseHook::new(
-            self.context.to_condition(),
-            process.into(),
-            self.context.native_event_operation,
-        ));
-        self.storage.register_mouse_wheel_hotkey(hook);
+    pub fn hook_target(&mut self, target: HookTarget) -> &mut Self {
+        self.hook_target = target;
         self
     }
 
-    /// Run `process` when a mouse cursor is moved.
+    /// Sets the tag that marks input generated by this program.
+    ///
+    /// Only input bearing this tag is treated as generated by this program, so multiple
+    /// programs using hookmap can run at the same time without skipping each other's input.
+    /// By default, the process ID is used as the tag, which is already unique.
+    /// See [`hookmap_core::set_injection_tag`] for details.
     ///
     /// # Examples
     ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.injection_tag(0x686d);
+    /// hotkey.install();
     /// ```
+    ///
+    pub fn injection_tag(&mut self, tag: u32) -> &mut Self {
+        self.injection_tag = Some(tag);
+        self
+    }
+
+    /// Makes the numpad keys trigger hotkeys for the numpad buttons regardless of Num Lock.
+    ///
+    /// By default, the numpad keys with Num Lock off are seen as the navigation keys such as
+    /// [`Button::Home`] and [`Button::Clear`], the same as the dedicated navigation keys.
+    /// See [`hookmap_core::set_physical_numpad`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
     /// use hookmap::prelude::*;
     ///
     /// let mut hotkey = Hotkey::new();
+    /// hotkey.physical_numpad(true);
     /// hotkey
     ///     .register(Context::default())
-    ///     .mouse_cursor(|e: CursorEvent| println!("movement distance: {:?}", e.delta));
+    ///     .remap(Button::Numpad7, Button::F13);
+    /// hotkey.install();
     /// ```
     ///
-    pub fn mouse_cursor(&mut self, process: impl Into<Process<CursorEvent>>) -> &mut Self {
-        let hook = Arc::new(MouseHook::new(
-            self.context.to_condition(),
-            process.into(),
-            self.context.native_event_operation,
-        ));
-        self.storage.register_mouse_cursor_hotkey(hook);
+    pub fn physical_numpad(&mut self, enabled: bool) -> &mut Self {
+        self.physical_numpad = enabled;
         self
     }
 
-    /// Disables the button and blocks events.
+    /// Runs the hooks of the OS on a thread with a higher scheduling priority.
+    ///
+    /// Under heavy load, Windows may remove hooks that respond too slowly. A higher priority
+    /// makes this less likely and lowers the input latency, but the hook thread takes the CPU
+    /// from other programs while it is busy. The callbacks of hotkeys are not affected.
+    /// See [`hookmap_core::set_high_priority_hook`] for details.
     ///
     /// # Examples
     ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.high_priority_hook(true);
+    /// hotkey.install();
     /// ```
+    ///
+    pub fn high_priority_hook(&mut self, enabled: bool) -> &mut Self {
+        self.high_priority_hook = enabled;
+        self
+    }
+
+    /// Makes the keyboard input generated by this program, such as [`seq!`](crate::seq) and
+    /// remaps, use scan codes instead of virtual-key codes.
+    ///
+    /// Enable this for games that read the keyboard through DirectInput or Raw Input and
+    /// ignore ordinary simulated input.
+    /// See [`hookmap_core::set_scancode_input`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
     /// use hookmap::prelude::*;
     ///
     /// let mut hotkey = Hotkey::new();
+    /// hotkey.scancode_input(true);
     /// hotkey
     ///     .register(Context::default())
-    ///     .disable(buttons!(A));
+    ///     .remap(Button::F21, Button::Space);
+    /// hotkey.install();
     /// ```
     ///
-    pub fn disable(&mut self, targets: impl Into<ButtonArg>) -> &mut Self {
-        let hook = Arc::new(HotkeyHook::new(
-            self.context.to_condition(),
-            HotkeyAction::Noop,
-            NativeEventOperation::Block,
-        ));
-        let targets = targets.into();
-        assert!(targets.is_all_plain());
+    pub fn scancode_input(&mut self, enabled: bool) -> &mut Self {
+        self.scancode_input = enabled;
+        self
+    }
 
-        for target in targets.iter_plain() {
-            self.storage
-                .register_hotkey_on_press(target, Arc::clone(&hook));
-            self.storage
-                .register_hotkey_on_release(target, Arc::clone(&hook));
-        }
+    /// Checks that the input generated by this program reaches the hook unmodified, and runs
+    /// `on_error` when another program blocked or replaced it.
+    ///
+    /// This is a diagnostic for running alongside other programs that hook input.
+    /// See [`hookmap_core::set_injection_diagnostics`] for details and limitations.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.on_injection_error(|e: InjectionError| eprintln!("{}", e));
+    /// hotkey.install();
+    /// ```
+    ///
+    pub fn on_injection_error(
+        &mut self,
+        on_error: impl Into<Process<InjectionError>>,
+    ) -> &mut Self {
+        self.injection_error_handler = Some(on_error.into());
+        self
+    }
 
+    /// Runs `process` when the system resumes from sleep or the session is unlocked.
+    ///
+    /// Buttons released while the system was asleep or locked are not seen by the hook, and
+    /// other programs may have reinstalled their hooks in front of this one, so this can be used
+    /// to reset state or to reinstall the hotkeys. `process` runs on a new thread, not on the
+    /// hook thread, so it may uninstall the hook.
+    /// The changes are notified only on Windows, while the hotkeys are installed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.on_session_change(|change: SessionChange| eprintln!("{:?}", change));
+    /// hotkey.install();
+    /// ```
+    ///
+    pub fn on_session_change(&mut self, process: impl Into<Process<SessionChange>>) -> &mut Self {
+        self.session_change_handler = Some(process.into());
         self
     }
+
+    /// Runs `on_panic` when a callback named by [`Process::named`] panics.
+    ///
+    /// `on_panic` is called on the thread of the callback before the panic continues, and the
+    /// panic is still printed by the panic hook. Callbacks without a name are not reported.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::hotkey::CallbackPanic;
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.on_callback_panic(|e: CallbackPanic| eprintln!("{}", e));
+    /// hotkey.install();
+    /// ```
+    ///
+    pub fn on_callback_panic(&mut self, on_panic: impl Into<Process<CallbackPanic>>) -> &mut Self {
+        self.callback_panic_handler = Some(on_panic.into());
+        self
+    }
+
+    /// Registers `value` as shared data that callbacks can get with [`context`].
+    ///
+    /// One value can be registered for each type. Registering a value of the same type again
+    /// replaces the previous one. Not to be confused with [`Context`], which specifies when
+    /// hotkeys are active.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    /// use std::sync::Mutex;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.context(Mutex::new(Vec::<Button>::new()));
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_press(Button::F1, |e: ButtonEvent| {
+    ///         let history = hookmap::hotkey::context::<Mutex<Vec<Button>>>();
+    ///         history.lock().unwrap().push(e.target);
+    ///     });
+    /// hotkey.install();
+    /// ```
+    ///
+    pub fn context<T: Send + Sync + 'static>(&mut self, value: T) -> &mut Self {
+        self.contexts.insert(TypeId::of::<T>(), Arc::new(value));
+        self
+    }
+
+    /// Sets a combination of buttons that uninstalls the hooks and returns from
+    /// [`Hotkey::install`], as an escape hatch when the hotkeys misbehave.
+    ///
+    /// The kill switch fires when the last of `buttons` is pressed while the others are held.
+    /// It is checked before any other hotkey and interceptor, so it cannot be blocked by them.
+    /// Input generated by this program does not fire it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buttons` is empty or contains buttons prefixed with `!`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.kill_switch(buttons!(LCtrl, LAlt, F12));
+    /// hotkey.install();
+    /// ```
+    ///
+    pub fn kill_switch(&mut self, buttons: impl Into<ButtonArg>) -> &mut Self {
+        let buttons = buttons.into();
+        assert!(buttons.is_all_plain());
+        self.kill_switch = Some(KillSwitch::new(buttons.iter_plain()));
+        self
+    }
+
+    /// Disables all hotkeys while `predicate` returns `true` for the foreground window.
+    ///
+    /// While disabled, input is passed to the foreground window as is, without running
+    /// hotkeys or interceptors, so that e.g. a full-screen game gets the raw input.
+    /// `predicate` is called once for each event. Release events are still processed so that
+    /// the buttons pressed before the window got focus are not left pressed.
+    /// The kill switch works regardless of this.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    ///
+    /// let game = Window::find(Some("UnityWndClass"), None);
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.disable_in(move |window| Some(window) == game);
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .remap(Button::CapsLock, Button::LCtrl);
+    /// hotkey.install();
+    /// ```
+    ///
+    pub fn disable_in(
+        &mut self,
+        predicate: impl Fn(Window) -> bool + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.disable_condition = Some(DisableCondition::new(move || {
+            Window::foreground().is_some_and(&predicate)
+        }));
+        self
+    }
+
+    /// Releases the buttons held down by remaps when the foreground window changes.
+    ///
+    /// While a window run as administrator has the focus, the release of a source button
+    /// does not reach the hooks, so the button it is remapped to stays pressed. With this
+    /// enabled, such buttons are released when an event is received after the focus has
+    /// changed, and the later release of the source is passed through as is.
+    ///
+    /// This also releases the remapped buttons that are still held down on purpose, so it
+    /// does not suit remaps used to switch windows, such as `CapsLock` to `LAlt` for Alt+Tab.
+    /// Disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.release_remaps_on_focus_change(true);
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .remap(Button::CapsLock, Button::LCtrl);
+    /// hotkey.install();
+    /// ```
+    ///
+    pub fn release_remaps_on_focus_change(&mut self, enabled: bool) -> &mut Self {
+        self.release_on_focus_change = enabled;
+        self
+    }
+
+    /// Sets how the callbacks of hotkeys are queued when they cannot keep up with the input.
+    /// See [`EventQueue`] for the defaults.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::hotkey::{EventQueue, OverflowPolicy};
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .event_queue(EventQueue::new().button_overflow(OverflowPolicy::DropOldest))
+    ///     .register(Context::default())
+    ///     .mouse_cursor(|e: CursorEvent| println!("{:?}", e.position));
+    /// hotkey.install();
+    /// ```
+    ///
+    pub fn event_queue(&mut self, event_queue: EventQueue) -> &mut Self {
+        self.event_queue = event_queue;
+        self
+    }
+
+    /// Retries installing the hooks up to `retries` times if the OS fails to install them,
+    /// waiting `delay` before the first retry and twice as long before each following one.
+    ///
+    /// Installing the hooks can fail for a while on some systems, such as when a program
+    /// started at logon runs before the session is ready, or after the lock screen.
+    /// By default, installing the hooks is not retried.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.install_retries(5, Duration::from_millis(500));
+    /// hotkey.install();
+    /// ```
+    ///
+    pub fn install_retries(&mut self, retries: u32, delay: Duration) -> &mut Self {
+        self.install_retry = InstallRetry::new(retries, delay);
+        self
+    }
+
+    /// Checks every `interval` that the hooks still receive input, and reinstalls them if they
+    /// do not.
+    ///
+    /// Windows removes the hooks without notice when a hook takes too long to handle an event,
+    /// and after some system events, after which the hotkeys silently stop working.
+    /// The check compares the hooks with the last input of the user, as described in
+    /// [`hookmap_core::verify_hooks`]. By default, the hooks are not checked.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .hook_watchdog(Duration::from_secs(30))
+    ///     .on_hook_reinstalled(|| eprintln!("The hooks were reinstalled."));
+    /// hotkey.install();
+    /// ```
+    ///
+    pub fn hook_watchdog(&mut self, interval: Duration) -> &mut Self {
+        self.hook_watchdog_interval = Some(interval);
+        self
+    }
+
+    /// Calls `callback` when [`Hotkey::hook_watchdog`] reinstalls the hooks.
+    ///
+    /// `callback` is called on the thread of the watchdog. Buttons released while the hooks
+    /// were removed are not seen by the hotkeys, so this can be used to reset state.
+    pub fn on_hook_reinstalled(
+        &mut self,
+        callback: impl Fn() + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.hook_reinstalled_handler = Some(Process::from(move |()| callback()));
+        self
+    }
+
+    /// Installs hotkeys and blocks the current thread.
+    /// Returns when the hooks are uninstalled, e.g. by [`Hotkey::kill_switch`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if installing the hooks fails. See [`Hotkey::try_install`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.install();
+    /// ```
+    ///
+    pub fn install(self) {
+        self.try_install().unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    /// Same as [`Hotkey::install`], but returns an error instead of panicking if installing
+    /// the hooks fails, e.g. because hotkeys are already installed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// if let Err(e) = hotkey.try_install() {
+    ///     eprintln!("{}", e);
+    /// }
+    /// ```
+    ///
+    pub fn try_install(self) -> Result<(), InstallHookError> {
+        let (runtime, target) = self.into_runtime();
+        runtime.start(target)
+    }
+
+    /// Installs hotkeys and blocks the current thread.
+    /// `handler` is called on the current thread about every `interval`.
+    ///
+    /// While `handler` is running, input events are not processed and all keyboard and mouse
+    /// input in the system is delayed. `handler` should return quickly.
+    ///
+    /// # Panics
+    ///
+    /// Panics if installing the hooks fails. See [`Hotkey::try_install_with_handler`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.install_with_handler(Duration::from_secs(1), || println!("tick"));
+    /// ```
+    ///
+    pub fn install_with_handler(self, interval: Duration, handler: impl FnMut()) {
+        self.try_install_with_handler(interval, handler)
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    /// Same as [`Hotkey::install_with_handler`], but returns an error instead of panicking if
+    /// installing the hooks fails.
+    pub fn try_install_with_handler(
+        self,
+        interval: Duration,
+        handler: impl FnMut(),
+    ) -> Result<(), InstallHookError> {
+        let (runtime, target) = self.into_runtime();
+        runtime.start_with_handler(target, interval, handler)
+    }
+
+    /// Installs hotkeys and blocks the current thread until `stop` is set to `true`, then
+    /// uninstalls the hooks and returns.
+    ///
+    /// This is convenient for scripts and tests that run the hotkeys for a while, since the
+    /// hooks need not be stopped from another thread. `stop` is checked about every
+    /// 10 milliseconds. As with [`Hotkey::install`], this also returns when the hooks are
+    /// uninstalled in other ways, e.g. by [`Hotkey::kill_switch`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if installing the hooks fails, as with [`Hotkey::try_install`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let stop = Arc::new(AtomicBool::new(false));
+    /// let mut hotkey = Hotkey::new();
+    /// let stop_ = Arc::clone(&stop);
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_press(Button::F13, move |_| stop_.store(true, Ordering::SeqCst));
+    /// hotkey.run_until(stop).unwrap();
+    /// ```
+    ///
+    pub fn run_until(self, stop: Arc<AtomicBool>) -> Result<(), InstallHookError> {
+        self.run_while(move || !stop.load(Ordering::SeqCst))
+    }
+
+    /// Installs hotkeys and blocks the current thread for `duration`, then uninstalls the hooks
+    /// and returns. See [`Hotkey::run_until`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if installing the hooks fails, as with [`Hotkey::try_install`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .remap(Button::F13, Button::F14);
+    /// hotkey.run_for(Duration::from_secs(10)).unwrap();
+    /// ```
+    ///
+    pub fn run_for(self, duration: Duration) -> Result<(), InstallHookError> {
+        let deadline = Instant::now() + duration;
+        self.run_while(move || Instant::now() < deadline)
+    }
+
+    fn run_while(self, mut running: impl FnMut() -> bool) -> Result<(), InstallHookError> {
+        const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+        let (runtime, target) = self.into_runtime();
+        runtime.start_until(target, POLL_INTERVAL, || {
+            if running() {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        })
+    }
+
+    fn into_runtime(self) -> (Runtime<HotkeyStorage>, HookTarget) {
+        self.apply_settings();
+        let hook_watchdog = self.build_hook_watchdog();
+        self.handle.storage.replace(self.storage);
+        let runtime = Runtime::reloadable(self.handle.storage)
+            .with_kill_switch(self.kill_switch)
+            .with_disable_condition(self.disable_condition)
+            .with_focus_watch(self.release_on_focus_change.then(FocusWatch::foreground))
+            .with_event_queue(self.event_queue)
+            .with_install_retry(self.install_retry)
+            .with_hook_watchdog(hook_watchdog);
+        (runtime, self.hook_target)
+    }
+
+    fn build_hook_watchdog(&self) -> Option<HookWatchdog> {
+        let interval = self.hook_watchdog_interval?;
+        let on_reinstalled = self
+            .hook_reinstalled_handler
+            .clone()
+            .map(|process| Arc::new(move || process.0(())) as Arc<dyn Fn() + Send + Sync>);
+        Some(HookWatchdog::new(interval, on_reinstalled))
+    }
+
+    fn apply_settings(&self) {
+        if let Some(tag) = self.injection_tag {
+            hookmap_core::set_injection_tag(tag);
+        }
+        hookmap_core::set_physical_numpad(self.physical_numpad);
+        hookmap_core::set_scancode_input(self.scancode_input);
+        hookmap_core::set_high_priority_hook(self.high_priority_hook);
+        if let Some(process) = self.injection_error_handler.clone() {
+            hookmap_core::set_injection_diagnostics(move |e| process.0(e));
+        }
+        if let Some(process) = self.session_change_handler.clone() {
+            hookmap_core::set_session_change_handler(spawn_on_change(process));
+        }
+        panic::set_handler(self.callback_panic_handler.clone());
+        shared::publish(&self.contexts);
+    }
+}
+
+/// Runs `process` on a new thread for each change, so that it does not block the hook thread.
+fn spawn_on_change(process: Process<SessionChange>) -> impl Fn(SessionChange) + Send + Sync {
+    move |change| {
+        let process = process.clone();
+        thread::spawn(move || process.0(change));
+    }
+}
+
+/// Register hotkeys.
+/// Calls [`Hotkey::register`] to get this instance.
+///
+/// Each method registers its hotkeys into the [`Hotkey`] as soon as it is called.
+/// Nothing is buffered in the `Registrar`, so dropping it neither registers nor discards
+/// anything.
+///
+/// # Examples
+///
+/// ```
+/// use hookmap::prelude::*;
+///
+/// let mut hotkey = Hotkey::new();
+/// hotkey
+///     .register(Context::default())
+///     .remap(Button::A, Button::B)
+///     .on_press(Button::C, |e| println!("{:?}", e));
+///
+/// ```
+pub struct Registrar<'a> {
+    storage: &'a mut HotkeyStorage,
+    context: Context,
+    id: RegistrationId,
+}
+
+/// Identifies the hotkeys registered through a [`Registrar`].
+/// Pass it to [`Hotkey::unregister`] to remove them. Callbacks can get the ID of their
+/// registration with [`registration_id`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RegistrationId(u64);
+
+impl<'a> Registrar<'a> {
+    /// The default window of [`Registrar::mouse_wheel_coalesced`].
+    pub const DEFAULT_WHEEL_COALESCING_WINDOW: Duration = Duration::from_millis(8);
+
+    /// The default timeout of [`Registrar::on_wheel_threshold`].
+    pub const DEFAULT_WHEEL_THRESHOLD_TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// Returns the ID of the hotkeys registered through this [`Registrar`].
+    /// See [`Hotkey::unregister`].
+    pub fn id(&self) -> RegistrationId {
+        self.id
+    }
+
+    /// Makes `target` behave like a `behavior`.
+    ///
+    /// The modifiers of hotkeys given by [`Context::modifiers`] observe the physical buttons
+    /// before they are remapped. If `target` is also a modifier of other hotkeys, they see it
+    /// held down while it is physically held, even though it is sent as `behavior`, and
+    /// [`Context::release_on_exit`] still releases their remaps when it is released.
+    /// `behavior` is seen as held down while the remap holds it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .remap(Button::A, Button::B);
+    /// ```
+    ///
+    pub fn remap(&mut self, targets: impl Into<ButtonArg>, behavior: Button) -> &mut Self {
+        let condition = self.context.to_condition();
+        self.remap_with_condition(targets.into(), behavior, condition, None)
+    }
+
+    /// Same as [`Registrar::remap`], but also runs `process` with each event of `targets`
+    /// handled by the remap.
+    ///
+    /// `process` is run right after `behavior` is pressed or released, including for the key
+    /// repeats of `targets`. Unlike registering [`Registrar::on_press`] separately, it runs
+    /// exactly when the remap does. Since `process` is run in the order of events on the
+    /// thread dispatching them, it should return quickly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .remap_and(Button::CapsLock, Button::Esc, |e: ButtonEvent| {
+    ///         println!("Esc: {:?}", e.action)
+    ///     });
+    /// ```
+    ///
+    pub fn remap_and(
+        &mut self,
+        targets: impl Into<ButtonArg>,
+        behavior: Button,
+        process: impl Into<Process<ButtonEvent>>,
+    ) -> &mut Self {
+        let condition = self.context.to_condition();
+        self.remap_with_condition(
+            targets.into(),
+            behavior,
+            condition,
+            Some(process.into().registered_as(self.id)),
+        )
+    }
+
+    /// Same as [`Registrar::remap`], but the remap is active only while `condition`
+    /// returns `true`.
+    ///
+    /// `condition` is called when `target` is pressed. Once a press is remapped, its key
+    /// repeats and release are remapped too, even if `condition` changes in the meantime.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let is_vim_mode = Arc::new(AtomicBool::new(false));
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .remap_if(Button::Esc, Button::CapsLock, move || {
+    ///         is_vim_mode.load(Ordering::SeqCst)
+    ///     });
+    /// ```
+    ///
+    pub fn remap_if(
+        &mut self,
+        targets: impl Into<ButtonArg>,
+        behavior: Button,
+        condition: impl Fn() -> bool + Send + Sync + 'static,
+    ) -> &mut Self {
+        let condition = Condition::Predicate(
+            Box::new(self.context.to_condition()),
+            Predicate(Arc::new(condition)),
+        );
+        self.remap_with_condition(targets.into(), behavior, condition, None)
+    }
+
+    fn remap_with_condition(
+        &mut self,
+        targets: ButtonArg,
+        behavior: Button,
+        condition: Condition,
+        process: Option<Process<ButtonEvent>>,
+    ) -> &mut Self {
+        let holders = self.storage.remap_holders(behavior);
+        let hook = Arc::new(RemapHook::new(condition, behavior, holders).with_process(process));
+        assert!(targets.is_all_plain());
+
+        if self.context.release_on_exit {
+            let release_hook = Arc::new(HotkeyHook::new(
+                Condition::Any,
+                HotkeyAction::ReleaseRemap(Arc::clone(&hook)),
+                NativeEventOperation::Dispatch,
+            ));
+            let modifiers: ButtonSet = self
+                .context
+                .iter_pressed()
+                .flat_map(|&modifier| runtime::sides(modifier))
+                .collect();
+            for modifier in modifiers.iter() {
+                self.storage
+                    .register_hotkey_on_release(modifier, Arc::clone(&release_hook));
+            }
+        }
+        self.storage.register_remap(targets.iter_plain(), hook);
+        self
+    }
+    /// Run `process` when `target` is pressed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_press(buttons!(A), |e| println!("Pressed: {:?}", e));
+    /// ```
+    ///
+    pub fn on_press(
+        &mut self,
+        targets: impl Into<ButtonArg>,
+        process: impl Into<Process<ButtonEvent>>,
+    ) -> &mut Self {
+        self.on_press_with(targets.into(), process.into(), false)
+    }
+
+    /// Same as [`Registrar::on_press`], but `process` is run on the thread that receives the
+    /// events from the hook, in the order of the events, instead of on a worker thread.
+    ///
+    /// The workers run the callbacks of the events at the same time, so they may see a press
+    /// after the release that follows it. `process` is run after the native event is blocked
+    /// or dispatched, and the next event is not handled until it returns, so it must return
+    /// quickly, such as by sending the event to another thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use std::sync::mpsc;
+    ///
+    /// let (tx, _rx) = mpsc::sync_channel(64);
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_press_sync(buttons!(A), move |e: ButtonEvent| {
+    ///         let _ = tx.try_send(e);
+    ///     });
+    /// ```
+    ///
+    pub fn on_press_sync(
+        &mut self,
+        targets: impl Into<ButtonArg>,
+        process: impl Into<Process<ButtonEvent>>,
+    ) -> &mut Self {
+        self.on_press_with(targets.into(), process.into(), true)
+    }
+
+    fn on_press_with(
+        &mut self,
+        targets: ButtonArg,
+        process: Process<ButtonEvent>,
+        ordered: bool,
+    ) -> &mut Self {
+        let hook = HotkeyHook::new(
+            self.context.to_condition(),
+            HotkeyAction::Process(process.registered_as(self.id)),
+            self.context.native_event_operation,
+        );
+        let hook = Arc::new(if ordered { hook.ordered() } else { hook });
+
+        for target in targets.iter_plain() {
+            self.storage
+                .register_hotkey_on_press(target, Arc::clone(&hook));
+        }
+        for target in targets.iter_not() {
+            self.storage
+                .register_hotkey_on_release(target, Arc::clone(&hook));
+        }
+        self
+    }
+
+    /// Run `process` the first time any of `targets` is pressed, and never again.
+    ///
+    /// This is useful for prompts such as "press any key to continue". Once `process` has
+    /// been run, the hotkey neither runs nor blocks the events of any of `targets`, as if it
+    /// had been unregistered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_press_once(buttons!(Enter, Space), |_| println!("Continuing"));
+    /// ```
+    ///
+    pub fn on_press_once(
+        &mut self,
+        targets: impl Into<ButtonArg>,
+        process: impl Into<Process<ButtonEvent>>,
+    ) -> &mut Self {
+        let targets = targets.into();
+        let condition = Condition::Once(Box::new(self.context.to_condition()), Arc::default());
+        let hook = Arc::new(HotkeyHook::new(
+            condition,
+            HotkeyAction::Process(process.into().registered_as(self.id)),
+            self.context.native_event_operation,
+        ));
+
+        for target in targets.iter_plain() {
+            self.storage
+                .register_hotkey_on_press(target, Arc::clone(&hook));
+        }
+        for target in targets.iter_not() {
+            self.storage
+                .register_hotkey_on_release(target, Arc::clone(&hook));
+        }
+        self
+    }
+
+    /// Run `process` when `target` is pressed, and block the event only if `process`
+    /// returns `true`.
+    ///
+    /// Unlike [`Registrar::on_press`], `process` decides whether it consumed the event, so
+    /// it is run on the thread of the hook before the event is passed to other programs, and
+    /// must return quickly. Whether the context blocks the event is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_press_consuming(Button::Tab, |_| Button::CapsLock.is_toggled());
+    /// ```
+    ///
+    pub fn on_press_consuming(
+        &mut self,
+        targets: impl Into<ButtonArg>,
+        process: impl Fn(ButtonEvent) -> bool + Send + Sync + 'static,
+    ) -> &mut Self {
+        let targets = targets.into();
+        let hook = Arc::new(HotkeyHook::new(
+            self.context.to_condition(),
+            HotkeyAction::Consume(Consumer(Arc::new(process))),
+            NativeEventOperation::Dispatch,
+        ));
+
+        for target in targets.iter_plain() {
+            self.storage
+                .register_hotkey_on_press(target, Arc::clone(&hook));
+        }
+        for target in targets.iter_not() {
+            self.storage
+                .register_hotkey_on_release(target, Arc::clone(&hook));
+        }
+        self
+    }
+
+    /// Run `process` when any button for which `predicate` returns `true` is pressed.
+    ///
+    /// `predicate` is evaluated for every button when this is called. The hotkeys registered
+    /// with this are run after those registered for the pressed button itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.register(Context::default()).on_press_matching(
+    ///     |button| button.category() == ButtonCategory::Function,
+    ///     |e: ButtonEvent| println!("Function key: {:?}", e.target),
+    /// );
+    /// ```
+    ///
+    pub fn on_press_matching(
+        &mut self,
+        predicate: impl Fn(Button) -> bool,
+        process: impl Into<Process<ButtonEvent>>,
+    ) -> &mut Self {
+        let targets = Button::ALL.into_iter().filter(|&button| predicate(button));
+        let hook = Arc::new(HotkeyHook::new(
+            self.context.to_condition(),
+            HotkeyAction::Process(process.into().registered_as(self.id)),
+            self.context.native_event_operation,
+        ));
+        self.storage
+            .register_hotkey_on_press_matching(targets.collect(), hook);
+        self
+    }
+
+    /// Run `process` when a button event matches no other hotkey.
+    ///
+    /// `process` is run for the presses and releases of the buttons for which no remap or
+    /// hotkey is registered, or whose hotkeys are not active in their contexts, e.g. to log
+    /// unbound keys. Registering this makes every button event go through the hotkeys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .remap(Button::CapsLock, Button::LCtrl)
+    ///     .on_unhandled(|e: ButtonEvent| println!("Unbound: {:?}", e.target));
+    /// ```
+    ///
+    pub fn on_unhandled(&mut self, process: impl Into<Process<ButtonEvent>>) -> &mut Self {
+        let hook = Arc::new(HotkeyHook::new(
+            self.context.to_condition(),
+            HotkeyAction::Process(process.into().registered_as(self.id)),
+            self.context.native_event_operation,
+        ));
+        self.storage.register_hotkey_on_unhandled(hook);
+        self
+    }
+
+    /// Run `process` when `modifier` is released without any other key pressed while it was
+    /// held down, such as tapping Alt alone to open a menu.
+    ///
+    /// Pressing other modifier keys does not cancel the tap, but pressing any other button,
+    /// including the mouse buttons, does, so Alt+Tab does not run `process`.
+    /// A generic modifier such as [`Button::Alt`] matches either side.
+    /// `process` is run only if the context is satisfied when `modifier` is released, and the
+    /// events of `modifier` are never blocked. Registering this makes every button event go
+    /// through the hotkeys.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `modifier` is not a modifier key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_modifier_tap(Button::LAlt, |_| println!("LAlt tapped"));
+    /// ```
+    ///
+    pub fn on_modifier_tap(
+        &mut self,
+        modifier: Button,
+        process: impl Into<Process<ButtonEvent>>,
+    ) -> &mut Self {
+        assert!(
+            modifier.category() == ButtonCategory::Modifier,
+            "`{modifier:?}` is not a modifier key."
+        );
+        let tap = Arc::new(ModifierTap::new(modifier));
+        let condition = Condition::Enabled(Box::new(self.context.to_condition()), tap.tapped());
+        let hook = Arc::new(HotkeyHook::new(
+            condition,
+            HotkeyAction::Process(process.into().registered_as(self.id)),
+            NativeEventOperation::Dispatch,
+        ));
+        for target in Button::ALL
+            .into_iter()
+            .filter(|b| b.matches_sided(modifier))
+        {
+            self.storage
+                .register_hotkey_on_release(target, Arc::clone(&hook));
+        }
+        self.storage.register_modifier_tap(tap);
+        self
+    }
+
+    /// Run `process` when `target` is released.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_release(buttons!(A), |e| println!("Released: {:?}", e));
+    /// ```
+    ///
+    pub fn on_release(
+        &mut self,
+        targets: impl Into<ButtonArg>,
+        process: impl Into<Process<ButtonEvent>>,
+    ) -> &mut Self {
+        self.on_release_with(targets.into(), process.into(), false)
+    }
+
+    /// Same as [`Registrar::on_release`], but `process` is run on the thread that receives
+    /// the events from the hook, in the order of the events, instead of on a worker thread.
+    ///
+    /// See [`Registrar::on_press_sync`] for when this is needed and what `process` must do.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use std::sync::mpsc;
+    ///
+    /// let (tx, _rx) = mpsc::sync_channel(64);
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_release_sync(buttons!(A), move |e: ButtonEvent| {
+    ///         let _ = tx.try_send(e);
+    ///     });
+    /// ```
+    ///
+    pub fn on_release_sync(
+        &mut self,
+        targets: impl Into<ButtonArg>,
+        process: impl Into<Process<ButtonEvent>>,
+    ) -> &mut Self {
+        self.on_release_with(targets.into(), process.into(), true)
+    }
+
+    fn on_release_with(
+        &mut self,
+        targets: ButtonArg,
+        process: Process<ButtonEvent>,
+        ordered: bool,
+    ) -> &mut Self {
+        let condition = self.context.to_condition();
+        let process = HotkeyAction::Process(process.registered_as(self.id));
+        let hook = |condition| {
+            let hook = HotkeyHook::new(
+                condition,
+                process.clone(),
+                self.context.native_event_operation,
+            );
+            Arc::new(if ordered { hook.ordered() } else { hook })
+        };
+
+        if self.context.has_no_modifiers() {
+            let hook = hook(condition);
+
+            for target in targets.iter_plain() {
+                self.storage
+                    .register_hotkey_on_release(target, Arc::clone(&hook));
+            }
+            for target in targets.iter_not() {
+                self.storage
+                    .register_hotkey_on_press(target, Arc::clone(&hook));
+            }
+            return self;
+        }
+
+        for target in targets.iter() {
+            let is_active = Arc::default();
+            let inactivation_hook = hook(Condition::Activation(Arc::clone(&is_active)));
+            let activation_hook = Arc::new(HotkeyHook::new(
+                condition.clone(),
+                HotkeyAction::Activate(is_active),
+                NativeEventOperation::Dispatch,
+            ));
+
+            match target {
+                ButtonArgUnit::Plain(target) => {
+                    self.storage
+                        .register_hotkey_on_press(target, Arc::clone(&activation_hook));
+                    self.storage
+                        .register_hotkey_on_release(target, Arc::clone(&inactivation_hook));
+                }
+                ButtonArgUnit::Not(target) => {
+                    self.storage
+                        .register_hotkey_on_release(target, Arc::clone(&activation_hook));
+                    self.storage
+                        .register_hotkey_on_press(target, Arc::clone(&inactivation_hook));
+                }
+            }
+
+            for target in self.context.iter_pressed() {
+                self.storage
+                    .register_hotkey_on_release(*target, Arc::clone(&inactivation_hook));
+            }
+            for target in self.context.iter_released() {
+                self.storage
+                    .register_hotkey_on_press(*target, Arc::clone(&inactivation_hook));
+            }
+        }
+        self
+    }
+
+    /// Run `process` when the last of `buttons` is pressed while the others are held down,
+    /// only if they were pressed in the order of `buttons`.
+    ///
+    /// Unlike `modifiers` of [`Context`], this distinguishes "LCtrl then C" from
+    /// "C then LCtrl".
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buttons` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_ordered_combo(&[Button::LCtrl, Button::LShift, Button::Tab], |_| {
+    ///         println!("LCtrl, LShift, Tab")
+    ///     });
+    /// ```
+    ///
+    pub fn on_ordered_combo(
+        &mut self,
+        buttons: &[Button],
+        process: impl Into<Process<ButtonEvent>>,
+    ) -> &mut Self {
+        let (&trigger, held) = buttons.split_last().expect("`buttons` must not be empty");
+        let hook = Arc::new(HotkeyHook::new(
+            Condition::Ordered(Box::new(self.context.to_condition()), held.into()),
+            HotkeyAction::Process(process.into().registered_as(self.id)),
+            self.context.native_event_operation,
+        ));
+        self.storage.observe(held.iter().copied());
+        self.storage.register_hotkey_on_press(trigger, hook);
+        self
+    }
+
+    /// Run `process` when one of `buttons` is released after all of them were held down.
+    ///
+    /// `process` runs once each time the combination is broken, with the event of the first
+    /// button released. Releasing the other buttons afterwards does not run it again, nor does
+    /// releasing the buttons before all of them were held down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buttons` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_combo_release(&[Button::LCtrl, Button::LShift, Button::K], |_| {
+    ///         println!("LCtrl + LShift + K released")
+    ///     });
+    /// ```
+    ///
+    pub fn on_combo_release(
+        &mut self,
+        buttons: &[Button],
+        process: impl Into<Process<ButtonEvent>>,
+    ) -> &mut Self {
+        assert!(!buttons.is_empty(), "`buttons` must not be empty");
+        let is_active = Arc::default();
+        let activation_hook = Arc::new(HotkeyHook::new(
+            Condition::Held(Box::new(self.context.to_condition()), buttons.into()),
+            HotkeyAction::Activate(Arc::clone(&is_active)),
+            NativeEventOperation::Dispatch,
+        ));
+        let inactivation_hook = Arc::new(HotkeyHook::new(
+            Condition::Activation(is_active),
+            HotkeyAction::Process(process.into().registered_as(self.id)),
+            self.context.native_event_operation,
+        ));
+        for &button in buttons {
+            self.storage
+                .register_hotkey_on_press(button, Arc::clone(&activation_hook));
+            self.storage
+                .register_hotkey_on_release(button, Arc::clone(&inactivation_hook));
+        }
+        self
+    }
+
+    /// Makes `target` behave like `behavior`, but holds `behavior` down for at most `duration`.
+    ///
+    /// When `target` is pressed, `behavior` is pressed and then released when `duration`
+    /// elapses or `target` is released, whichever comes first. Key repeats of `target` are
+    /// blocked. Only the press needs the context to be satisfied, so `behavior` is released
+    /// even if the modifiers are released before `target`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.register(Context::default()).remap_hold(
+    ///     Button::F21,
+    ///     Button::Enter,
+    ///     Duration::from_millis(100),
+    /// );
+    /// ```
+    ///
+    pub fn remap_hold(
+        &mut self,
+        target: Button,
+        behavior: Button,
+        duration: Duration,
+    ) -> &mut Self {
+        self.remap_hold_with(target, duration, move |action| match action {
+            ButtonAction::Press => behavior.press(),
+            ButtonAction::Release => behavior.release(),
+        })
+    }
+
+    fn remap_hold_with(
+        &mut self,
+        target: Button,
+        duration: Duration,
+        output: impl Fn(ButtonAction) + Send + Sync + 'static,
+    ) -> &mut Self {
+        let hold = Arc::new(TimedHold::new(duration, output));
+        let (hold_, hold__) = (Arc::clone(&hold), Arc::clone(&hold));
+        self.register_held(
+            target,
+            move || hold.is_held(),
+            move |_| hold_.press(),
+            move |_| hold__.release(),
+        )
+    }
+
+    /// Makes `target` behave like `behavior`, but repeats `behavior` at a fixed `interval`
+    /// while `target` is held down, regardless of the key repeat settings of the OS.
+    ///
+    /// When `target` is pressed, `behavior` is pressed, and then pressed again every
+    /// `interval` until `target` is released. Key repeats of `target` are blocked. Only the
+    /// press needs the context to be satisfied, so the repeat stops even if the modifiers are
+    /// released before `target`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.register(Context::default()).remap_repeat(
+    ///     Button::F21,
+    ///     Button::DownArrow,
+    ///     Duration::from_millis(50),
+    /// );
+    /// ```
+    ///
+    pub fn remap_repeat(
+        &mut self,
+        target: Button,
+        behavior: Button,
+        interval: Duration,
+    ) -> &mut Self {
+        self.remap_repeat_with(target, interval, move |action| match action {
+            ButtonAction::Press => behavior.press(),
+            ButtonAction::Release => behavior.release(),
+        })
+    }
+
+    fn remap_repeat_with(
+        &mut self,
+        target: Button,
+        interval: Duration,
+        output: impl Fn(ButtonAction) + Send + Sync + 'static,
+    ) -> &mut Self {
+        let repeat = Arc::new(AutoRepeat::new(interval, output));
+        let (repeat_, repeat__) = (Arc::clone(&repeat), Arc::clone(&repeat));
+        self.register_held(
+            target,
+            move || repeat.is_held(),
+            move |_| repeat_.press(),
+            move |_| repeat__.release(),
+        )
+    }
+
+    /// Runs `process` with every button event in the order of events, before the hotkeys
+    /// handle it.
+    ///
+    /// This neither blocks the events nor affects the hotkeys that handle them, and the
+    /// context is ignored, so that `process` can keep track of the buttons.
+    pub(crate) fn observe_buttons(
+        &mut self,
+        process: impl Fn(ButtonEvent) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.storage
+            .register_button_observer(Arc::new(Process::from(process)));
+        self
+    }
+
+    /// Blocks `target` and runs `press` and `release` for it in the order of events.
+    ///
+    /// Only the press is gated by the context. While `is_held` returns `true`, the key
+    /// repeats and the release of `target` are handled regardless of the context, so that
+    /// releasing the modifiers first does not leave the behavior held down.
+    pub(crate) fn register_held(
+        &mut self,
+        target: Button,
+        is_held: impl Fn() -> bool + Send + Sync + 'static,
+        press: impl Fn(ButtonEvent) + Send + Sync + 'static,
+        release: impl Fn(ButtonEvent) + Send + Sync + 'static,
+    ) -> &mut Self {
+        let held = Condition::Predicate(Box::new(Condition::Any), Predicate(Arc::new(is_held)));
+        let on_press = HotkeyHook::new(
+            self.context.to_condition(),
+            HotkeyAction::Process(press.into()),
+            NativeEventOperation::Block,
+        );
+        let on_repeat = HotkeyHook::new(
+            held.clone(),
+            HotkeyAction::Noop,
+            NativeEventOperation::Block,
+        );
+        let on_release = HotkeyHook::new(
+            held,
+            HotkeyAction::Process(release.into()),
+            NativeEventOperation::Block,
+        );
+        self.storage
+            .register_hotkey_on_press(target, Arc::new(on_press.ordered()));
+        self.storage
+            .register_hotkey_on_press(target, Arc::new(on_repeat.ordered()));
+        self.storage
+            .register_hotkey_on_release(target, Arc::new(on_release.ordered()));
+        self
+    }
+
+    /// Makes `target` rotate the mouse wheel by `notches` in `direction` instead of being
+    /// input.
+    ///
+    /// The wheel is rotated on every press of `target`, including its key repeats, so holding
+    /// it down keeps scrolling. Both the press and the release of `target` are blocked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use hookmap::hotkey::WheelDirection;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .remap_to_wheel(Button::PageDown, WheelDirection::Down, 3)
+    ///     .remap_to_wheel(Button::PageUp, WheelDirection::Up, 3);
+    /// ```
+    ///
+    pub fn remap_to_wheel(
+        &mut self,
+        target: Button,
+        direction: WheelDirection,
+        notches: u32,
+    ) -> &mut Self {
+        self.remap_to_wheel_with(target, direction.delta(notches), mouse::rotate)
+    }
+
+    fn remap_to_wheel_with(
+        &mut self,
+        target: Button,
+        delta: i32,
+        rotate: impl Fn(i32) + Send + Sync + 'static,
+    ) -> &mut Self {
+        let condition = self.context.to_condition();
+        let on_press = Arc::new(HotkeyHook::new(
+            condition.clone(),
+            HotkeyAction::Process((move |_| rotate(delta)).into()),
+            NativeEventOperation::Block,
+        ));
+        let on_release = Arc::new(HotkeyHook::new(
+            condition,
+            HotkeyAction::Process((|_| {}).into()),
+            NativeEventOperation::Block,
+        ));
+        self.storage.register_hotkey_on_press(target, on_press);
+        self.storage.register_hotkey_on_release(target, on_release);
+        self
+    }
+
+    /// Makes `target` behave like one of the buttons in `mapping`, depending on how many
+    /// times it is tapped in a row.
+    ///
+    /// `target` is blocked, and each pair `(n, behavior)` of `mapping` clicks `behavior`
+    /// when `target` is tapped `n` times with less than `interval` between a release and the
+    /// next press. Since a further tap may follow, the behavior is clicked `interval` after
+    /// the last release, except for the largest count in `mapping`, which is clicked
+    /// immediately. Counts that are not in `mapping` click nothing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mapping` contains a count of zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.register(Context::default()).tap_count_remap(
+    ///     Button::F21,
+    ///     vec![(1, Button::Esc), (2, Button::Tab)],
+    ///     Duration::from_millis(200),
+    /// );
+    /// ```
+    ///
+    pub fn tap_count_remap(
+        &mut self,
+        target: Button,
+        mapping: Vec<(u32, Button)>,
+        interval: Duration,
+    ) -> &mut Self {
+        assert!(
+            mapping.iter().all(|&(count, _)| count > 0),
+            "tap counts must be positive"
+        );
+        let counter = Arc::new(TapCounter::new(mapping, interval, Button::click));
+        let condition = self.context.to_condition();
+
+        // The taps are counted in the order of events, since a release handled before its
+        // press would be lost.
+        let counter_ = Arc::clone(&counter);
+        let on_press = HotkeyHook::new(
+            condition.clone(),
+            HotkeyAction::Process((move |_| counter_.press()).into()),
+            NativeEventOperation::Block,
+        );
+        let on_release = HotkeyHook::new(
+            condition,
+            HotkeyAction::Process((move |_| counter.release()).into()),
+            NativeEventOperation::Block,
+        );
+        let (on_press, on_release) = (Arc::new(on_press.ordered()), Arc::new(on_release.ordered()));
+        self.storage.register_hotkey_on_press(target, on_press);
+        self.storage.register_hotkey_on_release(target, on_release);
+        self
+    }
+
+    /// Run `process` when a mouse wheel is rotated.
+    ///
+    /// As with buttons, `process` is run only while the modifiers of the context are
+    /// satisfied, and the native event is blocked if the context says so.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .mouse_wheel(|e: WheelEvent| println!("Delta: {}", e.delta));
+    /// ```
+    ///
+    pub fn mouse_wheel(&mut self, process: impl Into<Process<WheelEvent>>) -> &mut Self {
+        let hook = Arc::new(MouseHook::new(
+            self.context.to_condition(),
+            process.into().registered_as(self.id),
+            self.context.native_event_operation,
+        ));
+        self.storage.register_mouse_wheel_hotkey(hook);
+        self
+    }
+
+    /// Run `process` with the sum of the mouse wheel rotations within a short window.
+    ///
+    /// Precision touchpads generate many small wheel events. This calls `process` once per
+    /// [`Registrar::DEFAULT_WHEEL_COALESCING_WINDOW`] instead of once per event.
+    /// The hook carries rotations of less than a notch over to the following events, so most
+    /// of these events have a delta of `0`. `process` is not called if the rotations within
+    /// the window do not add up to a notch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .mouse_wheel_coalesced(|e: WheelEvent| println!("Delta: {}", e.delta));
+    /// ```
+    ///
+    pub fn mouse_wheel_coalesced(&mut self, process: impl Into<Process<WheelEvent>>) -> &mut Self {
+        self.mouse_wheel_coalesced_within(Self::DEFAULT_WHEEL_COALESCING_WINDOW, process)
+    }
+
+    /// Same as [`Registrar::mouse_wheel_coalesced`], but the window is specified by `window`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .mouse_wheel_coalesced_within(Duration::from_millis(50), |e: WheelEvent| {
+    ///         println!("Delta: {}", e.delta)
+    ///     });
+    /// ```
+    ///
+    pub fn mouse_wheel_coalesced_within(
+        &mut self,
+        window: Duration,
+        process: impl Into<Process<WheelEvent>>,
+    ) -> &mut Self {
+        let process = process.into().registered_as(self.id);
+        let coalescer = Arc::new(WheelCoalescer::default());
+        self.mouse_wheel(move |event: WheelEvent| {
+            if coalescer.push(event.delta) {
+                let coalescer = Arc::clone(&coalescer);
+                let process = process.clone();
+                runtime::schedule(Instant::now() + window, move || {
+                    let delta = coalescer.take();
+                    if delta != 0 {
+                        let mut event = event;
+                        event.delta = delta;
+                        runtime::spawn_callback(move || process.0(event));
+                    }
+                });
+            }
+        })
+    }
+
+    /// Run `process` each time a mouse wheel is rotated `notches` notches in `direction`.
+    ///
+    /// This avoids triggering by an accidental single notch. The count is reset when the
+    /// wheel is rotated in the other direction, or when no notch is rotated for
+    /// [`Registrar::DEFAULT_WHEEL_THRESHOLD_TIMEOUT`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::hotkey::WheelDirection;
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_wheel_threshold(WheelDirection::Up, 2, |_| println!("Zoom in"))
+    ///     .on_wheel_threshold(WheelDirection::Down, 2, |_| println!("Zoom out"));
+    /// ```
+    ///
+    pub fn on_wheel_threshold(
+        &mut self,
+        direction: WheelDirection,
+        notches: u32,
+        process: impl Into<Process<WheelEvent>>,
+    ) -> &mut Self {
+        self.on_wheel_threshold_within(
+            direction,
+            notches,
+            Self::DEFAULT_WHEEL_THRESHOLD_TIMEOUT,
+            process,
+        )
+    }
+
+    /// Same as [`Registrar::on_wheel_threshold`], but the timeout is specified by `timeout`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::hotkey::WheelDirection;
+    /// use hookmap::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.register(Context::default()).on_wheel_threshold_within(
+    ///     WheelDirection::Down,
+    ///     3,
+    ///     Duration::from_secs(1),
+    ///     |_| println!("Three notches down"),
+    /// );
+    /// ```
+    ///
+    pub fn on_wheel_threshold_within(
+        &mut self,
+        direction: WheelDirection,
+        notches: u32,
+        timeout: Duration,
+        process: impl Into<Process<WheelEvent>>,
+    ) -> &mut Self {
+        let process = process.into().registered_as(self.id);
+        let threshold = WheelThreshold::new(direction, notches, timeout);
+        self.mouse_wheel(move |event: WheelEvent| {
+            if threshold.push(event.delta, Instant::now()) {
+                process.0(event);
+            }
+        })
+    }
+
+    /// Run `process` when a mouse cursor is moved.
+    ///
+    /// As with buttons, `process` is run only while the modifiers of the context are
+    /// satisfied, and the native event is blocked if the context says so.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .mouse_cursor(|e: CursorEvent| println!("movement distance: {:?}", e.delta));
+    /// ```
+    ///
+    pub fn mouse_cursor(&mut self, process: impl Into<Process<CursorEvent>>) -> &mut Self {
+        let hook = Arc::new(MouseHook::new(
+            self.context.to_condition(),
+            self.cursor_process(process.into()),
+            self.context.native_event_operation,
+        ));
+        self.storage.register_mouse_cursor_hotkey(hook);
+        self
+    }
+
+    /// Same as [`Registrar::mouse_cursor`], but `process` is run on the thread that receives
+    /// the events from the hook, in the order of the events, instead of on a worker thread.
+    ///
+    /// This removes the latency and the reordering of handing each move to another thread,
+    /// which matters when `process` moves or transforms the cursor itself.
+    /// The next event is not handled until `process` returns, so it must return quickly.
+    /// A slow `process` delays all the input, and the OS may remove the hook if it does not
+    /// respond in time.
+    ///
+    /// Whether the native move is blocked is decided only by
+    /// [`Context::native_event_operation`] of the context, since `process` is run after the
+    /// native event is blocked or dispatched. To move the cursor somewhere else, block the
+    /// moves in the context and move the cursor from `process`, e.g. with
+    /// [`Context::accumulate_cursor_position`] to follow the position the user points at.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .mouse_cursor_sync(|e: CursorEvent| println!("moved to {:?}", e.position));
+    /// ```
+    ///
+    pub fn mouse_cursor_sync(&mut self, process: impl Into<Process<CursorEvent>>) -> &mut Self {
+        let hook = MouseHook::new(
+            self.context.to_condition(),
+            self.cursor_process(process.into()),
+            self.context.native_event_operation,
+        );
+        self.storage
+            .register_mouse_cursor_hotkey(Arc::new(hook.ordered()));
+        self
+    }
+
+    // Wraps `process` to receive accumulated positions if the context asks for them.
+    fn cursor_process(&self, process: Process<CursorEvent>) -> Process<CursorEvent> {
+        let process = process.registered_as(self.id);
+        if !self.context.accumulate_cursor_position {
+            return process;
+        }
+        let accumulator = CursorAccumulator::default();
+        Process(Arc::new(move |event| {
+            process.0(accumulator.accumulate(event))
+        }))
+    }
+
+    /// Run `process` when the mouse cursor stays at `edge` of the screen for `dwell`, and then
+    /// every `interval` until the cursor leaves the edge.
+    ///
+    /// The screen is the bounding rectangle of all monitors, so an edge between two monitors
+    /// is not an edge of the screen. Moving the cursor along the edge does not restart the
+    /// dwell time. If `interval` is `None`, `process` is run once per visit to the edge.
+    /// The cursor events are never blocked, and `process` is run on a worker thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use hookmap::hotkey::ScreenEdge;
+    /// use std::time::Duration;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.register(Context::default()).on_cursor_edge(
+    ///     ScreenEdge::Top,
+    ///     Duration::from_millis(500),
+    ///     Some(Duration::from_millis(200)),
+    ///     |edge| println!("the cursor is at the {edge:?} edge"),
+    /// );
+    /// ```
+    ///
+    pub fn on_cursor_edge(
+        &mut self,
+        edge: ScreenEdge,
+        dwell: Duration,
+        interval: Option<Duration>,
+        process: impl Into<Process<ScreenEdge>>,
+    ) -> &mut Self {
+        let process = process.into().registered_as(self.id);
+        let dwell = Arc::new(EdgeDwell::new(dwell, interval, move || {
+            let process = process.clone();
+            runtime::spawn_callback(move || process.0(edge));
+        }));
+        // The cursor events are followed in order, so that a stale position does not keep
+        // the dwell going after the cursor has left the edge.
+        let hook = MouseHook::new(
+            self.context.to_condition(),
+            Process::from(move |event: CursorEvent| {
+                dwell.update(edge.contains(event.position, mouse::screen_bounds()));
+            }),
+            NativeEventOperation::Dispatch,
+        );
+        self.storage
+            .register_mouse_cursor_hotkey(Arc::new(hook.ordered()));
+        self
+    }
+
+    /// Run `process` with the last cursor event when the mouse cursor has not moved for
+    /// `duration`.
+    ///
+    /// Every cursor movement restarts the wait, so `process` is run once each time the
+    /// cursor comes to rest. The cursor events are never blocked, and `process` is run on a
+    /// worker thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_cursor_dwell(Duration::from_millis(800), |e: CursorEvent| {
+    ///         println!("the cursor rests at {:?}", e.position);
+    ///     });
+    /// ```
+    ///
+    pub fn on_cursor_dwell(
+        &mut self,
+        duration: Duration,
+        process: impl Into<Process<CursorEvent>>,
+    ) -> &mut Self {
+        let process = process.into().registered_as(self.id);
+        let dwell = Arc::new(CursorDwell::new(duration, move |event| {
+            let process = process.clone();
+            runtime::spawn_callback(move || process.0(event));
+        }));
+        // The cursor events are followed in order, so that the last one is reported.
+        let hook = MouseHook::new(
+            self.context.to_condition(),
+            Process::from(move |event: CursorEvent| dwell.moved(event)),
+            NativeEventOperation::Dispatch,
+        );
+        self.storage
+            .register_mouse_cursor_hotkey(Arc::new(hook.ordered()));
+        self
+    }
+
+    /// Run `process` when any mouse event occurs.
+    ///
+    /// Button, wheel and cursor events of the mouse are passed to `process` in the order in which
+    /// they occurred.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_mouse(|e: MouseEvent| println!("{:?}", e));
+    /// ```
+    ///
+    pub fn on_mouse(&mut self, process: impl Into<Process<MouseEvent>>) -> &mut Self {
+        let hook = Arc::new(MouseHook::new(
+            self.context.to_condition(),
+            process.into().registered_as(self.id),
+            self.context.native_event_operation,
+        ));
+        self.storage.register_mouse_hotkey(hook);
+        self
+    }
+
+    /// Blocks mouse cursor movements and moves the cursor by the distance transformed by `transform`.
+    ///
+    /// `transform` receives the movement distance `(dx, dy)` and returns a new one.
+    /// Movements generated by this program are not transformed.
+    ///
+    /// If too many movements generated by this program are observed in a short time,
+    /// the transformation is considered to be in a feedback loop and is disabled.
+    /// After that, the movements are passed through as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .transform_cursor(|(dx, dy)| (dx * 2, dy * 2));
+    /// ```
+    ///
+    pub fn transform_cursor<F>(&mut self, transform: F) -> &mut Self
+    where
+        F: Fn((i32, i32)) -> (i32, i32) + Send + Sync + 'static,
+    {
+        self.transform_cursor_with_error(transform, || {})
+    }
+
+    /// Same as [`Registrar::transform_cursor`], but `on_error` is called when the transformation
+    /// is disabled due to a feedback loop.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .transform_cursor_with_error(
+    ///         |(dx, dy)| (dx * 2, dy * 2),
+    ///         || eprintln!("Cursor transformation is disabled."),
+    ///     );
+    /// ```
+    ///
+    pub fn transform_cursor_with_error<F, G>(&mut self, transform: F, on_error: G) -> &mut Self
+    where
+        F: Fn((i32, i32)) -> (i32, i32) + Send + Sync + 'static,
+        G: Fn() + Send + Sync + 'static,
+    {
+        let governor = Governor::default();
+        let process = move |event: CursorEvent| {
+            let (dx, dy) = match governor.observe(event.injected, Instant::now()) {
+                GovernorState::Active if !event.injected => transform(event.delta.into()),
+                GovernorState::Active | GovernorState::Disabled => event.delta.into(),
+                GovernorState::Tripped => {
+                    on_error();
+                    event.delta.into()
+                }
+            };
+            mouse::move_relative(dx, dy);
+        };
+        let hook = Arc::new(MouseHook::new(
+            self.context.to_condition(),
+            process.into(),
+            NativeEventOperation::Block,
+        ));
+        self.storage.register_mouse_cursor_hotkey(hook);
+        self
+    }
+
+    /// Disables the button and blocks events.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .disable(buttons!(A));
+    /// ```
+    ///
+    pub fn disable(&mut self, targets: impl Into<ButtonArg>) -> &mut Self {
+        let targets = targets.into();
+        self.disable_inner(&targets, ButtonAction::Press);
+        self.disable_inner(&targets, ButtonAction::Release);
+        self
+    }
+
+    /// Blocks only press events of the button. Release events are not blocked.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .disable_press(buttons!(A));
+    /// ```
+    ///
+    pub fn disable_press(&mut self, targets: impl Into<ButtonArg>) -> &mut Self {
+        self.disable_inner(&targets.into(), ButtonAction::Press);
+        self
+    }
+
+    /// Blocks only release events of the button. Press events are not blocked.
+    ///
+    /// Note that on Windows, release events of keyboard keys are always passed to other programs,
+    /// so this only takes effect on mouse buttons.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .disable_release(buttons!(SideButton1));
+    /// ```
+    ///
+    pub fn disable_release(&mut self, targets: impl Into<ButtonArg>) -> &mut Self {
+        self.disable_inner(&targets.into(), ButtonAction::Release);
+        self
+    }
+
+    fn disable_inner(&mut self, targets: &ButtonArg, action: ButtonAction) {
+        let hook = Arc::new(HotkeyHook::new(
+            self.context.to_condition(),
+            HotkeyAction::Noop,
+            NativeEventOperation::Block,
+        ));
+        assert!(targets.is_all_plain());
+
+        for target in targets.iter_plain() {
+            match action {
+                ButtonAction::Press => self
+                    .storage
+                    .register_hotkey_on_press(target, Arc::clone(&hook)),
+                ButtonAction::Release => self
+                    .storage
+                    .register_hotkey_on_release(target, Arc::clone(&hook)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hook::ButtonHook;
+    use super::*;
+    use crate::hook::{ButtonState, Hook, HookStorage};
+    use crate::runtime::FakeButtonState;
+    use hookmap_core::button::ButtonCategory;
+    use hookmap_core::event::{Event, Position};
+    use std::sync::Mutex;
+    use std::thread;
+
+    fn native_event_operation(
+        hotkey: &Hotkey,
+        target: Button,
+        action: ButtonAction,
+    ) -> NativeEventOperation {
+        let event = ButtonEvent::new(target, action);
+        let hooks = hotkey
+            .storage
+            .fetch_button_hook(event, &FakeButtonState::default());
+        if hooks
+            .iter()
+            .any(|hook| hook.native_event_operation() == NativeEventOperation::Block)
+        {
+            NativeEventOperation::Block
+        } else {
+            NativeEventOperation::Dispatch
+        }
+    }
+
+    #[test]
+    fn kill_switch_is_not_blocked_by_disable() {
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .disable(crate::buttons!(LCtrl, F12));
+        hotkey.kill_switch(crate::buttons!(LCtrl, F12));
+        assert_eq!(
+            native_event_operation(&hotkey, Button::F12, ButtonAction::Press),
+            NativeEventOperation::Block
+        );
+
+        let event = |target, action, injected| {
+            Event::Button(ButtonEvent::new(target, action).injected(injected))
+        };
+        let runtime = Runtime::with_state(hotkey.storage, FakeButtonState::held(&[Button::LCtrl]))
+            .with_kill_switch(hotkey.kill_switch);
+        assert!(runtime.is_killed(&event(Button::F12, ButtonAction::Press, false)));
+        assert!(!runtime.is_killed(&event(Button::F12, ButtonAction::Press, true)));
+        assert!(!runtime.is_killed(&event(Button::F12, ButtonAction::Release, false)));
+        assert!(!runtime.is_killed(&event(Button::LCtrl, ButtonAction::Press, false)));
+    }
+
+    #[test]
+    fn sub_notch_wheel_events_are_coalesced_into_one_call() {
+        let (output, rx) = runtime::recorder();
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .mouse_wheel_coalesced_within(Duration::from_millis(30), move |event: WheelEvent| {
+                output(event.delta)
+            });
+        let runtime = Runtime::with_state(hotkey.storage, FakeButtonState::default());
+        let rotate = |deltas: &[i32]| {
+            for &delta in deltas {
+                let _ = runtime.dispatch(Event::Wheel(WheelEvent::new(delta)), |_| {});
+            }
+        };
+
+        // The hook reports two notches over twelve events of a precision touchpad.
+        rotate(&[0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok(2));
+        assert!(rx.recv_timeout(Duration::from_millis(60)).is_err());
+
+        rotate(&[0, 0, 0]);
+        assert!(rx.recv_timeout(Duration::from_millis(60)).is_err());
+    }
+
+    #[test]
+    fn disabled_runtime_passes_events_through() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let is_game_focused = Arc::new(AtomicBool::new(true));
+        let is_game_focused_ = Arc::clone(&is_game_focused);
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::new().native_event_operation(NativeEventOperation::Block))
+            .disable(Button::A)
+            .mouse_wheel(|_| {});
+        let runtime = Runtime::with_state(hotkey.storage, FakeButtonState::default())
+            .with_disable_condition(Some(DisableCondition::new(move || {
+                is_game_focused_.load(Ordering::SeqCst)
+            })));
+        let dispatch = |event| {
+            let mut operation = None;
+            let _ = runtime.dispatch(event, |o| operation = Some(o));
+            operation
+        };
+        let button = |action| Event::Button(ButtonEvent::new(Button::A, action));
+        let wheel = Event::Wheel(WheelEvent::new(1));
+
+        assert_eq!(
+            dispatch(button(ButtonAction::Press)),
+            Some(NativeEventOperation::Dispatch)
+        );
+        // The game received the press, so it must receive the release too.
+        assert_eq!(
+            dispatch(button(ButtonAction::Release)),
+            Some(NativeEventOperation::Dispatch)
+        );
+        assert_eq!(dispatch(wheel), Some(NativeEventOperation::Dispatch));
+
+        is_game_focused.store(false, Ordering::SeqCst);
+        assert_eq!(
+            dispatch(button(ButtonAction::Press)),
+            Some(NativeEventOperation::Block)
+        );
+        assert_eq!(dispatch(wheel), Some(NativeEventOperation::Block));
+        // The hooks received the press, so they receive the release even when the condition
+        // is satisfied in between.
+        is_game_focused.store(true, Ordering::SeqCst);
+        assert_eq!(
+            dispatch(button(ButtonAction::Release)),
+            Some(NativeEventOperation::Block)
+        );
+    }
+
+    #[test]
+    fn buttons_passed_through_stay_passed_through_until_released() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let is_game_focused = Arc::new(AtomicBool::new(true));
+        let is_game_focused_ = Arc::clone(&is_game_focused);
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .remap(Button::F13, Button::F14);
+        let runtime = Runtime::with_state(hotkey.storage, FakeButtonState::default())
+            .with_disable_condition(Some(DisableCondition::new(move || {
+                is_game_focused_.load(Ordering::SeqCst)
+            })));
+        let dispatch = |action| {
+            let event = Event::Button(ButtonEvent::new(Button::F13, action));
+            let mut operation = None;
+            let _ = runtime.dispatch(event, |o| operation = Some(o));
+            operation
+        };
+
+        assert_eq!(
+            dispatch(ButtonAction::Press),
+            Some(NativeEventOperation::Dispatch)
+        );
+        // The game loses focus while the button is held down, including its key repeats.
+        is_game_focused.store(false, Ordering::SeqCst);
+        assert_eq!(
+            dispatch(ButtonAction::Press),
+            Some(NativeEventOperation::Dispatch)
+        );
+        assert_eq!(
+            dispatch(ButtonAction::Release),
+            Some(NativeEventOperation::Dispatch)
+        );
+
+        assert_eq!(
+            dispatch(ButtonAction::Press),
+            Some(NativeEventOperation::Block)
+        );
+        assert_eq!(
+            dispatch(ButtonAction::Release),
+            Some(NativeEventOperation::Block)
+        );
+    }
+
+    #[test]
+    fn unregistered_hotkeys_no_longer_fire() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let count_ = Arc::clone(&count);
+        let mut hotkey = Hotkey::new();
+        let id = hotkey
+            .register(Context::default())
+            .on_press(Button::A, move |_| {
+                count_.fetch_add(1, Ordering::SeqCst);
+            })
+            .remap(Button::B, Button::C)
+            .id();
+        hotkey.register(Context::default()).disable(Button::D);
+
+        let press = |target| ButtonEvent::new(target, ButtonAction::Press);
+        let hooks = hotkey
+            .storage
+            .fetch_button_hook(press(Button::A), &FakeButtonState::default());
+        hooks.iter().for_each(|hook| hook.run(press(Button::A)));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        assert!(hotkey.unregister(id));
+        for target in [Button::A, Button::B] {
+            assert!(hotkey
+                .storage
+                .fetch_button_hook(press(target), &FakeButtonState::default())
+                .is_empty());
+        }
+        assert_eq!(
+            native_event_operation(&hotkey, Button::D, ButtonAction::Press),
+            NativeEventOperation::Block
+        );
+        assert!(!hotkey.storage.handled_buttons().contains(Button::A));
+        assert!(!hotkey.unregister(id));
+    }
+
+    #[test]
+    fn unregister_stops_observing_buttons_and_drops_remap_holders() {
+        let mut hotkey = Hotkey::new();
+        hotkey.remap_release_policy(RemapReleasePolicy::RefCount);
+        let id = hotkey
+            .register(Context::new().modifiers_with_grace(Button::F13, Duration::from_millis(50)))
+            .remap(Button::F14, Button::F20)
+            .id();
+        hotkey
+            .register(Context::default())
+            .remap(Button::F15, Button::F21);
+        let handled = hotkey.storage.handled_buttons();
+        assert!(handled.contains(Button::F13));
+        assert!(handled.contains(Button::F14));
+
+        assert!(hotkey.unregister(id));
+        let handled = hotkey.storage.handled_buttons();
+        assert!(!handled.contains(Button::F13));
+        assert!(!handled.contains(Button::F14));
+        assert!(handled.contains(Button::F15));
+        assert!(!hotkey.storage.remap_holders.contains_key(&Button::F20));
+        assert!(hotkey.storage.remap_holders.contains_key(&Button::F21));
+    }
+
+    #[test]
+    fn registration_without_hooks_can_be_unregistered() {
+        let mut hotkey = Hotkey::new();
+        let id = hotkey.register(Context::default()).id();
+        assert!(hotkey.unregister(id));
+        assert!(!hotkey.unregister(id));
+    }
+
+    #[test]
+    fn registrar_registers_eagerly() {
+        let mut hotkey = Hotkey::new();
+        let mut registrar = hotkey.register(Context::default());
+        registrar.remap(Button::A, Button::B);
+        registrar.disable(Button::C);
+        drop(registrar);
+        let _ = hotkey.register(Context::default());
+
+        let press = |target| ButtonEvent::new(target, ButtonAction::Press);
+        for target in [Button::A, Button::C] {
+            assert_eq!(
+                hotkey
+                    .storage
+                    .fetch_button_hook(press(target), &FakeButtonState::default())
+                    .len(),
+                1
+            );
+        }
+        assert!(hotkey
+            .storage
+            .fetch_button_hook(press(Button::B), &FakeButtonState::default())
+            .is_empty());
+        assert_eq!(
+            native_event_operation(&hotkey, Button::C, ButtonAction::Press),
+            NativeEventOperation::Block
+        );
+    }
+
+    #[test]
+    fn on_press_matching_fires_for_matching_buttons() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .on_press_matching(
+                |button| button.category() == ButtonCategory::Function,
+                move |e: ButtonEvent| tx.lock().unwrap().send(e.target).unwrap(),
+            )
+            .disable(Button::F2);
+
+        let press = |target| ButtonEvent::new(target, ButtonAction::Press);
+        for target in [Button::F1, Button::F24, Button::A] {
+            let hooks = hotkey
+                .storage
+                .fetch_button_hook(press(target), &FakeButtonState::default());
+            hooks.iter().for_each(|hook| hook.run(press(target)));
+        }
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [Button::F1, Button::F24]);
+
+        let hooks = hotkey
+            .storage
+            .fetch_button_hook(press(Button::F2), &FakeButtonState::default());
+        assert_eq!(hooks.len(), 2);
+        assert_eq!(
+            hooks[0].native_event_operation(),
+            NativeEventOperation::Block
+        );
+        assert!(hotkey
+            .storage
+            .fetch_button_hook(
+                ButtonEvent::new(Button::F1, ButtonAction::Release),
+                &FakeButtonState::default()
+            )
+            .is_empty());
+
+        let handled = hotkey.storage.handled_buttons();
+        assert!(handled.contains(Button::F13));
+        assert!(!handled.contains(Button::A));
+    }
+
+    #[test]
+    fn remap_if_follows_condition() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let is_enabled = Arc::new(AtomicBool::new(false));
+        let is_enabled_ = Arc::clone(&is_enabled);
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .remap_if(Button::A, Button::B, move || {
+                is_enabled_.load(Ordering::SeqCst)
+            });
+
+        assert_eq!(
+            native_event_operation(&hotkey, Button::A, ButtonAction::Press),
+            NativeEventOperation::Dispatch
+        );
+        is_enabled.store(true, Ordering::SeqCst);
+        assert_eq!(
+            native_event_operation(&hotkey, Button::A, ButtonAction::Press),
+            NativeEventOperation::Block
+        );
+        // The remapped press is released through the remap after the condition is disabled.
+        is_enabled.store(false, Ordering::SeqCst);
+        assert_eq!(
+            native_event_operation(&hotkey, Button::A, ButtonAction::Release),
+            NativeEventOperation::Block
+        );
+        assert_eq!(
+            native_event_operation(&hotkey, Button::A, ButtonAction::Press),
+            NativeEventOperation::Dispatch
+        );
+    }
+
+    #[test]
+    fn remap_and_runs_process_once_per_event() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let count = Arc::new(AtomicUsize::new(0));
+        let count_ = Arc::clone(&count);
+        let mut hotkey = Hotkey::new();
+        hotkey.register(Context::default()).remap_and(
+            Button::F13,
+            Button::F14,
+            move |e: ButtonEvent| {
+                assert_eq!(e.target, Button::F13);
+                assert_eq!(Button::F14.is_pressed(), e.action == ButtonAction::Press);
+                count_.fetch_add(1, Ordering::SeqCst);
+            },
+        );
+
+        for (action, expected) in [(ButtonAction::Press, 1), (ButtonAction::Release, 2)] {
+            let event = ButtonEvent::new(Button::F13, action);
+            let hooks = hotkey
+                .storage
+                .fetch_button_hook(event, &FakeButtonState::default());
+            assert_eq!(hooks.len(), 1);
+            assert_eq!(
+                hooks[0].native_event_operation(),
+                NativeEventOperation::Block
+            );
+            hooks.iter().for_each(|hook| hook.run(event));
+            assert_eq!(count.load(Ordering::SeqCst), expected);
+        }
+        assert!(Button::F14.is_released());
+    }
+
+    #[test]
+    fn remap_to_modifier_satisfies_modifier_gates() {
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .remap(Button::F15, Button::RCtrl);
+        hotkey
+            .register(Context::new().modifiers(Button::RCtrl))
+            .disable(Button::F16);
+        hotkey
+            .register(Context::new().modifiers_with_grace(Button::RCtrl, Duration::from_secs(60)))
+            .disable(Button::F13);
+        let runtime = Runtime::new(hotkey.storage);
+        let dispatch = |target, action| {
+            let event = Event::Button(ButtonEvent::new(target, action));
+            let mut operation = None;
+            let _ = runtime.dispatch(event, |o| operation = Some(o));
+            operation.unwrap()
+        };
+        let is_blocked =
+            |target| dispatch(target, ButtonAction::Press) == NativeEventOperation::Block;
+
+        assert!(!is_blocked(Button::F16));
+        assert!(!is_blocked(Button::F13));
+
+        dispatch(Button::F15, ButtonAction::Press);
+        assert!(Button::RCtrl.is_pressed());
+        assert!(is_blocked(Button::F16));
+        assert!(is_blocked(Button::F13));
+
+        dispatch(Button::F15, ButtonAction::Release);
+        assert!(Button::RCtrl.is_released());
+        assert!(!is_blocked(Button::F16));
+        assert!(is_blocked(Button::F13));
+    }
+
+    #[test]
+    fn focus_change_releases_held_remaps() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .remap(Button::F17, Button::F18);
+        let foreground = Arc::new(AtomicU32::new(1));
+        let watch = {
+            let foreground = Arc::clone(&foreground);
+            FocusWatch::new(move || foreground.load(Ordering::SeqCst))
+        };
+        let runtime = Runtime::new(hotkey.storage).with_focus_watch(Some(watch));
+        let dispatch = |target, action| {
+            let event = Event::Button(ButtonEvent::new(target, action));
+            let mut operation = None;
+            let _ = runtime.dispatch(event, |o| operation = Some(o));
+            operation.unwrap()
+        };
+
+        dispatch(Button::F17, ButtonAction::Press);
+        assert!(Button::F18.is_pressed());
+        dispatch(Button::F17, ButtonAction::Press);
+        assert!(Button::F18.is_pressed());
+
+        // The release of F17 is lost while another window has the focus.
+        foreground.store(2, Ordering::SeqCst);
+        dispatch(Button::F19, ButtonAction::Press);
+        assert!(Button::F18.is_released());
+
+        let operation = dispatch(Button::F17, ButtonAction::Release);
+        assert_eq!(operation, NativeEventOperation::Dispatch);
+        assert!(Button::F18.is_released());
+    }
+
+    #[test]
+    fn releasing_modifier_releases_remaps_of_its_context() {
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(
+                Context::default()
+                    .modifiers(Button::Pause)
+                    .release_on_exit(true),
+            )
+            .remap(Button::Numpad8, Button::PrintScreen);
+        // The remap is released by the release event of Pause, not by the state.
+        let runtime = Runtime::with_state(hotkey.storage, FakeButtonState::held(&[Button::Pause]));
+        let dispatch = |target, action| {
+            let event = Event::Button(ButtonEvent::new(target, action));
+            let mut operation = None;
+            let _ = runtime.dispatch(event, |o| operation = Some(o));
+            operation.unwrap()
+        };
+
+        dispatch(Button::Pause, ButtonAction::Press);
+        dispatch(Button::Numpad8, ButtonAction::Press);
+        assert!(Button::PrintScreen.is_pressed());
+
+        // The layer key is released while the remapped key is still held down.
+        let operation = dispatch(Button::Pause, ButtonAction::Release);
+        assert_eq!(operation, NativeEventOperation::Dispatch);
+        assert!(Button::PrintScreen.is_released());
+
+        let operation = dispatch(Button::Numpad8, ButtonAction::Release);
+        assert_eq!(operation, NativeEventOperation::Dispatch);
+        assert!(Button::PrintScreen.is_released());
+    }
+
+    #[test]
+    fn remapped_modifier_is_observed_before_remapping() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx_ = Mutex::new(tx.clone());
+        let tx = Mutex::new(tx);
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .remap(Button::Pause, Button::F20);
+        hotkey
+            .register(
+                Context::default()
+                    .modifiers(Button::Pause)
+                    .release_on_exit(true),
+            )
+            .remap(Button::Numpad8, Button::PrintScreen)
+            .on_press(Button::F21, move |e: ButtonEvent| {
+                tx.lock().unwrap().send(e.target).unwrap()
+            });
+        hotkey
+            .register(Context::default().modifiers(Button::F20))
+            .on_press(Button::F22, move |e: ButtonEvent| {
+                tx_.lock().unwrap().send(e.target).unwrap()
+            });
+        // Only the events from the devices are recorded, so the state has the physical
+        // Pause but not F20 sent by the remap.
+        let state = FakeButtonState::default();
+        let runtime = Runtime::with_state(hotkey.storage, state.clone());
+        let dispatch = |target, action| {
+            let event = ButtonEvent::new(target, action);
+            state.record(event);
+            let mut operation = None;
+            let _ = runtime.dispatch(Event::Button(event), |o| operation = Some(o));
+            operation.unwrap()
+        };
+
+        assert_eq!(
+            dispatch(Button::Pause, ButtonAction::Press),
+            NativeEventOperation::Block
+        );
+        assert!(Button::F20.is_pressed());
+        dispatch(Button::F21, ButtonAction::Press);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok(Button::F21));
+        dispatch(Button::F22, ButtonAction::Press);
+        dispatch(Button::F22, ButtonAction::Release);
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+        dispatch(Button::Numpad8, ButtonAction::Press);
+        assert!(Button::PrintScreen.is_pressed());
+
+        // The release of the remapped Pause still ends the layer.
+        assert_eq!(
+            dispatch(Button::Pause, ButtonAction::Release),
+            NativeEventOperation::Block
+        );
+        assert!(Button::F20.is_released());
+        assert!(Button::PrintScreen.is_released());
+        dispatch(Button::Numpad8, ButtonAction::Release);
+        dispatch(Button::F21, ButtonAction::Release);
+        dispatch(Button::F21, ButtonAction::Press);
+        dispatch(Button::F21, ButtonAction::Release);
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn switching_profile_replaces_all_its_remaps() {
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .add_profile(
+                Profile::new("coding")
+                    .remap(Button::F13, Button::F14)
+                    .remap(Button::F15, Button::F16),
+            )
+            .add_profile(Profile::new("gaming").remap(Button::F13, Button::F17));
+        let remapped = |hotkey: &Hotkey, target| {
+            let event = ButtonEvent::new(target, ButtonAction::Press);
+            let hooks = hotkey
+                .storage
+                .fetch_button_hook(event, &FakeButtonState::default());
+            hooks.iter().find_map(|hook| match hook {
+                ButtonHook::Remap(hook) => {
+                    // Releases the source, so that the next press is matched again.
+                    hook.track(ButtonEvent::new(target, ButtonAction::Release));
+                    Some(hook.behavior())
+                }
+                ButtonHook::Hotkey(_) | ButtonHook::Consumed(_) => None,
+            })
+        };
+
+        assert_eq!(remapped(&hotkey, Button::F13), None);
+        assert!(hotkey.activate_profile("coding"));
+        assert_eq!(remapped(&hotkey, Button::F13), Some(Button::F14));
+        assert_eq!(remapped(&hotkey, Button::F15), Some(Button::F16));
+
+        let handle = hotkey.handle();
+        assert!(handle.activate_profile("gaming"));
+        assert_eq!(handle.active_profile().as_deref(), Some("gaming"));
+        assert_eq!(remapped(&hotkey, Button::F13), Some(Button::F17));
+        assert_eq!(remapped(&hotkey, Button::F15), None);
+
+        assert!(!handle.activate_profile("writing"));
+        assert_eq!(handle.active_profile().as_deref(), Some("gaming"));
+        handle.deactivate_profile();
+        assert_eq!(remapped(&hotkey, Button::F13), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "`A` is given both with and without `!`")]
+    fn contradicting_modifiers_are_rejected() {
+        let _ = Context::new().modifiers(crate::buttons!(A, !A, !B, C));
+    }
+
+    #[test]
+    #[should_panic(expected = "`Insert` is given both with and without `!`")]
+    fn contradicting_toggles_are_rejected() {
+        let _ = Context::new().toggled(crate::buttons!(Insert, !Insert));
+    }
+
+    #[test]
+    fn disabled_group_suppresses_only_its_hotkeys() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        let tx = Arc::new(move |e: ButtonEvent| tx.lock().unwrap().send(e.target).unwrap());
+        let mut hotkey = Hotkey::new();
+        let group = hotkey.group("macros");
+        let context = Context::new()
+            .modifiers(Button::LCtrl)
+            .native_event_operation(NativeEventOperation::Block);
+        hotkey
+            .register(context.clone().group(&group))
+            .on_press(Button::F13, Arc::clone(&tx));
+        hotkey.register(context).on_press(Button::F14, tx);
+        assert_eq!(hotkey.group("macros").name(), "macros");
+
+        let runtime = Runtime::with_state(hotkey.storage, FakeButtonState::held(&[Button::LCtrl]));
+        let press = |target| {
+            let event = ButtonEvent::new(target, ButtonAction::Press);
+            let mut operation = None;
+            let _ = runtime.dispatch(Event::Button(event), |op| operation = Some(op));
+            operation.unwrap()
+        };
+        let timeout = Duration::from_millis(100);
+
+        hotkey.groups[0].set_enabled(false);
+        assert!(!group.is_enabled());
+        assert_eq!(press(Button::F13), NativeEventOperation::Dispatch);
+        assert_eq!(press(Button::F14), NativeEventOperation::Block);
+        assert_eq!(rx.recv_timeout(timeout), Ok(Button::F14));
+        assert!(rx.recv_timeout(timeout).is_err());
+
+        group.set_enabled(true);
+        assert_eq!(press(Button::F13), NativeEventOperation::Block);
+        assert_eq!(rx.recv_timeout(timeout), Ok(Button::F13));
+    }
+
+    #[test]
+    fn shared_process_tells_registrations_apart() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        let process = Process::from(move |e: ButtonEvent| {
+            tx.lock()
+                .unwrap()
+                .send((e.target, registration_id()))
+                .unwrap()
+        });
+        let mut hotkey = Hotkey::new();
+        let first = hotkey
+            .register(Context::default())
+            .on_press(Button::F13, process.clone())
+            .id();
+        let second = hotkey
+            .register(Context::default())
+            .on_press(Button::F14, process)
+            .id();
+
+        let runtime = Runtime::with_state(hotkey.storage, FakeButtonState::default());
+        let timeout = Duration::from_secs(1);
+        for target in [Button::F13, Button::F14] {
+            let event = ButtonEvent::new(target, ButtonAction::Press);
+            let _ = runtime.dispatch(Event::Button(event), |_| {});
+            // Waits for each callback, since they may run on different workers.
+            let (fired, id) = rx.recv_timeout(timeout).unwrap();
+            assert_eq!(fired, target);
+            assert_eq!(id, Some(if target == Button::F13 { first } else { second }));
+        }
+        assert_ne!(first, second);
+        assert_eq!(registration_id(), None);
+    }
+
+    #[test]
+    fn disable_during_suppresses_only_given_registrations() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        let tx = Arc::new(move |e: ButtonEvent| tx.lock().unwrap().send(e.target).unwrap());
+        let mut hotkey = Hotkey::new();
+        let context = Context::new().native_event_operation(NativeEventOperation::Block);
+        let disabled = hotkey
+            .register(context.clone())
+            .on_press(Button::F13, Arc::clone(&tx))
+            .remap(Button::F15, Button::F16)
+            .id();
+        hotkey.register(context).on_press(Button::F14, tx);
+
+        let handle = hotkey.handle();
+        let runtime = Runtime::reloadable(Arc::clone(&handle.storage));
+        handle.reload(hotkey);
+        let press = |target| {
+            let event = Event::Button(ButtonEvent::new(target, ButtonAction::Press));
+            let mut operation = None;
+            let _ = runtime.dispatch(event, |o| operation = Some(o));
+            operation.unwrap()
+        };
+        let timeout = Duration::from_millis(100);
+
+        let result = handle.disable_during(&[disabled], || {
+            assert_eq!(press(Button::F13), NativeEventOperation::Dispatch);
+            assert_eq!(press(Button::F15), NativeEventOperation::Dispatch);
+            handle.disable_during(&[disabled], || {});
+            assert_eq!(press(Button::F13), NativeEventOperation::Dispatch);
+            assert_eq!(press(Button::F14), NativeEventOperation::Block);
+            42
+        });
+        assert_eq!(result, 42);
+        assert_eq!(rx.recv_timeout(timeout), Ok(Button::F14));
+        assert!(rx.recv_timeout(timeout).is_err());
+
+        assert_eq!(press(Button::F13), NativeEventOperation::Block);
+        assert_eq!(rx.recv_timeout(timeout), Ok(Button::F13));
+    }
+
+    #[test]
+    fn overlapping_disable_during_keeps_hotkeys_disabled_until_all_return() {
+        let mut hotkey = Hotkey::new();
+        let id = hotkey
+            .register(Context::default())
+            .disable(Button::F13)
+            .id();
+        let handle = hotkey.handle();
+        let runtime = Runtime::reloadable(Arc::clone(&handle.storage));
+        handle.reload(hotkey);
+        let is_blocked = || {
+            let event = Event::Button(ButtonEvent::new(Button::F13, ButtonAction::Press));
+            let mut operation = None;
+            let _ = runtime.dispatch(event, |o| operation = Some(o));
+            operation.unwrap() == NativeEventOperation::Block
+        };
+
+        let (entered_tx, entered_rx) = std::sync::mpsc::channel();
+        let (resume_tx, resume_rx) = std::sync::mpsc::channel::<()>();
+        std::thread::scope(|s| {
+            let handle_ = handle.clone();
+            // The first call returns while the second one is still running.
+            let first = s.spawn(move || {
+                handle_.disable_during(&[id], || {
+                    entered_tx.send(()).unwrap();
+                    let _ = resume_rx.recv();
+                })
+            });
+            entered_rx.recv().unwrap();
+            handle.disable_during(&[id], || {
+                drop(resume_tx);
+                first.join().unwrap();
+                assert!(!is_blocked());
+            });
+        });
+        assert!(is_blocked());
+    }
+
+    #[test]
+    fn reload_replaces_hotkeys_at_swap_point() {
+        let mut config_a = Hotkey::new();
+        config_a.register(Context::default()).disable(Button::F13);
+        let mut config_b = Hotkey::new();
+        config_b.register(Context::default()).disable(Button::F14);
+
+        let handle = config_a.handle();
+        let runtime = Runtime::reloadable(Arc::clone(&handle.storage));
+        let is_blocked = |target| {
+            let event = Event::Button(ButtonEvent::new(target, ButtonAction::Press));
+            let mut operation = None;
+            let _ = runtime.dispatch(event, |o| operation = Some(o));
+            operation.unwrap() == NativeEventOperation::Block
+        };
+        assert!(!is_blocked(Button::F13));
+
+        handle.reload(config_a);
+        assert!(is_blocked(Button::F13));
+        assert!(!is_blocked(Button::F14));
+
+        handle.reload(config_b);
+        assert!(!is_blocked(Button::F13));
+        assert!(is_blocked(Button::F14));
+    }
+
+    #[test]
+    fn on_unhandled_runs_only_for_unbound_buttons() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .remap(Button::A, Button::F16)
+            .on_press(Button::C, |_| {})
+            .on_unhandled(move |e: ButtonEvent| tx.lock().unwrap().send(e.target).unwrap());
+        hotkey
+            .register(Context::new().modifiers(Button::LShift))
+            .on_press(Button::D, |_| {});
+
+        for target in [Button::A, Button::C, Button::D, Button::E] {
+            let event = ButtonEvent::new(target, ButtonAction::Press);
+            let hooks = hotkey
+                .storage
+                .fetch_button_hook(event, &FakeButtonState::default());
+            hooks.iter().for_each(|hook| hook.run(event));
+        }
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [Button::D, Button::E]);
+        assert!(hotkey.storage.handled_buttons().contains(Button::E));
+    }
+
+    #[test]
+    #[ignore = "installs real hooks"]
+    fn synthetic_event_triggers_hotkey() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = std::sync::Mutex::new(tx);
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::new().native_event_operation(NativeEventOperation::Block))
+            .on_press(Button::F13, move |e| tx.lock().unwrap().send(e).unwrap());
+
+        let poster = thread::spawn(move || {
+            while !hookmap_core::is_hook_installed() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            let post = |target| {
+                let event = ButtonEvent::new(target, ButtonAction::Press).injected(true);
+                (event, crate::post_synthetic(event))
+            };
+            let handled = post(Button::F13);
+            let unhandled = post(Button::F14);
+            hookmap_core::uninstall_hook();
+            (handled, unhandled)
+        });
+        hotkey.install();
+
+        let ((event, operation), (_, unhandled)) = poster.join().unwrap();
+        assert_eq!(operation, NativeEventOperation::Block);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok(event));
+        assert_eq!(unhandled, NativeEventOperation::Dispatch);
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn callbacks_get_modifiers_held_at_dispatch() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::new())
+            .on_press(Button::A, move |_| {
+                tx.lock().unwrap().send(crate::event_modifiers()).unwrap()
+            });
+
+        let event = ButtonEvent::new(Button::A, ButtonAction::Press);
+        let runtime = Runtime::with_state(
+            hotkey.storage,
+            FakeButtonState::held(&[Button::RCtrl, Button::B]),
+        );
+        let _ = runtime.dispatch(Event::Button(event), |_| {});
+        let modifiers = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(modifiers.iter().collect::<Vec<_>>(), [Button::RCtrl]);
+        assert!(modifiers.contains(Button::Ctrl));
+        assert!(!modifiers.contains(Button::LCtrl));
+        assert!(!modifiers.contains(Button::B));
+        assert!(crate::event_modifiers().is_empty());
+    }
+
+    #[test]
+    fn contexts_are_shared_with_callbacks() {
+        struct Presses(Mutex<Vec<Button>>);
+
+        let mut hotkey = Hotkey::new();
+        hotkey.context(Presses(Mutex::default()));
+        hotkey
+            .register(Context::new())
+            .on_press(crate::buttons!(A, B), |e: ButtonEvent| {
+                context::<Presses>().0.lock().unwrap().push(e.target);
+            });
+        shared::publish(&hotkey.contexts);
+
+        for target in [Button::A, Button::B] {
+            let event = ButtonEvent::new(target, ButtonAction::Press);
+            for hook in hotkey
+                .storage
+                .fetch_button_hook(event, &FakeButtonState::default())
+            {
+                hook.run(event);
+            }
+        }
+        assert_eq!(
+            *context::<Presses>().0.lock().unwrap(),
+            [Button::A, Button::B]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "no context of type")]
+    fn missing_context() {
+        struct Missing;
+        context::<Missing>();
+    }
+
+    #[test]
+    fn hotkey_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Hotkey>();
+    }
+
+    #[test]
+    fn register_from_multiple_threads() {
+        let hotkey = std::sync::Mutex::new(Hotkey::new());
+        thread::scope(|s| {
+            s.spawn(|| {
+                hotkey
+                    .lock()
+                    .unwrap()
+                    .register(Context::default())
+                    .disable(Button::A);
+            });
+            s.spawn(|| {
+                hotkey
+                    .lock()
+                    .unwrap()
+                    .register(Context::default())
+                    .disable(Button::B);
+            });
+        });
+
+        let hotkey = hotkey.into_inner().unwrap();
+        for target in [Button::A, Button::B] {
+            assert_eq!(
+                native_event_operation(&hotkey, target, ButtonAction::Press),
+                NativeEventOperation::Block
+            );
+        }
+    }
+
+    #[test]
+    fn remap_group_remaps_all_members() {
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .remap(crate::buttons!(A, B, C), Button::D);
+
+        for target in [Button::A, Button::B, Button::C] {
+            for action in [ButtonAction::Press, ButtonAction::Release] {
+                let event = ButtonEvent::new(target, action);
+                let hooks = hotkey
+                    .storage
+                    .fetch_button_hook(event, &FakeButtonState::default());
+                assert_eq!(hooks.len(), 1);
+                assert!(matches!(&hooks[0], ButtonHook::Remap(_)));
+            }
+        }
+        assert_eq!(
+            native_event_operation(&hotkey, Button::E, ButtonAction::Press),
+            NativeEventOperation::Dispatch
+        );
+    }
+
+    #[test]
+    fn inverted_modifiers_must_be_released() {
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::new().modifiers(crate::buttons!(!LShift)))
+            .disable(Button::X);
+        hotkey
+            .register(Context::new().modifiers(crate::buttons!(A, !B)))
+            .disable(Button::Y);
+
+        let is_blocked = |target, held| {
+            let event = ButtonEvent::new(target, ButtonAction::Press);
+            !hotkey
+                .storage
+                .fetch_button_hook(event, &FakeButtonState::held(held))
+                .is_empty()
+        };
+        assert!(is_blocked(Button::X, &[]));
+        assert!(!is_blocked(Button::X, &[Button::LShift]));
+        assert!(is_blocked(Button::X, &[Button::RShift]));
+
+        assert!(is_blocked(Button::Y, &[Button::A]));
+        assert!(!is_blocked(Button::Y, &[Button::A, Button::B]));
+        assert!(!is_blocked(Button::Y, &[Button::B]));
+        assert!(!is_blocked(Button::Y, &[]));
+    }
+
+    #[test]
+    fn without_modifiers_ignores_shortcuts() {
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::new().without_modifiers())
+            .disable(Button::A);
+
+        let is_blocked = |held| {
+            let event = ButtonEvent::new(Button::A, ButtonAction::Press);
+            !hotkey
+                .storage
+                .fetch_button_hook(event, &FakeButtonState::held(held))
+                .is_empty()
+        };
+        assert!(is_blocked(&[]));
+        assert!(is_blocked(&[Button::B]));
+        assert!(!is_blocked(&[Button::LCtrl]));
+        assert!(!is_blocked(&[Button::RSuper]));
+    }
+
+    #[test]
+    fn ordered_combo_distinguishes_press_order() {
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::new())
+            .on_ordered_combo(&[Button::LCtrl, Button::LShift, Button::Tab], |_| {});
+        assert!(hotkey.storage.handled_buttons().contains(Button::LCtrl));
+        assert!(hotkey.storage.handled_buttons().contains(Button::LShift));
+
+        let now = Instant::now();
+        let later = now + Duration::from_millis(10);
+        let fires = |held: Vec<(Button, Instant)>| {
+            let event = ButtonEvent::new(Button::Tab, ButtonAction::Press);
+            let state = FakeButtonState::default();
+            held.into_iter()
+                .for_each(|(button, at)| state.press_at(button, at));
+            !hotkey.storage.fetch_button_hook(event, &state).is_empty()
+        };
+        assert!(fires(vec![(Button::LCtrl, now), (Button::LShift, later)]));
+        assert!(!fires(vec![(Button::LCtrl, later), (Button::LShift, now)]));
+        assert!(!fires(vec![(Button::LCtrl, now)]));
+        assert!(!fires(vec![]));
+    }
+
+    #[test]
+    fn combo_release_fires_once_after_combo_is_satisfied() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        let mut hotkey = Hotkey::new();
+        hotkey.register(Context::new()).on_combo_release(
+            &[Button::LCtrl, Button::LShift, Button::K],
+            move |e: ButtonEvent| tx.lock().unwrap().send(e.target).unwrap(),
+        );
+        let handle = |target, action, held: &'static [Button]| {
+            let event = ButtonEvent::new(target, action);
+            let hooks = hotkey
+                .storage
+                .fetch_button_hook(event, &FakeButtonState::held(held));
+            hooks.iter().for_each(|hook| hook.run(event));
+        };
+        let press = |target, held| handle(target, ButtonAction::Press, held);
+        let release = |target, held| handle(target, ButtonAction::Release, held);
+
+        press(Button::LCtrl, &[Button::LCtrl]);
+        press(Button::K, &[Button::LCtrl, Button::K]);
+        release(Button::K, &[Button::LCtrl]);
+        assert!(rx.try_recv().is_err());
+
+        press(Button::LShift, &[Button::LCtrl, Button::LShift]);
+        press(Button::K, &[Button::LCtrl, Button::LShift, Button::K]);
+        press(Button::K, &[Button::LCtrl, Button::LShift, Button::K]);
+        release(Button::LShift, &[Button::LCtrl, Button::K]);
+        assert_eq!(rx.try_recv(), Ok(Button::LShift));
+
+        release(Button::K, &[Button::LCtrl]);
+        release(Button::LCtrl, &[]);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn modifiers_with_grace() {
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::new().modifiers_with_grace(Button::LShift, Duration::from_secs(1)))
+            .disable(Button::A);
+        assert!(hotkey.storage.handled_buttons().contains(Button::LShift));
+
+        let released_before = |elapsed| Instant::now().checked_sub(elapsed);
+        let is_blocked = |pressed, released_at| {
+            let event = ButtonEvent::new(Button::A, ButtonAction::Press);
+            let state = FakeButtonState::default();
+            if pressed {
+                state.press(Button::LShift);
+            }
+            if let Some(released_at) = released_at {
+                state.release_at(Button::LShift, released_at);
+            }
+            !hotkey.storage.fetch_button_hook(event, &state).is_empty()
+        };
+        assert!(is_blocked(true, None));
+        assert!(is_blocked(
+            false,
+            released_before(Duration::from_millis(10))
+        ));
+        assert!(!is_blocked(false, released_before(Duration::from_secs(2))));
+        assert!(!is_blocked(false, None));
+    }
+
+    #[test]
+    fn layer_released_while_remapped_key_is_held() {
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::new().modifiers(Button::Space))
+            .remap(Button::Comma, Button::LeftArrow)
+            .remap(Button::Dot, Button::RightArrow);
+
+        let is_remapped = |target, action, held| {
+            let event = ButtonEvent::new(target, action);
+            let hooks = hotkey
+                .storage
+                .fetch_button_hook(event, &FakeButtonState::held(held));
+            assert!(hooks.len() <= 1);
+            hooks
+                .iter()
+                .any(|hook| matches!(hook, ButtonHook::Remap(_)))
+        };
+        use ButtonAction::{Press, Release};
+
+        assert!(is_remapped(Button::Comma, Press, &[Button::Space]));
+        assert!(is_remapped(Button::Dot, Press, &[Button::Space]));
+        // The layer key is released. The held keys keep their remaps until they are released.
+        assert!(is_remapped(Button::Comma, Press, &[]));
+        assert!(is_remapped(Button::Comma, Release, &[]));
+        assert!(is_remapped(Button::Dot, Release, &[]));
+
+        // Keys pressed outside the layer are released natively even if the layer is held.
+        assert!(!is_remapped(Button::Comma, Press, &[]));
+        assert!(!is_remapped(Button::Comma, Release, &[Button::Space]));
+        assert!(is_remapped(Button::Comma, Press, &[Button::Space]));
+    }
+
+    #[test]
+    fn toggle_state_switches_remaps() {
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::new().toggled(Button::Insert))
+            .remap(Button::A, Button::B);
+        hotkey
+            .register(Context::new().toggled(crate::buttons!(!Insert)))
+            .disable(Button::C);
+
+        fn count(hotkey: &Hotkey, target: Button, state: &impl ButtonState) -> usize {
+            let event = ButtonEvent::new(target, ButtonAction::Press);
+            hotkey.storage.fetch_button_hook(event, state).len()
+        }
+
+        let toggled = FakeButtonState::default();
+        toggled.toggle(Button::Insert);
+        assert_eq!(count(&hotkey, Button::A, &toggled), 1);
+        assert_eq!(count(&hotkey, Button::C, &toggled), 0);
+        // Releases `A` remapped above, which would otherwise keep the remap.
+        let release = ButtonEvent::new(Button::A, ButtonAction::Release);
+        hotkey.storage.fetch_button_hook(release, &toggled);
+        assert_eq!(count(&hotkey, Button::A, &FakeButtonState::default()), 0);
+        assert_eq!(count(&hotkey, Button::C, &FakeButtonState::default()), 1);
+    }
+
+    #[test]
+    fn modifiers_keep_toggles() {
+        let context = Context::new()
+            .toggled(Button::Insert)
+            .modifiers(Button::LShift);
+        let mut hotkey = Hotkey::new();
+        hotkey.register(context).disable(Button::A);
+
+        let event = ButtonEvent::new(Button::A, ButtonAction::Press);
+        let state = FakeButtonState::held(&[Button::LShift]);
+        assert!(hotkey.storage.fetch_button_hook(event, &state).is_empty());
+    }
+
+    #[test]
+    fn modifiers_and_native_event_operation_compose_in_any_order() {
+        let contexts = [
+            Context::new()
+                .modifiers(Button::LShift)
+                .native_event_operation(NativeEventOperation::Block),
+            Context::new()
+                .native_event_operation(NativeEventOperation::Block)
+                .modifiers(Button::LShift),
+        ];
+        for context in contexts {
+            let mut hotkey = Hotkey::new();
+            hotkey.register(context).on_press(Button::A, |_| {});
+
+            let event = ButtonEvent::new(Button::A, ButtonAction::Press);
+            assert!(hotkey
+                .storage
+                .fetch_button_hook(event, &FakeButtonState::default())
+                .is_empty());
+
+            let hooks = hotkey
+                .storage
+                .fetch_button_hook(event, &FakeButtonState::held(&[Button::LShift]));
+            assert_eq!(hooks.len(), 1);
+            assert_eq!(
+                hooks[0].native_event_operation(),
+                NativeEventOperation::Block
+            );
+        }
+    }
+
+    #[test]
+    fn modifier_tap_fires_only_without_other_keys() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .on_modifier_tap(Button::LAlt, move |e: ButtonEvent| {
+                tx.lock().unwrap().send(e.target).unwrap();
+            });
+
+        let runtime = Runtime::with_state(hotkey.storage, FakeButtonState::default());
+        let send = |events: &[(Button, ButtonAction)]| {
+            for &(target, action) in events {
+                let event = ButtonEvent::new(target, action);
+                let mut operation = None;
+                let _ = runtime.dispatch(Event::Button(event), |op| operation = Some(op));
+                assert_eq!(operation, Some(NativeEventOperation::Dispatch));
+            }
+        };
+        let timeout = Duration::from_millis(100);
+        use ButtonAction::{Press, Release};
+
+        send(&[(Button::LAlt, Press), (Button::LAlt, Release)]);
+        assert_eq!(rx.recv_timeout(timeout), Ok(Button::LAlt));
+
+        send(&[
+            (Button::LAlt, Press),
+            (Button::Tab, Press),
+            (Button::Tab, Release),
+            (Button::LAlt, Release),
+        ]);
+        assert!(rx.recv_timeout(timeout).is_err());
+
+        send(&[
+            (Button::LAlt, Press),
+            (Button::LShift, Press),
+            (Button::LShift, Release),
+            (Button::RAlt, Press),
+            (Button::RAlt, Release),
+            (Button::LAlt, Release),
+        ]);
+        assert_eq!(rx.recv_timeout(timeout), Ok(Button::LAlt));
+        assert!(rx.recv_timeout(timeout).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "`A` is not a modifier key.")]
+    fn modifier_tap_requires_modifier() {
+        Hotkey::new()
+            .register(Context::default())
+            .on_modifier_tap(Button::A, |_| {});
+    }
+
+    #[test]
+    fn sync_cursor_hook_runs_before_dispatch_returns() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let log_ = Arc::clone(&log);
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::new().native_event_operation(NativeEventOperation::Block))
+            .mouse_cursor_sync(move |e: CursorEvent| {
+                log_.lock().unwrap().push(("process", e.position.x));
+            });
+
+        let runtime = Runtime::with_state(hotkey.storage, FakeButtonState::default());
+        for x in 0..3 {
+            let cursor = CursorEvent::new((1, 0).into(), Position { x, y: 0 });
+            let _ = runtime.dispatch(Event::Cursor(cursor), |op| {
+                assert_eq!(op, NativeEventOperation::Block);
+                log.lock().unwrap().push(("respond", x));
+            });
+            let expected: Vec<_> = (0..=x)
+                .flat_map(|x| [("respond", x), ("process", x)])
+                .collect();
+            assert_eq!(*log.lock().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn sync_cursor_hook_receives_accumulated_positions() {
+        let positions = Arc::new(Mutex::new(Vec::new()));
+        let positions_ = Arc::clone(&positions);
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::new().accumulate_cursor_position(true))
+            .mouse_cursor_sync(move |e: CursorEvent| {
+                positions_.lock().unwrap().push(e.position);
+            });
+
+        let runtime = Runtime::with_state(hotkey.storage, FakeButtonState::default());
+        for delta in [(0, 0), (3, 4), (-1, 2), (-10, 0)] {
+            // The hook keeps reporting the same position, as it does while moves are blocked.
+            let cursor = CursorEvent::new(delta.into(), Position { x: 500, y: 500 });
+            let _ = runtime.dispatch(Event::Cursor(cursor), |_| {});
+        }
+
+        // The origin is wherever the real cursor is, so compare positions relative to it.
+        let positions = positions.lock().unwrap();
+        let origin = positions[0];
+        let relative: Vec<_> = positions
+            .iter()
+            .map(|p| (p.x - origin.x, p.y - origin.y))
+            .collect();
+        assert_eq!(relative, [(0, 0), (3, 4), (2, 6), (-8, 6)]);
+    }
+
+    #[test]
+    fn cursor_lock_blocks_moves_only_while_held() {
+        fn dispatch(event: Event, state: impl ButtonState) -> NativeEventOperation {
+            let mut hotkey = Hotkey::new();
+            crate::utils::cursor_lock(&mut hotkey, &Context::new(), Button::Space);
+            let runtime = Runtime::with_state(hotkey.storage, state);
+            let mut operation = None;
+            let _ = runtime.dispatch(event, |op| operation = Some(op));
+            operation.unwrap()
+        }
+
+        let cursor = Event::Cursor(CursorEvent::new((1, 0).into(), Position { x: 1, y: 0 }));
+        let click = Event::Button(ButtonEvent::new(Button::LeftButton, ButtonAction::Press));
+        assert_eq!(
+            dispatch(cursor, FakeButtonState::held(&[Button::Space])),
+            NativeEventOperation::Block
+        );
+        assert_eq!(
+            dispatch(cursor, FakeButtonState::default()),
+            NativeEventOperation::Dispatch
+        );
+        assert_eq!(
+            dispatch(click, FakeButtonState::held(&[Button::Space])),
+            NativeEventOperation::Dispatch
+        );
+    }
+
+    #[test]
+    fn mouse_hooks_respect_context() {
+        let mut hotkey = Hotkey::new();
+        let context = Context::new()
+            .modifiers(Button::LShift)
+            .native_event_operation(NativeEventOperation::Block);
+        hotkey
+            .register(context)
+            .mouse_wheel(|_| {})
+            .mouse_cursor(|_| {});
+
+        let wheel = WheelEvent::new(120);
+        let cursor = CursorEvent::new((1, 1).into(), Position::default());
+
+        assert!(hotkey
+            .storage
+            .fetch_mouse_wheel_hook(wheel, &FakeButtonState::default())
+            .is_empty());
+        assert!(hotkey
+            .storage
+            .fetch_mouse_cursor_hook(cursor, &FakeButtonState::default())
+            .is_empty());
+
+        let state = FakeButtonState::held(&[Button::LShift]);
+        let wheel_hooks = hotkey.storage.fetch_mouse_wheel_hook(wheel, &state);
+        assert_eq!(wheel_hooks.len(), 1);
+        assert_eq!(
+            wheel_hooks[0].native_event_operation(),
+            NativeEventOperation::Block
+        );
+        let cursor_hooks = hotkey.storage.fetch_mouse_cursor_hook(cursor, &state);
+        assert_eq!(cursor_hooks.len(), 1);
+        assert_eq!(
+            cursor_hooks[0].native_event_operation(),
+            NativeEventOperation::Block
+        );
+    }
+
+    #[test]
+    fn handled_buttons() {
+        let mut hotkey = Hotkey::new();
+        let context = Context::new().modifiers(Button::LShift);
+        hotkey
+            .register(context)
+            .remap(Button::A, Button::B)
+            .on_release(Button::C, |_| {});
+
+        let handled = hotkey.storage.handled_buttons();
+        // Releasing the modifier also ends the `on_release` hotkey.
+        let expected = [Button::A, Button::C, Button::LShift];
+        for button in Button::ALL {
+            assert_eq!(
+                handled.contains(button),
+                expected.contains(&button),
+                "{:?}",
+                button
+            );
+        }
+
+        hotkey.register(Context::default()).on_mouse(|_| {});
+        let handled = hotkey.storage.handled_buttons();
+        assert!(handled.contains(Button::LeftButton));
+        assert!(handled.contains(Button::SideButton2));
+        assert!(!handled.contains(Button::D));
+    }
+
+    #[test]
+    fn disable_blocks_press_and_release() {
+        let mut hotkey = Hotkey::new();
+        hotkey.register(Context::default()).disable(Button::A);
+
+        use NativeEventOperation::Block;
+        assert_eq!(
+            native_event_operation(&hotkey, Button::A, ButtonAction::Press),
+            Block
+        );
+        assert_eq!(
+            native_event_operation(&hotkey, Button::A, ButtonAction::Release),
+            Block
+        );
+    }
+
+    #[test]
+    fn key_remapped_to_wheel_rotates_it() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        let mut hotkey = Hotkey::new();
+        hotkey.register(Context::default()).remap_to_wheel_with(
+            Button::F15,
+            WheelDirection::Down.delta(3),
+            move |delta| tx.lock().unwrap().send(delta).unwrap(),
+        );
+
+        let runtime = Runtime::with_state(hotkey.storage, FakeButtonState::default());
+        let operations: Vec<_> = [ButtonAction::Press, ButtonAction::Release]
+            .into_iter()
+            .map(|action| {
+                let event = ButtonEvent::new(Button::F15, action);
+                let mut operation = None;
+                let _ = runtime.dispatch(Event::Button(event), |op| operation = Some(op));
+                operation.unwrap()
+            })
+            .collect();
+
+        assert_eq!(operations, [NativeEventOperation::Block; 2]);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok(-3));
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn held_remap_is_released_after_its_modifiers() {
+        use ButtonAction::{Press, Release};
+        use NativeEventOperation::{Block, Dispatch};
+
+        type Register = fn(&mut Registrar, Box<dyn Fn(ButtonAction) + Send + Sync>);
+        let registers: [Register; 2] = [
+            |registrar, output| {
+                registrar.remap_hold_with(Button::F21, Duration::from_secs(60), output);
+            },
+            |registrar, output| {
+                registrar.remap_repeat_with(Button::F21, Duration::from_secs(60), output);
+            },
+        ];
+        for register in registers {
+            let (output, rx) = crate::runtime::recorder();
+            let mut hotkey = Hotkey::new();
+            register(
+                &mut hotkey.register(Context::new().modifiers(Button::LCtrl)),
+                Box::new(output),
+            );
+            let state = FakeButtonState::default();
+            let runtime = Runtime::with_state(hotkey.storage, state.clone());
+            let dispatch = |target, action| {
+                let event = ButtonEvent::new(target, action);
+                state.record(event);
+                let mut operation = None;
+                let _ = runtime.dispatch(Event::Button(event), |op| operation = Some(op));
+                operation.unwrap()
+            };
+
+            dispatch(Button::LCtrl, Press);
+            assert_eq!(dispatch(Button::F21, Press), Block);
+            dispatch(Button::LCtrl, Release);
+            // The key repeats and the release are handled without the modifiers.
+            assert_eq!(dispatch(Button::F21, Press), Block);
+            assert_eq!(dispatch(Button::F21, Release), Block);
+            assert_eq!(rx.try_iter().collect::<Vec<_>>(), [Press, Release]);
+
+            assert_eq!(dispatch(Button::F21, Press), Dispatch);
+            assert_eq!(dispatch(Button::F21, Release), Dispatch);
+            assert!(rx.try_recv().is_err());
+        }
+    }
+
+    #[test]
+    fn push_to_talk_releases_after_its_modifiers() {
+        use ButtonAction::{Press, Release};
+        use NativeEventOperation::{Block, Dispatch};
+
+        let mut hotkey = Hotkey::new();
+        let context = Context::new().modifiers(Button::LAlt);
+        crate::utils::push_to_talk(&mut hotkey, &context, Button::Numpad0, Button::F24);
+        let state = FakeButtonState::default();
+        let runtime = Runtime::with_state(hotkey.storage, state.clone());
+        let dispatch = |target, action| {
+            let event = ButtonEvent::new(target, action);
+            state.record(event);
+            let mut operation = None;
+            let _ = runtime.dispatch(Event::Button(event), |op| operation = Some(op));
+            operation.unwrap()
+        };
+
+        dispatch(Button::LAlt, Press);
+        assert_eq!(dispatch(Button::Numpad0, Press), Block);
+        assert!(Button::F24.is_pressed());
+        dispatch(Button::LAlt, Release);
+        assert_eq!(dispatch(Button::Numpad0, Press), Block);
+        assert_eq!(dispatch(Button::Numpad0, Release), Block);
+        assert!(Button::F24.is_released());
+
+        assert_eq!(dispatch(Button::Numpad0, Press), Dispatch);
+        assert_eq!(dispatch(Button::Numpad0, Release), Dispatch);
+    }
+
+    #[test]
+    fn sticky_modifier_applies_to_the_next_button_in_order() {
+        use ButtonAction::{Press, Release};
+
+        let (output, rx) = runtime::recorder();
+        let mut hotkey = Hotkey::new();
+        crate::utils::sticky_modifier(
+            &mut hotkey,
+            &Context::new(),
+            Button::Numpad9,
+            Button::Numpad7,
+        );
+        hotkey
+            .register(Context::default())
+            .on_unhandled(move |e: ButtonEvent| output(e.target));
+        let runtime = Runtime::with_state(hotkey.storage, FakeButtonState::default());
+        let dispatch = |target, action| {
+            let event = Event::Button(ButtonEvent::new(target, action));
+            let _ = runtime.dispatch(event, |_| {});
+        };
+
+        dispatch(Button::Numpad9, Press);
+        dispatch(Button::Numpad9, Release);
+        assert!(Button::Numpad7.is_pressed());
+        dispatch(Button::Numpad6, Press);
+        assert!(Button::Numpad7.is_pressed());
+        dispatch(Button::Numpad6, Release);
+        assert!(Button::Numpad7.is_released());
+        // Observing the buttons does not keep the other hotkeys from handling them.
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok(Button::Numpad6));
+
+        // Tapping twice locks the modifier until the next tap.
+        for _ in 0..2 {
+            dispatch(Button::Numpad9, Press);
+            dispatch(Button::Numpad9, Release);
+        }
+        dispatch(Button::Numpad6, Press);
+        dispatch(Button::Numpad6, Release);
+        assert!(Button::Numpad7.is_pressed());
+        dispatch(Button::Numpad9, Press);
+        assert!(Button::Numpad7.is_released());
+    }
+
+    #[test]
+    fn taps_are_counted_in_order() {
+        let mut hotkey = Hotkey::new();
+        hotkey.register(Context::default()).tap_count_remap(
+            Button::F21,
+            vec![(2, Button::F22)],
+            Duration::from_millis(200),
+        );
+        for action in [ButtonAction::Press, ButtonAction::Release] {
+            let event = ButtonEvent::new(Button::F21, action);
+            let hooks = hotkey
+                .storage
+                .fetch_button_hook(event, &FakeButtonState::default());
+            assert!(!hooks.is_empty());
+            assert!(hooks.iter().all(Hook::is_ordered), "{action:?}");
+        }
+    }
+
+    #[test]
+    fn cursor_edge_and_dwell_follow_the_events_in_order() {
+        let mut hotkey = Hotkey::new();
+        hotkey.register(Context::default()).on_cursor_edge(
+            ScreenEdge::Top,
+            Duration::from_millis(500),
+            None,
+            |_| {},
+        );
+        hotkey
+            .register(Context::default())
+            .on_cursor_dwell(Duration::from_millis(500), |_| {});
+        let event = CursorEvent::new((0, -1).into(), Position::default());
+        let hooks = hotkey
+            .storage
+            .fetch_mouse_cursor_hook(event, &FakeButtonState::default());
+        assert_eq!(hooks.len(), 2);
+        assert!(hooks.iter().all(Hook::is_ordered));
+    }
+
+    #[test]
+    fn one_shot_hotkey_fires_once_for_all_targets() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default().native_event_operation(NativeEventOperation::Block))
+            .on_press_once(crate::buttons!(F13, F14), move |e: ButtonEvent| {
+                tx.lock().unwrap().send(e.target).unwrap();
+            });
+
+        let runtime = Runtime::with_state(hotkey.storage, FakeButtonState::default());
+        let operations: Vec<_> = [Button::F14, Button::F13, Button::F14]
+            .into_iter()
+            .map(|target| {
+                let event = ButtonEvent::new(target, ButtonAction::Press);
+                let mut operation = None;
+                let _ = runtime.dispatch(Event::Button(event), |op| operation = Some(op));
+                operation.unwrap()
+            })
+            .collect();
+
+        use NativeEventOperation::{Block, Dispatch};
+        assert_eq!(operations, [Block, Dispatch, Dispatch]);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok(Button::F14));
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn consuming_hotkey_blocks_only_consumed_events() {
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+        use NativeEventOperation::{Block, Dispatch};
+
+        let consume = Arc::new(AtomicBool::new(false));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut hotkey = Hotkey::new();
+        {
+            let consume = Arc::clone(&consume);
+            let calls = Arc::clone(&calls);
+            hotkey
+                .register(Context::default())
+                .on_press_consuming(Button::A, move |e| {
+                    assert_eq!(e.target, Button::A);
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    consume.load(Ordering::SeqCst)
+                });
+        }
+
+        assert_eq!(
+            native_event_operation(&hotkey, Button::A, ButtonAction::Press),
+            Dispatch
+        );
+        consume.store(true, Ordering::SeqCst);
+        assert_eq!(
+            native_event_operation(&hotkey, Button::A, ButtonAction::Press),
+            Block
+        );
+        assert_eq!(
+            native_event_operation(&hotkey, Button::A, ButtonAction::Release),
+            Dispatch
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        let event = ButtonEvent::new(Button::A, ButtonAction::Press);
+        assert_eq!(
+            hotkey.would_block_with_state(event, &FakeButtonState::default()),
+            Dispatch
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn disable_press_blocks_only_press() {
+        let mut hotkey = Hotkey::new();
+        hotkey.register(Context::default()).disable_press(Button::A);
+
+        use NativeEventOperation::{Block, Dispatch};
+        assert_eq!(
+            native_event_operation(&hotkey, Button::A, ButtonAction::Press),
+            Block
+        );
+        assert_eq!(
+            native_event_operation(&hotkey, Button::A, ButtonAction::Release),
+            Dispatch
+        );
+    }
+
+    #[test]
+    fn disable_release_blocks_only_release() {
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .disable_release(Button::A);
+
+        use NativeEventOperation::{Block, Dispatch};
+        assert_eq!(
+            native_event_operation(&hotkey, Button::A, ButtonAction::Press),
+            Dispatch
+        );
+        assert_eq!(
+            native_event_operation(&hotkey, Button::A, ButtonAction::Release),
+            Block
+        );
+    }
+
+    #[test]
+    fn alt_tab_binds_on_the_same_tab_hold_their_modifiers_independently() {
+        let mut hotkey = Hotkey::new();
+        let context = Context::new();
+        crate::utils::alt_tab_with_modifier(
+            &mut hotkey,
+            &context,
+            Button::F13,
+            Button::F15,
+            Button::F20,
+        );
+        crate::utils::shift_alt_tab_with_modifier(
+            &mut hotkey,
+            &context,
+            Button::F14,
+            Button::F15,
+            Button::F21,
+        );
+        let runtime = Runtime::with_state(
+            hotkey.storage,
+            FakeButtonState::held(&[Button::F13, Button::F14]),
+        );
+        let dispatch = |target, action| {
+            let event = Event::Button(ButtonEvent::new(target, action));
+            let mut operation = None;
+            let _ = runtime.dispatch(event, |o| operation = Some(o));
+            operation.unwrap()
+        };
+        let wait_until = |condition: &dyn Fn() -> bool| {
+            let deadline = Instant::now() + Duration::from_secs(1);
+            while !condition() {
+                assert!(Instant::now() < deadline, "timed out");
+                thread::sleep(Duration::from_millis(1));
+            }
+        };
+
+        // Both binds handle the tab, so both hold their modifiers.
+        assert_eq!(
+            dispatch(Button::F15, ButtonAction::Press),
+            NativeEventOperation::Block
+        );
+        wait_until(&|| Button::F20.is_pressed() && Button::F21.is_pressed());
+        dispatch(Button::F15, ButtonAction::Release);
+
+        // Releasing the trigger of one bind releases only its modifier.
+        dispatch(Button::F13, ButtonAction::Release);
+        wait_until(&|| Button::F20.is_released());
+        thread::sleep(Duration::from_millis(50));
+        assert!(Button::F21.is_pressed());
+
+        dispatch(Button::F14, ButtonAction::Release);
+        wait_until(&|| Button::F21.is_released());
+    }
+
+    #[test]
+    fn would_block_agrees_with_dispatch() {
+        use NativeEventOperation::Dispatch;
+
+        fn dispatched(
+            hotkey: Hotkey,
+            event: ButtonEvent,
+            state: FakeButtonState,
+        ) -> NativeEventOperation {
+            let runtime = Runtime::with_state(hotkey.storage, state)
+                .with_kill_switch(hotkey.kill_switch)
+                .with_disable_condition(hotkey.disable_condition);
+            let mut operation = None;
+            let _ = runtime.dispatch(Event::Button(event), |op| operation = Some(op));
+            operation.unwrap()
+        }
+
+        let configs: [fn(&mut Hotkey); 6] = [
+            |hotkey| {
+                hotkey
+                    .register(Context::default())
+                    .remap(Button::F21, Button::F22);
+            },
+            |hotkey| {
+                hotkey
+                    .register(Context::default().modifiers(Button::LShift))
+                    .on_press(Button::F21, |_| {});
+            },
+            |hotkey| {
+                hotkey
+                    .register(Context::default().native_event_operation(Dispatch))
+                    .on_press(Button::F21, |_| {})
+                    .on_release(Button::F23, |_| {});
+            },
+            |hotkey| {
+                hotkey
+                    .register(Context::default())
+                    .disable(crate::buttons!(F21, F23));
+            },
+            |hotkey| {
+                hotkey
+                    .register(Context::default())
+                    .on_press(Button::F21, |_| {});
+                hotkey.kill_switch(crate::buttons!(LShift, F21));
+            },
+            |hotkey| {
+                hotkey
+                    .register(Context::default())
+                    .remap(Button::F21, Button::F22)
+                    .on_release(Button::F23, |_| {});
+                hotkey.disable_condition = Some(DisableCondition::new(|| true));
+            },
+        ];
+        for (i, config) in configs.into_iter().enumerate() {
+            for modifier in [Button::LShift, Button::LCtrl] {
+                for target in [Button::F21, Button::F23] {
+                    for action in [ButtonAction::Press, ButtonAction::Release] {
+                        let event = ButtonEvent::new(target, action);
+                        let mut hotkey = Hotkey::new();
+                        config(&mut hotkey);
+                        let expected = hotkey
+                            .would_block_with_state(event, &FakeButtonState::held(&[modifier]));
+                        assert_eq!(
+                            dispatched(hotkey, event, FakeButtonState::held(&[modifier])),
+                            expected,
+                            "config {}, {:?} held, {:?}",
+                            i,
+                            modifier,
+                            event
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn would_block_has_no_side_effects() {
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .remap(Button::F21, Button::F22)
+            .on_combo_release(&[Button::F23], |_| {});
+        let event = |target, action| ButtonEvent::new(target, action);
+        let release = event(Button::F21, ButtonAction::Release);
+        assert_eq!(
+            hotkey.would_block_with_state(
+                event(Button::F21, ButtonAction::Press),
+                &FakeButtonState::default()
+            ),
+            NativeEventOperation::Block
+        );
+        // The remap did not record the press, so it does not handle the release.
+        assert_eq!(
+            hotkey.would_block_with_state(release, &FakeButtonState::default()),
+            NativeEventOperation::Dispatch
+        );
+
+        let press = event(Button::F23, ButtonAction::Press);
+        let hooks = hotkey
+            .storage
+            .fetch_button_hook(press, &FakeButtonState::held(&[Button::F23]));
+        hooks.iter().for_each(|hook| hook.run(press));
+        let release = event(Button::F23, ButtonAction::Release);
+        hotkey.would_block_with_state(release, &FakeButtonState::default());
+        // The activation was not consumed by `would_block`.
+        assert_eq!(
+            hotkey
+                .storage
+                .fetch_button_hook(release, &FakeButtonState::default())
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn session_changes_run_the_process_off_the_notifying_thread() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = Mutex::new(tx);
+        let mut hotkey = Hotkey::new();
+        hotkey.on_session_change(move |change| {
+            tx.lock()
+                .unwrap()
+                .send((change, thread::current().id()))
+                .unwrap();
+        });
+
+        // Stands in for the hook thread, which is notified by the OS.
+        let on_change = spawn_on_change(hotkey.session_change_handler.clone().unwrap());
+        on_change(SessionChange::Resume);
+        let (change, thread_id) = rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(change, SessionChange::Resume);
+        assert_ne!(thread_id, thread::current().id());
+    }
+
+    #[test]
+    #[ignore = "installs real hooks"]
+    fn run_for_returns_after_uninstalling() {
+        let duration = Duration::from_millis(100);
+        let start = Instant::now();
+        Hotkey::new().run_for(duration).unwrap();
+        assert!(start.elapsed() >= duration);
+        assert!(!hookmap_core::is_hook_installed());
+    }
+
+    #[test]
+    #[ignore = "installs real hooks"]
+    fn run_until_returns_when_stopped() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_ = Arc::clone(&stop);
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            stop_.store(true, Ordering::SeqCst);
+        });
+        Hotkey::new().run_until(stop).unwrap();
+        stopper.join().unwrap();
+        assert!(!hookmap_core::is_hook_installed());
+
+        // The hooks can be installed again after returning.
+        Hotkey::new().run_for(Duration::ZERO).unwrap();
+    }
 }
@@ -0,0 +1,35 @@
+//! Entry points for the benchmarks of the dispatch path. Not a public API, so it is only
+//! built with the `bench` feature.
+
+use hookmap_core::event::{ButtonEvent, NativeEventOperation};
+
+use super::Hotkey;
+use crate::hook::{self, HookStorage};
+use crate::runtime::interceptor::{self, Filter};
+pub use crate::runtime::FakeButtonState;
+use crate::HeldModifiers;
+
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+
+/// Fetches the hooks for `event` and decides the operation for the native event, as the
+/// runtime does before running the hooks.
+pub fn native_event_operation(
+    hotkey: &Hotkey,
+    event: ButtonEvent,
+    state: &FakeButtonState,
+) -> NativeEventOperation {
+    let hooks = hotkey.storage.fetch_button_hook(event, state);
+    hook::native_event_operation(&hooks)
+}
+
+/// Publishes `event` to the interceptors, as the runtime does for every button event.
+pub fn publish_event(event: ButtonEvent, state: &FakeButtonState) -> NativeEventOperation {
+    interceptor::publish_event(event, false, state)
+}
+
+/// Makes an interceptor with `filter` wait for an event without blocking the caller.
+/// The interceptor waits until an event passes `filter`, even after the receiver is dropped.
+pub fn subscribe(filter: Filter) -> Receiver<(ButtonEvent, HeldModifiers)> {
+    interceptor::subscribe_once(&Arc::new(filter), NativeEventOperation::Dispatch)
+}
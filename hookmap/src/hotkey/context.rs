@@ -1,11 +1,15 @@
 use hookmap_core::button::Button;
 use hookmap_core::event::NativeEventOperation;
 
+use super::group::HotkeyGroup;
 use super::hook::Condition;
 use crate::hook::ButtonState;
 use crate::macros::button_arg::ButtonArg;
+use crate::macros::sequence::MODIFIER_LIST;
 
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::sync::Arc;
+use std::time::Duration;
 
 /// Represents hotkey information.
 ///
@@ -13,15 +17,32 @@ use std::sync::Arc;
 ///
 /// ```
 /// use hookmap::prelude::*;
-/// Context::new()
+/// let context = Context::new()
 ///     .modifiers(buttons!(A, B, C))
 ///     .native_event_operation(NativeEventOperation::Block);
 /// ```
 ///
+/// Methods of `Context` return a new context instead of modifying it,
+/// so the returned value must be used.
+///
+/// ```compile_fail
+/// #![deny(unused_must_use)]
+/// use hookmap::prelude::*;
+/// let context = Context::new();
+/// context.modifiers(buttons!(A)); // The modifiers are lost.
+/// ```
+///
 #[derive(Debug, Default, Clone)]
 pub struct Context {
     modifiers: Option<Arc<Modifiers>>,
     pub(crate) native_event_operation: NativeEventOperation,
+    pub(super) accumulate_cursor_position: bool,
+    pub(super) release_on_exit: bool,
+    // Flags that must all be set for the hotkey to be active, such as those of groups.
+    enabled: Vec<Arc<AtomicBool>>,
+    // Counters that must all be zero for the hotkey to be active, such as that of its
+    // registration.
+    disabled: Vec<Arc<AtomicUsize>>,
 }
 
 impl Context {
@@ -46,11 +67,12 @@ impl Context {
     /// ```
     /// use hookmap::prelude::*;
     /// let context = Context::new().modifiers(buttons!(A, B));
-    /// Context::new()
+    /// let merged = Context::new()
     ///     .modifiers(buttons!(C, D))
     ///     .merge(&context);
     /// ```
     ///
+    #[must_use]
     pub fn merge(mut self, other: &Self) -> Self {
         self.modifiers = match (self.modifiers.as_ref(), other.modifiers.as_ref()) {
             (Some(s), Some(o)) => Some(Arc::new(s.merge(o))),
@@ -64,22 +86,153 @@ impl Context {
                 (Dispatch, Dispatch) => Dispatch,
                 _ => Block,
             };
+        self.accumulate_cursor_position |= other.accumulate_cursor_position;
+        self.release_on_exit |= other.release_on_exit;
+        self.enabled.extend(other.enabled.iter().cloned());
+        self.disabled.extend(other.disabled.iter().cloned());
 
         self
     }
 
     /// Adds modifier keys to the hotkey to be registered.
+    /// Buttons prefixed with `!` must not be pressed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `modifiers` contains the same button with and without `!`, such as
+    /// `buttons!(A, !A)`, since the hotkey could never be active.
     ///
     /// # Examples
     ///
     /// ```
     /// use hookmap::prelude::*;
-    /// Context::new()
+    /// let context = Context::new()
     ///     .modifiers(buttons!(A, B));
+    ///
+    /// // Active while `A` is pressed and `B` is not pressed.
+    /// let context = Context::new()
+    ///     .modifiers(buttons!(A, !B));
     /// ```
     ///
+    #[must_use]
     pub fn modifiers(mut self, modifiers: impl Into<ButtonArg>) -> Self {
-        self.modifiers = Some(Arc::new(Modifiers::from(modifiers.into())));
+        let modifiers = modifiers.into();
+        assert_no_contradiction(&modifiers);
+        let mut new = Modifiers::from(modifiers);
+        if let Some(old) = &self.modifiers {
+            new.toggled_on = old.toggled_on.clone();
+            new.toggled_off = old.toggled_off.clone();
+            new.graced = old.graced.clone();
+        }
+        self.modifiers = Some(Arc::new(new));
+        self
+    }
+
+    /// Adds modifier keys that are considered pressed for `grace` after they are released.
+    ///
+    /// This allows pressing the modifier and the target key one after another instead of
+    /// at the same time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `modifiers` contains buttons prefixed with `!`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use std::time::Duration;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::new().modifiers_with_grace(Button::LShift, Duration::from_secs(1)))
+    ///     .on_press(Button::A, |_| println!("Shift+A"));
+    /// ```
+    ///
+    #[must_use]
+    pub fn modifiers_with_grace(
+        mut self,
+        modifiers: impl Into<ButtonArg>,
+        grace: Duration,
+    ) -> Self {
+        let modifiers = modifiers.into();
+        assert!(modifiers.is_all_plain());
+        let graced = Modifiers {
+            graced: modifiers.iter_plain().map(|b| (b, grace)).collect(),
+            ..Modifiers::default()
+        };
+        self.modifiers = Some(Arc::new(match &self.modifiers {
+            Some(modifiers) => modifiers.merge(&graced),
+            None => graced,
+        }));
+        self
+    }
+
+    /// Requires that none of the modifier keys (Shift, Ctrl, Alt and Super on both sides)
+    /// are pressed, so that the hotkey does not take over shortcuts such as Ctrl+A.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::new().without_modifiers())
+    ///     .on_press(Button::A, |_| println!("A without modifiers"));
+    /// ```
+    ///
+    #[must_use]
+    pub fn without_modifiers(mut self) -> Self {
+        let released = Modifiers {
+            released: MODIFIER_LIST.to_vec(),
+            ..Modifiers::default()
+        };
+        self.modifiers = Some(Arc::new(match &self.modifiers {
+            Some(modifiers) => modifiers.merge(&released),
+            None => released,
+        }));
+        self
+    }
+
+    /// Adds buttons whose toggle state must be on, such as Caps Lock.
+    /// The toggle state of buttons prefixed with `!` must be off.
+    ///
+    /// The toggle state is read when each event is dispatched, so this can be used to switch
+    /// an entire keymap with a lock key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buttons` contains the same button with and without `!`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::new().toggled(Button::Insert))
+    ///     .remap(Button::H, Button::LeftArrow)
+    ///     .remap(Button::L, Button::RightArrow);
+    /// hotkey
+    ///     .register(Context::new().toggled(buttons!(!Insert)))
+    ///     .disable(Button::F1);
+    /// ```
+    ///
+    #[must_use]
+    pub fn toggled(mut self, buttons: impl Into<ButtonArg>) -> Self {
+        let buttons = buttons.into();
+        assert_no_contradiction(&buttons);
+        let toggles = Modifiers {
+            toggled_on: buttons.iter_plain().collect(),
+            toggled_off: buttons.iter_not().collect(),
+            ..Modifiers::default()
+        };
+        self.modifiers = Some(Arc::new(match &self.modifiers {
+            Some(modifiers) => modifiers.merge(&toggles),
+            None => toggles,
+        }));
         self
     }
 
@@ -89,23 +242,123 @@ impl Context {
     ///
     /// ```
     /// use hookmap::prelude::*;
-    /// Context::new()
+    /// let context = Context::new()
     ///     .native_event_operation(NativeEventOperation::Block);
     /// ```
     ///
+    #[must_use]
     pub fn native_event_operation(mut self, native_event_operation: NativeEventOperation) -> Self {
         self.native_event_operation = native_event_operation;
         self
     }
 
+    /// Makes [`CursorEvent::position`] passed to [`Registrar::mouse_cursor`] and
+    /// [`Registrar::mouse_cursor_sync`] the sum of the cursor position, queried when the first
+    /// event is handled, and the [`CursorEvent::delta`] of every event since.
+    ///
+    /// Both are in screen coordinates. Without this, the position is where the hook reports
+    /// the cursor, which stays put while the moves are blocked. With this, the position keeps
+    /// following the deltas, such as for a virtual pointer driven by the mouse while the real
+    /// cursor is held still.
+    ///
+    /// [`CursorEvent::position`]: hookmap_core::event::CursorEvent::position
+    /// [`CursorEvent::delta`]: hookmap_core::event::CursorEvent::delta
+    /// [`Registrar::mouse_cursor`]: super::Registrar::mouse_cursor
+    /// [`Registrar::mouse_cursor_sync`]: super::Registrar::mouse_cursor_sync
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(
+    ///         Context::new()
+    ///             .native_event_operation(NativeEventOperation::Block)
+    ///             .accumulate_cursor_position(true),
+    ///     )
+    ///     .mouse_cursor_sync(|e: CursorEvent| println!("pointer at {:?}", e.position));
+    /// ```
+    ///
+    #[must_use]
+    pub fn accumulate_cursor_position(mut self, enabled: bool) -> Self {
+        self.accumulate_cursor_position = enabled;
+        self
+    }
+
+    /// Releases the buttons held down by the remaps of this context when any of the modifier
+    /// keys given by [`Context::modifiers`] is released.
+    ///
+    /// Without this, a button remapped while the modifiers are pressed is held down until
+    /// the source button is released, even after the modifiers are released.
+    /// The source button is then no longer remapped, so its key repeats and release are
+    /// passed through.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::new().modifiers(Button::Space).release_on_exit(true))
+    ///     .remap(Button::J, Button::LShift)
+    ///     .remap(Button::K, Button::LCtrl);
+    /// ```
+    ///
+    #[must_use]
+    pub fn release_on_exit(mut self, enabled: bool) -> Self {
+        self.release_on_exit = enabled;
+        self
+    }
+
+    /// Adds the hotkey to be registered to `group`, so that it is active only while the
+    /// group is enabled. A hotkey in several groups is active only while all of them are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// let arrows = hotkey.group("arrows");
+    /// hotkey
+    ///     .register(Context::new().modifiers(Button::Space).group(&arrows))
+    ///     .remap(Button::H, Button::LeftArrow);
+    /// arrows.set_enabled(false);
+    /// ```
+    ///
+    #[must_use]
+    pub fn group(self, group: &HotkeyGroup) -> Self {
+        self.enabled_by(Arc::clone(&group.enabled))
+    }
+
+    pub(super) fn enabled_by(mut self, enabled: Arc<AtomicBool>) -> Self {
+        self.enabled.push(enabled);
+        self
+    }
+
+    pub(super) fn disabled_by(mut self, disabled: Arc<AtomicUsize>) -> Self {
+        self.disabled.push(disabled);
+        self
+    }
+
     pub(super) fn has_no_modifiers(&self) -> bool {
         self.modifiers.is_none()
     }
 
     pub(super) fn to_condition(&self) -> Condition {
-        self.modifiers
+        let condition = self
+            .modifiers
             .clone()
-            .map_or(Condition::Any, Condition::Modifier)
+            .map_or(Condition::Any, Condition::Modifier);
+        let condition = self.enabled.iter().fold(condition, |condition, enabled| {
+            Condition::Enabled(Box::new(condition), Arc::clone(enabled))
+        });
+        self.disabled.iter().fold(condition, |condition, disabled| {
+            Condition::NotDisabled(Box::new(condition), Arc::clone(disabled))
+        })
     }
 
     pub(super) fn iter_pressed(&self) -> impl Iterator<Item = &Button> {
@@ -115,12 +368,27 @@ impl Context {
     pub(super) fn iter_released(&self) -> impl Iterator<Item = &Button> {
         self.modifiers.iter().flat_map(|m| m.iter_released())
     }
+
+    pub(super) fn iter_graced(&self) -> impl Iterator<Item = Button> + '_ {
+        self.modifiers
+            .iter()
+            .flat_map(|m| m.graced.iter().map(|&(button, _)| button))
+    }
+}
+
+fn assert_no_contradiction(buttons: &ButtonArg) {
+    if let Some(button) = buttons.find_contradiction() {
+        panic!("`{button:?}` is given both with and without `!`, so it can never be matched.");
+    }
 }
 
 #[derive(Clone, Debug, Default)]
 pub(crate) struct Modifiers {
     pressed: Vec<Button>,
     released: Vec<Button>,
+    toggled_on: Vec<Button>,
+    toggled_off: Vec<Button>,
+    graced: Vec<(Button, Duration)>,
 }
 
 impl Modifiers {
@@ -138,18 +406,44 @@ impl Modifiers {
                 .chain(other.released.iter())
                 .cloned()
                 .collect(),
+            toggled_on: self
+                .toggled_on
+                .iter()
+                .chain(other.toggled_on.iter())
+                .cloned()
+                .collect(),
+            toggled_off: self
+                .toggled_off
+                .iter()
+                .chain(other.toggled_off.iter())
+                .cloned()
+                .collect(),
+            graced: self
+                .graced
+                .iter()
+                .chain(other.graced.iter())
+                .cloned()
+                .collect(),
         }
     }
     pub(super) fn is_matched(&self, state: &impl ButtonState) -> bool {
         self.iter_pressed().all(|&b| state.is_pressed(b))
             && self.iter_released().all(|&b| state.is_released(b))
+            && self.toggled_on.iter().all(|&b| state.is_toggled(b))
+            && self.toggled_off.iter().all(|&b| !state.is_toggled(b))
+            && self.graced.iter().all(|&(b, grace)| {
+                state.is_pressed(b)
+                    || state
+                        .released_at(b)
+                        .is_some_and(|released_at| released_at.elapsed() <= grace)
+            })
     }
 
-    pub(super) fn iter_pressed(&self) -> std::slice::Iter<Button> {
+    pub(super) fn iter_pressed(&self) -> std::slice::Iter<'_, Button> {
         self.pressed.iter()
     }
 
-    pub(super) fn iter_released(&self) -> std::slice::Iter<Button> {
+    pub(super) fn iter_released(&self) -> std::slice::Iter<'_, Button> {
         self.released.iter()
     }
 }
@@ -159,6 +453,7 @@ impl From<ButtonArg> for Modifiers {
         Self {
             pressed: args.iter_plain().collect(),
             released: args.iter_not().collect(),
+            ..Self::default()
         }
     }
 }
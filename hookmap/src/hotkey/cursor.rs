@@ -0,0 +1,90 @@
+use hookmap_core::event::{CursorEvent, Position};
+use std::sync::Mutex;
+
+/// Computes absolute cursor positions by accumulating the deltas of cursor events onto
+/// the cursor position queried when the first event is handled.
+#[derive(Debug)]
+pub(super) struct CursorAccumulator {
+    origin: fn() -> (i32, i32),
+    position: Mutex<Option<Position>>,
+}
+
+impl Default for CursorAccumulator {
+    fn default() -> Self {
+        Self::new(hookmap_core::mouse::get_position)
+    }
+}
+
+impl CursorAccumulator {
+    /// Creates an accumulator that queries the origin with `origin`.
+    pub(super) fn new(origin: fn() -> (i32, i32)) -> Self {
+        Self {
+            origin,
+            position: Mutex::default(),
+        }
+    }
+
+    /// Returns `event` with its position replaced by the accumulated one.
+    pub(super) fn accumulate(&self, event: CursorEvent) -> CursorEvent {
+        let mut position = self.position.lock().unwrap();
+        let Position { x, y } = *position.get_or_insert_with(|| (self.origin)().into());
+        let accumulated = Position {
+            x: x.saturating_add(event.delta.dx),
+            y: y.saturating_add(event.delta.dy),
+        };
+        *position = Some(accumulated);
+        let mut event = event;
+        event.position = accumulated;
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hookmap_core::event::Delta;
+
+    fn moved(dx: i32, dy: i32, position: (i32, i32)) -> CursorEvent {
+        CursorEvent::new(Delta { dx, dy }, position.into())
+    }
+
+    #[test]
+    fn deltas_are_accumulated_onto_the_origin() {
+        let accumulator = CursorAccumulator::new(|| (100, 50));
+
+        // The positions reported by the hook are ignored.
+        let positions: Vec<_> = [
+            moved(0, 0, (0, 0)),
+            moved(10, -5, (0, 0)),
+            moved(-30, 20, (9, 9)),
+        ]
+        .into_iter()
+        .map(|event| accumulator.accumulate(event).position)
+        .collect();
+
+        assert_eq!(
+            positions,
+            [
+                Position { x: 100, y: 50 },
+                Position { x: 110, y: 45 },
+                Position { x: 80, y: 65 },
+            ]
+        );
+    }
+
+    #[test]
+    fn origin_is_queried_only_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static QUERIES: AtomicUsize = AtomicUsize::new(0);
+        let accumulator = CursorAccumulator::new(|| {
+            QUERIES.fetch_add(1, Ordering::SeqCst);
+            (-20, 7)
+        });
+
+        accumulator.accumulate(moved(1, 1, (0, 0)));
+        let event = accumulator.accumulate(moved(2, -3, (0, 0)));
+
+        assert_eq!(event.position, Position { x: -17, y: 5 });
+        assert_eq!(QUERIES.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::runtime::PendingTask;
+
+use hookmap_core::event::CursorEvent;
+
+use std::fmt::{self, Debug};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type Output = Box<dyn Fn(CursorEvent) + Send + Sync>;
+
+/// Fires with the last cursor event once the cursor has not moved for a while.
+pub(super) struct CursorDwell {
+    duration: Duration,
+    output: Output,
+    // Rescheduled on every move.
+    pending: Mutex<PendingTask>,
+}
+
+impl Debug for CursorDwell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CursorDwell")
+            .field("duration", &self.duration)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CursorDwell {
+    pub(super) fn new(
+        duration: Duration,
+        output: impl Fn(CursorEvent) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            duration,
+            output: Box::new(output),
+            pending: Mutex::default(),
+        }
+    }
+
+    /// Restarts the wait for the cursor to come to rest at the position of `event`.
+    pub(super) fn moved(self: &Arc<Self>, event: CursorEvent) {
+        let this = Arc::clone(self);
+        self.pending
+            .lock()
+            .unwrap()
+            .schedule(Instant::now() + self.duration, move |generation| {
+                this.fire(generation, event)
+            });
+    }
+
+    fn fire(&self, generation: u64, event: CursorEvent) {
+        if self.pending.lock().unwrap().take(generation) {
+            (self.output)(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime;
+    use hookmap_core::event::{Delta, Position};
+    use std::thread;
+
+    const DURATION: Duration = Duration::from_millis(40);
+
+    fn cursor(x: i32) -> CursorEvent {
+        CursorEvent::new(Delta { dx: 1, dy: 0 }, Position { x, y: 0 })
+    }
+
+    #[test]
+    fn fires_once_after_cursor_stops() {
+        let (output, rx) = runtime::recorder();
+        let dwell = Arc::new(CursorDwell::new(DURATION, move |event: CursorEvent| {
+            output(event.position)
+        }));
+
+        for x in 0..5 {
+            dwell.moved(cursor(x));
+            thread::sleep(DURATION / 4);
+        }
+        assert_eq!(rx.recv_timeout(DURATION * 3), Ok(Position { x: 4, y: 0 }));
+        assert!(rx.recv_timeout(DURATION * 2).is_err());
+
+        dwell.moved(cursor(10));
+        assert_eq!(rx.recv_timeout(DURATION * 3), Ok(Position { x: 10, y: 0 }));
+    }
+}
@@ -0,0 +1,200 @@
+use crate::runtime::PendingTask;
+
+use hookmap_core::event::Position;
+
+use std::fmt::{self, Debug};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// An edge of the screen, the bounding rectangle of all monitors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScreenEdge {
+    Left,
+    Top,
+    Right,
+    Bottom,
+}
+
+impl ScreenEdge {
+    /// Returns `true` if `position` is on this edge of the screen from `upper_left` to
+    /// `lower_right`, exclusive. Positions beyond the edge, which the hook reports while the
+    /// cursor is pushed against it, are also on the edge.
+    pub(super) fn contains(
+        self,
+        position: Position,
+        (upper_left, lower_right): (Position, Position),
+    ) -> bool {
+        match self {
+            ScreenEdge::Left => position.x <= upper_left.x,
+            ScreenEdge::Top => position.y <= upper_left.y,
+            ScreenEdge::Right => position.x >= lower_right.x - 1,
+            ScreenEdge::Bottom => position.y >= lower_right.y - 1,
+        }
+    }
+}
+
+type Output = Box<dyn Fn() + Send + Sync>;
+
+#[derive(Default)]
+struct DwellState {
+    is_at_edge: bool,
+    // The next firing while the cursor is at the edge.
+    pending: PendingTask,
+}
+
+/// Fires when the cursor stays at a screen edge for a while, and then repeatedly until it
+/// leaves the edge.
+pub(super) struct EdgeDwell {
+    dwell: Duration,
+    interval: Option<Duration>,
+    output: Output,
+    state: Mutex<DwellState>,
+}
+
+impl Debug for EdgeDwell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EdgeDwell")
+            .field("dwell", &self.dwell)
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EdgeDwell {
+    pub(super) fn new(
+        dwell: Duration,
+        interval: Option<Duration>,
+        output: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            dwell,
+            interval,
+            output: Box::new(output),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Starts dwelling when the cursor reaches the edge, and stops when it leaves the edge.
+    /// Moves along the edge do not restart the dwell time.
+    pub(super) fn update(self: &Arc<Self>, is_at_edge: bool) {
+        let mut state = self.state.lock().unwrap();
+        if state.is_at_edge == is_at_edge {
+            return;
+        }
+        state.is_at_edge = is_at_edge;
+        if is_at_edge {
+            self.schedule(&mut state, Instant::now() + self.dwell);
+        } else {
+            state.pending.cancel();
+        }
+    }
+
+    fn schedule(self: &Arc<Self>, state: &mut DwellState, deadline: Instant) {
+        let this = Arc::clone(self);
+        state
+            .pending
+            .schedule(deadline, move |generation| this.fire(generation, deadline));
+    }
+
+    fn fire(self: &Arc<Self>, generation: u64, deadline: Instant) {
+        let mut state = self.state.lock().unwrap();
+        if !state.pending.take(generation) {
+            return;
+        }
+        if let Some(interval) = self.interval {
+            let next = (deadline + interval).max(Instant::now());
+            self.schedule(&mut state, next);
+        }
+        drop(state);
+        (self.output)();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime;
+    use std::sync::mpsc::Receiver;
+    use std::thread;
+
+    const DWELL: Duration = Duration::from_millis(40);
+    const INTERVAL: Duration = Duration::from_millis(20);
+
+    #[test]
+    fn positions_on_edges() {
+        let screen = (Position { x: -1280, y: 0 }, Position { x: 1920, y: 1080 });
+        let cases = [
+            ((-1280, 500), ScreenEdge::Left),
+            ((-1300, 500), ScreenEdge::Left),
+            ((100, 0), ScreenEdge::Top),
+            ((1919, 500), ScreenEdge::Right),
+            ((1925, 500), ScreenEdge::Right),
+            ((100, 1079), ScreenEdge::Bottom),
+        ];
+        for ((x, y), edge) in cases {
+            let position = Position { x, y };
+            for other in [
+                ScreenEdge::Left,
+                ScreenEdge::Top,
+                ScreenEdge::Right,
+                ScreenEdge::Bottom,
+            ] {
+                assert_eq!(other.contains(position, screen), other == edge, "{x}, {y}");
+            }
+        }
+        let center = Position { x: 0, y: 540 };
+        assert!(!ScreenEdge::Left.contains(center, screen));
+        assert!(!ScreenEdge::Right.contains(Position { x: 1918, y: 540 }, screen));
+    }
+
+    fn dwell(interval: Option<Duration>) -> (Arc<EdgeDwell>, Receiver<Instant>) {
+        let (output, rx) = runtime::recorder();
+        let dwell = EdgeDwell::new(DWELL, interval, move || output(Instant::now()));
+        (Arc::new(dwell), rx)
+    }
+
+    #[test]
+    fn fires_after_dwell_time_and_repeats() {
+        let (dwell, rx) = dwell(Some(INTERVAL));
+        let reached_at = Instant::now();
+        dwell.update(true);
+        dwell.update(true);
+        let fired_at = rx.recv().unwrap();
+        assert!(fired_at - reached_at >= DWELL);
+        // Repeats are scheduled from the deadline of the firing, not from when it ran.
+        let repeated_at = rx.recv().unwrap();
+        assert!(repeated_at - reached_at >= DWELL + INTERVAL);
+
+        dwell.update(false);
+        let _ = rx.try_iter().count();
+        assert!(rx.recv_timeout(INTERVAL * 3).is_err());
+    }
+
+    #[test]
+    fn leaving_before_dwell_time_does_not_fire() {
+        let (dwell, rx) = dwell(None);
+        dwell.update(true);
+        thread::sleep(DWELL / 2);
+        dwell.update(false);
+        assert!(rx.recv_timeout(DWELL * 2).is_err());
+
+        dwell.update(true);
+        assert!(rx.recv_timeout(DWELL * 3).is_ok());
+        assert!(rx.recv_timeout(DWELL * 2).is_err());
+    }
+
+    #[test]
+    fn panicking_output_does_not_break_updates() {
+        let (output, rx) = runtime::recorder();
+        let dwell = Arc::new(EdgeDwell::new(DWELL, None, move || {
+            output(());
+            panic!("panicking output");
+        }));
+        dwell.update(true);
+        assert!(rx.recv_timeout(DWELL * 3).is_ok());
+
+        dwell.update(false);
+        dwell.update(true);
+        assert!(rx.recv_timeout(DWELL * 3).is_ok());
+    }
+}
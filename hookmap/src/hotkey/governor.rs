@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum GovernorState {
+    Active,
+    Tripped,
+    Disabled,
+}
+
+/// Detects runaway feedback of injected events.
+///
+/// The governor trips when more than `limit` injected events are observed within `window`
+/// without any real event in between. Once tripped, it stays disabled.
+#[derive(Debug)]
+pub(super) struct Governor {
+    limit: usize,
+    window: Duration,
+    injected: Mutex<Option<VecDeque<Instant>>>,
+}
+
+impl Governor {
+    pub(super) const DEFAULT_LIMIT: usize = 50;
+    pub(super) const DEFAULT_WINDOW: Duration = Duration::from_millis(100);
+
+    pub(super) fn new(limit: usize, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            injected: Mutex::new(Some(VecDeque::with_capacity(limit + 1))),
+        }
+    }
+
+    pub(super) fn observe(&self, injected: bool, now: Instant) -> GovernorState {
+        let mut guard = self.injected.lock().unwrap();
+        let history = match guard.as_mut() {
+            Some(history) => history,
+            None => return GovernorState::Disabled,
+        };
+
+        if !injected {
+            history.clear();
+            return GovernorState::Active;
+        }
+
+        history.push_back(now);
+        while let Some(&oldest) = history.front() {
+            if now.duration_since(oldest) <= self.window {
+                break;
+            }
+            history.pop_front();
+        }
+
+        if history.len() > self.limit {
+            *guard = None;
+            GovernorState::Tripped
+        } else {
+            GovernorState::Active
+        }
+    }
+}
+
+impl Default for Governor {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_LIMIT, Self::DEFAULT_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn governor_trips_on_feedback_loop() {
+        let governor = Governor::new(3, Duration::from_millis(100));
+        let start = Instant::now();
+
+        for i in 0..3 {
+            let now = start + Duration::from_millis(i);
+            assert_eq!(governor.observe(true, now), GovernorState::Active);
+        }
+        let now = start + Duration::from_millis(3);
+        assert_eq!(governor.observe(true, now), GovernorState::Tripped);
+        assert_eq!(governor.observe(false, now), GovernorState::Disabled);
+        assert_eq!(governor.observe(true, now), GovernorState::Disabled);
+    }
+
+    #[test]
+    fn governor_is_reset_by_real_events() {
+        let governor = Governor::new(3, Duration::from_millis(100));
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            assert_eq!(governor.observe(true, now), GovernorState::Active);
+            assert_eq!(governor.observe(true, now), GovernorState::Active);
+            assert_eq!(governor.observe(false, now), GovernorState::Active);
+        }
+    }
+
+    #[test]
+    fn governor_forgets_old_events() {
+        let governor = Governor::new(3, Duration::from_millis(100));
+        let start = Instant::now();
+
+        for i in 0..10 {
+            let now = start + Duration::from_millis(i * 50);
+            assert_eq!(governor.observe(true, now), GovernorState::Active);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A named set of hotkeys that can be enabled and disabled together.
+///
+/// This is created by [`Hotkey::group`] and the hotkeys are added to it with
+/// [`Context::group`]. Unlike [`Profile`]s, any number of groups can be enabled at a time,
+/// and the hotkeys of a group can be registered with any [`Context`].
+///
+/// The handle can be cloned and sent to other threads to switch the group while the
+/// hotkeys are installed.
+///
+/// [`Hotkey::group`]: super::Hotkey::group
+/// [`Context::group`]: super::Context::group
+/// [`Context`]: super::Context
+/// [`Profile`]: super::Profile
+///
+/// # Examples
+///
+/// ```
+/// use hookmap::prelude::*;
+///
+/// let mut hotkey = Hotkey::new();
+/// let vim = hotkey.group("vim");
+/// hotkey
+///     .register(Context::new().modifiers(Button::CapsLock).group(&vim))
+///     .remap(Button::H, Button::LeftArrow)
+///     .remap(Button::L, Button::RightArrow);
+///
+/// vim.set_enabled(false);
+/// assert!(!hotkey.group("vim").is_enabled());
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct HotkeyGroup {
+    name: Arc<str>,
+    pub(super) enabled: Arc<AtomicBool>,
+}
+
+impl HotkeyGroup {
+    pub(super) fn new(name: &str) -> Self {
+        Self {
+            name: Arc::from(name),
+            enabled: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Returns the name of this group.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Enables or disables all the hotkeys of this group. Groups are enabled when created.
+    ///
+    /// Disabled hotkeys are skipped as if their modifiers were not pressed, so the events
+    /// are passed to the other hotkeys and to the OS.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if the hotkeys of this group are enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+}
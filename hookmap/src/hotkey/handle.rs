@@ -0,0 +1,175 @@
+use super::profile::Profiles;
+use super::storage::HotkeyStorage;
+use super::{shared, Hotkey, RegistrationId};
+use crate::runtime::ReloadableStorage;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// A handle to replace the hotkeys of a [`Hotkey`] while it is installed.
+///
+/// This is created by [`Hotkey::handle`] and can be cloned and sent to other threads.
+#[derive(Debug, Clone, Default)]
+pub struct HotkeyHandle {
+    pub(super) storage: Arc<ReloadableStorage<HotkeyStorage>>,
+    pub(super) profiles: Arc<Profiles>,
+}
+
+impl HotkeyHandle {
+    /// Replaces the installed hotkeys with those registered to `hotkey`, along with the
+    /// values registered by [`Hotkey::context`].
+    ///
+    /// The replacement is atomic: each event is handled either entirely by the old hotkeys
+    /// or entirely by the new ones. Events being handled when this is called are finished
+    /// with the old hotkeys first. The other settings of `hotkey`, such as
+    /// [`Hotkey::kill_switch`], are ignored and those of the installed one are kept.
+    ///
+    /// Buttons held down by the old remaps are not released by the new hotkeys.
+    /// Calling this before the hotkeys are installed has no effect.
+    ///
+    /// The profiles of `hotkey` are switched through its own handle, not through `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.register(Context::default()).remap(Button::A, Button::B);
+    /// let handle = hotkey.handle();
+    ///
+    /// thread::spawn(move || {
+    ///     thread::sleep(Duration::from_secs(10));
+    ///     let mut hotkey = Hotkey::new();
+    ///     hotkey.register(Context::default()).remap(Button::A, Button::C);
+    ///     handle.reload(hotkey);
+    /// });
+    /// hotkey.install();
+    /// ```
+    ///
+    pub fn reload(&self, hotkey: Hotkey) {
+        shared::publish(&hotkey.contexts);
+        // The old hotkeys are dropped after the lock is released.
+        drop(self.storage.replace(hotkey.storage));
+    }
+
+    /// Activates the profile named `name` and deactivates the others.
+    /// Returns `false` if no profile named `name` has been added.
+    ///
+    /// Buttons held down by the remaps of the previous profile stay remapped until they are
+    /// released. The other buttons are remapped by the new profile from the next event.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use hookmap::hotkey::Profile;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .add_profile(Profile::new("coding").remap(Button::CapsLock, Button::Esc))
+    ///     .add_profile(Profile::new("gaming").remap(Button::CapsLock, Button::LCtrl));
+    /// let handle = hotkey.handle();
+    /// assert!(handle.activate_profile("coding"));
+    /// assert!(!handle.activate_profile("writing"));
+    /// assert_eq!(handle.active_profile().as_deref(), Some("coding"));
+    /// ```
+    ///
+    pub fn activate_profile(&self, name: &str) -> bool {
+        self.profiles.activate(Some(name))
+    }
+
+    /// Deactivates the active profile, if any.
+    pub fn deactivate_profile(&self) {
+        self.profiles.activate(None);
+    }
+
+    /// Returns the name of the active profile.
+    pub fn active_profile(&self) -> Option<String> {
+        self.profiles.active()
+    }
+
+    /// Disables the hotkeys registered with `ids` while `f` runs, and then restores them.
+    ///
+    /// This is useful to send input from a callback that some of the other hotkeys should not
+    /// react to. Input sent with the non-recursive functions such as [`Button::click`] is
+    /// never hooked, so this is needed only for the recursive ones. The OS may deliver the
+    /// input to the hooks after it is sent, so `f` should wait until it is handled before
+    /// returning, e.g. with a short sleep.
+    ///
+    /// The hotkeys are restored even if `f` panics. Calls may overlap, such as from callbacks
+    /// running at the same time, and the hotkeys stay disabled until all of them return.
+    /// IDs of hotkeys that are not installed are ignored.
+    ///
+    /// [`Button::click`]: hookmap_core::button::Button::click
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// let id = hotkey
+    ///     .register(Context::default())
+    ///     .on_press(Button::A, |_| println!("A"))
+    ///     .id();
+    /// let handle = hotkey.handle();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_press(Button::F1, move |_| {
+    ///         handle.disable_during(&[id], || seq!(A).send_recursive());
+    ///     });
+    /// hotkey.install();
+    /// ```
+    ///
+    pub fn disable_during<R>(&self, ids: &[RegistrationId], f: impl FnOnce() -> R) -> R {
+        let counters: Vec<_> = {
+            let loaded = self.storage.read();
+            ids.iter()
+                .filter_map(|&id| loaded.storage.registration_disabled(id))
+                .collect()
+        };
+        for counter in &counters {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+        let _guard = DisableGuard(counters);
+        f()
+    }
+
+    /// Returns the ID of the thread that owns the hooks while the hotkeys are installed,
+    /// or `None` if no hook is installed.
+    ///
+    /// The hooks run on their own thread with a message loop, so the thread calling
+    /// [`Hotkey::install`] does not need to pump messages. See [`hookmap_core::hook_thread_id`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    /// use std::thread;
+    ///
+    /// let hotkey = Hotkey::new();
+    /// let handle = hotkey.handle();
+    /// thread::spawn(move || hotkey.install());
+    /// thread::sleep(std::time::Duration::from_secs(1));
+    /// println!("{:?}", handle.hook_thread_id());
+    /// ```
+    ///
+    pub fn hook_thread_id(&self) -> Option<u32> {
+        hookmap_core::hook_thread_id()
+    }
+}
+
+// Counts down the calls disabling the hotkeys, so that the hotkeys stay disabled until all
+// of the overlapping calls return, even on other threads.
+struct DisableGuard(Vec<Arc<AtomicUsize>>);
+
+impl Drop for DisableGuard {
+    fn drop(&mut self) {
+        for counter in &self.0 {
+            counter.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
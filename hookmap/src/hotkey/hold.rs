@@ -0,0 +1,146 @@
+use crate::runtime::PendingTask;
+
+use hookmap_core::button::ButtonAction;
+
+use std::fmt::{self, Debug};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type Output = Box<dyn Fn(ButtonAction) + Send + Sync>;
+
+#[derive(Default)]
+struct HoldState {
+    is_held: bool,
+    // The release of the behavior held down by the last press.
+    pending: PendingTask,
+}
+
+/// Holds the behavior of a remap down for at most a fixed duration.
+pub(super) struct TimedHold {
+    duration: Duration,
+    output: Output,
+    state: Mutex<HoldState>,
+}
+
+impl Debug for TimedHold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimedHold")
+            .field("duration", &self.duration)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TimedHold {
+    pub(super) fn new(
+        duration: Duration,
+        output: impl Fn(ButtonAction) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            duration,
+            output: Box::new(output),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Presses the behavior and schedules its release. Key repeats are ignored.
+    pub(super) fn press(self: &Arc<Self>) {
+        let mut state = self.state.lock().unwrap();
+        if state.is_held {
+            return;
+        }
+        state.is_held = true;
+        (self.output)(ButtonAction::Press);
+
+        let this = Arc::clone(self);
+        state
+            .pending
+            .schedule(Instant::now() + self.duration, move |generation| {
+                this.expire(generation)
+            });
+    }
+
+    /// Returns `true` if the target is held down, that is, pressed and not released yet.
+    pub(super) fn is_held(&self) -> bool {
+        self.state.lock().unwrap().is_held
+    }
+
+    /// Releases the behavior unless it has already been released by the timer.
+    pub(super) fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.is_held = false;
+        if state.pending.cancel() {
+            (self.output)(ButtonAction::Release);
+        }
+    }
+
+    fn expire(&self, generation: u64) {
+        let mut state = self.state.lock().unwrap();
+        if state.pending.take(generation) {
+            (self.output)(ButtonAction::Release);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime;
+    use std::sync::mpsc::Receiver;
+
+    const DURATION: Duration = Duration::from_millis(50);
+
+    fn hold() -> (Arc<TimedHold>, Receiver<(ButtonAction, Instant)>) {
+        let (output, rx) = runtime::recorder();
+        let hold = TimedHold::new(DURATION, move |action| output((action, Instant::now())));
+        (Arc::new(hold), rx)
+    }
+
+    #[test]
+    fn released_early_releases_immediately() {
+        let (hold, rx) = hold();
+        hold.press();
+        hold.press();
+        let (action, pressed_at) = rx.try_recv().unwrap();
+        assert_eq!(action, ButtonAction::Press);
+
+        hold.release();
+        let (action, released_at) = rx.try_recv().unwrap();
+        assert_eq!(action, ButtonAction::Release);
+        assert!(released_at - pressed_at < DURATION);
+        assert!(rx.recv_timeout(DURATION * 2).is_err());
+    }
+
+    #[test]
+    fn held_longer_is_released_by_timer() {
+        let (hold, rx) = hold();
+        hold.press();
+        let (action, pressed_at) = rx.try_recv().unwrap();
+        assert_eq!(action, ButtonAction::Press);
+
+        let (action, released_at) = rx.recv().unwrap();
+        assert_eq!(action, ButtonAction::Release);
+        assert!(released_at - pressed_at >= DURATION);
+
+        hold.release();
+        assert!(rx.recv_timeout(DURATION * 2).is_err());
+    }
+
+    #[test]
+    fn each_press_gets_its_own_duration() {
+        let (hold, rx) = hold();
+        hold.press();
+        hold.release();
+        hold.press();
+        let actions: Vec<_> = rx.iter().take(4).map(|(action, _)| action).collect();
+        assert_eq!(
+            actions,
+            [
+                ButtonAction::Press,
+                ButtonAction::Release,
+                ButtonAction::Press,
+                ButtonAction::Release
+            ]
+        );
+        assert!(rx.recv_timeout(DURATION * 2).is_err());
+    }
+}
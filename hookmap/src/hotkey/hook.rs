@@ -2,16 +2,212 @@ use hookmap_core::button::{Button, ButtonAction};
 use hookmap_core::event::{ButtonEvent, NativeEventOperation};
 
 use super::context::Modifiers;
+use super::panic;
+use super::RegistrationId;
 use crate::hook::{ButtonState, Hook};
+use crate::runtime;
 
+use std::cell::Cell;
+use std::collections::HashSet;
 use std::fmt::Debug;
+use std::panic::AssertUnwindSafe;
 use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Mutex,
 };
 
+/// A callback function of hotkeys.
+///
+/// This is created from a closure with [`From`], so the methods taking
+/// `impl Into<Process<E>>` accept a closure as is. A closure in an [`Arc`] or a [`Box`] is
+/// also accepted.
+///
+/// # Examples
+///
+/// ```
+/// use hookmap::prelude::*;
+/// use std::sync::Arc;
+///
+/// let boxed: Box<dyn Fn(ButtonEvent) + Send + Sync> = Box::new(|_| println!("boxed"));
+///
+/// let mut hotkey = Hotkey::new();
+/// hotkey
+///     .register(Context::default())
+///     .on_press(Button::A, |e: ButtonEvent| println!("{:?}", e.target))
+///     .on_press(Button::B, Arc::new(|_: ButtonEvent| println!("shared")))
+///     .on_press(Button::C, boxed);
+/// ```
+///
 #[derive(Clone)]
-pub struct Process<E>(Arc<dyn Fn(E) + Send + Sync>);
+pub struct Process<E>(pub(super) Arc<dyn Fn(E) + Send + Sync>);
+
+impl<E: Copy + 'static> Process<E> {
+    /// Creates a [`Process`] that runs `self` and then `next`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use hookmap::hotkey::Process;
+    ///
+    /// let process = Process::from(|_: ButtonEvent| println!("first"))
+    ///     .chain(|_: ButtonEvent| println!("second"));
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_press(Button::A, process);
+    /// ```
+    ///
+    #[must_use]
+    pub fn chain(self, next: impl Into<Process<E>>) -> Self {
+        let next = next.into();
+        Process(Arc::new(move |event| {
+            self.0(event);
+            next.0(event);
+        }))
+    }
+}
+
+impl<E: 'static> Process<E> {
+    /// Creates a [`Process`] that is skipped while the previous invocation is running.
+    ///
+    /// This prevents a long-running process from being started again by key repeats.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use hookmap::hotkey::Process;
+    ///
+    /// let process = Process::from(|_: ButtonEvent| seq!(A, B, C).send()).non_reentrant();
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_press(Button::F1, process);
+    /// ```
+    ///
+    #[must_use]
+    pub fn non_reentrant(self) -> Self {
+        let is_running = AtomicBool::new(false);
+        Process(Arc::new(move |event| {
+            if is_running.swap(true, Ordering::SeqCst) {
+                return;
+            }
+            let _guard = ResetOnDrop(&is_running);
+            self.0(event);
+        }))
+    }
+
+    /// Names the [`Process`] so that it can be identified when it panics.
+    ///
+    /// When the named process panics, the handler set by [`Hotkey::on_callback_panic`] is
+    /// called with `name` before the panic continues.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use hookmap::hotkey::{CallbackPanic, Process};
+    ///
+    /// let process = Process::from(|_: ButtonEvent| println!("mute")).named("toggle_mute");
+    ///
+    /// let mut hotkey = Hotkey::new();
+    /// hotkey.on_callback_panic(|e: CallbackPanic| eprintln!("{}", e));
+    /// hotkey
+    ///     .register(Context::default())
+    ///     .on_press(Button::F1, process);
+    /// ```
+    ///
+    /// [`Hotkey::on_callback_panic`]: super::Hotkey::on_callback_panic
+    ///
+    #[must_use]
+    pub fn named(self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        Process(Arc::new(move |event| {
+            if let Err(payload) = std::panic::catch_unwind(AssertUnwindSafe(|| self.0(event))) {
+                panic::report(&name, &*payload);
+                std::panic::resume_unwind(payload);
+            }
+        }))
+    }
+}
+
+thread_local! {
+    static REGISTRATION_ID: Cell<Option<RegistrationId>> = const { Cell::new(None) };
+}
+
+impl<E: 'static> Process<E> {
+    /// Creates a [`Process`] that runs `self` with `id` as the result of [`registration_id`].
+    pub(super) fn registered_as(self, id: RegistrationId) -> Self {
+        Process(Arc::new(move |event| {
+            let previous = REGISTRATION_ID.with(|cell| cell.replace(Some(id)));
+            let _guard = RestoreRegistrationId(previous);
+            self.0(event);
+        }))
+    }
+}
+
+// Restores the result of `registration_id` even if the callback panics, since the worker
+// thread that catches the panic runs other callbacks afterwards.
+struct RestoreRegistrationId(Option<RegistrationId>);
+
+impl Drop for RestoreRegistrationId {
+    fn drop(&mut self) {
+        REGISTRATION_ID.with(|cell| cell.set(self.0));
+    }
+}
+
+/// Returns the ID of the [`Registrar`] through which the running callback was registered,
+/// so that a [`Process`] registered on several hotkeys can tell which one fired.
+///
+/// Outside of hotkey callbacks, and in callbacks moved to another thread, such as the async
+/// callbacks of `tokio_runtime`, this returns `None`.
+///
+/// # Examples
+///
+/// ```
+/// use hookmap::hotkey::{self, Process};
+/// use hookmap::prelude::*;
+///
+/// let mut hotkey = Hotkey::new();
+/// let process = Process::from(|e: ButtonEvent| {
+///     println!("{:?} of {:?}", e.target, hotkey::registration_id());
+/// });
+/// let copy = hotkey
+///     .register(Context::new().modifiers(buttons!(LCtrl)))
+///     .on_press(Button::C, process.clone())
+///     .id();
+/// let paste = hotkey
+///     .register(Context::new().modifiers(buttons!(LCtrl)))
+///     .on_press(Button::V, process)
+///     .id();
+/// assert_ne!(copy, paste);
+/// ```
+///
+/// [`Registrar`]: super::Registrar
+///
+pub fn registration_id() -> Option<RegistrationId> {
+    REGISTRATION_ID.with(Cell::get)
+}
+
+struct ResetOnDrop<'a>(&'a AtomicBool);
+
+impl Drop for ResetOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+impl<E: Copy + 'static> From<Vec<Process<E>>> for Process<E> {
+    /// Creates a [`Process`] that runs all the processes in order.
+    fn from(processes: Vec<Process<E>>) -> Self {
+        Process(Arc::new(move |event| {
+            processes.iter().for_each(|process| process.0(event));
+        }))
+    }
+}
 
 impl<E> Debug for Process<E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -31,27 +227,106 @@ impl<E, F: Fn(E) + Send + Sync + 'static> From<Arc<F>> for Process<E> {
     }
 }
 
+/// A callback that handles an event and returns `true` if it consumed the event.
+#[derive(Clone)]
+pub(super) struct Consumer<E>(pub(super) Arc<dyn Fn(E) -> bool + Send + Sync>);
+
+impl<E> Debug for Consumer<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Consumer").field(&"Fn").finish()
+    }
+}
+
+/// A user-defined condition evaluated when each event is dispatched.
+#[derive(Clone)]
+pub(super) struct Predicate(pub(super) Arc<dyn Fn() -> bool + Send + Sync>);
+
+impl Debug for Predicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Predicate").field(&"Fn").finish()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(super) enum Condition {
     Any,
     Activation(Arc<AtomicBool>),
     Modifier(Arc<Modifiers>),
+    // The buttons must be held down in this order, in addition to the inner condition.
+    Ordered(Box<Condition>, Arc<[Button]>),
+    // The buttons must all be held down in any order, in addition to the inner condition.
+    Held(Box<Condition>, Arc<[Button]>),
+    // The predicate must return `true`, in addition to the inner condition.
+    Predicate(Box<Condition>, Predicate),
+    // Satisfied only the first time the inner condition is, then never again.
+    Once(Box<Condition>, Arc<AtomicBool>),
+    // The flag must be set, in addition to the inner condition.
+    Enabled(Box<Condition>, Arc<AtomicBool>),
+    // The counter must be zero, in addition to the inner condition.
+    NotDisabled(Box<Condition>, Arc<AtomicUsize>),
 }
 
 impl Condition {
     fn is_satisfied(&self, state: &impl ButtonState) -> bool {
+        self.check(state, true)
+    }
+
+    /// Returns the same as [`Condition::is_satisfied`] without consuming the activation.
+    fn would_be_satisfied(&self, state: &impl ButtonState) -> bool {
+        self.check(state, false)
+    }
+
+    fn check(&self, state: &impl ButtonState, consume: bool) -> bool {
         match self {
             Condition::Any => true,
-            Condition::Activation(is_active) => is_active.swap(false, Ordering::SeqCst),
+            Condition::Activation(is_active) if consume => is_active.swap(false, Ordering::SeqCst),
+            Condition::Activation(is_active) => is_active.load(Ordering::SeqCst),
             Condition::Modifier(modifiers) => modifiers.is_matched(state),
+            Condition::Ordered(condition, order) => {
+                is_pressed_in_order(order, state) && condition.check(state, consume)
+            }
+            Condition::Held(condition, buttons) => {
+                buttons.iter().all(|&button| state.is_pressed(button))
+                    && condition.check(state, consume)
+            }
+            Condition::Predicate(condition, predicate) => {
+                condition.check(state, consume) && predicate.0()
+            }
+            Condition::Once(condition, is_fired) => {
+                condition.check(state, consume)
+                    && if consume {
+                        !is_fired.swap(true, Ordering::SeqCst)
+                    } else {
+                        !is_fired.load(Ordering::SeqCst)
+                    }
+            }
+            // The flag is checked first so that a disabled condition consumes nothing.
+            Condition::Enabled(condition, enabled) => {
+                enabled.load(Ordering::SeqCst) && condition.check(state, consume)
+            }
+            Condition::NotDisabled(condition, disabled) => {
+                disabled.load(Ordering::SeqCst) == 0 && condition.check(state, consume)
+            }
         }
     }
 }
 
+fn is_pressed_in_order(order: &[Button], state: &impl ButtonState) -> bool {
+    order
+        .iter()
+        .map(|&button| state.pressed_at(button))
+        .collect::<Option<Vec<_>>>()
+        .is_some_and(|pressed_at| pressed_at.windows(2).all(|w| w[0] <= w[1]))
+}
+
 #[derive(Debug, Clone)]
 pub(super) enum HotkeyAction<E> {
     Process(Process<E>),
+    // Decides whether the event is blocked, so it is run before the native event is handled.
+    Consume(Consumer<E>),
     Activate(Arc<AtomicBool>),
+    // Releases the button held down by the remap, such as when its modifiers are released.
+    ReleaseRemap(Arc<RemapHook>),
     Noop,
 }
 
@@ -59,7 +334,11 @@ impl<E> HotkeyAction<E> {
     pub(super) fn run(&self, event: E) {
         match self {
             HotkeyAction::Process(callback) => callback.0(event),
+            HotkeyAction::Consume(consumer) => {
+                consumer.0(event);
+            }
             HotkeyAction::Activate(is_active) => is_active.store(true, Ordering::SeqCst),
+            HotkeyAction::ReleaseRemap(hook) => hook.release_held(),
             HotkeyAction::Noop => {}
         }
     }
@@ -70,6 +349,7 @@ pub(super) struct HotkeyHook {
     condition: Condition,
     action: HotkeyAction<ButtonEvent>,
     native_event_operation: NativeEventOperation,
+    ordered: bool,
 }
 
 impl HotkeyHook {
@@ -82,35 +362,194 @@ impl HotkeyHook {
             condition,
             action,
             native_event_operation,
+            ordered: false,
         }
     }
 
+    /// Runs the hook on the event loop in the order of events instead of on a worker.
+    pub(super) fn ordered(mut self) -> Self {
+        self.ordered = true;
+        self
+    }
+
     pub(super) fn is_executable(&self, state: &impl ButtonState) -> bool {
         self.condition.is_satisfied(state)
     }
+
+    /// Returns the same as [`HotkeyHook::is_executable`] without side effects.
+    pub(super) fn would_be_executable(&self, state: &impl ButtonState) -> bool {
+        self.condition.would_be_satisfied(state)
+    }
+
+    /// Returns `true` if the hook keeps track of the state for other hooks or for a button
+    /// held down by it, rather than running a user-defined process.
+    pub(super) fn is_ordered(&self) -> bool {
+        self.ordered
+            || matches!(
+                self.action,
+                HotkeyAction::Activate(_) | HotkeyAction::ReleaseRemap(_)
+            )
+    }
+}
+
+/// Determines when the button that is the destination of remapping is released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RemapReleasePolicy {
+    /// The button is released when the source button is released.
+    #[default]
+    Strict,
+
+    /// The button is released when all the source buttons remapped to it are released.
+    RefCount,
+}
+
+/// Source buttons holding down the same remapped button.
+#[derive(Debug, Default)]
+pub(super) struct RemapHolders(Mutex<HashSet<Button>>);
+
+impl RemapHolders {
+    pub(super) fn press(&self, source: Button) {
+        self.0.lock().unwrap().insert(source);
+    }
+
+    /// Returns `true` if no source holds the button.
+    pub(super) fn release(&self, source: Button) -> bool {
+        let mut holders = self.0.lock().unwrap();
+        holders.remove(&source);
+        holders.is_empty()
+    }
 }
 
 #[derive(Debug)]
 pub(super) struct RemapHook {
     condition: Condition,
     button: Button,
+    holders: Option<Arc<RemapHolders>>,
+    // Source buttons that are held down through this remap.
+    sources: Mutex<HashSet<Button>>,
+    // Run with each source event after the destination button is pressed or released.
+    process: Option<Process<ButtonEvent>>,
 }
 
 impl RemapHook {
-    pub(super) fn new(condition: Condition, button: Button) -> Self {
+    pub(super) fn new(
+        condition: Condition,
+        button: Button,
+        holders: Option<Arc<RemapHolders>>,
+    ) -> Self {
         assert!(!matches!(condition, Condition::Activation(_)));
-        RemapHook { condition, button }
+        RemapHook {
+            condition,
+            button,
+            holders,
+            sources: Mutex::default(),
+            process: None,
+        }
+    }
+
+    pub(super) fn with_process(mut self, process: Option<Process<ButtonEvent>>) -> Self {
+        self.process = process;
+        self
     }
 
     pub(super) fn is_executable(&self, state: &impl ButtonState) -> bool {
         self.condition.is_satisfied(state)
     }
+
+    /// Returns `true` if `source` was pressed through this remap and is not released yet.
+    pub(super) fn is_held_by(&self, source: Button) -> bool {
+        self.sources.lock().unwrap().contains(&source)
+    }
+
+    /// Records that `event` is handled by this remap.
+    pub(super) fn track(&self, event: ButtonEvent) {
+        let mut sources = self.sources.lock().unwrap();
+        match event.action {
+            ButtonAction::Press => sources.insert(event.target),
+            ButtonAction::Release => sources.remove(&event.target),
+        };
+    }
+
+    pub(super) fn behavior(&self) -> Button {
+        self.button
+    }
+
+    /// Releases the destination button if it is held down through this remap, and forgets
+    /// the sources so that their releases are not handled by this remap.
+    pub(super) fn release_held(&self) {
+        let sources = std::mem::take(&mut *self.sources.lock().unwrap());
+        if sources.is_empty() {
+            return;
+        }
+        let mut is_released = true;
+        if let Some(holders) = &self.holders {
+            for &source in &sources {
+                is_released = holders.release(source);
+            }
+        }
+        if is_released {
+            self.send(ButtonAction::Release);
+        }
+    }
+
+    fn send(&self, action: ButtonAction) {
+        match action {
+            ButtonAction::Press => self.button.press(),
+            ButtonAction::Release => self.button.release(),
+        }
+        // The behavior is not hooked since it is sent non-recursively, so its
+        // time is recorded here for modifiers with grace, e.g. CapsLock => LCtrl.
+        runtime::record_button_state(
+            &mut ButtonEvent::new(self.button, action)
+                .injected(true)
+                .time(hookmap_core::event_time()),
+        );
+    }
+
+    /// Returns the action to be performed on the destination button for `event`.
+    /// Each press of the source, including key repeats, presses the destination again.
+    fn output(&self, event: ButtonEvent) -> Option<ButtonAction> {
+        match event.action {
+            ButtonAction::Press => {
+                if let Some(holders) = &self.holders {
+                    holders.press(event.target);
+                }
+                Some(ButtonAction::Press)
+            }
+            ButtonAction::Release => {
+                let is_released = match &self.holders {
+                    Some(holders) => holders.release(event.target),
+                    None => true,
+                };
+                is_released.then_some(ButtonAction::Release)
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
 pub(super) enum ButtonHook {
     Hotkey(Arc<HotkeyHook>),
     Remap(Arc<RemapHook>),
+    // A consuming hotkey that has already been run to decide the operation.
+    Consumed(NativeEventOperation),
+}
+
+impl ButtonHook {
+    /// Returns the hook to run for `event`. The consumer of a consuming hotkey is run here,
+    /// unless `dry_run` is `true`, in which case the event is assumed not to be consumed.
+    pub(super) fn for_event(hook: &Arc<HotkeyHook>, event: ButtonEvent, dry_run: bool) -> Self {
+        match &hook.action {
+            HotkeyAction::Consume(_) if dry_run => {
+                ButtonHook::Consumed(NativeEventOperation::Dispatch)
+            }
+            HotkeyAction::Consume(consumer) if consumer.0(event) => {
+                ButtonHook::Consumed(NativeEventOperation::Block)
+            }
+            HotkeyAction::Consume(_) => ButtonHook::Consumed(NativeEventOperation::Dispatch),
+            _ => ButtonHook::Hotkey(Arc::clone(hook)),
+        }
+    }
 }
 
 impl Hook<ButtonEvent> for ButtonHook {
@@ -118,16 +557,33 @@ impl Hook<ButtonEvent> for ButtonHook {
         match self {
             ButtonHook::Hotkey(hook) => hook.native_event_operation,
             ButtonHook::Remap(_) => NativeEventOperation::Block,
+            ButtonHook::Consumed(operation) => *operation,
         }
     }
 
     fn run(&self, event: ButtonEvent) {
         match self {
             ButtonHook::Hotkey(hook) => hook.action.run(event),
-            ButtonHook::Remap(hook) => match event.action {
-                ButtonAction::Press => hook.button.press(),
-                ButtonAction::Release => hook.button.release(),
-            },
+            ButtonHook::Remap(hook) => {
+                if let Some(action) = hook.output(event) {
+                    hook.send(action);
+                }
+                if let Some(process) = &hook.process {
+                    process.0(event);
+                }
+            }
+            ButtonHook::Consumed(_) => {}
+        }
+    }
+
+    // Remaps are run in order so that every key repeat of the source button is reproduced
+    // as a press of the destination, and a repeat never follows the release.
+    // Activations and releases of remaps are also run in order so that the events following
+    // them see them.
+    fn is_ordered(&self) -> bool {
+        match self {
+            ButtonHook::Hotkey(hook) => hook.is_ordered(),
+            ButtonHook::Remap(_) | ButtonHook::Consumed(_) => true,
         }
     }
 }
@@ -147,6 +603,7 @@ pub(super) struct MouseHook<E> {
     condition: Condition,
     process: Process<E>,
     native_event_operation: NativeEventOperation,
+    ordered: bool,
 }
 
 impl<E> MouseHook<E> {
@@ -160,8 +617,15 @@ impl<E> MouseHook<E> {
             condition,
             process,
             native_event_operation,
+            ordered: false,
         }
     }
+
+    /// Runs the hook on the event loop in the order of events instead of on a worker.
+    pub(super) fn ordered(mut self) -> Self {
+        self.ordered = true;
+        self
+    }
 }
 
 impl<E> MouseHook<E> {
@@ -178,6 +642,10 @@ impl<E> Hook<E> for MouseHook<E> {
     fn run(&self, event: E) {
         self.process.0(event);
     }
+
+    fn is_ordered(&self) -> bool {
+        self.ordered
+    }
 }
 
 impl<E, T: Hook<E>> Hook<E> for Arc<T> {
@@ -188,4 +656,140 @@ impl<E, T: Hook<E>> Hook<E> for Arc<T> {
     fn run(&self, event: E) {
         (**self).run(event);
     }
+
+    fn is_ordered(&self) -> bool {
+        (**self).is_ordered()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hookmap_core::event::{CursorEvent, WheelEvent};
+
+    #[test]
+    fn hooks_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Process<ButtonEvent>>();
+        assert_send_sync::<HotkeyHook>();
+        assert_send_sync::<RemapHook>();
+        assert_send_sync::<ButtonHook>();
+        assert_send_sync::<MouseHook<CursorEvent>>();
+        assert_send_sync::<MouseHook<WheelEvent>>();
+    }
+
+    #[test]
+    fn non_reentrant_process_is_skipped_while_running() {
+        use std::sync::mpsc;
+        use std::thread;
+
+        let count = Arc::new(Mutex::new(0));
+        let (started_tx, started_rx) = mpsc::channel();
+        let (finish_tx, finish_rx) = mpsc::channel::<()>();
+        let finish_rx = Mutex::new(finish_rx);
+
+        let count_ = Arc::clone(&count);
+        let process = Process::from(move |_: i32| {
+            *count_.lock().unwrap() += 1;
+            started_tx.send(()).unwrap();
+            finish_rx.lock().unwrap().recv().unwrap();
+        })
+        .non_reentrant();
+
+        let process_ = process.clone();
+        let handle = thread::spawn(move || process_.0(0));
+        started_rx.recv().unwrap();
+
+        for _ in 0..10 {
+            process.0(0);
+        }
+        assert_eq!(*count.lock().unwrap(), 1);
+
+        finish_tx.send(()).unwrap();
+        handle.join().unwrap();
+
+        let handle = thread::spawn(move || process.0(0));
+        started_rx.recv().unwrap();
+        finish_tx.send(()).unwrap();
+        handle.join().unwrap();
+        assert_eq!(*count.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn registration_id_is_restored_after_panic() {
+        let process =
+            Process::from(|_: i32| panic!("callback panicked")).registered_as(RegistrationId(1));
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| process.0(0)));
+        assert!(result.is_err());
+        assert_eq!(registration_id(), None);
+    }
+
+    #[test]
+    fn chained_processes_run_in_order() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let push = |n: i32| {
+            let log = Arc::clone(&log);
+            move |_: i32| log.lock().unwrap().push(n)
+        };
+
+        let process = Process::from(push(1)).chain(push(2)).chain(push(3));
+        process.0(0);
+        assert_eq!(*log.lock().unwrap(), vec![1, 2, 3]);
+
+        log.lock().unwrap().clear();
+        let process = Process::from(vec![push(3).into(), push(1).into(), push(2).into()]);
+        process.0(0);
+        assert_eq!(*log.lock().unwrap(), vec![3, 1, 2]);
+    }
+
+    #[test]
+    fn remap_holders_release_after_all_sources_are_released() {
+        let holders = RemapHolders::default();
+        holders.press(Button::A);
+        holders.press(Button::B);
+        assert!(!holders.release(Button::A));
+        assert!(holders.release(Button::B));
+    }
+
+    #[test]
+    fn remap_holders_ignore_repeated_press() {
+        let holders = RemapHolders::default();
+        holders.press(Button::A);
+        holders.press(Button::A);
+        holders.press(Button::B);
+        assert!(!holders.release(Button::B));
+        assert!(holders.release(Button::A));
+    }
+
+    #[test]
+    fn remap_reproduces_key_repeats() {
+        let event = |action| ButtonEvent::new(Button::A, action);
+        let holders = Some(Arc::new(RemapHolders::default()));
+        for holders in [None, holders] {
+            let hook = RemapHook::new(Condition::Any, Button::B, holders);
+            let outputs: Vec<_> = [
+                ButtonAction::Press,
+                ButtonAction::Press,
+                ButtonAction::Press,
+                ButtonAction::Release,
+            ]
+            .into_iter()
+            .map(|action| hook.output(event(action)))
+            .collect();
+            assert_eq!(
+                outputs,
+                [
+                    Some(ButtonAction::Press),
+                    Some(ButtonAction::Press),
+                    Some(ButtonAction::Press),
+                    Some(ButtonAction::Release),
+                ]
+            );
+        }
+        assert!(
+            ButtonHook::from(Arc::new(RemapHook::new(Condition::Any, Button::B, None)))
+                .is_ordered()
+        );
+    }
 }
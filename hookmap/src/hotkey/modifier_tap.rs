@@ -0,0 +1,94 @@
+use hookmap_core::button::{Button, ButtonAction, ButtonCategory};
+use hookmap_core::event::ButtonEvent;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum TapState {
+    #[default]
+    Released,
+    Held,
+    // Another key was pressed while the modifier was held down.
+    Interrupted,
+}
+
+/// Tells whether a modifier key was released without any other key pressed while it was
+/// held down.
+#[derive(Debug)]
+pub(super) struct ModifierTap {
+    modifier: Button,
+    state: Mutex<TapState>,
+    // Set on each release of the modifier to whether it was a tap.
+    tapped: Arc<AtomicBool>,
+}
+
+impl ModifierTap {
+    pub(super) fn new(modifier: Button) -> Self {
+        Self {
+            modifier,
+            state: Mutex::default(),
+            tapped: Arc::default(),
+        }
+    }
+
+    /// Returns the flag that is set when the modifier is tapped and cleared when it is
+    /// released after another key.
+    pub(super) fn tapped(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.tapped)
+    }
+
+    /// Updates the state with `event`, which may be of any button.
+    /// Other modifier keys and key repeats of the modifier do not interrupt the tap.
+    pub(super) fn observe(&self, event: ButtonEvent) {
+        let mut state = self.state.lock().unwrap();
+        if event.target.matches_sided(self.modifier) {
+            match event.action {
+                ButtonAction::Press if *state == TapState::Released => *state = TapState::Held,
+                ButtonAction::Press => {}
+                ButtonAction::Release => {
+                    self.tapped
+                        .store(*state == TapState::Held, Ordering::SeqCst);
+                    *state = TapState::Released;
+                }
+            }
+        } else if event.action == ButtonAction::Press
+            && event.target.category() != ButtonCategory::Modifier
+            && *state == TapState::Held
+        {
+            *state = TapState::Interrupted;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(target: Button, action: ButtonAction) -> ButtonEvent {
+        ButtonEvent::new(target, action)
+    }
+
+    #[test]
+    fn only_modifier_keys_keep_the_tap() {
+        use ButtonAction::{Press, Release};
+
+        let tap = ModifierTap::new(Button::Ctrl);
+        let tapped = tap.tapped();
+        for events in [
+            &[(Button::LCtrl, Press), (Button::LCtrl, Press)][..],
+            &[(Button::RCtrl, Press), (Button::LShift, Press)],
+            &[(Button::A, Release), (Button::RCtrl, Press)],
+        ] {
+            events.iter().for_each(|&(b, a)| tap.observe(event(b, a)));
+            tap.observe(event(Button::LCtrl, Release));
+            assert!(tapped.load(Ordering::SeqCst), "{events:?}");
+        }
+
+        tap.observe(event(Button::LCtrl, Press));
+        tap.observe(event(Button::C, Press));
+        tap.observe(event(Button::LCtrl, Press));
+        tap.observe(event(Button::LCtrl, Release));
+        assert!(!tapped.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,88 @@
+use super::hook::Process;
+
+use once_cell::sync::Lazy;
+use std::any::Any;
+use std::fmt::{self, Display};
+use std::sync::RwLock;
+
+/// A panic of a named callback. See [`Process::named`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CallbackPanic {
+    /// The name given by [`Process::named`].
+    pub name: String,
+
+    /// The message of the panic, or `"Box<dyn Any>"` if it is not a string.
+    pub message: String,
+}
+
+impl Display for CallbackPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "callback `{}` panicked: {}", self.name, self.message)
+    }
+}
+
+static HANDLER: Lazy<RwLock<Option<Process<CallbackPanic>>>> = Lazy::new(RwLock::default);
+
+/// Sets the handler called by [`report`].
+pub(super) fn set_handler(handler: Option<Process<CallbackPanic>>) {
+    *HANDLER.write().unwrap() = handler;
+}
+
+fn message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_owned()
+    }
+}
+
+/// Passes the panic of the callback named `name` to the handler, if any.
+pub(super) fn report(name: &str, payload: &(dyn Any + Send)) {
+    let handler = HANDLER.read().unwrap().clone();
+    if let Some(handler) = handler {
+        handler.0(CallbackPanic {
+            name: name.to_owned(),
+            message: message(payload),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::mpsc;
+    use std::sync::Mutex;
+
+    #[test]
+    fn panicking_named_callback_reports_its_name() {
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        set_handler(Some(Process::from(move |e: CallbackPanic| {
+            tx.lock().unwrap().send(e).unwrap();
+        })));
+
+        let process = Process::from(|n: u32| panic!("bad input {}", n)).named("toggle_mute");
+        let result = panic::catch_unwind(AssertUnwindSafe(|| process.0(3)));
+        assert!(result.is_err());
+        let report = rx.try_recv().unwrap();
+        assert_eq!(
+            report,
+            CallbackPanic {
+                name: "toggle_mute".to_owned(),
+                message: "bad input 3".to_owned(),
+            }
+        );
+        assert_eq!(
+            report.to_string(),
+            "callback `toggle_mute` panicked: bad input 3"
+        );
+
+        let process = Process::from(|_: u32| {}).named("ok");
+        process.0(0);
+        assert!(rx.try_recv().is_err());
+        set_handler(None);
+    }
+}
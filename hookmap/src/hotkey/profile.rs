@@ -0,0 +1,102 @@
+use hookmap_core::button::Button;
+
+use crate::macros::button_arg::ButtonArg;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{PoisonError, RwLock};
+
+/// A named set of remaps, such as for gaming or coding.
+///
+/// Unlike hotkeys registered with [`Context`]s, only one of the profiles added by
+/// [`Hotkey::add_profile`] is active at a time, and switching the profile replaces all its
+/// remaps at once.
+///
+/// [`Context`]: super::Context
+/// [`Hotkey::add_profile`]: super::Hotkey::add_profile
+///
+/// # Examples
+///
+/// ```
+/// use hookmap::prelude::*;
+/// use hookmap::hotkey::Profile;
+///
+/// let gaming = Profile::new("gaming")
+///     .remap(Button::CapsLock, Button::LCtrl)
+///     .remap(Button::LSuper, Button::F24);
+/// assert_eq!(gaming.name(), "gaming");
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct Profile {
+    name: String,
+    pub(super) remaps: Vec<(ButtonArg, Button)>,
+}
+
+impl Profile {
+    /// Creates an empty profile named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            remaps: Vec::new(),
+        }
+    }
+
+    /// Makes `targets` behave like `behavior` while this profile is active.
+    /// See [`Registrar::remap`](super::Registrar::remap).
+    #[must_use]
+    pub fn remap(mut self, targets: impl Into<ButtonArg>, behavior: Button) -> Self {
+        self.remaps.push((targets.into(), behavior));
+        self
+    }
+
+    /// Returns the name of this profile.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The names of the profiles added to a [`Hotkey`](super::Hotkey), and the active one.
+#[derive(Debug, Default)]
+pub(super) struct Profiles {
+    names: RwLock<Vec<String>>,
+    // The index of the active profile plus one, or zero if no profile is active.
+    active: AtomicUsize,
+}
+
+impl Profiles {
+    /// Returns the index of the profile named `name`, adding it if it does not exist.
+    pub(super) fn add(&self, name: &str) -> usize {
+        let mut names = self.names.write().unwrap_or_else(PoisonError::into_inner);
+        names.iter().position(|n| n == name).unwrap_or_else(|| {
+            names.push(name.to_owned());
+            names.len() - 1
+        })
+    }
+
+    pub(super) fn is_active(&self, index: usize) -> bool {
+        self.active.load(Ordering::SeqCst) == index + 1
+    }
+
+    /// Activates the profile named `name`, or deactivates all the profiles if `name` is
+    /// `None`. Returns `false` if there is no such profile.
+    pub(super) fn activate(&self, name: Option<&str>) -> bool {
+        let names = self.names.read().unwrap_or_else(PoisonError::into_inner);
+        let active = match name {
+            Some(name) => match names.iter().position(|n| n == name) {
+                Some(index) => index + 1,
+                None => return false,
+            },
+            None => 0,
+        };
+        self.active.store(active, Ordering::SeqCst);
+        true
+    }
+
+    pub(super) fn active(&self) -> Option<String> {
+        let names = self.names.read().unwrap_or_else(PoisonError::into_inner);
+        self.active
+            .load(Ordering::SeqCst)
+            .checked_sub(1)
+            .map(|index| names[index].clone())
+    }
+}
@@ -0,0 +1,138 @@
+use crate::runtime::PendingTask;
+
+use hookmap_core::button::ButtonAction;
+
+use std::fmt::{self, Debug};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type Output = Box<dyn Fn(ButtonAction) + Send + Sync>;
+
+#[derive(Default)]
+struct RepeatState {
+    is_held: bool,
+    // The next repeat while the target is held down.
+    pending: PendingTask,
+}
+
+/// Presses the behavior of a remap again at a fixed interval while the target is held down.
+pub(super) struct AutoRepeat {
+    interval: Duration,
+    output: Output,
+    state: Mutex<RepeatState>,
+}
+
+impl Debug for AutoRepeat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutoRepeat")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AutoRepeat {
+    pub(super) fn new(
+        interval: Duration,
+        output: impl Fn(ButtonAction) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            interval,
+            output: Box::new(output),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Presses the behavior and starts repeating it.
+    /// Key repeats of the target are ignored, since the behavior is repeated by the timer.
+    pub(super) fn press(self: &Arc<Self>) {
+        let mut state = self.state.lock().unwrap();
+        if state.is_held {
+            return;
+        }
+        state.is_held = true;
+        (self.output)(ButtonAction::Press);
+        self.schedule(&mut state, Instant::now() + self.interval);
+    }
+
+    /// Returns `true` if the target is held down, that is, pressed and not released yet.
+    pub(super) fn is_held(&self) -> bool {
+        self.state.lock().unwrap().is_held
+    }
+
+    /// Stops repeating and releases the behavior.
+    pub(super) fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.is_held = false;
+        if state.pending.cancel() {
+            (self.output)(ButtonAction::Release);
+        }
+    }
+
+    fn schedule(self: &Arc<Self>, state: &mut RepeatState, deadline: Instant) {
+        let this = Arc::clone(self);
+        state.pending.schedule(deadline, move |generation| {
+            this.repeat(generation, deadline)
+        });
+    }
+
+    fn repeat(self: &Arc<Self>, generation: u64, deadline: Instant) {
+        let mut state = self.state.lock().unwrap();
+        if !state.pending.take(generation) {
+            return;
+        }
+        (self.output)(ButtonAction::Press);
+        // Repeats missed while the timer was busy are skipped rather than sent in a burst.
+        let next = (deadline + self.interval).max(Instant::now());
+        self.schedule(&mut state, next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime;
+    use std::sync::mpsc::Receiver;
+
+    const INTERVAL: Duration = Duration::from_millis(20);
+
+    fn repeat() -> (Arc<AutoRepeat>, Receiver<(ButtonAction, Instant)>) {
+        let (output, rx) = runtime::recorder();
+        let repeat = AutoRepeat::new(INTERVAL, move |action| output((action, Instant::now())));
+        (Arc::new(repeat), rx)
+    }
+
+    #[test]
+    fn behavior_is_repeated_at_interval_while_held() {
+        let (repeat, rx) = repeat();
+        repeat.press();
+        let presses: Vec<_> = (0..3)
+            .map(|_| rx.recv_timeout(INTERVAL * 10).unwrap())
+            .collect();
+        assert!(presses
+            .iter()
+            .all(|&(action, _)| action == ButtonAction::Press));
+        assert!(presses[2].1 - presses[0].1 >= INTERVAL * 2);
+        repeat.release();
+
+        // A repeat may have been sent before the release, but none follows it.
+        let actions: Vec<_> = rx.try_iter().map(|(action, _)| action).collect();
+        let (release, presses) = actions.split_last().unwrap();
+        assert_eq!(*release, ButtonAction::Release);
+        assert!(presses.iter().all(|&action| action == ButtonAction::Press));
+        assert!(rx.recv_timeout(INTERVAL * 3).is_err());
+    }
+
+    #[test]
+    fn key_repeats_of_target_are_ignored() {
+        let (repeat, rx) = repeat();
+        repeat.press();
+        repeat.press();
+        repeat.press();
+        repeat.release();
+        let actions: Vec<_> = rx.try_iter().map(|(action, _)| action).collect();
+        assert_eq!(actions, [ButtonAction::Press, ButtonAction::Release]);
+
+        repeat.release();
+        assert!(rx.recv_timeout(INTERVAL * 3).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use once_cell::sync::Lazy;
+use std::any::{self, Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Values registered by [`Hotkey::context`](super::Hotkey::context), keyed by their types.
+pub(super) type ContextMap = HashMap<TypeId, Arc<dyn Any + Send + Sync>>;
+
+static CONTEXTS: Lazy<RwLock<ContextMap>> = Lazy::new(RwLock::default);
+
+/// Makes `contexts` available to [`context`].
+pub(super) fn publish(contexts: &ContextMap) {
+    CONTEXTS.write().unwrap().extend(
+        contexts
+            .iter()
+            .map(|(&type_id, value)| (type_id, Arc::clone(value))),
+    );
+}
+
+/// Returns the value of type `T` registered by [`Hotkey::context`].
+///
+/// This can be called from any callback after the hotkeys are installed, including the
+/// callbacks of [`Interceptor`], so shared data does not need to be cloned into each closure.
+///
+/// # Panics
+///
+/// Panics if no value of type `T` is registered, or the hotkeys are not installed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use hookmap::prelude::*;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// #[derive(Default)]
+/// struct Counter(AtomicUsize);
+///
+/// let mut hotkey = Hotkey::new();
+/// hotkey.context(Counter::default());
+/// hotkey.register(Context::default()).on_press(Button::F1, |_| {
+///     let counter = hookmap::hotkey::context::<Counter>();
+///     println!("{}", counter.0.fetch_add(1, Ordering::SeqCst));
+/// });
+/// hotkey.install();
+/// ```
+///
+/// [`Hotkey::context`]: super::Hotkey::context
+/// [`Interceptor`]: crate::interceptor::Interceptor
+///
+pub fn context<T: Send + Sync + 'static>() -> Arc<T> {
+    let value = CONTEXTS
+        .read()
+        .unwrap()
+        .get(&TypeId::of::<T>())
+        .map(Arc::clone)
+        .unwrap_or_else(|| panic!("no context of type `{}`", any::type_name::<T>()));
+    value.downcast().unwrap()
+}
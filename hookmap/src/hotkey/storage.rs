@@ -1,78 +1,375 @@
-use hookmap_core::button::{Button, ButtonAction};
-use hookmap_core::event::{ButtonEvent, CursorEvent, WheelEvent};
+use hookmap_core::button::{Button, ButtonAction, ButtonKind};
+use hookmap_core::event::{
+    ButtonEvent, CursorEvent, Event, MouseEvent, NativeEventOperation, WheelEvent,
+};
 
-use super::hook::{ButtonHook, HotkeyHook, MouseHook, RemapHook};
-use crate::hook::{ButtonState, HookStorage};
-use std::{collections::HashMap, sync::Arc};
+use super::hook::{
+    ButtonHook, HotkeyHook, MouseHook, Process, RemapHolders, RemapHook, RemapReleasePolicy,
+};
+use super::modifier_tap::ModifierTap;
+use super::RegistrationId;
+use crate::button_set::ButtonSet;
+use crate::hook::{native_event_operation, ButtonState, HookStorage, Hooks};
+use std::collections::HashMap;
+use std::sync::atomic::AtomicUsize;
+use std::sync::Arc;
 
 #[derive(Debug, Default)]
 pub(super) struct HotkeyStorage {
-    remap: HashMap<Button, Vec<Arc<RemapHook>>>,
+    // A group of buttons remapped to the same button is stored as a single entry.
+    remap: Vec<(ButtonSet, Arc<RemapHook>)>,
+    // The union of the targets of `remap`, so that other buttons skip the remaps.
+    remapped: ButtonSet,
     hotkey_on_press: HashMap<Button, Vec<Arc<HotkeyHook>>>,
     hotkey_on_release: HashMap<Button, Vec<Arc<HotkeyHook>>>,
+    // Hotkeys whose targets are given by a predicate, consulted after the exact matches.
+    hotkey_on_press_matching: Vec<(ButtonSet, Arc<HotkeyHook>)>,
+    // Hotkeys run for button events that no other hotkey matches.
+    hotkey_on_unhandled: Vec<Arc<HotkeyHook>>,
     mouse_cursor: Vec<Arc<MouseHook<CursorEvent>>>,
     mouse_wheel: Vec<Arc<MouseHook<WheelEvent>>>,
+    mouse: Vec<Arc<MouseHook<MouseEvent>>>,
+    pub(super) remap_release_policy: RemapReleasePolicy,
+    pub(super) remap_holders: HashMap<Button, Arc<RemapHolders>>,
+    // Updated with every button event before the hooks are selected.
+    modifier_taps: Vec<Arc<ModifierTap>>,
+    // Run with every button event before the hooks are selected, in the order of events.
+    button_observers: Vec<Arc<Process<ButtonEvent>>>,
+    registrations: HashMap<RegistrationId, Registration>,
+    // The number of calls disabling the hotkeys registered with each ID.
+    registration_disabled: HashMap<RegistrationId, Arc<AtomicUsize>>,
+    current_registration: Option<RegistrationId>,
+    next_registration_id: u64,
+}
+
+/// What was registered through a [`Registrar`](super::Registrar).
+#[derive(Debug, Default)]
+struct Registration {
+    // Since hooks are stored in `Arc`, an address identifies a hook while it is stored.
+    hooks: Vec<usize>,
+    // Buttons whose events are needed to evaluate conditions, such as modifiers with grace.
+    observed: ButtonSet,
+}
+
+fn address<T>(hook: &Arc<T>) -> usize {
+    Arc::as_ptr(hook) as usize
 }
 
 impl HotkeyStorage {
-    fn fetch_mouse_hook<E, S: ButtonState>(
+    fn filter_mouse_hook<E, S: ButtonState>(
         hooks: &[Arc<MouseHook<E>>],
         state: &S,
     ) -> Vec<Arc<MouseHook<E>>> {
         hooks
             .iter()
             .filter(|hook| hook.is_executable(state))
-            .map(|hook| Arc::clone(hook))
+            .map(Arc::clone)
             .collect()
     }
 
-    pub(super) fn register_remap(&mut self, target: Button, hook: Arc<RemapHook>) {
-        self.remap.entry(target).or_default().push(hook);
+    pub(super) fn remap_holders(&mut self, behavior: Button) -> Option<Arc<RemapHolders>> {
+        match self.remap_release_policy {
+            RemapReleasePolicy::Strict => None,
+            RemapReleasePolicy::RefCount => {
+                Some(Arc::clone(self.remap_holders.entry(behavior).or_default()))
+            }
+        }
+    }
+
+    pub(super) fn observe(&mut self, buttons: impl IntoIterator<Item = Button>) {
+        if let Some(registration) = self.current_registration_mut() {
+            registration.observed.extend(buttons);
+        }
+    }
+
+    fn current_registration_mut(&mut self) -> Option<&mut Registration> {
+        let id = self.current_registration?;
+        self.registrations.get_mut(&id)
+    }
+
+    /// Starts a registration. Hooks registered after this call are associated with the
+    /// returned ID until the next call.
+    ///
+    /// The returned counter must be zero for the hooks to be active.
+    pub(super) fn begin_registration(&mut self) -> (RegistrationId, Arc<AtomicUsize>) {
+        let id = RegistrationId(self.next_registration_id);
+        self.next_registration_id += 1;
+        self.current_registration = Some(id);
+        self.registrations.insert(id, Registration::default());
+        let disabled = Arc::new(AtomicUsize::new(0));
+        self.registration_disabled.insert(id, Arc::clone(&disabled));
+        (id, disabled)
+    }
+
+    /// Returns the counter that disables the hooks registered with `id` while it is not zero.
+    pub(super) fn registration_disabled(&self, id: RegistrationId) -> Option<Arc<AtomicUsize>> {
+        self.registration_disabled.get(&id).cloned()
+    }
+
+    fn record<T>(&mut self, hook: &Arc<T>) {
+        if let Some(registration) = self.current_registration_mut() {
+            registration.hooks.push(address(hook));
+        }
+    }
+
+    /// Removes the hooks registered with `id`, and stops observing the buttons for them.
+    /// Returns `false` if `id` is unknown or has already been unregistered.
+    pub(super) fn unregister(&mut self, id: RegistrationId) -> bool {
+        self.registration_disabled.remove(&id);
+        let addresses = match self.registrations.remove(&id) {
+            Some(registration) => registration.hooks,
+            None => return false,
+        };
+        if self.current_registration == Some(id) {
+            self.current_registration = None;
+        }
+        self.remap
+            .retain(|(_, hook)| !addresses.contains(&address(hook)));
+        self.remapped = self
+            .remap
+            .iter()
+            .flat_map(|(targets, _)| *targets)
+            .collect();
+        for hotkey_map in [&mut self.hotkey_on_press, &mut self.hotkey_on_release] {
+            hotkey_map.values_mut().for_each(|hooks| {
+                hooks.retain(|hook| !addresses.contains(&address(hook)));
+            });
+            hotkey_map.retain(|_, hooks| !hooks.is_empty());
+        }
+        self.hotkey_on_press_matching
+            .retain(|(_, hook)| !addresses.contains(&address(hook)));
+        self.hotkey_on_unhandled
+            .retain(|hook| !addresses.contains(&address(hook)));
+        self.mouse_cursor
+            .retain(|hook| !addresses.contains(&address(hook)));
+        self.mouse_wheel
+            .retain(|hook| !addresses.contains(&address(hook)));
+        self.mouse
+            .retain(|hook| !addresses.contains(&address(hook)));
+        self.modifier_taps
+            .retain(|tap| !addresses.contains(&address(tap)));
+        self.button_observers
+            .retain(|observer| !addresses.contains(&address(observer)));
+        let behaviors: ButtonSet = self.remap.iter().map(|(_, hook)| hook.behavior()).collect();
+        self.remap_holders
+            .retain(|&behavior, _| behaviors.contains(behavior));
+        true
+    }
+
+    pub(super) fn register_remap(
+        &mut self,
+        targets: impl IntoIterator<Item = Button>,
+        hook: Arc<RemapHook>,
+    ) {
+        self.record(&hook);
+        let targets: ButtonSet = targets.into_iter().collect();
+        self.remapped.extend(targets);
+        self.remap.push((targets, hook));
     }
 
     pub(super) fn register_hotkey_on_press(&mut self, target: Button, hook: Arc<HotkeyHook>) {
+        self.record(&hook);
         self.hotkey_on_press.entry(target).or_default().push(hook);
     }
 
+    pub(super) fn register_hotkey_on_press_matching(
+        &mut self,
+        targets: ButtonSet,
+        hook: Arc<HotkeyHook>,
+    ) {
+        self.record(&hook);
+        self.hotkey_on_press_matching.push((targets, hook));
+    }
+
+    pub(super) fn register_hotkey_on_unhandled(&mut self, hook: Arc<HotkeyHook>) {
+        self.record(&hook);
+        self.hotkey_on_unhandled.push(hook);
+    }
+
     pub(super) fn register_hotkey_on_release(&mut self, target: Button, hook: Arc<HotkeyHook>) {
+        self.record(&hook);
         self.hotkey_on_release.entry(target).or_default().push(hook);
     }
 
+    pub(super) fn register_modifier_tap(&mut self, tap: Arc<ModifierTap>) {
+        self.record(&tap);
+        self.modifier_taps.push(tap);
+    }
+
+    pub(super) fn register_button_observer(&mut self, observer: Arc<Process<ButtonEvent>>) {
+        self.record(&observer);
+        self.button_observers.push(observer);
+    }
+
     pub(super) fn register_mouse_cursor_hotkey(&mut self, hook: Arc<MouseHook<CursorEvent>>) {
+        self.record(&hook);
         self.mouse_cursor.push(hook);
     }
 
     pub(super) fn register_mouse_wheel_hotkey(&mut self, hook: Arc<MouseHook<WheelEvent>>) {
+        self.record(&hook);
         self.mouse_wheel.push(hook);
     }
-}
-
-impl HookStorage for HotkeyStorage {
-    type ButtonHook = ButtonHook;
-    type MouseCursorHook = Arc<MouseHook<CursorEvent>>;
-    type MouseWheelHook = Arc<MouseHook<WheelEvent>>;
 
-    fn fetch_button_hook<S: ButtonState>(&self, event: ButtonEvent, state: &S) -> Vec<ButtonHook> {
+    /// Selects the hooks for `event`. If `dry_run` is `true`, neither the remaps nor the
+    /// activations are updated.
+    fn select_button_hooks<S: ButtonState>(
+        &self,
+        event: ButtonEvent,
+        state: &S,
+        dry_run: bool,
+    ) -> Hooks<ButtonHook> {
+        let is_executable = |hook: &HotkeyHook| {
+            if dry_run {
+                hook.would_be_executable(state)
+            } else {
+                hook.is_executable(state)
+            }
+        };
+        if !dry_run {
+            self.modifier_taps.iter().for_each(|tap| tap.observe(event));
+            self.button_observers
+                .iter()
+                .for_each(|observer| observer.0(event));
+        }
+        let remaps = || {
+            self.remap
+                .iter()
+                .filter(|(targets, _)| targets.contains(event.target))
+                .map(|(_, hook)| hook)
+        };
+        // A remap that handled the press also handles the key repeats and the release even if
+        // its condition is no longer satisfied, so that neither button gets stuck.
         let remap_hook = self
-            .remap
-            .get(&event.target)
-            .and_then(|hooks| hooks.iter().find(|hook| hook.is_executable(state)));
+            .remapped
+            .contains(event.target)
+            .then(|| {
+                remaps()
+                    .find(|hook| hook.is_held_by(event.target))
+                    .or_else(|| match event.action {
+                        ButtonAction::Press => remaps().find(|hook| hook.is_executable(state)),
+                        ButtonAction::Release => None,
+                    })
+            })
+            .flatten();
+        let (hotkey_map, matching) = match event.action {
+            ButtonAction::Press => (&self.hotkey_on_press, &*self.hotkey_on_press_matching),
+            ButtonAction::Release => (&self.hotkey_on_release, &[][..]),
+        };
         if let Some(hook) = remap_hook {
-            let hook = ButtonHook::from(Arc::clone(hook));
-            return vec![hook];
+            if !dry_run {
+                hook.track(event);
+            }
+            // The source may also be a modifier of other hotkeys, which observe the physical
+            // button rather than the remapped one. The hooks that keep track of it, such as
+            // releasing the remaps of a context on exit, are run along with the remap.
+            let observers: Vec<_> = hotkey_map
+                .get(&event.target)
+                .into_iter()
+                .flatten()
+                .filter(|hook| hook.is_ordered() && is_executable(hook))
+                .map(|hook| ButtonHook::from(Arc::clone(hook)))
+                .collect();
+            let remap = ButtonHook::from(Arc::clone(hook));
+            if observers.is_empty() {
+                return Hooks::One(remap);
+            }
+            return std::iter::once(remap)
+                .chain(observers)
+                .collect::<Vec<_>>()
+                .into();
         }
 
-        let hotkey_map = match event.action {
-            ButtonAction::Press => &self.hotkey_on_press,
-            ButtonAction::Release => &self.hotkey_on_release,
-        };
-        hotkey_map
+        let matching = matching
+            .iter()
+            .filter(|(targets, _)| targets.contains(event.target))
+            .map(|(_, hook)| hook);
+        let hooks: Vec<_> = hotkey_map
             .get(&event.target)
             .into_iter()
             .flatten()
-            .filter(|hook| hook.is_executable(state))
-            .map(|hook| ButtonHook::from(Arc::clone(hook)))
-            .collect()
+            .chain(matching)
+            .filter(|hook| is_executable(hook))
+            .map(|hook| ButtonHook::for_event(hook, event, dry_run))
+            .collect();
+        if !hooks.is_empty() {
+            return hooks.into();
+        }
+        self.hotkey_on_unhandled
+            .iter()
+            .filter(|hook| is_executable(hook))
+            .map(|hook| ButtonHook::for_event(hook, event, dry_run))
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Returns whether `event` would be blocked, without running or updating any hook.
+    pub(super) fn would_block<S: ButtonState>(
+        &self,
+        event: ButtonEvent,
+        state: &S,
+    ) -> NativeEventOperation {
+        let hooks = self.select_button_hooks(event, state, true);
+        match native_event_operation(&hooks) {
+            NativeEventOperation::Block => NativeEventOperation::Block,
+            NativeEventOperation::Dispatch => Event::Button(event)
+                .to_mouse_event()
+                .map_or(NativeEventOperation::Dispatch, |mouse_event| {
+                    native_event_operation(&self.fetch_mouse_hook(mouse_event, state))
+                }),
+        }
+    }
+
+    pub(super) fn register_mouse_hotkey(&mut self, hook: Arc<MouseHook<MouseEvent>>) {
+        self.record(&hook);
+        self.mouse.push(hook);
+    }
+}
+
+impl HookStorage for HotkeyStorage {
+    type ButtonHook = ButtonHook;
+    type MouseCursorHook = Arc<MouseHook<CursorEvent>>;
+    type MouseWheelHook = Arc<MouseHook<WheelEvent>>;
+    type MouseHook = Arc<MouseHook<MouseEvent>>;
+
+    fn fetch_button_hook<S: ButtonState>(
+        &self,
+        event: ButtonEvent,
+        state: &S,
+    ) -> Hooks<ButtonHook> {
+        self.select_button_hooks(event, state, false)
+    }
+
+    fn release_held(&self) {
+        self.remap.iter().for_each(|(_, hook)| hook.release_held());
+    }
+
+    fn handled_buttons(&self) -> ButtonSet {
+        let mut buttons: ButtonSet = self
+            .registrations
+            .values()
+            .flat_map(|registration| registration.observed)
+            .collect();
+        buttons.extend(self.remapped);
+        buttons.extend(self.hotkey_on_press.keys().copied());
+        buttons.extend(self.hotkey_on_release.keys().copied());
+        for (targets, _) in &self.hotkey_on_press_matching {
+            buttons.extend(*targets);
+        }
+        if !self.hotkey_on_unhandled.is_empty()
+            || !self.modifier_taps.is_empty()
+            || !self.button_observers.is_empty()
+        {
+            buttons.extend(Button::ALL);
+        }
+        if !self.mouse.is_empty() {
+            buttons.extend(
+                Button::ALL
+                    .into_iter()
+                    .filter(|button| button.kind() == ButtonKind::Mouse),
+            );
+        }
+        buttons
     }
 
     fn fetch_mouse_cursor_hook<S: ButtonState>(
@@ -80,7 +377,7 @@ impl HookStorage for HotkeyStorage {
         _: CursorEvent,
         state: &S,
     ) -> Vec<Arc<MouseHook<CursorEvent>>> {
-        Self::fetch_mouse_hook(&self.mouse_cursor, state)
+        Self::filter_mouse_hook(&self.mouse_cursor, state)
     }
 
     fn fetch_mouse_wheel_hook<S: ButtonState>(
@@ -88,6 +385,14 @@ impl HookStorage for HotkeyStorage {
         _: WheelEvent,
         state: &S,
     ) -> Vec<Arc<MouseHook<WheelEvent>>> {
-        Self::fetch_mouse_hook(&self.mouse_wheel, state)
+        Self::filter_mouse_hook(&self.mouse_wheel, state)
+    }
+
+    fn fetch_mouse_hook<S: ButtonState>(
+        &self,
+        _: MouseEvent,
+        state: &S,
+    ) -> Vec<Arc<MouseHook<MouseEvent>>> {
+        Self::filter_mouse_hook(&self.mouse, state)
     }
 }
@@ -0,0 +1,174 @@
+use crate::runtime::PendingTask;
+
+use hookmap_core::button::Button;
+
+use std::fmt::{self, Debug};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type Output = Box<dyn Fn(Button) + Send + Sync>;
+
+#[derive(Default)]
+struct TapState {
+    count: u32,
+    is_held: bool,
+    // The resolution of the count, cancelled by the next tap.
+    pending: PendingTask,
+}
+
+/// Counts the taps of a button and emits the behavior mapped to the final count
+/// once no further tap follows within the interval.
+pub(super) struct TapCounter {
+    mapping: Vec<(u32, Button)>,
+    max_count: u32,
+    interval: Duration,
+    output: Output,
+    state: Mutex<TapState>,
+}
+
+impl Debug for TapCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TapCounter")
+            .field("mapping", &self.mapping)
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TapCounter {
+    pub(super) fn new(
+        mapping: Vec<(u32, Button)>,
+        interval: Duration,
+        output: impl Fn(Button) + Send + Sync + 'static,
+    ) -> Self {
+        let max_count = mapping.iter().map(|&(count, _)| count).max().unwrap_or(0);
+        Self {
+            mapping,
+            max_count,
+            interval,
+            output: Box::new(output),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Counts a tap. Key repeats while the button is held down are not counted.
+    pub(super) fn press(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.is_held {
+            return;
+        }
+        state.is_held = true;
+        state.count += 1;
+        state.pending.cancel();
+    }
+
+    /// Ends a tap, and waits for the next one unless the count has reached
+    /// the largest one in the mapping.
+    pub(super) fn release(self: &Arc<Self>) {
+        let mut state = self.state.lock().unwrap();
+        if !state.is_held {
+            return;
+        }
+        state.is_held = false;
+        if state.count >= self.max_count {
+            drop(state);
+            self.resolve(None);
+            return;
+        }
+        let this = Arc::clone(self);
+        state
+            .pending
+            .schedule(Instant::now() + self.interval, move |generation| {
+                this.resolve(Some(generation))
+            });
+    }
+
+    fn resolve(&self, generation: Option<u64>) {
+        let count = {
+            let mut state = self.state.lock().unwrap();
+            if generation.is_some_and(|generation| !state.pending.take(generation)) {
+                return;
+            }
+            std::mem::take(&mut state.count)
+        };
+        let behavior = self
+            .mapping
+            .iter()
+            .find(|&&(n, _)| n == count)
+            .map(|&(_, behavior)| behavior);
+        if let Some(behavior) = behavior {
+            (self.output)(behavior);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime;
+    use std::sync::mpsc::Receiver;
+
+    const INTERVAL: Duration = Duration::from_millis(50);
+
+    fn counter(mapping: Vec<(u32, Button)>) -> (Arc<TapCounter>, Receiver<Button>) {
+        let (output, rx) = runtime::recorder();
+        (Arc::new(TapCounter::new(mapping, INTERVAL, output)), rx)
+    }
+
+    fn esc_tab_enter() -> (Arc<TapCounter>, Receiver<Button>) {
+        counter(vec![(1, Button::Esc), (2, Button::Tab), (3, Button::Enter)])
+    }
+
+    fn tap(counter: &Arc<TapCounter>, times: usize) {
+        for _ in 0..times {
+            counter.press();
+            counter.release();
+        }
+    }
+
+    #[test]
+    fn single_tap_is_resolved_after_interval() {
+        let (counter, rx) = esc_tab_enter();
+        let start = Instant::now();
+        tap(&counter, 1);
+        assert_eq!(rx.recv(), Ok(Button::Esc));
+        assert!(start.elapsed() >= INTERVAL);
+        assert!(rx.recv_timeout(INTERVAL * 2).is_err());
+    }
+
+    #[test]
+    fn double_tap_is_resolved_after_interval() {
+        let (counter, rx) = esc_tab_enter();
+        tap(&counter, 2);
+        assert_eq!(rx.recv(), Ok(Button::Tab));
+        assert!(rx.recv_timeout(INTERVAL * 2).is_err());
+    }
+
+    #[test]
+    fn triple_tap_is_resolved_without_waiting() {
+        let (counter, rx) = esc_tab_enter();
+        tap(&counter, 3);
+        assert_eq!(rx.try_recv(), Ok(Button::Enter));
+        assert!(rx.recv_timeout(INTERVAL * 2).is_err());
+    }
+
+    #[test]
+    fn key_repeats_are_not_counted() {
+        let (counter, rx) = esc_tab_enter();
+        for _ in 0..5 {
+            counter.press();
+        }
+        counter.release();
+        assert_eq!(rx.recv(), Ok(Button::Esc));
+    }
+
+    #[test]
+    fn unmapped_counts_emit_nothing() {
+        let (counter, rx) = counter(vec![(1, Button::Esc), (3, Button::Enter)]);
+        tap(&counter, 2);
+        assert!(rx.recv_timeout(INTERVAL * 3).is_err());
+
+        tap(&counter, 1);
+        assert_eq!(rx.recv(), Ok(Button::Esc));
+    }
+}
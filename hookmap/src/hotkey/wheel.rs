@@ -0,0 +1,183 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Sums up wheel deltas generated within a window.
+#[derive(Debug, Default)]
+pub(super) struct WheelCoalescer {
+    pending: Mutex<Option<i32>>,
+}
+
+impl WheelCoalescer {
+    /// Adds `delta` to the pending window.
+    /// Returns `true` if this starts a new window, in which case the caller should
+    /// call [`WheelCoalescer::take`] when the window ends.
+    pub(super) fn push(&self, delta: i32) -> bool {
+        let mut pending = self.pending.lock().unwrap();
+        match pending.as_mut() {
+            Some(sum) => {
+                *sum += delta;
+                false
+            }
+            None => {
+                *pending = Some(delta);
+                true
+            }
+        }
+    }
+
+    /// Ends the current window and returns the summed delta.
+    pub(super) fn take(&self) -> i32 {
+        self.pending.lock().unwrap().take().unwrap_or_default()
+    }
+}
+
+/// The direction in which a mouse wheel is rotated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WheelDirection {
+    /// Rotated away from the user. [`WheelEvent::delta`] is positive.
+    ///
+    /// [`WheelEvent::delta`]: hookmap_core::event::WheelEvent::delta
+    Up,
+
+    /// Rotated toward the user. [`WheelEvent::delta`] is negative.
+    ///
+    /// [`WheelEvent::delta`]: hookmap_core::event::WheelEvent::delta
+    Down,
+}
+
+impl WheelDirection {
+    /// Returns the direction of `delta`, or `None` if it is `0`.
+    pub fn of(delta: i32) -> Option<Self> {
+        match delta.signum() {
+            1 => Some(WheelDirection::Up),
+            -1 => Some(WheelDirection::Down),
+            _ => None,
+        }
+    }
+
+    /// Returns the [`WheelEvent::delta`] of rotating the wheel by `notches` in this direction.
+    ///
+    /// [`WheelEvent::delta`]: hookmap_core::event::WheelEvent::delta
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::hotkey::WheelDirection;
+    ///
+    /// assert_eq!(WheelDirection::Up.delta(3), 3);
+    /// assert_eq!(WheelDirection::Down.delta(3), -3);
+    /// ```
+    ///
+    pub fn delta(self, notches: u32) -> i32 {
+        let notches = i32::try_from(notches).unwrap_or(i32::MAX);
+        match self {
+            WheelDirection::Up => notches,
+            WheelDirection::Down => -notches,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ThresholdState {
+    notches: u32,
+    last_notch_at: Option<Instant>,
+}
+
+/// Counts the notches rotated in one direction.
+#[derive(Debug)]
+pub(super) struct WheelThreshold {
+    direction: WheelDirection,
+    threshold: u32,
+    timeout: Duration,
+    state: Mutex<ThresholdState>,
+}
+
+impl WheelThreshold {
+    pub(super) fn new(direction: WheelDirection, threshold: u32, timeout: Duration) -> Self {
+        Self {
+            direction,
+            threshold,
+            timeout,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Counts `delta` rotated at `now`. Returns `true` if the count reaches the threshold,
+    /// in which case the count starts over.
+    ///
+    /// The count is reset by a rotation in the other direction, or when no notch has been
+    /// rotated for the timeout.
+    pub(super) fn push(&self, delta: i32, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if WheelDirection::of(delta) != Some(self.direction) {
+            *state = ThresholdState::default();
+            return false;
+        }
+        let is_timed_out = state
+            .last_notch_at
+            .is_some_and(|last| now.duration_since(last) > self.timeout);
+        if is_timed_out {
+            state.notches = 0;
+        }
+        state.notches += delta.unsigned_abs();
+        state.last_notch_at = Some(now);
+        if state.notches < self.threshold {
+            return false;
+        }
+        *state = ThresholdState::default();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wheel_deltas_are_coalesced() {
+        let coalescer = WheelCoalescer::default();
+        assert!(coalescer.push(1));
+        for _ in 0..9 {
+            assert!(!coalescer.push(1));
+        }
+        assert!(!coalescer.push(-3));
+        assert_eq!(coalescer.take(), 7);
+
+        assert!(coalescer.push(-1));
+        assert_eq!(coalescer.take(), -1);
+        assert_eq!(coalescer.take(), 0);
+    }
+
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    #[test]
+    fn threshold_fires_after_enough_notches() {
+        let threshold = WheelThreshold::new(WheelDirection::Up, 2, TIMEOUT);
+        let now = Instant::now();
+        assert!(!threshold.push(1, now));
+        assert!(threshold.push(1, now));
+        assert!(!threshold.push(1, now));
+        assert!(threshold.push(1, now));
+        assert!(threshold.push(3, now));
+        assert!(!threshold.push(-2, now));
+    }
+
+    #[test]
+    fn direction_change_resets_count() {
+        let threshold = WheelThreshold::new(WheelDirection::Down, 2, TIMEOUT);
+        let now = Instant::now();
+        assert!(!threshold.push(-1, now));
+        assert!(!threshold.push(1, now));
+        assert!(!threshold.push(-1, now));
+        assert!(threshold.push(-1, now));
+    }
+
+    #[test]
+    fn timeout_resets_count() {
+        let threshold = WheelThreshold::new(WheelDirection::Up, 2, TIMEOUT);
+        let now = Instant::now();
+        assert!(!threshold.push(1, now));
+        assert!(!threshold.push(1, now + TIMEOUT * 2));
+        assert!(threshold.push(1, now + TIMEOUT * 5 / 2));
+    }
+}
@@ -0,0 +1,364 @@
+//! Forwards button events to another process.
+//!
+//! An [`IpcReceiver`] creates a named pipe and waits for an [`IpcSink`] in another process
+//! to connect to it. The sink is usually the process that installs the hotkeys, and sends
+//! the events chosen by registering [`IpcSink::process`] as their callback. Register it with
+//! [`Registrar::on_press_sync`] and [`Registrar::on_release_sync`] so that the events are sent
+//! in the order they occurred.
+//!
+//! Each event is sent as a line of JSON, so the pipe can also be read by programs not written
+//! in Rust. Any [`Write`] and [`Read`] can be used instead of the named pipe with
+//! [`IpcSink::new`] and [`IpcReceiver::new`].
+//!
+//! # Examples
+//!
+//! The process installing the hotkeys:
+//!
+//! ```no_run
+//! use hookmap::ipc::IpcSink;
+//! use hookmap::prelude::*;
+//!
+//! let sink = IpcSink::connect("hookmap-events").unwrap();
+//! let mut hotkey = Hotkey::new();
+//! hotkey
+//!     .register(Context::default())
+//!     .on_press_sync(Button::F13, sink.process())
+//!     .on_release_sync(Button::F13, sink.process());
+//! hotkey.install();
+//! ```
+//!
+//! The process receiving the events, which must be started first:
+//!
+//! ```no_run
+//! use hookmap::ipc::IpcReceiver;
+//!
+//! for event in IpcReceiver::listen("hookmap-events").unwrap() {
+//!     println!("{:?}", event.unwrap());
+//! }
+//! ```
+//!
+//! [`Registrar::on_press_sync`]: crate::hotkey::Registrar::on_press_sync
+//! [`Registrar::on_release_sync`]: crate::hotkey::Registrar::on_release_sync
+
+use crate::hotkey::Process;
+
+use hookmap_core::event::ButtonEvent;
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread;
+
+#[cfg(windows)]
+use std::fs::File;
+
+#[cfg(windows)]
+fn pipe_path(name: &str) -> String {
+    format!(r"\\.\pipe\{name}")
+}
+
+/// Sends button events to an [`IpcReceiver`].
+///
+/// The events are written on a dedicated thread, so a receiver that stops reading does not
+/// block the hotkeys. Up to [`IpcSink::CAPACITY`] events wait to be written, and the events
+/// sent while the queue is full are dropped. Clones of the sink share the same connection,
+/// which is closed when all of them are dropped.
+#[derive(Debug, Clone)]
+pub struct IpcSink {
+    sender: SyncSender<ButtonEvent>,
+}
+
+impl IpcSink {
+    /// The number of events that can wait to be written.
+    pub const CAPACITY: usize = 1024;
+
+    /// Connects to the named pipe created by [`IpcReceiver::listen`] with the same `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no [`IpcReceiver`] is waiting on the pipe.
+    #[cfg(windows)]
+    pub fn connect(name: &str) -> io::Result<Self> {
+        let pipe = File::options().write(true).open(pipe_path(name))?;
+        Ok(Self::new(pipe))
+    }
+
+    /// Creates a sink that writes the events to `writer`.
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(Self::CAPACITY);
+        thread::spawn(move || write_events(writer, receiver));
+        Self { sender }
+    }
+
+    /// Queues `event` to be written and flushed. This never blocks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of [`io::ErrorKind::WouldBlock`] if the queue is full because the
+    /// receiver is not reading, and one of [`io::ErrorKind::BrokenPipe`] if writing has
+    /// failed, such as when the receiver has been closed. The event is dropped in both cases.
+    pub fn send(&self, event: ButtonEvent) -> io::Result<()> {
+        self.sender.try_send(event).map_err(|e| match e {
+            TrySendError::Full(_) => io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "the receiver is not reading the events",
+            ),
+            TrySendError::Disconnected(_) => io::ErrorKind::BrokenPipe.into(),
+        })
+    }
+
+    /// Creates a [`Process`] that sends each event.
+    ///
+    /// Errors are ignored so that the hotkeys keep working after the receiver has been
+    /// closed. Use [`IpcSink::send`] in a callback to handle them.
+    /// The [`Process`] returns immediately, so it can be registered with
+    /// [`Registrar::on_press_sync`] and [`Registrar::on_release_sync`] to keep the order of
+    /// the events.
+    ///
+    /// [`Registrar::on_press_sync`]: crate::hotkey::Registrar::on_press_sync
+    /// [`Registrar::on_release_sync`]: crate::hotkey::Registrar::on_release_sync
+    pub fn process(&self) -> Process<ButtonEvent> {
+        let sink = self.clone();
+        Process::from(move |event| {
+            let _ = sink.send(event);
+        })
+    }
+}
+
+// Writes the events until all the senders are dropped or writing fails. The receiver is
+// dropped on return, which tells the senders that the events are no longer written.
+fn write_events(mut writer: impl Write, receiver: Receiver<ButtonEvent>) -> io::Result<()> {
+    for event in receiver {
+        let mut line = serde_json::to_vec(&event)?;
+        line.push(b'\n');
+        writer.write_all(&line)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Receives button events from an [`IpcSink`].
+///
+/// This is an iterator over the received events, which ends when the sink is closed.
+#[derive(Debug)]
+pub struct IpcReceiver<R> {
+    reader: BufReader<R>,
+}
+
+#[cfg(windows)]
+impl IpcReceiver<File> {
+    /// Creates a named pipe and waits for an [`IpcSink`] to connect to it with
+    /// [`IpcSink::connect`]. The pipe accepts only one sink on the local computer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pipe cannot be created, such as when another receiver is
+    /// listening with the same `name`.
+    pub fn listen(name: &str) -> io::Result<Self> {
+        use std::os::windows::io::FromRawHandle;
+        use windows::core::PCWSTR;
+        use windows::Win32::Foundation::ERROR_PIPE_CONNECTED;
+        use windows::Win32::Storage::FileSystem::PIPE_ACCESS_INBOUND;
+        use windows::Win32::System::Pipes;
+
+        let path: Vec<u16> = pipe_path(name).encode_utf16().chain(Some(0)).collect();
+        let handle = unsafe {
+            Pipes::CreateNamedPipeW(
+                PCWSTR(path.as_ptr()),
+                PIPE_ACCESS_INBOUND,
+                Pipes::PIPE_TYPE_BYTE
+                    | Pipes::PIPE_READMODE_BYTE
+                    | Pipes::PIPE_WAIT
+                    | Pipes::PIPE_REJECT_REMOTE_CLIENTS,
+                1,
+                0,
+                0,
+                0,
+                std::ptr::null(),
+            )
+        };
+        if handle.is_invalid() {
+            return Err(io::Error::last_os_error());
+        }
+        // The file owns the handle from here, so it is closed on errors too.
+        let pipe = unsafe { File::from_raw_handle(handle.0 as _) };
+        if !unsafe { Pipes::ConnectNamedPipe(handle, std::ptr::null_mut()) }.as_bool() {
+            let error = io::Error::last_os_error();
+            // The sink connected before waiting for it.
+            if error.raw_os_error() != Some(ERROR_PIPE_CONNECTED.0 as i32) {
+                return Err(error);
+            }
+        }
+        Ok(Self::new(pipe))
+    }
+}
+
+impl<R: Read> IpcReceiver<R> {
+    /// Creates a receiver that reads the events from `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+        }
+    }
+
+    /// Blocks until an event is received. Returns `None` if the sink has been closed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails or the received data is not an event.
+    pub fn recv(&mut self) -> Option<io::Result<ButtonEvent>> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(serde_json::from_str(&line).map_err(io::Error::from)),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+impl<R: Read> Iterator for IpcReceiver<R> {
+    type Item = io::Result<ButtonEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkey::{Context, Hotkey};
+    use hookmap_core::button::{Button, ButtonAction};
+    use hookmap_core::event::Event;
+    use std::thread;
+    use std::time::Duration;
+
+    fn events() -> Vec<ButtonEvent> {
+        [ButtonAction::Press, ButtonAction::Release]
+            .into_iter()
+            .map(|action| {
+                ButtonEvent::new(Button::F13, action)
+                    .held_for(Some(Duration::from_millis(30)))
+                    .time(Duration::from_millis(1234))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn events_round_trip_through_a_pipe() {
+        let (reader, writer) = io::pipe().unwrap();
+        let sink = IpcSink::new(writer);
+        let sender = thread::spawn(move || {
+            events()
+                .into_iter()
+                .for_each(|event| sink.send(event).unwrap());
+        });
+
+        let mut receiver = IpcReceiver::new(reader);
+        for expected in events() {
+            assert_eq!(receiver.recv().unwrap().unwrap(), expected);
+        }
+        // The sink is closed when the thread ends.
+        sender.join().unwrap();
+        assert!(receiver.recv().is_none());
+    }
+
+    #[test]
+    fn hotkeys_forward_chosen_events() {
+        let (reader, writer) = io::pipe().unwrap();
+        let sink = IpcSink::new(writer);
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .on_press_sync(Button::F13, sink.process());
+
+        let [press, _] = events()[..] else {
+            unreachable!()
+        };
+        let mut other = press;
+        other.target = Button::F14;
+        // Stands in for the hook thread, which dispatches the events of the OS.
+        let events = [other, press].map(Event::Button);
+        thread::spawn(move || hotkey.dispatch_all(events))
+            .join()
+            .unwrap();
+
+        let mut receiver = IpcReceiver::new(reader);
+        assert_eq!(receiver.recv().unwrap().unwrap(), press);
+    }
+
+    #[test]
+    fn events_are_dropped_while_the_receiver_is_not_reading() {
+        // Stands in for a receiver that stops reading without closing the pipe.
+        struct Stalled(Receiver<()>);
+
+        impl Write for Stalled {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                let _ = self.0.recv();
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let (resume, stalled) = mpsc::channel();
+        let sink = IpcSink::new(Stalled(stalled));
+        let event = events()[0];
+        // The writer may have taken one of the events.
+        let results: Vec<_> = (0..IpcSink::CAPACITY + 2)
+            .map(|_| sink.send(event))
+            .collect();
+        assert!(results[..IpcSink::CAPACITY].iter().all(Result::is_ok));
+        let error = results.last().unwrap().as_ref().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+        drop(resume);
+    }
+
+    #[test]
+    fn sending_fails_after_writing_fails() {
+        let (reader, writer) = io::pipe().unwrap();
+        drop(reader);
+        let sink = IpcSink::new(writer);
+        let deadline = std::time::Instant::now() + Duration::from_secs(1);
+        let error = loop {
+            match sink.send(events()[0]) {
+                Ok(()) if std::time::Instant::now() < deadline => thread::yield_now(),
+                result => break result.unwrap_err(),
+            }
+        };
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn invalid_data_is_an_error() {
+        let mut receiver = IpcReceiver::new(&b"press A\n"[..]);
+        let error = receiver.recv().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn events_round_trip_through_a_named_pipe() {
+        let name = format!("hookmap-test-{}", std::process::id());
+        let receiver = {
+            let name = name.clone();
+            thread::spawn(move || {
+                IpcReceiver::listen(&name)
+                    .unwrap()
+                    .collect::<io::Result<Vec<_>>>()
+            })
+        };
+        let sink = loop {
+            match IpcSink::connect(&name) {
+                Ok(sink) => break sink,
+                Err(_) => thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        events()
+            .into_iter()
+            .for_each(|event| sink.send(event).unwrap());
+        drop(sink);
+        assert_eq!(receiver.join().unwrap().unwrap(), events());
+    }
+}
@@ -4,36 +4,82 @@
 //!
 //! * `us-keyboard-layout` (default): Use US keyboard layout. This changes the [`Button`] variant.
 //! * `japanese-keyboard-layout`: Use Japanese keyboard layout. This changes the [`Button`] variant.
+//! * `tokio`: Enable [`tokio_runtime`] to run the callbacks as async tasks on a Tokio runtime.
+//! * `serde`: Implements `Serialize` and `Deserialize` of serde for the buttons and events.
+//! * `ipc`: Enable [`ipc`] to forward button events to another process. This enables `serde`.
 //!
 //! [`Button`]: crate::device::Button
 
 pub mod hotkey;
+pub mod recording;
 pub mod utils;
 
+#[cfg(feature = "tokio")]
+pub mod tokio_runtime;
+
+#[cfg(feature = "ipc")]
+pub mod ipc;
+
 #[doc(hidden)]
 pub mod macros;
 
+mod button_set;
 mod hook;
 mod runtime;
 
-pub use runtime::interceptor;
+pub use hookmap_core::{event_time, pressed_buttons, run_on_hook_thread};
+pub use macros::sequence::{type_combo, type_combo_recursive, CancellationToken, Inputs};
+pub use runtime::{event_modifiers, interceptor, post_synthetic, reemit, HeldModifiers};
 
 /// Representation of keyboard and mouse events.
+///
+/// All items in this module are also included in the [`prelude`].
+///
+/// # Examples
+///
+/// ```
+/// use hookmap::device::{CursorEvent, WheelEvent};
+/// use hookmap::prelude::*;
+///
+/// fn on_cursor(e: CursorEvent) {
+///     let (x, y) = e.position.into();
+///     println!("Position: ({}, {})", x, y);
+/// }
+///
+/// fn on_wheel(e: WheelEvent) {
+///     println!("Delta: {}", e.delta);
+/// }
+///
+/// let mut hotkey = Hotkey::new();
+/// hotkey
+///     .register(Context::default())
+///     .mouse_cursor(on_cursor)
+///     .mouse_wheel(on_wheel);
+/// ```
+///
 pub mod device {
-    pub use hookmap_core::button::{Button, ButtonAction, ButtonKind};
-    pub use hookmap_core::event::{ButtonEvent, CursorEvent, NativeEventOperation, WheelEvent};
+    pub use hookmap_core::button::{
+        AliasConflictError, Button, ButtonAction, ButtonCategory, ButtonKind, HoldGuard,
+        ParseButtonError,
+    };
+    pub use hookmap_core::event::{
+        ButtonEvent, CursorEvent, Delta, HookTarget, InjectionError, InstallHookError, MouseEvent,
+        NativeEventOperation, Position, SessionChange, WheelEvent,
+    };
+    pub use hookmap_core::lock_keys;
     pub use hookmap_core::mouse;
+    pub use hookmap_core::window::Window;
 }
 
 /// A prelude for conveniently defining hotkeys.
 pub mod prelude {
     // Macros
-    pub use super::{buttons, hotkey, seq};
+    pub use super::{buttons, hotkey, seq, seq_data};
 
     pub use super::{
         device::*,
         hotkey::{Context, Hotkey},
-        interceptor::{Filter, Interceptor},
-        utils,
+        interceptor::{Filter, Interceptor, ModifierFilterExt},
+        utils, CancellationToken,
     };
 }
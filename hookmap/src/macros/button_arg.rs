@@ -1,5 +1,6 @@
-use hookmap_core::button::Button;
+use hookmap_core::button::{Button, ParseButtonError};
 use std::borrow::Borrow;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ButtonArgUnit<T> {
@@ -8,6 +9,7 @@ pub enum ButtonArgUnit<T> {
 }
 
 impl<T: Clone> ButtonArgUnit<T> {
+    #[must_use]
     pub fn invert(&self) -> Self {
         match self {
             Self::Plain(v) => Self::Not(v.clone()),
@@ -17,11 +19,109 @@ impl<T: Clone> ButtonArgUnit<T> {
 }
 
 /// A struct to pass multiple buttons to a function.
-/// This struct constructs by [`buttons!`].
+/// This struct constructs by [`buttons!`], or by [`ButtonArg::of`] and its combinators.
 #[derive(Clone, PartialEq, Eq, Debug, Default)]
 pub struct ButtonArg(Vec<ButtonArgUnit<Button>>);
 
 impl ButtonArg {
+    /// Creates a [`ButtonArg`] with a single button.
+    /// This is equivalent to `buttons!([button])`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use hookmap::macros::button_arg::ButtonArg;
+    ///
+    /// assert_eq!(ButtonArg::of(Button::A), buttons!(A));
+    /// ```
+    ///
+    pub fn of(button: Button) -> Self {
+        ButtonArg(vec![ButtonArgUnit::Plain(button)])
+    }
+
+    /// Creates a [`ButtonArg`] from the buttons in `buttons`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use hookmap::macros::button_arg::ButtonArg;
+    ///
+    /// let arg = ButtonArg::from_buttons([Button::A, Button::B]);
+    /// assert_eq!(arg, buttons!(A, B));
+    /// ```
+    ///
+    pub fn from_buttons(buttons: impl IntoIterator<Item = Button>) -> Self {
+        ButtonArg(buttons.into_iter().map(ButtonArgUnit::Plain).collect())
+    }
+
+    /// Adds `button`.
+    /// This is equivalent to appending `[button]` in [`buttons!`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use hookmap::macros::button_arg::ButtonArg;
+    ///
+    /// let arg = ButtonArg::of(Button::A).or(Button::B);
+    /// assert_eq!(arg, buttons!(A, B));
+    /// ```
+    ///
+    #[must_use]
+    pub fn or(mut self, button: Button) -> Self {
+        self.0.push(ButtonArgUnit::Plain(button));
+        self
+    }
+
+    /// Adds `button` with the prefix `!`, which means that the button is released.
+    /// This is equivalent to appending `![button]` in [`buttons!`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use hookmap::macros::button_arg::ButtonArg;
+    ///
+    /// let arg = ButtonArg::of(Button::A).not(Button::B);
+    /// assert_eq!(arg, buttons!(A, !B));
+    /// ```
+    ///
+    #[must_use]
+    pub fn not(mut self, button: Button) -> Self {
+        self.0.push(ButtonArgUnit::Not(button));
+        self
+    }
+
+    /// Parses buttons separated by commas, such as `"A, !B, LCtrl"`.
+    /// This is the runtime equivalent of [`buttons!`].
+    ///
+    /// Each button is parsed by [`Button::from_str`], and the prefix `!` means that the button
+    /// is released.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use hookmap::macros::button_arg::ButtonArg;
+    ///
+    /// assert_eq!(ButtonArg::parse("A, !B, 1"), Ok(buttons!(A, !B, 1)));
+    /// assert!(ButtonArg::parse("A, Unknown").is_err());
+    /// ```
+    ///
+    pub fn parse(s: &str) -> Result<ButtonArg, ParseButtonError> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|unit| !unit.is_empty())
+            .map(|unit| match unit.strip_prefix('!') {
+                Some(button) => button.trim().parse().map(ButtonArgUnit::Not),
+                None => unit.parse().map(ButtonArgUnit::Plain),
+            })
+            .collect::<Result<_, _>>()
+            .map(ButtonArg)
+    }
+
     pub(crate) fn invert(&self) -> ButtonArg {
         let inner = self.0.iter().map(|unit| unit.invert()).collect();
         ButtonArg(inner)
@@ -33,6 +133,12 @@ impl ButtonArg {
             .all(|unit| matches!(unit, ButtonArgUnit::Plain(_)))
     }
 
+    /// Returns a button that is given both with and without `!`, if any.
+    pub(crate) fn find_contradiction(&self) -> Option<Button> {
+        self.iter_plain()
+            .find(|&button| self.iter_not().any(|not| not == button))
+    }
+
     pub(crate) fn iter(&self) -> impl Iterator<Item = ButtonArgUnit<Button>> + '_ {
         self.0.iter().copied()
     }
@@ -58,6 +164,22 @@ impl From<Button> for ButtonArg {
     }
 }
 
+impl FromStr for ButtonArg {
+    type Err = ParseButtonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<&str> for ButtonArg {
+    type Error = ParseButtonError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Self::parse(s)
+    }
+}
+
 impl<T> From<&T> for ButtonArg
 where
     T: Clone + Into<ButtonArg>,
@@ -219,4 +341,43 @@ mod tests {
             ]),
         );
     }
+
+    #[test]
+    fn parse_matches_macro() {
+        assert_eq!(ButtonArg::parse("A"), Ok(buttons!(A)));
+        assert_eq!(ButtonArg::parse("!A"), Ok(buttons!(!A)));
+        assert_eq!(ButtonArg::parse("A, !B"), Ok(buttons!(A, !B)));
+        assert_eq!(ButtonArg::parse(" A ,! B,"), Ok(buttons!(A, !B)));
+        assert_eq!(ButtonArg::parse("0, !9"), Ok(buttons!(0, !9)));
+        assert_eq!(ButtonArg::parse(""), Ok(buttons!()));
+        assert_eq!("LCtrl, Esc".parse(), Ok(buttons!(LCtrl, Esc)));
+        assert_eq!(ButtonArg::try_from("!F12"), Ok(buttons!(!F12)));
+        assert!(ButtonArg::parse("A, Foo").is_err());
+        assert!(ButtonArg::parse("!!A").is_err());
+    }
+
+    #[test]
+    fn combinators_match_macro() {
+        use Button::*;
+        assert_eq!(ButtonArg::of(A), buttons!(A));
+        assert_eq!(ButtonArg::of(A).or(B).not(C), buttons!(A, B, !C));
+        assert_eq!(ButtonArg::default().not(A).not(B), buttons!(!A, !B));
+        assert_eq!(ButtonArg::from_buttons([A, B, C]), buttons!(A, B, C));
+        assert_eq!(ButtonArg::from_buttons([]), buttons!());
+        assert_eq!(
+            ButtonArg::from_buttons(vec![A, B]).not(C),
+            buttons!(A, B, !C)
+        );
+    }
+
+    #[test]
+    fn find_contradiction() {
+        use Button::*;
+        assert_eq!(buttons!(A, !B, C).find_contradiction(), None);
+        assert_eq!(buttons!(A, !B, !A).find_contradiction(), Some(A));
+        assert_eq!(
+            ButtonArg::parse("!C, D, C").unwrap().find_contradiction(),
+            Some(C)
+        );
+    }
 }
@@ -1,5 +1,60 @@
 pub use hookmap_core::button::{Button, ButtonAction};
 pub use hookmap_core::event::ButtonEvent;
+use hookmap_core::window::Window;
+
+use super::button_arg::ButtonArg;
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// A token to stop sending a sequence from another thread.
+///
+/// # Examples
+///
+/// ```no_run
+/// use hookmap::prelude::*;
+///
+/// let token = CancellationToken::new();
+/// let token_ = token.clone();
+/// std::thread::spawn(move || {
+///     seq!(A, B, C).send_cancellable(&token_);
+/// });
+/// token.cancel();
+/// ```
+///
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<(Mutex<bool>, Condvar)>);
+
+impl CancellationToken {
+    /// Creates a new instance of [`CancellationToken`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests cancellation.
+    pub fn cancel(&self) {
+        let (is_cancelled, cancelled) = &*self.0;
+        *is_cancelled.lock().unwrap() = true;
+        cancelled.notify_all();
+    }
+
+    /// Returns `true` if the cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        *self.0 .0.lock().unwrap()
+    }
+
+    /// Waits for `duration` unless the cancellation is requested in the meantime.
+    /// Returns `true` if the cancellation was requested.
+    pub(crate) fn sleep(&self, duration: Duration) -> bool {
+        let (is_cancelled, cancelled) = &*self.0;
+        let guard = is_cancelled.lock().unwrap();
+        let (guard, _) = cancelled
+            .wait_timeout_while(guard, duration, |is_cancelled| !*is_cancelled)
+            .unwrap();
+        *guard
+    }
+}
 
 /// Emulates button input.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -7,6 +62,9 @@ pub enum SequenceOperation {
     Click(Button),
     Press(Button),
     Release(Button),
+    Delay(Duration),
+    /// Types the text as Unicode input. See [`hookmap_core::send_text`].
+    Text(&'static str),
 }
 
 impl SequenceOperation {
@@ -15,6 +73,8 @@ impl SequenceOperation {
             SequenceOperation::Click(button) => button.click(),
             SequenceOperation::Press(button) => button.press(),
             SequenceOperation::Release(button) => button.release(),
+            SequenceOperation::Delay(duration) => thread::sleep(*duration),
+            SequenceOperation::Text(text) => hookmap_core::send_text(text),
         }
     }
 
@@ -23,10 +83,60 @@ impl SequenceOperation {
             SequenceOperation::Click(button) => button.click_recursive(),
             SequenceOperation::Press(button) => button.press_recursive(),
             SequenceOperation::Release(button) => button.release_recursive(),
+            SequenceOperation::Delay(duration) => thread::sleep(*duration),
+            SequenceOperation::Text(text) => hookmap_core::send_text_recursive(text),
         }
     }
 }
 
+/// A button, or a set of buttons, that [`seq!`] resolves to a single button to emulate.
+///
+/// When matching events, [`Button::Shift`], [`Button::Ctrl`], [`Button::Alt`] and
+/// [`Button::Super`] match either side, and a [`ButtonArg`] matches any of its buttons.
+/// When emulating, only one button can be sent, so:
+///
+/// * The generic modifiers resolve to their left variant, e.g. [`Button::LShift`].
+/// * A [`ButtonArg`] resolves to its first button without `!`, which is then resolved as above.
+///
+/// # Panics
+///
+/// [`EmulatedButton::emulated_button`] panics if a [`ButtonArg`] has no buttons without `!`.
+///
+/// # Examples
+///
+/// ```
+/// use hookmap::prelude::*;
+///
+/// assert_eq!(seq!([Button::Shift], A), seq!(LShift, A));
+/// assert_eq!(seq!(with([buttons!(RCtrl, LCtrl)]), C), seq!(with(RCtrl), C));
+/// ```
+///
+pub trait EmulatedButton {
+    /// Returns the button to emulate.
+    fn emulated_button(&self) -> Button;
+}
+
+impl EmulatedButton for Button {
+    fn emulated_button(&self) -> Button {
+        match self {
+            Button::Shift => Button::LShift,
+            Button::Ctrl => Button::LCtrl,
+            Button::Alt => Button::LAlt,
+            Button::Super => Button::LSuper,
+            _ => *self,
+        }
+    }
+}
+
+impl EmulatedButton for ButtonArg {
+    fn emulated_button(&self) -> Button {
+        self.iter_plain()
+            .next()
+            .expect("A `ButtonArg` to emulate must have a button without `!`.")
+            .emulated_button()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Sequence {
     with: Vec<Button>,
@@ -53,6 +163,37 @@ impl Sequence {
         self.with.iter().copied().for_each(operation);
     }
 
+    /// Returns the operations to send, with the modifier keys held down by the sequence
+    /// released around each text so that they do not apply to its characters.
+    fn operations(&self) -> Vec<SequenceOperation> {
+        let mut held: Vec<_> = self
+            .with
+            .iter()
+            .copied()
+            .filter(|button| MODIFIER_LIST.contains(button))
+            .collect();
+        let mut operations = Vec::with_capacity(self.seq.len());
+        for &operation in &self.seq {
+            match operation {
+                SequenceOperation::Press(button)
+                    if MODIFIER_LIST.contains(&button) && !held.contains(&button) =>
+                {
+                    held.push(button);
+                }
+                SequenceOperation::Release(button) => held.retain(|&b| b != button),
+                SequenceOperation::Text(_) => {
+                    operations.extend(held.iter().rev().map(|&b| SequenceOperation::Release(b)));
+                    operations.push(operation);
+                    operations.extend(held.iter().map(|&b| SequenceOperation::Press(b)));
+                    continue;
+                }
+                _ => {}
+            }
+            operations.push(operation);
+        }
+        operations
+    }
+
     fn send_inner(
         &self,
         press: fn(Button),
@@ -60,7 +201,7 @@ impl Sequence {
         operation: fn(&SequenceOperation),
     ) {
         self.operate_with_keys(press);
-        self.seq.iter().for_each(operation);
+        self.operations().iter().for_each(operation);
         self.operate_with_keys(release);
     }
 
@@ -68,6 +209,37 @@ impl Sequence {
         self.send_inner(Button::press, Button::release, SequenceOperation::operate);
     }
 
+    /// Returns the inputs that [`Sequence::send`] sends, in order.
+    ///
+    /// Delays are not included since they are not button inputs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sequence contains a text, since it is typed regardless of the keyboard
+    /// layout and cannot be represented as button inputs.
+    pub fn inputs(&self) -> Vec<(Button, ButtonAction)> {
+        let press = |&button: &Button| (button, ButtonAction::Press);
+        let release = |&button: &Button| (button, ButtonAction::Release);
+        let operations = self
+            .operations()
+            .into_iter()
+            .flat_map(|operation| match operation {
+                SequenceOperation::Click(button) => vec![press(&button), release(&button)],
+                SequenceOperation::Press(button) => vec![press(&button)],
+                SequenceOperation::Release(button) => vec![release(&button)],
+                SequenceOperation::Delay(_) => vec![],
+                SequenceOperation::Text(text) => {
+                    panic!("{text:?} cannot be represented as button inputs")
+                }
+            });
+        self.with
+            .iter()
+            .map(press)
+            .chain(operations)
+            .chain(self.with.iter().map(release))
+            .collect()
+    }
+
     pub fn send_recursive(&self) {
         self.send_inner(
             Button::press_recursive,
@@ -76,6 +248,105 @@ impl Sequence {
         );
     }
 
+    fn send_cancellable_inner(
+        &self,
+        token: &CancellationToken,
+        press: impl Fn(Button),
+        release: impl Fn(Button),
+        text: impl Fn(&str),
+    ) -> bool {
+        self.with.iter().copied().for_each(&press);
+
+        let mut held = vec![];
+        let completed = self.operations().iter().all(|operation| {
+            if token.is_cancelled() {
+                return false;
+            }
+            match *operation {
+                SequenceOperation::Click(button) => {
+                    press(button);
+                    release(button);
+                }
+                SequenceOperation::Press(button) => {
+                    press(button);
+                    held.push(button);
+                }
+                SequenceOperation::Release(button) => {
+                    release(button);
+                    held.retain(|&b| b != button);
+                }
+                SequenceOperation::Delay(duration) => {
+                    if token.sleep(duration) {
+                        return false;
+                    }
+                }
+                SequenceOperation::Text(s) => text(s),
+            }
+            true
+        });
+        if !completed {
+            held.iter().rev().copied().for_each(&release);
+        }
+
+        self.with.iter().copied().for_each(&release);
+        completed
+    }
+
+    /// Sends the sequence, stopping early if `token` is cancelled.
+    /// Buttons pressed by the sequence are released when it is cancelled.
+    ///
+    /// Returns `true` if all the operations are sent.
+    pub fn send_cancellable(&self, token: &CancellationToken) -> bool {
+        self.send_cancellable_inner(
+            token,
+            Button::press,
+            Button::release,
+            hookmap_core::send_text,
+        )
+    }
+
+    /// Same as [`Sequence::send_cancellable`], but events generated by this method can be hooked.
+    pub fn send_cancellable_recursive(&self, token: &CancellationToken) -> bool {
+        self.send_cancellable_inner(
+            token,
+            Button::press_recursive,
+            Button::release_recursive,
+            hookmap_core::send_text_recursive,
+        )
+    }
+
+    /// Posts the sequence to `window` instead of the foreground window.
+    ///
+    /// See [`hookmap_core::window`] for the caveats.
+    ///
+    /// Returns `false` if any of the messages could not be posted.
+    pub fn send_to_window(&self, window: Window) -> bool {
+        let mut posted = true;
+        let mut post = |button, action| posted &= window.post(button, action);
+        let mut text_posted = true;
+
+        self.with
+            .iter()
+            .for_each(|&button| post(button, ButtonAction::Press));
+        for operation in self.operations() {
+            match operation {
+                SequenceOperation::Click(button) => {
+                    post(button, ButtonAction::Press);
+                    post(button, ButtonAction::Release);
+                }
+                SequenceOperation::Press(button) => post(button, ButtonAction::Press),
+                SequenceOperation::Release(button) => post(button, ButtonAction::Release),
+                SequenceOperation::Delay(duration) => thread::sleep(duration),
+                SequenceOperation::Text(text) => text_posted &= window.post_text(text),
+            }
+        }
+        self.with
+            .iter()
+            .for_each(|&button| post(button, ButtonAction::Release));
+
+        posted && text_posted
+    }
+
     fn send_ignore_modifiers_inner(
         &self,
         press: fn(Button),
@@ -110,6 +381,134 @@ impl Sequence {
     }
 }
 
+/// A list of inputs to send in a single batch, built one button at a time.
+///
+/// This is the programmatic counterpart of [`seq_data!`], for inputs that are not known at
+/// compile time such as those read from a config file. It dereferences to a slice, so it can be
+/// passed to [`hookmap_core::send_batch`].
+///
+/// # Examples
+///
+/// ```
+/// use hookmap::prelude::*;
+/// use hookmap::Inputs;
+///
+/// let inputs = Inputs::new()
+///     .press(Button::LCtrl)
+///     .click(Button::C)
+///     .release(Button::LCtrl);
+/// assert_eq!(*inputs, seq_data!(LCtrl down, C, LCtrl up));
+/// ```
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Inputs(Vec<(Button, ButtonAction)>);
+
+impl Inputs {
+    /// Creates an empty list of inputs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a press of `button`.
+    #[must_use]
+    pub fn press(mut self, button: Button) -> Self {
+        self.0.push((button, ButtonAction::Press));
+        self
+    }
+
+    /// Adds a release of `button`.
+    #[must_use]
+    pub fn release(mut self, button: Button) -> Self {
+        self.0.push((button, ButtonAction::Release));
+        self
+    }
+
+    /// Adds a press and a release of `button`.
+    #[must_use]
+    pub fn click(self, button: Button) -> Self {
+        self.press(button).release(button)
+    }
+
+    /// Sends the inputs in a single batch. See [`hookmap_core::send_batch`].
+    pub fn send(&self) {
+        hookmap_core::send_batch(&self.0);
+    }
+
+    /// Same as [`Inputs::send`], but events generated by this method can be hooked.
+    pub fn send_recursive(&self) {
+        hookmap_core::send_batch_recursive(&self.0);
+    }
+}
+
+impl std::ops::Deref for Inputs {
+    type Target = [(Button, ButtonAction)];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<(Button, ButtonAction)> for Inputs {
+    fn from(input: (Button, ButtonAction)) -> Self {
+        Inputs(vec![input])
+    }
+}
+
+impl From<Inputs> for Vec<(Button, ButtonAction)> {
+    fn from(inputs: Inputs) -> Self {
+        inputs.0
+    }
+}
+
+impl FromIterator<(Button, ButtonAction)> for Inputs {
+    fn from_iter<I: IntoIterator<Item = (Button, ButtonAction)>>(iter: I) -> Self {
+        Inputs(iter.into_iter().collect())
+    }
+}
+
+fn combo_inputs(buttons: &[Button], held_modifiers: &[Button]) -> Vec<(Button, ButtonAction)> {
+    let press = |&button: &Button| (button, ButtonAction::Press);
+    let release = |&button: &Button| (button, ButtonAction::Release);
+    held_modifiers
+        .iter()
+        .map(release)
+        .chain(buttons.iter().map(press))
+        .chain(buttons.iter().rev().map(release))
+        .chain(held_modifiers.iter().map(press))
+        .collect()
+}
+
+fn pressed_modifiers() -> Vec<Button> {
+    MODIFIER_LIST
+        .iter()
+        .copied()
+        .filter(|button| button.is_pressed())
+        .collect()
+}
+
+/// Presses `buttons` in order and releases them in reverse order.
+///
+/// The input is sent in a single batch, so no other input is inserted in the middle.
+/// Modifier keys held down when this is called are released during the combo
+/// and pressed again after it.
+///
+/// # Examples
+///
+/// ```no_run
+/// use hookmap::prelude::*;
+///
+/// hookmap::type_combo(&[Button::LCtrl, Button::LShift, Button::Esc]);
+/// ```
+///
+pub fn type_combo(buttons: &[Button]) {
+    hookmap_core::send_batch(&combo_inputs(buttons, &pressed_modifiers()));
+}
+
+/// Same as [`type_combo`], but events generated by this function can be hooked.
+pub fn type_combo_recursive(buttons: &[Button]) {
+    hookmap_core::send_batch_recursive(&combo_inputs(buttons, &pressed_modifiers()));
+}
+
 /// Sends keyboard input.
 ///
 /// # Examples
@@ -134,14 +533,54 @@ impl Sequence {
 /// seq!(LShift down, LCtrl down, Tab, LShift up, LCtrl up).send(); // equals to above
 /// ```
 ///
+/// Use `with(...) { ... }` to hold down the keys only while sending the keys in the block.
+/// The blocks can be nested.
+///
+/// ```no_run
+/// use hookmap::*;
+/// seq!(A, with(LCtrl) { C, V }, B).send();
+/// seq!(A, LCtrl down, C, V, LCtrl up, B).send(); // equals to above
+/// ```
+///
+/// Generic modifiers such as `Shift` and [`ButtonArg`]s in `[...]` are sent as a single button.
+/// See [`EmulatedButton`] for how it is chosen.
+///
+/// ```no_run
+/// use hookmap::prelude::*;
+/// seq!([Button::Shift], A).send();
+/// seq!(LShift, A).send(); // equals to above
+/// ```
+///
+/// Use `delay(ms)` to wait for `ms` milliseconds between keys.
+///
+/// ```no_run
+/// use hookmap::*;
+/// seq!(A, delay(50), B).send();
+/// seq!(with(LCtrl) { C, delay(100), V }).send();
+/// ```
+///
+/// Use `text "..."` to type a string as Unicode input, regardless of the keyboard layout.
+/// The modifier keys held down by `with(...)` or `down` are released while typing it,
+/// so they apply only to the buttons.
+///
+/// ```no_run
+/// use hookmap::*;
+/// seq!(text "café", Enter).send();
+/// seq!(with(LCtrl) { A, text "é", C }).send(); // Ctrl+A, é, Ctrl+C
+/// ```
+///
 #[macro_export]
 macro_rules! seq {
     (@with $($modifier:tt),*) => {
-        vec![ $($crate::button_name!($modifier)),* ]
+        vec![ $($crate::seq!(@emulated $modifier)),* ]
+    };
+
+    (@emulated $button:tt) => {
+        $crate::macros::sequence::EmulatedButton::emulated_button(&$crate::button_name!($button))
     };
 
     (@single $button:tt $op:ident) => {
-        $crate::macros::sequence::SequenceOperation::$op($crate::button_name!($button))
+        $crate::macros::sequence::SequenceOperation::$op($crate::seq!(@emulated $button))
     };
 
     (@button $parsed:tt) => {
@@ -152,6 +591,35 @@ macro_rules! seq {
         $crate::seq!(@button $parsed $($rest)*)
     };
 
+    (@button [ $($parsed:tt),* ] with( $($modifier:tt),* ) { $($inner:tt)* } $($rest:tt)*) => {
+        $crate::seq!(
+            @button
+            [ $($parsed,)* $(($crate::seq!(@single $modifier Press))),* ]
+            $($inner)* , $($modifier up),* , $($rest)*
+        )
+    };
+
+    (@button [ $($parsed:tt),* ] delay($ms:expr) $($rest:tt)*) => {
+        $crate::seq!(
+            @button
+            [
+                $($parsed,)*
+                ($crate::macros::sequence::SequenceOperation::Delay(
+                    ::std::time::Duration::from_millis($ms)
+                ))
+            ]
+            $($rest)*
+        )
+    };
+
+    (@button [ $($parsed:tt),* ] text $text:literal $($rest:tt)*) => {
+        $crate::seq!(
+            @button
+            [ $($parsed,)* ($crate::macros::sequence::SequenceOperation::Text($text)) ]
+            $($rest)*
+        )
+    };
+
     (@button [ $($parsed:tt),* ] $button:tt up $($rest:tt)*) => {
         $crate::seq!(
             @button
@@ -191,6 +659,36 @@ macro_rules! seq {
     };
 }
 
+/// Builds the inputs of [`seq!`] as a `Vec<(Button, ButtonAction)>` instead of sending them.
+///
+/// This takes the same syntax as [`seq!`]. Delays are left out, and this panics if the
+/// sequence contains a `text`, which cannot be represented as button inputs.
+/// The inputs can be sent in a single batch with [`hookmap_core::send_batch`].
+///
+/// # Examples
+///
+/// ```
+/// use hookmap::prelude::*;
+///
+/// assert_eq!(
+///     seq_data!(with(LCtrl), A, B down),
+///     vec![
+///         (Button::LCtrl, ButtonAction::Press),
+///         (Button::A, ButtonAction::Press),
+///         (Button::A, ButtonAction::Release),
+///         (Button::B, ButtonAction::Press),
+///         (Button::LCtrl, ButtonAction::Release),
+///     ]
+/// );
+/// ```
+///
+#[macro_export]
+macro_rules! seq_data {
+    ($($token:tt)*) => {
+        $crate::seq!($($token)*).inputs()
+    };
+}
+
 #[doc(hidden)]
 pub const MODIFIER_LIST: [Button; 8] = [
     Button::LShift,
@@ -205,9 +703,35 @@ pub const MODIFIER_LIST: [Button; 8] = [
 
 #[cfg(test)]
 mod tests {
-    use super::{Sequence, SequenceOperation};
-    use crate::button_name;
+    use super::{
+        combo_inputs, CancellationToken, EmulatedButton, Inputs, Sequence, SequenceOperation,
+    };
     use crate::device::Button;
+    use crate::macros::button_arg::ButtonArg;
+    use crate::{button_name, buttons};
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn combo_inputs_suppress_held_modifiers() {
+        use hookmap_core::button::ButtonAction::{Press, Release};
+
+        let inputs = combo_inputs(&[Button::LCtrl, Button::Esc], &[Button::RAlt]);
+        assert_eq!(
+            inputs,
+            vec![
+                (Button::RAlt, Release),
+                (Button::LCtrl, Press),
+                (Button::Esc, Press),
+                (Button::Esc, Release),
+                (Button::LCtrl, Release),
+                (Button::RAlt, Press),
+            ]
+        );
+        assert!(combo_inputs(&[], &[]).is_empty());
+    }
 
     #[test]
     fn button_name_macro() {
@@ -249,4 +773,407 @@ mod tests {
             Sequence::new(vec![Button::A, Button::B], vec![Release(Button::C)])
         );
     }
+
+    #[test]
+    fn seq_macro_with_grouped_buttons() {
+        use SequenceOperation::Click;
+        assert_eq!(
+            seq!([Button::Shift], A),
+            Sequence::new(vec![], vec![Click(Button::LShift), Click(Button::A)])
+        );
+        assert_eq!(
+            seq!(with(Ctrl, [buttons!(RAlt, LAlt)]), A),
+            Sequence::new(vec![Button::LCtrl, Button::RAlt], vec![Click(Button::A)])
+        );
+        assert_eq!(
+            seq!([buttons!(!Super, Super)] down, [Button::RShift] up),
+            Sequence::new(
+                vec![],
+                vec![
+                    SequenceOperation::Press(Button::LSuper),
+                    SequenceOperation::Release(Button::RShift)
+                ]
+            )
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn emulating_button_arg_without_plain_button_panics() {
+        ButtonArg::default().not(Button::A).emulated_button();
+    }
+
+    #[test]
+    fn seq_data_macro() {
+        use hookmap_core::button::ButtonAction::{Press, Release};
+
+        assert_eq!(seq_data!(), vec![]);
+        assert_eq!(
+            seq_data!(A, B, LCtrl down),
+            vec![
+                (Button::A, Press),
+                (Button::A, Release),
+                (Button::B, Press),
+                (Button::B, Release),
+                (Button::LCtrl, Press),
+            ]
+        );
+        assert_eq!(
+            seq_data!(with(LShift, [Button::LAlt]), Tab up, delay(10)),
+            vec![
+                (Button::LShift, Press),
+                (Button::LAlt, Press),
+                (Button::Tab, Release),
+                (Button::LShift, Release),
+                (Button::LAlt, Release),
+            ]
+        );
+        assert_eq!(
+            seq_data!(with(LSuper), D),
+            vec![
+                (Button::LSuper, Press),
+                (Button::D, Press),
+                (Button::D, Release),
+                (Button::LSuper, Release),
+            ]
+        );
+        assert_eq!(
+            seq_data!(A, with(LCtrl) { C, delay(10), V }),
+            vec![
+                (Button::A, Press),
+                (Button::A, Release),
+                (Button::LCtrl, Press),
+                (Button::C, Press),
+                (Button::C, Release),
+                (Button::V, Press),
+                (Button::V, Release),
+                (Button::LCtrl, Release),
+            ]
+        );
+    }
+
+    #[test]
+    fn inputs_builder_matches_macro() {
+        use hookmap_core::button::ButtonAction::{Press, Release};
+
+        assert_eq!(*Inputs::new(), seq_data!());
+        assert_eq!(
+            *Inputs::new().click(Button::A).click(Button::B),
+            seq_data!(A, B)
+        );
+        assert_eq!(
+            *Inputs::new()
+                .press(Button::LShift)
+                .release(Button::Tab)
+                .release(Button::LShift),
+            seq_data!(with(LShift), Tab up)
+        );
+        assert_eq!(
+            *Inputs::new()
+                .click(Button::A)
+                .press(Button::LCtrl)
+                .click(Button::C)
+                .click(Button::V)
+                .release(Button::LCtrl),
+            seq_data!(A, with(LCtrl) { C, V })
+        );
+
+        let generated: Inputs = ["A", "Enter"]
+            .iter()
+            .map(|name| name.parse::<Button>().unwrap())
+            .fold(Inputs::new(), Inputs::click);
+        assert_eq!(*generated, seq_data!(A, Enter));
+        assert_eq!(
+            Inputs::from((Button::B, Press)),
+            [(Button::B, Press)].into_iter().collect()
+        );
+        assert_eq!(
+            Vec::from(Inputs::new().click(Button::Z)),
+            vec![(Button::Z, Press), (Button::Z, Release)]
+        );
+    }
+
+    #[test]
+    fn seq_macro_with_text() {
+        use SequenceOperation::{Click, Press, Release, Text};
+
+        assert_eq!(
+            seq!(text "café", Enter),
+            Sequence::new(vec![], vec![Text("café"), Click(Button::Enter)])
+        );
+        assert_eq!(
+            seq!(with(LCtrl) { A, text "é" }, B),
+            Sequence::new(
+                vec![],
+                vec![
+                    Press(Button::LCtrl),
+                    Click(Button::A),
+                    Text("é"),
+                    Release(Button::LCtrl),
+                    Click(Button::B),
+                ]
+            )
+        );
+
+        // The modifiers held down by the sequence are released around the text.
+        assert_eq!(
+            seq!(with(LShift, Tab), LAlt down, text "x", LAlt up, text "y").operations(),
+            vec![
+                Press(Button::LAlt),
+                Release(Button::LAlt),
+                Release(Button::LShift),
+                Text("x"),
+                Press(Button::LShift),
+                Press(Button::LAlt),
+                Release(Button::LAlt),
+                Release(Button::LShift),
+                Text("y"),
+                Press(Button::LShift),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be represented as button inputs")]
+    fn seq_data_rejects_text() {
+        let _ = seq_data!(with(LCtrl), A, text "é", Enter);
+    }
+
+    #[test]
+    fn seq_macro_with_block() {
+        use SequenceOperation::{Click, Press, Release};
+        assert_eq!(
+            seq!(A, with(LCtrl) { C, V }, B),
+            Sequence::new(
+                vec![],
+                vec![
+                    Click(Button::A),
+                    Press(Button::LCtrl),
+                    Click(Button::C),
+                    Click(Button::V),
+                    Release(Button::LCtrl),
+                    Click(Button::B),
+                ]
+            )
+        );
+        assert_eq!(
+            seq!(with(LCtrl, [Button::LShift]) { Tab }),
+            Sequence::new(
+                vec![],
+                vec![
+                    Press(Button::LCtrl),
+                    Press(Button::LShift),
+                    Click(Button::Tab),
+                    Release(Button::LCtrl),
+                    Release(Button::LShift),
+                ]
+            )
+        );
+        assert_eq!(
+            seq!(with(LCtrl) { with(LShift) { Z, }, A down, }, A up,),
+            Sequence::new(
+                vec![],
+                vec![
+                    Press(Button::LCtrl),
+                    Press(Button::LShift),
+                    Click(Button::Z),
+                    Release(Button::LShift),
+                    Press(Button::A),
+                    Release(Button::LCtrl),
+                    Release(Button::A),
+                ]
+            )
+        );
+        assert_eq!(
+            seq!(with(LAlt) {}),
+            Sequence::new(vec![], vec![Press(Button::LAlt), Release(Button::LAlt)])
+        );
+        assert_eq!(
+            seq!(with(LShift), with(LCtrl) { A }),
+            Sequence::new(
+                vec![Button::LShift],
+                vec![
+                    Press(Button::LCtrl),
+                    Click(Button::A),
+                    Release(Button::LCtrl)
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn seq_macro_with_delay() {
+        use std::time::Duration;
+        use SequenceOperation::{Click, Delay, Press, Release};
+
+        let ms = 20;
+        assert_eq!(
+            seq!(A, delay(50), B, delay(ms)),
+            Sequence::new(
+                vec![],
+                vec![
+                    Click(Button::A),
+                    Delay(Duration::from_millis(50)),
+                    Click(Button::B),
+                    Delay(Duration::from_millis(20)),
+                ]
+            )
+        );
+        assert_eq!(
+            seq!(with(LShift), A down, delay(10), A up),
+            Sequence::new(
+                vec![Button::LShift],
+                vec![
+                    Press(Button::A),
+                    Delay(Duration::from_millis(10)),
+                    Release(Button::A),
+                ]
+            )
+        );
+        assert_eq!(
+            seq!(with(LCtrl) { C, delay(5) }),
+            Sequence::new(
+                vec![],
+                vec![
+                    Press(Button::LCtrl),
+                    Click(Button::C),
+                    Delay(Duration::from_millis(5)),
+                    Release(Button::LCtrl),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn delays_are_not_sent_as_input() {
+        let token = CancellationToken::new();
+        let count = RefCell::new(0);
+        let completed = seq!(A, delay(1), B).send_cancellable_inner(
+            &token,
+            |_| *count.borrow_mut() += 1,
+            |_| {},
+            |_| {},
+        );
+        assert!(completed);
+        assert_eq!(*count.borrow(), 2);
+    }
+
+    #[test]
+    fn cancelling_during_delay_returns_promptly() {
+        let token = CancellationToken::new();
+        let token_ = token.clone();
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            token_.cancel();
+        });
+        let pressed = RefCell::new(HashSet::new());
+
+        let start = Instant::now();
+        let completed = seq!(A down, delay(10_000), B).send_cancellable_inner(
+            &token,
+            |button| {
+                pressed.borrow_mut().insert(button);
+            },
+            |button| {
+                pressed.borrow_mut().remove(&button);
+            },
+            |_| {},
+        );
+        canceller.join().unwrap();
+        assert!(!completed);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(pressed.borrow().is_empty());
+    }
+
+    #[test]
+    fn cancelled_sequence_releases_held_buttons() {
+        let token = CancellationToken::new();
+        let pressed = RefCell::new(HashSet::new());
+        let press_count = RefCell::new(0);
+
+        let press = |button| {
+            pressed.borrow_mut().insert(button);
+            *press_count.borrow_mut() += 1;
+            if *press_count.borrow() == 3 {
+                token.cancel();
+            }
+        };
+        let release = |button| {
+            pressed.borrow_mut().remove(&button);
+        };
+
+        let completed = seq!(with(LShift), A down, B down, C, D).send_cancellable_inner(
+            &token,
+            press,
+            release,
+            |_| {},
+        );
+
+        assert!(!completed);
+        assert!(pressed.borrow().is_empty());
+        assert_eq!(*press_count.borrow(), 3);
+    }
+
+    #[test]
+    #[ignore = "installs real hooks and sends real input"]
+    fn every_button_round_trips_through_the_hook() {
+        use hookmap_core::button::ButtonAction::{Press, Release};
+        use hookmap_core::event::Event;
+        use std::time::Duration;
+
+        // Uninstalls the hook even if an assertion fails, so that the input is not blocked.
+        struct Installed;
+        impl Drop for Installed {
+            fn drop(&mut self) {
+                hookmap_core::uninstall_hook();
+            }
+        }
+
+        let rx = hookmap_core::install_hook();
+        let _installed = Installed;
+        let mut mismatches = Vec::new();
+        for button in Button::ALL {
+            // The generic modifiers are sent as both sides.
+            if matches!(
+                button,
+                Button::Shift | Button::Ctrl | Button::Alt | Button::Super
+            ) {
+                continue;
+            }
+            // Recursive input is tagged as injected instead of being skipped by the hook.
+            seq!([button]).send_recursive();
+            let mut received = Vec::new();
+            while received.len() < 2 {
+                let Ok((event, native_handler)) = rx.recv_timeout(Duration::from_secs(1)) else {
+                    break;
+                };
+                // Blocked so that the input has no effect on the session.
+                native_handler.block();
+                match event {
+                    Event::Button(event) if event.injected => {
+                        received.push((event.target, event.action))
+                    }
+                    _ => {}
+                }
+            }
+            if received != [(button, Press), (button, Release)] {
+                mismatches.push((button, received));
+            }
+        }
+        assert!(mismatches.is_empty(), "{mismatches:#?}");
+    }
+
+    #[test]
+    fn uncancelled_sequence_is_completed() {
+        let token = CancellationToken::new();
+        let count = RefCell::new(0);
+        let completed = seq!(A, B, C).send_cancellable_inner(
+            &token,
+            |_| *count.borrow_mut() += 1,
+            |_| {},
+            |_| {},
+        );
+        assert!(completed);
+        assert_eq!(*count.borrow(), 3);
+    }
 }
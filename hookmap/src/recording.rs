@@ -0,0 +1,541 @@
+//! Recording input and replaying it.
+//!
+//! A [`Recording`] can be converted to a human-readable script and parsed back.
+//! Each line of the script is one of the following commands.
+//!
+//! * `press <Button>` - Presses the button.
+//! * `release <Button>` - Releases the button.
+//! * `move <x> <y>` - Moves the mouse cursor to the absolute position.
+//! * `wheel <delta>` - Rotates the mouse wheel. Upward rotation is positive.
+//! * `wait <milliseconds>ms` - Waits for the duration.
+//!
+//! Button names are the variant names of [`Button`]. Empty lines and lines starting with `#` are
+//! ignored.
+//!
+//! ```text
+//! # Ctrl+C
+//! press LCtrl
+//! wait 50ms
+//! press C
+//! release C
+//! release LCtrl
+//! ```
+//!
+
+use hookmap_core::button::{Button, ButtonAction, ButtonKind};
+use hookmap_core::event::{ButtonEvent, CursorEvent, Event, WheelEvent};
+use hookmap_core::mouse;
+
+use crate::runtime::interceptor::{Filter, Interceptor};
+use crate::CancellationToken;
+
+use std::fmt::{self, Display, Write};
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+/// An operation in a [`Recording`].
+///
+/// This covers both keyboard and mouse input, so a sequence of input can be stored as
+/// `Vec<Step>`. Events can be converted with [`From`].
+///
+/// # Examples
+///
+/// ```
+/// use hookmap::prelude::*;
+/// use hookmap::recording::Step;
+///
+/// let event = WheelEvent::new(-1);
+/// assert_eq!(Step::from(event), Step::Wheel(-1));
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Step {
+    /// Presses the button.
+    Press(Button),
+
+    /// Releases the button.
+    Release(Button),
+
+    /// Moves the mouse cursor to the absolute position.
+    MoveTo(i32, i32),
+
+    /// Rotates the mouse wheel.
+    Wheel(i32),
+
+    /// Waits for the duration.
+    Wait(Duration),
+}
+
+impl Step {
+    fn play(&self, recursive: bool) {
+        match (*self, recursive) {
+            (Step::Press(button), false) => button.press(),
+            (Step::Press(button), true) => button.press_recursive(),
+            (Step::Release(button), false) => button.release(),
+            (Step::Release(button), true) => button.release_recursive(),
+            (Step::MoveTo(x, y), false) => mouse::move_absolute(x, y),
+            (Step::MoveTo(x, y), true) => mouse::move_absolute_recursive(x, y),
+            (Step::Wheel(delta), false) => mouse::rotate(delta),
+            (Step::Wheel(delta), true) => mouse::rotate_recursive(delta),
+            (Step::Wait(duration), _) => thread::sleep(duration),
+        }
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Press(button) => write!(f, "{} {:?}", ButtonAction::Press, button),
+            Step::Release(button) => write!(f, "{} {:?}", ButtonAction::Release, button),
+            Step::MoveTo(x, y) => write!(f, "move {} {}", x, y),
+            Step::Wheel(delta) => write!(f, "wheel {}", delta),
+            Step::Wait(duration) => write!(f, "wait {}ms", duration.as_millis()),
+        }
+    }
+}
+
+impl From<ButtonEvent> for Step {
+    fn from(event: ButtonEvent) -> Self {
+        match event.action {
+            ButtonAction::Press => Step::Press(event.target),
+            ButtonAction::Release => Step::Release(event.target),
+        }
+    }
+}
+
+impl From<CursorEvent> for Step {
+    fn from(event: CursorEvent) -> Self {
+        Step::MoveTo(event.position.x, event.position.y)
+    }
+}
+
+impl From<WheelEvent> for Step {
+    fn from(event: WheelEvent) -> Self {
+        Step::Wheel(event.delta)
+    }
+}
+
+impl From<Event> for Step {
+    fn from(event: Event) -> Self {
+        match event {
+            Event::Button(event) => event.into(),
+            Event::Wheel(event) => event.into(),
+            Event::Cursor(event) => event.into(),
+        }
+    }
+}
+
+/// An error returned when parsing a script of [`Recording`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseScriptError {
+    line: usize,
+    content: String,
+}
+
+impl ParseScriptError {
+    /// Returns the line number (starting from 1) where the error occurred.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl Display for ParseScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid command at line {}: {:?}",
+            self.line, self.content
+        )
+    }
+}
+
+impl std::error::Error for ParseScriptError {}
+
+fn parse_step(line: &str) -> Option<Step> {
+    let mut words = line.split_whitespace();
+    let command = words.next()?;
+    let step = match command {
+        "move" => {
+            let x = words.next()?.parse().ok()?;
+            let y = words.next()?.parse().ok()?;
+            Step::MoveTo(x, y)
+        }
+        "wheel" => Step::Wheel(words.next()?.parse().ok()?),
+        "wait" => {
+            let millis = words.next()?.strip_suffix("ms")?.parse().ok()?;
+            Step::Wait(Duration::from_millis(millis))
+        }
+        _ => {
+            let button: Button = words.next()?.parse().ok()?;
+            match command.parse().ok()? {
+                ButtonAction::Press => Step::Press(button),
+                ButtonAction::Release => Step::Release(button),
+            }
+        }
+    };
+    words.next().is_none().then_some(step)
+}
+
+/// Recorded input that can be replayed.
+///
+/// # Examples
+///
+/// ```
+/// use hookmap::recording::Recording;
+///
+/// let script = "press A\nwait 100ms\nrelease A\n";
+/// let recording: Recording = script.parse().unwrap();
+/// assert_eq!(recording.to_script(), script);
+/// ```
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    steps: Vec<Step>,
+}
+
+impl Recording {
+    /// Creates a new instance of [`Recording`] from steps.
+    pub fn new(steps: Vec<Step>) -> Self {
+        Self { steps }
+    }
+
+    /// Returns the recorded steps.
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Records button input until `stop` is pressed, blocking the current thread.
+    ///
+    /// Only the buttons of the keyboard and the mouse are recorded. The movements of the
+    /// cursor and the rotations of the wheel are not, but the cursor position is recorded
+    /// before each mouse button event, so the clicks are replayed where they occurred and a
+    /// drag is replayed as a straight move. To record them, build a [`Recording`] from the
+    /// events received by the hotkeys with [`Step::from`].
+    ///
+    /// The input generated by this program and the events of `stop` are not recorded.
+    /// Hotkeys must be installed in another thread to receive the input.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    /// use hookmap::recording::Recording;
+    ///
+    /// let recording = Recording::record_until(Button::Esc);
+    /// println!("{}", recording.to_script());
+    /// ```
+    ///
+    pub fn record_until(stop: Button) -> Self {
+        let events = Interceptor::dispatch(Filter::new().callback(|e| !e.injected)).iter();
+        Self::record_events(events, stop, mouse::get_position)
+    }
+
+    // Records `events` until `stop` is pressed, with the cursor position given by
+    // `get_position` before each mouse button event.
+    fn record_events(
+        events: impl IntoIterator<Item = ButtonEvent>,
+        stop: Button,
+        get_position: impl Fn() -> (i32, i32),
+    ) -> Self {
+        let mut steps = vec![];
+        let mut last_event = None;
+        let mut cursor_position = None;
+
+        for event in events {
+            if event.target == stop {
+                if event.action == ButtonAction::Press {
+                    break;
+                }
+                continue;
+            }
+
+            // The time of the event is used rather than the time it is received, so that
+            // the waits are not skewed by the delay of the handlers.
+            if let Some(last_event) = last_event {
+                let elapsed = event.time.saturating_sub(last_event);
+                let elapsed = Duration::from_millis(elapsed.as_millis() as u64);
+                if !elapsed.is_zero() {
+                    steps.push(Step::Wait(elapsed));
+                }
+            }
+            last_event = Some(event.time);
+
+            if event.target.kind() == ButtonKind::Mouse {
+                let position = get_position();
+                if cursor_position != Some(position) {
+                    steps.push(Step::MoveTo(position.0, position.1));
+                    cursor_position = Some(position);
+                }
+            }
+
+            steps.push(event.into());
+        }
+        Self { steps }
+    }
+
+    /// Converts to a script that can be parsed by [`Recording::from_script`].
+    pub fn to_script(&self) -> String {
+        self.steps.iter().fold(String::new(), |mut script, step| {
+            let _ = writeln!(script, "{}", step);
+            script
+        })
+    }
+
+    /// Parses a script generated by [`Recording::to_script`] or written by hand.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hookmap::prelude::*;
+    /// use hookmap::recording::{Recording, Step};
+    ///
+    /// let recording = Recording::from_script("press A\nmove 10 20").unwrap();
+    /// assert_eq!(recording.steps(), &[Step::Press(Button::A), Step::MoveTo(10, 20)]);
+    /// ```
+    ///
+    pub fn from_script(script: &str) -> Result<Self, ParseScriptError> {
+        script
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(i, line)| {
+                parse_step(line).ok_or_else(|| ParseScriptError {
+                    line: i + 1,
+                    content: line.to_owned(),
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Self::new)
+    }
+
+    /// Replays the recorded steps.
+    pub fn play(&self) {
+        self.steps.iter().for_each(|step| step.play(false));
+    }
+
+    /// Replays the recorded steps.
+    /// Events generated by this method can be hooked.
+    pub fn play_recursive(&self) {
+        self.steps.iter().for_each(|step| step.play(true));
+    }
+
+    /// Replays the recorded steps, stopping early if `token` is cancelled.
+    /// A cancellation during a wait stops it immediately, and the buttons pressed by the
+    /// recording are released.
+    ///
+    /// Returns `true` if all the steps are replayed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    /// use hookmap::recording::Recording;
+    ///
+    /// let recording: Recording = "press A\nwait 5000ms\nrelease A".parse().unwrap();
+    /// let token = CancellationToken::new();
+    /// let token_ = token.clone();
+    /// std::thread::spawn(move || recording.play_cancellable(&token_));
+    /// token.cancel();
+    /// ```
+    ///
+    pub fn play_cancellable(&self, token: &CancellationToken) -> bool {
+        self.play_cancellable_inner(token, |step| step.play(false))
+    }
+
+    /// Same as [`Recording::play_cancellable`], but events generated by this method can be
+    /// hooked.
+    pub fn play_cancellable_recursive(&self, token: &CancellationToken) -> bool {
+        self.play_cancellable_inner(token, |step| step.play(true))
+    }
+
+    fn play_cancellable_inner(&self, token: &CancellationToken, play: impl Fn(Step)) -> bool {
+        let mut held = vec![];
+        let completed = self.steps.iter().all(|&step| {
+            if token.is_cancelled() {
+                return false;
+            }
+            match step {
+                Step::Wait(duration) => return !token.sleep(duration),
+                Step::Press(button) => held.push(button),
+                Step::Release(button) => held.retain(|&b| b != button),
+                Step::MoveTo(..) | Step::Wheel(_) => {}
+            }
+            play(step);
+            true
+        });
+        if !completed {
+            held.into_iter()
+                .rev()
+                .for_each(|button| play(Step::Release(button)));
+        }
+        completed
+    }
+}
+
+impl FromStr for Recording {
+    type Err = ParseScriptError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_script(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hookmap_core::event::{Delta, Position};
+    use std::cell::RefCell;
+    use std::time::Instant;
+
+    #[test]
+    fn script_round_trip() {
+        let recording = Recording::new(vec![
+            Step::MoveTo(-120, 480),
+            Step::Press(Button::LeftButton),
+            Step::Wait(Duration::from_millis(35)),
+            Step::Release(Button::LeftButton),
+            Step::Press(Button::LShift),
+            Step::Press(Button::Key1),
+            Step::Release(Button::Key1),
+            Step::Release(Button::LShift),
+            Step::Wheel(-3),
+        ]);
+        let script = recording.to_script();
+        assert_eq!(
+            script.lines().take(3).collect::<Vec<_>>(),
+            ["move -120 480", "press LeftButton", "wait 35ms"]
+        );
+        assert_eq!(Recording::from_script(&script), Ok(recording));
+    }
+
+    #[test]
+    fn steps_from_events() {
+        let press = ButtonEvent::new(Button::A, ButtonAction::Press);
+        let release = ButtonEvent::new(Button::A, ButtonAction::Release);
+        let cursor = CursorEvent::new(Delta { dx: 1, dy: 2 }, Position { x: 30, y: 40 });
+        let wheel = WheelEvent::new(2);
+        let steps: Vec<Step> = [
+            Event::Button(press),
+            Event::Button(release),
+            Event::Cursor(cursor),
+            Event::Wheel(wheel),
+        ]
+        .into_iter()
+        .map(Step::from)
+        .collect();
+        assert_eq!(
+            steps,
+            [
+                Step::Press(Button::A),
+                Step::Release(Button::A),
+                Step::MoveTo(30, 40),
+                Step::Wheel(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_buttons_are_recorded_with_the_cursor_position_of_mouse_buttons() {
+        let event = |target, action, millis| {
+            ButtonEvent::new(target, action).time(Duration::from_millis(millis))
+        };
+        let events = [
+            event(Button::A, ButtonAction::Press, 100),
+            event(Button::A, ButtonAction::Release, 150),
+            event(Button::LeftButton, ButtonAction::Press, 150),
+            event(Button::LeftButton, ButtonAction::Release, 200),
+            event(Button::Esc, ButtonAction::Press, 300),
+            event(Button::B, ButtonAction::Press, 400),
+        ];
+        let positions = RefCell::new(vec![(30, 40), (10, 20)]);
+        let get_position = || positions.borrow_mut().pop().unwrap();
+
+        let recording = Recording::record_events(events, Button::Esc, get_position);
+        assert_eq!(
+            recording.steps(),
+            [
+                Step::Press(Button::A),
+                Step::Wait(Duration::from_millis(50)),
+                Step::Release(Button::A),
+                Step::MoveTo(10, 20),
+                Step::Press(Button::LeftButton),
+                Step::Wait(Duration::from_millis(50)),
+                Step::MoveTo(30, 40),
+                Step::Release(Button::LeftButton),
+            ]
+        );
+    }
+
+    #[test]
+    fn script_comments_and_blank_lines() {
+        let recording: Recording = "# comment\n\n  press A  \nrelease A".parse().unwrap();
+        assert_eq!(
+            recording.steps(),
+            &[Step::Press(Button::A), Step::Release(Button::A)]
+        );
+    }
+
+    #[test]
+    fn cancelled_playback_stops_waiting_and_releases_held_buttons() {
+        let recording: Recording = "press LShift\npress A\nwait 10000ms\nrelease A\nrelease LShift"
+            .parse()
+            .unwrap();
+        let token = CancellationToken::new();
+        let token_ = token.clone();
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            token_.cancel();
+        });
+        let played = RefCell::new(vec![]);
+
+        let start = Instant::now();
+        let completed =
+            recording.play_cancellable_inner(&token, |step| played.borrow_mut().push(step));
+        canceller.join().unwrap();
+        assert!(!completed);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(
+            played.into_inner(),
+            [
+                Step::Press(Button::LShift),
+                Step::Press(Button::A),
+                Step::Release(Button::A),
+                Step::Release(Button::LShift),
+            ]
+        );
+    }
+
+    #[test]
+    fn uncancelled_playback_plays_all_steps() {
+        let recording: Recording = "press A\nwait 1ms\nrelease A\nwheel 1".parse().unwrap();
+        let played = RefCell::new(vec![]);
+        let completed = recording.play_cancellable_inner(&CancellationToken::new(), |step| {
+            played.borrow_mut().push(step)
+        });
+        assert!(completed);
+        assert_eq!(
+            played.into_inner(),
+            [
+                Step::Press(Button::A),
+                Step::Release(Button::A),
+                Step::Wheel(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_script() {
+        for (script, line) in [
+            ("press A\npress Unknown", 2),
+            ("click A", 1),
+            ("\nwait 10", 2),
+            ("move 1", 1),
+            ("wheel up", 1),
+            ("press A B", 1),
+        ] {
+            assert_eq!(Recording::from_script(script).unwrap_err().line(), line);
+        }
+    }
+}
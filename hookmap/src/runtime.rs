@@ -1,88 +1,472 @@
 mod button_state;
+mod disable_condition;
 mod event_broker;
+mod event_queue;
+mod focus_watch;
+mod held_modifiers;
+mod hook_watchdog;
+mod install_retry;
 pub mod interceptor;
+mod kill_switch;
+mod reloadable;
+mod timer;
 
-use hookmap_core::event::{Event, NativeEventHandler, NativeEventOperation};
+use hookmap_core::button::ButtonAction;
+use hookmap_core::event::{
+    ButtonEvent, Event, EventReceiver, HookTarget, InstallHookError, MouseEvent,
+    NativeEventOperation,
+};
 
-use self::button_state::RealButtonState;
-use crate::hook::{ButtonState, Hook, HookStorage};
+pub(crate) use self::button_state::record as record_button_state;
+#[cfg(any(test, feature = "bench"))]
+pub use self::button_state::FakeButtonState;
+pub(crate) use self::button_state::RealButtonState;
+pub(crate) use self::disable_condition::DisableCondition;
+use self::event_queue::WorkerPool;
+pub use self::event_queue::{EventQueue, OverflowPolicy};
+pub(crate) use self::focus_watch::FocusWatch;
+pub(crate) use self::held_modifiers::sides;
+use self::held_modifiers::with_event_modifiers;
+pub use self::held_modifiers::{event_modifiers, reemit, HeldModifiers};
+pub(crate) use self::hook_watchdog::HookWatchdog;
+pub(crate) use self::install_retry::InstallRetry;
+pub(crate) use self::kill_switch::KillSwitch;
+pub(crate) use self::reloadable::ReloadableStorage;
+#[cfg(test)]
+pub(crate) use self::timer::recorder;
+pub(crate) use self::timer::{schedule, spawn_callback, PendingTask};
+use crate::button_set::ButtonSet;
+use crate::hook::{native_event_operation, ButtonState, Hook, HookStorage, Hooks};
 
-use std::thread;
+use std::ops::ControlFlow;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Processes `event` with the installed hotkeys as if it was input from a device.
+///
+/// The event is not sent to the OS, so it is only seen by hotkeys and interceptors of this
+/// program. Modifiers are evaluated with the current state of the buttons, which is not
+/// updated by this event. Returns whether the hotkeys would block the event.
+///
+/// This blocks until the event is dispatched, so it must not be called while
+/// [`Hotkey::install`] is being called on the same thread.
+///
+/// # Panics
+///
+/// Panics if hotkeys are not installed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use hookmap::prelude::*;
+///
+/// let event = ButtonEvent::new(Button::A, ButtonAction::Press)
+///     .injected(true)
+///     .time(hookmap::event_time());
+/// hookmap::post_synthetic(event);
+/// ```
+///
+/// [`Hotkey::install`]: crate::hotkey::Hotkey::install
+///
+pub fn post_synthetic(event: ButtonEvent) -> NativeEventOperation {
+    hookmap_core::post_event(Event::Button(event))
+}
 
 #[derive(Debug)]
 pub(crate) struct Runtime<T, S: ButtonState = RealButtonState>
 where
-    T: HookStorage + Send + 'static,
+    T: HookStorage + Send + Sync + 'static,
     <T as HookStorage>::ButtonHook: Send,
     <T as HookStorage>::MouseWheelHook: Send,
     <T as HookStorage>::MouseCursorHook: Send,
+    <T as HookStorage>::MouseHook: Send + 'static,
 {
-    storage: T,
+    storage: Arc<ReloadableStorage<T>>,
     state: S,
+    kill_switch: Option<KillSwitch>,
+    disable_condition: Option<DisableCondition>,
+    // Buttons whose presses were passed through by the disable condition.
+    passed_through: Mutex<ButtonSet>,
+    focus_watch: Option<FocusWatch>,
+    event_queue: EventQueue,
+    install_retry: InstallRetry,
+    hook_watchdog: Option<HookWatchdog>,
+    workers: WorkerPool,
+    // Hooks for unified mouse events are run on a single worker to preserve the order.
+    mouse_worker: WorkerPool,
 }
 
 impl<T> Runtime<T, RealButtonState>
 where
-    T: HookStorage + Send,
+    T: HookStorage + Send + Sync + 'static,
     <T as HookStorage>::ButtonHook: Send,
     <T as HookStorage>::MouseWheelHook: Send,
     <T as HookStorage>::MouseCursorHook: Send,
+    <T as HookStorage>::MouseHook: Send + 'static,
 {
+    #[cfg(test)]
     pub(crate) fn new(storage: T) -> Self {
         Self::with_state(storage, RealButtonState)
     }
+
+    /// Creates a runtime whose hooks are replaced through `storage` while it is running.
+    pub(crate) fn reloadable(storage: Arc<ReloadableStorage<T>>) -> Self {
+        Self::with_reloadable_state(storage, RealButtonState)
+    }
 }
 
 impl<T, S: ButtonState> Runtime<T, S>
 where
-    T: HookStorage + Send + 'static,
+    T: HookStorage + Send + Sync + 'static,
     <T as HookStorage>::ButtonHook: Send,
     <T as HookStorage>::MouseWheelHook: Send,
     <T as HookStorage>::MouseCursorHook: Send,
+    <T as HookStorage>::MouseHook: Send + 'static,
 {
+    #[cfg(test)]
     pub(crate) fn with_state(storage: T, state: S) -> Self {
-        Self { storage, state }
+        Self::with_reloadable_state(Arc::new(ReloadableStorage::new(storage)), state)
+    }
+
+    fn with_reloadable_state(storage: Arc<ReloadableStorage<T>>, state: S) -> Self {
+        Self {
+            storage,
+            state,
+            kill_switch: None,
+            disable_condition: None,
+            passed_through: Mutex::default(),
+            focus_watch: None,
+            event_queue: EventQueue::default(),
+            install_retry: InstallRetry::default(),
+            hook_watchdog: None,
+            workers: EventQueue::default().pool(EventQueue::WORKERS),
+            mouse_worker: EventQueue::default().pool(1),
+        }
+    }
+
+    /// Sets the combination of buttons that uninstalls the hooks and stops the runtime.
+    /// The kill switch is checked before any hook or interceptor.
+    pub(crate) fn with_kill_switch(mut self, kill_switch: Option<KillSwitch>) -> Self {
+        self.kill_switch = kill_switch;
+        self
+    }
+
+    /// Sets the condition under which hooks and interceptors are skipped.
+    pub(crate) fn with_disable_condition(
+        mut self,
+        disable_condition: Option<DisableCondition>,
+    ) -> Self {
+        self.disable_condition = disable_condition;
+        self
+    }
+
+    /// Sets the watch of the foreground window. When it changes, the buttons held down by
+    /// hooks are released.
+    pub(crate) fn with_focus_watch(mut self, focus_watch: Option<FocusWatch>) -> Self {
+        self.focus_watch = focus_watch;
+        self
+    }
+
+    /// Sets the capacity and the overflow policies of the queue of hooks waiting for a worker.
+    pub(crate) fn with_event_queue(mut self, event_queue: EventQueue) -> Self {
+        self.event_queue = event_queue;
+        self.workers = event_queue.pool(EventQueue::WORKERS);
+        self.mouse_worker = event_queue.pool(1);
+        self
     }
 
-    fn handle_event<F, E, H>(&self, fetch: F, event: E, native_handler: NativeEventHandler)
-    where
-        F: FnOnce(&T, E, &S) -> Vec<H>,
+    /// Sets how many times installing the hooks is retried when the OS fails to install them.
+    pub(crate) fn with_install_retry(mut self, install_retry: InstallRetry) -> Self {
+        self.install_retry = install_retry;
+        self
+    }
+
+    /// Sets the watchdog that reinstalls the hooks while the runtime is running if the OS
+    /// removed them.
+    pub(crate) fn with_hook_watchdog(mut self, hook_watchdog: Option<HookWatchdog>) -> Self {
+        self.hook_watchdog = hook_watchdog;
+        self
+    }
+
+    /// Returns `true` if `event` should be passed through without running any hook.
+    ///
+    /// A button is passed through from its press to its release, regardless of the condition
+    /// at the time of the release. So buttons pressed before the condition was satisfied, such
+    /// as the behaviors of remaps, are released by the hooks, and the applications receive
+    /// the releases of the buttons whose presses they received.
+    fn is_disabled(&self, event: &Event) -> bool {
+        let is_satisfied = || {
+            self.disable_condition
+                .as_ref()
+                .is_some_and(DisableCondition::is_satisfied)
+        };
+        let Event::Button(event) = event else {
+            return is_satisfied();
+        };
+        let mut passed_through = self.passed_through.lock().unwrap();
+        match event.action {
+            ButtonAction::Press if passed_through.contains(event.target) => true,
+            ButtonAction::Press if is_satisfied() => {
+                passed_through.insert(event.target);
+                true
+            }
+            ButtonAction::Press => false,
+            ButtonAction::Release => {
+                let is_passed_through = passed_through.contains(event.target);
+                passed_through.remove(event.target);
+                is_passed_through
+            }
+        }
+    }
+
+    /// Returns `true` if `event` triggers the kill switch.
+    pub(crate) fn is_killed(&self, event: &Event) -> bool {
+        match (self.kill_switch, event) {
+            (Some(kill_switch), Event::Button(event)) => {
+                kill_switch.is_triggered(*event, &self.state)
+            }
+            _ => false,
+        }
+    }
+
+    fn handle_event<F, R, E, H>(
+        &self,
+        fetch: F,
+        event: E,
+        respond: impl FnOnce(NativeEventOperation),
+        mouse_event: Option<MouseEvent>,
+        policy: OverflowPolicy,
+    ) where
+        F: FnOnce(&T, E, &S) -> R,
+        R: Into<Hooks<H>>,
         E: Copy + Send + 'static,
         H: Hook<E> + Send + 'static,
     {
-        let hooks = fetch(&self.storage, event, &self.state);
-        let has_block_operation = hooks
-            .iter()
-            .map(|hook| hook.native_event_operation())
-            .any(|operation| operation == NativeEventOperation::Block);
-        let operation = if has_block_operation {
-            NativeEventOperation::Block
+        // Both kinds of hooks are fetched from the same hooks even if they are being replaced.
+        let loaded = self.storage.read();
+        let hooks: Hooks<H> = fetch(&loaded.storage, event, &self.state).into();
+        let mouse_hooks = mouse_event
+            .map(|mouse_event| loaded.storage.fetch_mouse_hook(mouse_event, &self.state))
+            .unwrap_or_default();
+        drop(loaded);
+        let modifiers = if hooks.is_empty() {
+            HeldModifiers::default()
         } else {
-            NativeEventOperation::Dispatch
+            HeldModifiers::capture(&self.state)
         };
-        native_handler.handle(operation);
-        thread::spawn(move || hooks.iter().for_each(|hook| hook.run(event)));
+        let operation = match native_event_operation(&hooks) {
+            NativeEventOperation::Dispatch => native_event_operation(&mouse_hooks),
+            NativeEventOperation::Block => NativeEventOperation::Block,
+        };
+        respond(operation);
+        if let Some(mouse_event) = mouse_event {
+            if !mouse_hooks.is_empty() {
+                self.mouse_worker.push(
+                    move || mouse_hooks.iter().for_each(|hook| hook.run(mouse_event)),
+                    policy,
+                );
+            }
+        }
+        // Remaps are run here without allocating or spawning a thread, since they are the
+        // most common hooks and must keep the order of events.
+        hooks
+            .iter()
+            .filter(|hook| hook.is_ordered())
+            .for_each(|hook| hook.run(event));
+        let hooks: Vec<_> = hooks
+            .into_iter()
+            .filter(|hook| !hook.is_ordered())
+            .collect();
+        if !hooks.is_empty() {
+            self.workers.push(
+                move || {
+                    with_event_modifiers(modifiers, || {
+                        hooks.iter().for_each(|hook| hook.run(event))
+                    })
+                },
+                policy,
+            );
+        }
     }
 
-    pub(crate) fn start(&self) {
-        let event_receiver = hookmap_core::install_hook();
+    /// Runs the hooks for `event`. `respond` is called with the operation for the native event
+    /// before the hooks are run.
+    ///
+    /// Events posted by [`hookmap_core::post_event`] are also dispatched by this method, so they
+    /// are processed in the same way as the input from devices.
+    pub(crate) fn dispatch(
+        &self,
+        event: Event,
+        respond: impl FnOnce(NativeEventOperation),
+    ) -> ControlFlow<()> {
+        if self.is_killed(&event) {
+            respond(NativeEventOperation::Dispatch);
+            return ControlFlow::Break(());
+        }
+        // While another window has the focus, the release of a source may not reach the hook,
+        // e.g. when the window is run as administrator.
+        if self
+            .focus_watch
+            .as_ref()
+            .is_some_and(FocusWatch::is_changed)
+        {
+            self.storage.read().storage.release_held();
+        }
+        if self.is_disabled(&event) {
+            if let Event::Button(mut event) = event {
+                button_state::record(&mut event);
+            }
+            respond(NativeEventOperation::Dispatch);
+            return ControlFlow::Continue(());
+        }
+        let mouse_event = event.to_mouse_event();
+        match event {
+            Event::Button(mut event) => {
+                let is_repeat = button_state::record(&mut event);
+                if interceptor::publish_event(event, is_repeat, &self.state)
+                    == NativeEventOperation::Block
+                {
+                    respond(NativeEventOperation::Block);
+                    return ControlFlow::Continue(());
+                }
+                self.handle_event(
+                    HookStorage::fetch_button_hook,
+                    event,
+                    respond,
+                    mouse_event,
+                    self.event_queue.button_policy(),
+                );
+            }
+            Event::Wheel(event) => {
+                self.handle_event(
+                    HookStorage::fetch_mouse_wheel_hook,
+                    event,
+                    respond,
+                    mouse_event,
+                    self.event_queue.button_policy(),
+                );
+            }
+            Event::Cursor(event) => {
+                self.handle_event(
+                    HookStorage::fetch_mouse_cursor_hook,
+                    event,
+                    respond,
+                    mouse_event,
+                    self.event_queue.cursor_policy(),
+                );
+            }
+        }
+        ControlFlow::Continue(())
+    }
 
-        while let Ok((event, native_handler)) = event_receiver.recv() {
-            match event {
-                Event::Button(event) => {
-                    if interceptor::publish_event(event) == NativeEventOperation::Block {
-                        native_handler.block();
-                        continue;
+    fn set_button_filter(&self) {
+        // Button events that no hook or interceptor handles are dispatched on the hook thread
+        // without being sent to this runtime.
+        // The hooks may be replaced, so the buttons they handle are looked up on each event.
+        let storage = Arc::clone(&self.storage);
+        let kill_switch_buttons: ButtonSet = self
+            .kill_switch
+            .map(|kill_switch| kill_switch.buttons())
+            .unwrap_or_default();
+        hookmap_core::set_button_filter(move |button| {
+            kill_switch_buttons.contains(button)
+                || storage.read().handled_buttons.contains(button)
+                || interceptor::is_subscribed()
+        });
+    }
+
+    fn stop(event_receiver: EventReceiver) {
+        // The filter refers to this runtime, so it must not outlive it and filter the events
+        // for a later runtime or a direct user of the core.
+        hookmap_core::clear_button_filter();
+        // Events sent while uninstalling are dispatched once the receiver is dropped,
+        // so the hook thread does not wait for this thread.
+        drop(event_receiver);
+        hookmap_core::uninstall_hook();
+    }
+
+    pub(crate) fn start(&self, target: HookTarget) -> Result<(), InstallHookError> {
+        let event_receiver = self
+            .install_retry
+            .run(|| hookmap_core::try_install_hook_for(target))?;
+        self.set_button_filter();
+        let _stop_watchdog = self.hook_watchdog.as_ref().map(HookWatchdog::spawn);
+
+        loop {
+            match event_receiver.recv() {
+                Ok((event, native_handler)) => {
+                    if self
+                        .dispatch(event, |operation| native_handler.handle(operation))
+                        .is_break()
+                    {
+                        Self::stop(event_receiver);
+                        break;
                     }
-                    self.handle_event(HookStorage::fetch_button_hook, event, native_handler);
                 }
-                Event::Wheel(event) => {
-                    self.handle_event(HookStorage::fetch_mouse_wheel_hook, event, native_handler);
+                // The hooks were uninstalled by someone else.
+                Err(_) => {
+                    hookmap_core::clear_button_filter();
+                    break;
                 }
-                Event::Cursor(event) => {
-                    self.handle_event(HookStorage::fetch_mouse_cursor_hook, event, native_handler);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn start_with_handler(
+        &self,
+        target: HookTarget,
+        interval: Duration,
+        mut handler: impl FnMut(),
+    ) -> Result<(), InstallHookError> {
+        self.start_until(target, interval, || {
+            handler();
+            ControlFlow::Continue(())
+        })
+    }
+
+    /// Same as [`Runtime::start_with_handler`], but uninstalls the hooks and returns when
+    /// `handler` breaks.
+    pub(crate) fn start_until(
+        &self,
+        target: HookTarget,
+        interval: Duration,
+        mut handler: impl FnMut() -> ControlFlow<()>,
+    ) -> Result<(), InstallHookError> {
+        let event_receiver = self
+            .install_retry
+            .run(|| hookmap_core::try_install_hook_for(target))?;
+        self.set_button_filter();
+        let _stop_watchdog = self.hook_watchdog.as_ref().map(HookWatchdog::spawn);
+        let mut last_called = Instant::now();
+
+        loop {
+            let timeout = interval.saturating_sub(last_called.elapsed());
+            match event_receiver.recv_timeout(timeout) {
+                Ok((event, native_handler)) => {
+                    if self
+                        .dispatch(event, |operation| native_handler.handle(operation))
+                        .is_break()
+                    {
+                        Self::stop(event_receiver);
+                        break;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    hookmap_core::clear_button_filter();
+                    break;
+                }
+            }
+            if last_called.elapsed() >= interval {
+                last_called = Instant::now();
+                if handler().is_break() {
+                    Self::stop(event_receiver);
+                    break;
                 }
             }
         }
+        Ok(())
     }
 }
@@ -1,7 +1,42 @@
-use hookmap_core::button::Button;
+use hookmap_core::button::{Button, ButtonAction};
+use hookmap_core::event::ButtonEvent;
 
 use crate::hook::ButtonState;
 
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+#[cfg(any(test, feature = "bench"))]
+use std::collections::HashSet;
+use std::sync::Mutex;
+#[cfg(any(test, feature = "bench"))]
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+static RELEASED_AT: Lazy<Mutex<HashMap<Button, Instant>>> = Lazy::new(Mutex::default);
+static PRESSED_AT: Lazy<Mutex<HashMap<Button, Instant>>> = Lazy::new(Mutex::default);
+
+/// Records the time of `event` and sets [`ButtonEvent::held_for`] of a release.
+/// Key repeats keep the time of the first press.
+/// Returns `true` if `event` is a key repeat, that is, a press of a button already pressed.
+pub(crate) fn record(event: &mut ButtonEvent) -> bool {
+    let now = Instant::now();
+    match event.action {
+        ButtonAction::Press => {
+            let mut pressed_at = PRESSED_AT.lock().unwrap();
+            if pressed_at.contains_key(&event.target) {
+                return true;
+            }
+            pressed_at.insert(event.target, now);
+        }
+        ButtonAction::Release => {
+            let pressed_at = PRESSED_AT.lock().unwrap().remove(&event.target);
+            event.held_for = pressed_at.map(|pressed_at| now - pressed_at);
+            RELEASED_AT.lock().unwrap().insert(event.target, now);
+        }
+    }
+    false
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct RealButtonState;
 
@@ -13,4 +48,135 @@ impl ButtonState for RealButtonState {
     fn is_released(&self, button: Button) -> bool {
         button.is_released()
     }
+
+    fn is_toggled(&self, button: Button) -> bool {
+        button.is_toggled()
+    }
+
+    fn released_at(&self, button: Button) -> Option<Instant> {
+        RELEASED_AT.lock().unwrap().get(&button).copied()
+    }
+
+    fn pressed_at(&self, button: Button) -> Option<Instant> {
+        PRESSED_AT.lock().unwrap().get(&button).copied()
+    }
+}
+
+/// A state of the buttons set up by tests and benchmarks. No OS call is made.
+///
+/// Clones share the state, so it can be changed while a runtime holds a clone, e.g. to
+/// follow the dispatched events with [`FakeButtonState::record`].
+#[cfg(any(test, feature = "bench"))]
+#[derive(Debug, Clone, Default)]
+pub struct FakeButtonState(Arc<RwLock<FakeButtons>>);
+
+#[cfg(any(test, feature = "bench"))]
+#[derive(Debug, Default)]
+struct FakeButtons {
+    pressed_at: HashMap<Button, Instant>,
+    released_at: HashMap<Button, Instant>,
+    toggled: HashSet<Button>,
+}
+
+#[cfg(any(test, feature = "bench"))]
+impl FakeButtonState {
+    /// Returns a state in which only `buttons` are held down, pressed in this order.
+    pub fn held(buttons: &[Button]) -> Self {
+        let state = Self::default();
+        buttons.iter().for_each(|&button| state.press(button));
+        state
+    }
+
+    /// Holds `button` down from now on.
+    pub fn press(&self, button: Button) {
+        self.press_at(button, Instant::now());
+    }
+
+    /// Holds `button` down as if it was pressed at `at`.
+    pub fn press_at(&self, button: Button, at: Instant) {
+        self.0.write().unwrap().pressed_at.insert(button, at);
+    }
+
+    /// Releases `button` as if it was released at `at`.
+    pub fn release_at(&self, button: Button, at: Instant) {
+        let mut buttons = self.0.write().unwrap();
+        buttons.pressed_at.remove(&button);
+        buttons.released_at.insert(button, at);
+    }
+
+    /// Turns `button` on, such as [`Button::CapsLock`].
+    pub fn toggle(&self, button: Button) {
+        self.0.write().unwrap().toggled.insert(button);
+    }
+
+    /// Updates the state with `event` as the hook does before dispatching it.
+    /// Key repeats keep the time of the first press.
+    pub fn record(&self, event: ButtonEvent) {
+        match event.action {
+            ButtonAction::Press => {
+                let mut buttons = self.0.write().unwrap();
+                buttons
+                    .pressed_at
+                    .entry(event.target)
+                    .or_insert_with(Instant::now);
+            }
+            ButtonAction::Release => self.release_at(event.target, Instant::now()),
+        }
+    }
+}
+
+#[cfg(any(test, feature = "bench"))]
+impl ButtonState for FakeButtonState {
+    fn is_pressed(&self, button: Button) -> bool {
+        self.0.read().unwrap().pressed_at.contains_key(&button)
+    }
+
+    fn is_released(&self, button: Button) -> bool {
+        !self.is_pressed(button)
+    }
+
+    fn is_toggled(&self, button: Button) -> bool {
+        self.0.read().unwrap().toggled.contains(&button)
+    }
+
+    fn released_at(&self, button: Button) -> Option<Instant> {
+        self.0.read().unwrap().released_at.get(&button).copied()
+    }
+
+    fn pressed_at(&self, button: Button) -> Option<Instant> {
+        self.0.read().unwrap().pressed_at.get(&button).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn presses_of_held_button_are_repeats() {
+        let event = |action| ButtonEvent::new(Button::MediaStop, action);
+        assert!(!record(&mut event(ButtonAction::Press)));
+        assert!(record(&mut event(ButtonAction::Press)));
+        assert!(record(&mut event(ButtonAction::Press)));
+        assert!(!record(&mut event(ButtonAction::Release)));
+        assert!(!record(&mut event(ButtonAction::Press)));
+        record(&mut event(ButtonAction::Release));
+    }
+
+    #[test]
+    fn releases_carry_hold_time() {
+        let event = |action| {
+            let mut event = ButtonEvent::new(Button::MediaPrevTrack, action);
+            record(&mut event);
+            event.held_for
+        };
+        assert_eq!(event(ButtonAction::Release), None);
+        assert_eq!(event(ButtonAction::Press), None);
+        thread::sleep(Duration::from_millis(20));
+        let held_for = event(ButtonAction::Release).unwrap();
+        assert!(held_for >= Duration::from_millis(20), "{held_for:?}");
+    }
 }
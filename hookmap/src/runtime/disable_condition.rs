@@ -0,0 +1,22 @@
+use std::fmt::{self, Debug};
+use std::sync::Arc;
+
+/// A condition under which the runtime passes events through without running any hook.
+#[derive(Clone)]
+pub(crate) struct DisableCondition(Arc<dyn Fn() -> bool + Send + Sync>);
+
+impl DisableCondition {
+    pub(crate) fn new(condition: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(condition))
+    }
+
+    pub(crate) fn is_satisfied(&self) -> bool {
+        self.0()
+    }
+}
+
+impl Debug for DisableCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DisableCondition").field(&"Fn").finish()
+    }
+}
@@ -1,6 +1,7 @@
 use hookmap_core::button::{Button, ButtonAction};
 use hookmap_core::event::{ButtonEvent, NativeEventOperation};
 
+use super::held_modifiers::HeldModifiers;
 use crate::macros::button_arg::ButtonArg;
 
 use std::sync::mpsc::{self, Receiver, SyncSender};
@@ -71,6 +72,7 @@ impl Debug for Callback {
 pub struct Filter {
     target: Option<Target>,
     action: Option<ButtonAction>,
+    ignore_repeat: bool,
     callback: Vec<Callback>,
 }
 
@@ -100,6 +102,7 @@ impl Filter {
     /// let filter = Filter::new().target(buttons!(A, B));
     /// ```
     ///
+    #[must_use]
     pub fn target(mut self, target: impl Into<ButtonArg>) -> Self {
         self.target = Some(Target::from(target.into()));
         self
@@ -115,11 +118,35 @@ impl Filter {
     /// let filter = Filter::new().action(ButtonAction::Press);
     /// ```
     ///
+    #[must_use]
     pub fn action(mut self, action: ButtonAction) -> Self {
         self.action = Some(action);
         self
     }
 
+    /// Ignores the key repeats, which are the presses of a button that is already pressed.
+    ///
+    /// Key repeats are detected from all the events the hook receives, so this works even
+    /// though an interceptor does not receive the events while it is not waiting for one.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    ///
+    /// let filter = Filter::new().action(ButtonAction::Press).ignore_repeat();
+    /// for event in Interceptor::dispatch(filter).iter().take(3) {
+    ///     println!("{:?} was pressed", event.target);
+    /// }
+    /// ```
+    ///
+    #[must_use]
+    pub fn ignore_repeat(mut self) -> Self {
+        self.ignore_repeat = true;
+        self
+    }
+
+    #[must_use]
     pub fn callback<F>(mut self, callback: F) -> Self
     where
         F: Fn(&ButtonEvent) -> bool + Send + Sync + 'static,
@@ -128,18 +155,20 @@ impl Filter {
         self
     }
 
-    fn filter(&self, event: &ButtonEvent) -> bool {
-        self.target
-            .as_ref()
-            .map_or(true, |target| target.filter(event.target))
-            && self.action.map_or(true, |action| action == event.action)
+    fn filter(&self, event: &ButtonEvent, is_repeat: bool) -> bool {
+        !(self.ignore_repeat && is_repeat)
+            && self
+                .target
+                .as_ref()
+                .is_none_or(|target| target.filter(event.target))
+            && self.action.is_none_or(|action| action == event.action)
             && self.callback.iter().all(|callback| callback.0(event))
     }
 }
 
 #[derive(Debug)]
 struct EventSender {
-    tx: SyncSender<ButtonEvent>,
+    tx: SyncSender<(ButtonEvent, HeldModifiers)>,
     filter: Arc<Filter>,
 }
 
@@ -154,7 +183,7 @@ impl EventBroker {
         &mut self,
         filter: Arc<Filter>,
         operation: NativeEventOperation,
-    ) -> Receiver<ButtonEvent> {
+    ) -> Receiver<(ButtonEvent, HeldModifiers)> {
         let (tx, rx) = mpsc::sync_channel(1);
         let event_sender = EventSender { tx, filter };
 
@@ -166,15 +195,27 @@ impl EventBroker {
         rx
     }
 
-    pub(super) fn publish(&mut self, event: ButtonEvent) -> NativeEventOperation {
+    pub(super) fn is_empty(&self) -> bool {
+        self.dispatch.is_empty() && self.block.is_empty()
+    }
+
+    /// Sends `event` with `modifiers` to the subscribers whose filter accepts it.
+    /// `is_repeat` is `true` if `event` is a key repeat, and `modifiers` are the modifier keys
+    /// held down when it was dispatched.
+    pub(super) fn publish(
+        &mut self,
+        event: ButtonEvent,
+        is_repeat: bool,
+        modifiers: HeldModifiers,
+    ) -> NativeEventOperation {
         if !self.block.is_empty() {
             let satisfied_index = self
                 .block
                 .iter()
-                .rposition(|EventSender { filter, .. }| filter.filter(&event));
+                .rposition(|EventSender { filter, .. }| filter.filter(&event, is_repeat));
             if let Some(index) = satisfied_index {
                 let EventSender { tx, .. } = self.block.remove(index);
-                tx.send(event).unwrap();
+                tx.send((event, modifiers)).unwrap();
                 return NativeEventOperation::Block;
             }
         }
@@ -182,8 +223,8 @@ impl EventBroker {
         // drain_filter (https://doc.rust-lang.org/std/vec/struct.Vec.html#method.drain_filter)
         let mut i = 0;
         while i < self.dispatch.len() {
-            if self.dispatch[i].filter.filter(&event) {
-                self.dispatch.remove(i).tx.send(event).unwrap();
+            if self.dispatch[i].filter.filter(&event, is_repeat) {
+                self.dispatch.remove(i).tx.send((event, modifiers)).unwrap();
             } else {
                 i += 1;
             }
@@ -197,14 +238,43 @@ impl EventBroker {
 mod tests {
     use super::*;
     use crate::buttons;
+    use crate::runtime::FakeButtonState;
     use hookmap_core::button::{Button, ButtonAction};
 
     fn create_button_event(target: Button, action: ButtonAction) -> ButtonEvent {
-        ButtonEvent {
-            target,
-            action,
-            injected: false,
-        }
+        ButtonEvent::new(target, action)
+    }
+
+    #[test]
+    fn broker_is_empty_after_all_events_are_sent() {
+        let mut broker = EventBroker::default();
+        assert!(broker.is_empty());
+
+        let filter = Arc::new(Filter::new().target(Button::A));
+        let _rx_block = broker.subscribe_once(Arc::clone(&filter), NativeEventOperation::Block);
+        let _rx_dispatch = broker.subscribe_once(filter, NativeEventOperation::Dispatch);
+        assert!(!broker.is_empty());
+
+        broker.publish(
+            create_button_event(Button::B, ButtonAction::Press),
+            false,
+            HeldModifiers::default(),
+        );
+        assert!(!broker.is_empty());
+
+        broker.publish(
+            create_button_event(Button::A, ButtonAction::Press),
+            false,
+            HeldModifiers::default(),
+        );
+        assert!(!broker.is_empty());
+
+        broker.publish(
+            create_button_event(Button::A, ButtonAction::Press),
+            false,
+            HeldModifiers::default(),
+        );
+        assert!(broker.is_empty());
     }
 
     #[test]
@@ -214,8 +284,8 @@ mod tests {
         let rx = broker.subscribe_once(filter, NativeEventOperation::Block);
 
         let event = create_button_event(Button::A, ButtonAction::Press);
-        broker.publish(event);
-        assert_eq!(event, rx.recv().unwrap());
+        broker.publish(event, false, HeldModifiers::default());
+        assert_eq!(event, rx.recv().unwrap().0);
     }
 
     #[test]
@@ -225,7 +295,7 @@ mod tests {
         let rx = broker.subscribe_once(Arc::new(filter), NativeEventOperation::Block);
 
         let event = create_button_event(Button::B, ButtonAction::Press);
-        broker.publish(event);
+        broker.publish(event, false, HeldModifiers::default());
         assert!(rx.try_recv().is_err());
     }
 
@@ -239,14 +309,14 @@ mod tests {
         let rx_block = broker.subscribe_once(filter, NativeEventOperation::Block);
 
         let event = create_button_event(Button::A, ButtonAction::Press);
-        broker.publish(event);
+        broker.publish(event, false, HeldModifiers::default());
 
         assert!(rx_dispatch.try_recv().is_err());
-        assert_eq!(rx_block.recv().unwrap(), event);
+        assert_eq!(rx_block.recv().unwrap().0, event);
 
         let event = create_button_event(Button::B, ButtonAction::Press);
-        broker.publish(event);
-        assert_eq!(rx_dispatch.recv().unwrap(), event);
+        broker.publish(event, false, HeldModifiers::default());
+        assert_eq!(rx_dispatch.recv().unwrap().0, event);
         assert!(rx_block.try_recv().is_err());
     }
 
@@ -259,15 +329,26 @@ mod tests {
         let rx2 = broker.subscribe_once(filter, NativeEventOperation::Dispatch);
 
         let event = create_button_event(Button::C, ButtonAction::Release);
-        broker.publish(event);
+        broker.publish(event, false, HeldModifiers::default());
+
+        assert_eq!(rx1.recv().unwrap().0, event);
+        assert_eq!(rx2.recv().unwrap().0, event);
+    }
+
+    #[test]
+    fn event_sender_sends_the_modifiers_with_the_event() {
+        let mut broker = EventBroker::default();
+        let rx = broker.subscribe_once(Arc::new(Filter::new()), NativeEventOperation::Dispatch);
 
-        assert_eq!(rx1.recv().unwrap(), event);
-        assert_eq!(rx2.recv().unwrap(), event);
+        let modifiers = HeldModifiers::capture(&FakeButtonState::held(&[Button::LCtrl]));
+        let event = create_button_event(Button::A, ButtonAction::Press);
+        broker.publish(event, false, modifiers);
+        assert_eq!(rx.recv().unwrap(), (event, modifiers));
     }
 
     fn test_filter(expect: bool, filter: &Filter, target: Button, action: ButtonAction) {
         let event = create_button_event(target, action);
-        assert_eq!(expect, filter.filter(&event));
+        assert_eq!(expect, filter.filter(&event, false));
     }
 
     #[test]
@@ -323,4 +404,31 @@ mod tests {
         test_filter(true, &filter, Button::A, ButtonAction::Press);
         test_filter(false, &filter, Button::A, ButtonAction::Release);
     }
+
+    #[test]
+    fn filtering_out_key_repeats() {
+        let mut broker = EventBroker::default();
+        let filter = Arc::new(Filter::new().target(Button::A).ignore_repeat());
+        let press = create_button_event(Button::A, ButtonAction::Press);
+
+        let rx = broker.subscribe_once(Arc::clone(&filter), NativeEventOperation::Block);
+        assert_eq!(
+            broker.publish(press, false, HeldModifiers::default()),
+            NativeEventOperation::Block
+        );
+        assert_eq!(rx.try_recv().map(|(event, _)| event), Ok(press));
+
+        let rx = broker.subscribe_once(Arc::clone(&filter), NativeEventOperation::Block);
+        for _ in 0..3 {
+            assert_eq!(
+                broker.publish(press, true, HeldModifiers::default()),
+                NativeEventOperation::Dispatch
+            );
+        }
+        assert!(rx.try_recv().is_err());
+
+        let release = create_button_event(Button::A, ButtonAction::Release);
+        broker.publish(release, false, HeldModifiers::default());
+        assert_eq!(rx.try_recv().map(|(event, _)| event), Ok(release));
+    }
 }
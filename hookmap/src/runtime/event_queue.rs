@@ -0,0 +1,357 @@
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+/// What to do with the callbacks of an event when the queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverflowPolicy {
+    /// Discards the oldest queued callbacks that are not queued with [`OverflowPolicy::Block`]
+    /// to make room. If there are none, the new ones are discarded.
+    DropOldest,
+
+    /// Discards the new callbacks.
+    DropNewest,
+
+    /// Waits until there is room. No event is lost, but while waiting, all keyboard and mouse
+    /// input in the system is delayed.
+    Block,
+}
+
+/// The queue of the callbacks waiting for a worker thread.
+///
+/// Callbacks of hotkeys are run on a pool of worker threads. When they cannot keep up with
+/// the input, the callbacks of new events are queued up to `capacity`, and then handled
+/// according to the [`OverflowPolicy`]. Remaps are not queued, since they are run on the
+/// thread that receives the events.
+///
+/// By default, the callbacks of cursor events are dropped oldest first, and those of the
+/// other events block so that no keystroke is lost.
+///
+/// # Examples
+///
+/// ```no_run
+/// use hookmap::hotkey::{EventQueue, OverflowPolicy};
+/// use hookmap::prelude::*;
+///
+/// let mut hotkey = Hotkey::new();
+/// hotkey.event_queue(
+///     EventQueue::new()
+///         .capacity(64)
+///         .cursor_overflow(OverflowPolicy::DropNewest),
+/// );
+/// hotkey.install();
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventQueue {
+    capacity: usize,
+    button_overflow: OverflowPolicy,
+    cursor_overflow: OverflowPolicy,
+}
+
+impl Default for EventQueue {
+    fn default() -> Self {
+        Self {
+            capacity: Self::DEFAULT_CAPACITY,
+            button_overflow: OverflowPolicy::Block,
+            cursor_overflow: OverflowPolicy::DropOldest,
+        }
+    }
+}
+
+impl EventQueue {
+    /// The default number of callbacks that can wait for a worker thread.
+    pub const DEFAULT_CAPACITY: usize = 1024;
+
+    /// The number of worker threads that run callbacks at the same time.
+    pub const WORKERS: usize = 32;
+
+    /// Creates a new instance of [`EventQueue`] with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of callbacks that can wait for a worker thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is `0`.
+    #[must_use]
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0);
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets the policy for the callbacks of button and wheel events.
+    #[must_use]
+    pub fn button_overflow(mut self, policy: OverflowPolicy) -> Self {
+        self.button_overflow = policy;
+        self
+    }
+
+    /// Sets the policy for the callbacks of cursor events.
+    #[must_use]
+    pub fn cursor_overflow(mut self, policy: OverflowPolicy) -> Self {
+        self.cursor_overflow = policy;
+        self
+    }
+
+    pub(super) fn button_policy(&self) -> OverflowPolicy {
+        self.button_overflow
+    }
+
+    pub(super) fn cursor_policy(&self) -> OverflowPolicy {
+        self.cursor_overflow
+    }
+
+    /// Creates a pool that runs the callbacks on up to `workers` threads.
+    /// With a single worker, the callbacks are run in the order they are pushed.
+    pub(super) fn pool(&self, workers: usize) -> WorkerPool {
+        WorkerPool::new(self.capacity, workers)
+    }
+}
+
+type Task = Box<dyn FnOnce() + Send>;
+
+struct Queued {
+    task: Task,
+    droppable: bool,
+}
+
+#[derive(Default)]
+struct State {
+    queue: VecDeque<Queued>,
+    workers: usize,
+    idle: usize,
+    is_closed: bool,
+}
+
+struct Shared {
+    capacity: usize,
+    max_workers: usize,
+    state: Mutex<State>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+impl Shared {
+    // Tasks are run without holding the lock, so the state is consistent even if a thread
+    // panicked while holding it.
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn work(&self) {
+        loop {
+            let mut state = self.lock();
+            state.idle += 1;
+            while state.queue.is_empty() && !state.is_closed {
+                state = self
+                    .not_empty
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+            state.idle -= 1;
+            let Some(Queued { task, .. }) = state.queue.pop_front() else {
+                state.workers -= 1;
+                return;
+            };
+            drop(state);
+            self.not_full.notify_one();
+            // A panicking callback only loses its own event, as it did on its own thread.
+            let _ = panic::catch_unwind(AssertUnwindSafe(task));
+        }
+    }
+}
+
+/// Runs tasks on worker threads that are spawned as needed.
+pub(super) struct WorkerPool(Arc<Shared>);
+
+impl std::fmt::Debug for WorkerPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WorkerPool")
+            .field("capacity", &self.0.capacity)
+            .field("max_workers", &self.0.max_workers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WorkerPool {
+    pub(super) fn new(capacity: usize, max_workers: usize) -> Self {
+        Self(Arc::new(Shared {
+            capacity,
+            max_workers,
+            state: Mutex::default(),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+        }))
+    }
+
+    /// Queues `task` and returns `false` if a task was dropped because the queue was full.
+    pub(super) fn push(
+        &self,
+        task: impl FnOnce() + Send + 'static,
+        policy: OverflowPolicy,
+    ) -> bool {
+        let shared = &self.0;
+        let mut state = shared.lock();
+        let mut is_lost = false;
+        if state.queue.len() >= shared.capacity {
+            match policy {
+                OverflowPolicy::DropOldest => {
+                    match state.queue.iter().position(|queued| queued.droppable) {
+                        Some(oldest) => drop(state.queue.remove(oldest)),
+                        None => return false,
+                    }
+                    is_lost = true;
+                }
+                OverflowPolicy::DropNewest => return false,
+                OverflowPolicy::Block => {
+                    while state.queue.len() >= shared.capacity {
+                        state = shared
+                            .not_full
+                            .wait(state)
+                            .unwrap_or_else(PoisonError::into_inner);
+                    }
+                }
+            }
+        }
+        state.queue.push_back(Queued {
+            task: Box::new(task),
+            droppable: policy != OverflowPolicy::Block,
+        });
+        if state.idle < state.queue.len() && state.workers < shared.max_workers {
+            state.workers += 1;
+            let shared = Arc::clone(shared);
+            thread::spawn(move || shared.work());
+        }
+        drop(state);
+        shared.not_empty.notify_one();
+        !is_lost
+    }
+}
+
+impl Drop for WorkerPool {
+    // The workers exit after running the queued tasks.
+    fn drop(&mut self) {
+        self.0.lock().is_closed = true;
+        self.0.not_empty.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::time::Duration;
+
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    /// Returns a pool whose only worker is running a task that waits for the returned sender.
+    fn busy_pool(capacity: usize) -> (WorkerPool, Sender<()>) {
+        let pool = WorkerPool::new(capacity, 1);
+        let (started_tx, started_rx) = mpsc::channel();
+        let (resume_tx, resume_rx) = mpsc::channel();
+        pool.push(
+            move || {
+                started_tx.send(()).unwrap();
+                resume_rx.recv().unwrap();
+            },
+            OverflowPolicy::Block,
+        );
+        started_rx.recv().unwrap();
+        (pool, resume_tx)
+    }
+
+    fn push_numbered(
+        pool: &WorkerPool,
+        numbers: impl IntoIterator<Item = u32>,
+        policy: OverflowPolicy,
+    ) -> (Vec<bool>, Receiver<u32>) {
+        let (tx, rx) = mpsc::channel();
+        let pushed = numbers
+            .into_iter()
+            .map(|n| {
+                let tx = tx.clone();
+                pool.push(move || tx.send(n).unwrap(), policy)
+            })
+            .collect();
+        (pushed, rx)
+    }
+
+    #[test]
+    fn drop_oldest_keeps_latest_tasks() {
+        let (pool, resume) = busy_pool(2);
+        let (pushed, rx) = push_numbered(&pool, 1..=4, OverflowPolicy::DropOldest);
+        assert_eq!(pushed, [true, true, false, false]);
+
+        resume.send(()).unwrap();
+        assert_eq!(rx.iter().take(2).collect::<Vec<_>>(), [3, 4]);
+        assert!(rx.recv_timeout(TIMEOUT).is_err());
+    }
+
+    #[test]
+    fn drop_newest_keeps_earliest_tasks() {
+        let (pool, resume) = busy_pool(2);
+        let (pushed, rx) = push_numbered(&pool, 1..=4, OverflowPolicy::DropNewest);
+        assert_eq!(pushed, [true, true, false, false]);
+
+        resume.send(()).unwrap();
+        assert_eq!(rx.iter().take(2).collect::<Vec<_>>(), [1, 2]);
+        assert!(rx.recv_timeout(TIMEOUT).is_err());
+    }
+
+    #[test]
+    fn drop_oldest_never_drops_blocking_tasks() {
+        let (pool, resume) = busy_pool(1);
+        let (_, blocking) = push_numbered(&pool, [1], OverflowPolicy::Block);
+        let (pushed, dropping) = push_numbered(&pool, [2], OverflowPolicy::DropOldest);
+        assert_eq!(pushed, [false]);
+
+        resume.send(()).unwrap();
+        assert_eq!(blocking.recv(), Ok(1));
+        assert!(dropping.recv_timeout(TIMEOUT).is_err());
+    }
+
+    #[test]
+    fn block_waits_for_room() {
+        let (pool, resume) = busy_pool(1);
+        let pool = Arc::new(pool);
+        let (tx, rx) = mpsc::channel();
+        let pusher = {
+            let pool = Arc::clone(&pool);
+            thread::spawn(move || {
+                for _ in 0..3 {
+                    pool.push(|| {}, OverflowPolicy::Block);
+                }
+                tx.send(()).unwrap();
+            })
+        };
+        assert!(rx.recv_timeout(TIMEOUT).is_err());
+
+        resume.send(()).unwrap();
+        assert_eq!(rx.recv(), Ok(()));
+        pusher.join().unwrap();
+    }
+
+    #[test]
+    fn all_tasks_run_in_order_with_a_single_worker() {
+        let pool = WorkerPool::new(EventQueue::DEFAULT_CAPACITY, 1);
+        let (pushed, rx) = push_numbered(&pool, 0..100, OverflowPolicy::Block);
+        assert!(pushed.into_iter().all(|pushed| pushed));
+        assert_eq!(
+            rx.iter().take(100).collect::<Vec<_>>(),
+            (0..100).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn panicking_task_does_not_stop_worker() {
+        let pool = WorkerPool::new(1, 1);
+        pool.push(|| panic!("callback panicked"), OverflowPolicy::Block);
+        let (_, rx) = push_numbered(&pool, [1], OverflowPolicy::Block);
+        assert_eq!(rx.recv(), Ok(1));
+    }
+}
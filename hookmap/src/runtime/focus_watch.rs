@@ -0,0 +1,62 @@
+use hookmap_core::window::Window;
+
+use std::fmt::{self, Debug};
+use std::sync::Mutex;
+
+/// Detects changes of the foreground window between events.
+pub(crate) struct FocusWatch(Mutex<Box<dyn FnMut() -> bool + Send>>);
+
+impl FocusWatch {
+    /// Watches the actual foreground window.
+    pub(crate) fn foreground() -> Self {
+        Self::new(Window::foreground)
+    }
+
+    /// Watches the window returned by `foreground`.
+    pub(crate) fn new<T>(foreground: impl Fn() -> T + Send + 'static) -> Self
+    where
+        T: PartialEq + Send + 'static,
+    {
+        let mut last = None;
+        Self(Mutex::new(Box::new(move || {
+            let current = foreground();
+            let is_changed = last.as_ref().is_some_and(|last| *last != current);
+            last = Some(current);
+            is_changed
+        })))
+    }
+
+    /// Returns `true` if the foreground window has changed since the last call.
+    /// The first call only records the foreground window.
+    pub(crate) fn is_changed(&self) -> bool {
+        (self.0.lock().unwrap())()
+    }
+}
+
+impl Debug for FocusWatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FocusWatch").field(&"Fn").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn changes_are_detected_once() {
+        let foreground = Arc::new(AtomicU32::new(1));
+        let watch = {
+            let foreground = Arc::clone(&foreground);
+            FocusWatch::new(move || foreground.load(Ordering::SeqCst))
+        };
+        assert!(!watch.is_changed());
+        assert!(!watch.is_changed());
+
+        foreground.store(2, Ordering::SeqCst);
+        assert!(watch.is_changed());
+        assert!(!watch.is_changed());
+    }
+}
@@ -0,0 +1,196 @@
+use hookmap_core::button::{Button, ButtonAction};
+use hookmap_core::event::ButtonEvent;
+
+use super::button_state::RealButtonState;
+use crate::hook::ButtonState;
+use crate::macros::sequence::MODIFIER_LIST;
+
+use std::cell::Cell;
+use std::iter;
+
+/// The modifier keys held down when an event was dispatched.
+///
+/// Get this with [`event_modifiers`] in a hotkey callback.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct HeldModifiers(u8);
+
+impl HeldModifiers {
+    pub(super) fn capture(state: &impl ButtonState) -> Self {
+        let bits = MODIFIER_LIST
+            .iter()
+            .enumerate()
+            .filter(|(_, &button)| state.is_pressed(button))
+            .fold(0, |bits, (i, _)| bits | 1 << i);
+        Self(bits)
+    }
+
+    /// Returns `true` if `button` was held down.
+    ///
+    /// For [`Button::Shift`], [`Button::Ctrl`], [`Button::Alt`] and [`Button::Super`],
+    /// returns `true` if either side was held down.
+    /// Returns `false` for buttons that are not modifier keys.
+    pub fn contains(&self, button: Button) -> bool {
+        let [left, right] = sides(button);
+        self.iter().any(|held| held == left || held == right)
+    }
+
+    /// Returns `true` if no modifier key was held down.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Iterates over the held modifier keys, such as [`Button::LCtrl`].
+    pub fn iter(&self) -> impl Iterator<Item = Button> + '_ {
+        MODIFIER_LIST
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.0 & 1 << i != 0)
+            .map(|(_, &button)| button)
+    }
+}
+
+/// Returns both sides of [`Button::Shift`], [`Button::Ctrl`], [`Button::Alt`] and
+/// [`Button::Super`], or `button` twice for the other buttons.
+pub(crate) fn sides(button: Button) -> [Button; 2] {
+    match button {
+        Button::Shift => [Button::LShift, Button::RShift],
+        Button::Ctrl => [Button::LCtrl, Button::RCtrl],
+        Button::Alt => [Button::LAlt, Button::RAlt],
+        Button::Super => [Button::LSuper, Button::RSuper],
+        _ => [button, button],
+    }
+}
+
+thread_local! {
+    static EVENT_MODIFIERS: Cell<HeldModifiers> = Cell::default();
+}
+
+/// Runs `f` with `modifiers` as the result of [`event_modifiers`] on the current thread.
+pub(super) fn with_event_modifiers<R>(modifiers: HeldModifiers, f: impl FnOnce() -> R) -> R {
+    let previous = EVENT_MODIFIERS.with(|cell| cell.replace(modifiers));
+    let result = f();
+    EVENT_MODIFIERS.with(|cell| cell.set(previous));
+    result
+}
+
+/// Sets `modifiers` as the result of [`event_modifiers`] on the current thread until it is
+/// set again.
+pub(super) fn set_event_modifiers(modifiers: HeldModifiers) {
+    EVENT_MODIFIERS.with(|cell| cell.set(modifiers));
+}
+
+/// Returns the modifier keys held down when the event being processed by the current
+/// callback was dispatched.
+///
+/// Unlike [`Button::is_pressed`], this is not affected by the input after the event,
+/// so it tells whether Ctrl was held with the key that fired the hotkey even if Ctrl has
+/// already been released. After an [`interceptor::Iter`] yields an event, this returns the
+/// modifier keys held down when that event was dispatched. Elsewhere, outside of hotkey
+/// callbacks for button, wheel and cursor events, this returns an empty set.
+///
+/// [`interceptor::Iter`]: crate::interceptor::Iter
+///
+/// # Examples
+///
+/// ```
+/// use hookmap::prelude::*;
+///
+/// let mut hotkey = Hotkey::new();
+/// hotkey
+///     .register(Context::default())
+///     .on_press(Button::F1, |_| {
+///         if hookmap::event_modifiers().contains(Button::Ctrl) {
+///             println!("Ctrl+F1");
+///         }
+///     });
+/// ```
+///
+pub fn event_modifiers() -> HeldModifiers {
+    EVENT_MODIFIERS.with(Cell::get)
+}
+
+/// Sends `event` again with the modifier keys held down when it was dispatched, e.g.
+/// Ctrl+Shift+A for A pressed while Ctrl and Shift were held down.
+///
+/// The modifier keys are taken from [`event_modifiers`], so this should be called in a
+/// hotkey callback. The modifier keys that are still held down are neither pressed nor
+/// released. The input is sent in a single batch that is not hooked, so it does not fire
+/// the hotkey again.
+///
+/// # Examples
+///
+/// ```
+/// use hookmap::prelude::*;
+///
+/// let mut hotkey = Hotkey::new();
+/// hotkey
+///     .register(Context::default())
+///     .on_press(Button::F1, |mut e: ButtonEvent| {
+///         e.target = Button::F2;
+///         hookmap::reemit(e);
+///     });
+/// ```
+///
+pub fn reemit(event: ButtonEvent) {
+    hookmap_core::send_batch(&reemitted(event, event_modifiers(), &RealButtonState));
+}
+
+fn reemitted(
+    event: ButtonEvent,
+    modifiers: HeldModifiers,
+    state: &impl ButtonState,
+) -> Vec<(Button, ButtonAction)> {
+    let released: Vec<_> = modifiers
+        .iter()
+        .filter(|&modifier| !state.is_pressed(modifier))
+        .collect();
+    let press = released.iter().map(|&m| (m, ButtonAction::Press));
+    let release = released.iter().rev().map(|&m| (m, ButtonAction::Release));
+    press
+        .chain(iter::once((event.target, event.action)))
+        .chain(release)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::runtime::FakeButtonState;
+
+    #[test]
+    fn reemitted_with_captured_modifiers() {
+        use ButtonAction::{Press, Release};
+
+        let modifiers =
+            HeldModifiers::capture(&FakeButtonState::held(&[Button::LCtrl, Button::LShift]));
+        let event = ButtonEvent::new(Button::A, Press);
+        assert_eq!(
+            reemitted(event, modifiers, &FakeButtonState::held(&[])),
+            [
+                (Button::LShift, Press),
+                (Button::LCtrl, Press),
+                (Button::A, Press),
+                (Button::LCtrl, Release),
+                (Button::LShift, Release),
+            ]
+        );
+        // Modifiers still held down are kept as they are.
+        assert_eq!(
+            reemitted(
+                event,
+                modifiers,
+                &FakeButtonState::held(&[Button::LCtrl, Button::LShift])
+            ),
+            [(Button::A, Press)]
+        );
+        assert_eq!(
+            reemitted(event, modifiers, &FakeButtonState::held(&[Button::LCtrl])),
+            [
+                (Button::LShift, Press),
+                (Button::A, Press),
+                (Button::LShift, Release),
+            ]
+        );
+    }
+}
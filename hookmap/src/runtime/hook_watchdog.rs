@@ -0,0 +1,148 @@
+use hookmap_core::event::InstallHookError;
+
+use std::fmt::{self, Debug};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+type Verify = Arc<dyn Fn() -> bool + Send + Sync>;
+type Reinstall = Arc<dyn Fn() -> Result<(), InstallHookError> + Send + Sync>;
+type OnReinstalled = Arc<dyn Fn() + Send + Sync>;
+
+/// Periodically checks that the hooks are alive, and reinstalls them if the OS removed them.
+#[derive(Clone)]
+pub(crate) struct HookWatchdog {
+    interval: Duration,
+    verify: Verify,
+    reinstall: Reinstall,
+    on_reinstalled: Option<OnReinstalled>,
+}
+
+impl HookWatchdog {
+    /// How long the last input may take to reach the hooks in [`hookmap_core::verify_hooks`].
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// Checks the actual hooks every `interval`.
+    pub(crate) fn new(interval: Duration, on_reinstalled: Option<OnReinstalled>) -> Self {
+        Self::with_hooks(
+            interval,
+            || hookmap_core::verify_hooks(Self::TIMEOUT),
+            hookmap_core::reinstall_hook,
+            on_reinstalled,
+        )
+    }
+
+    /// Checks the hooks with `verify` and reinstalls them with `reinstall`.
+    fn with_hooks(
+        interval: Duration,
+        verify: impl Fn() -> bool + Send + Sync + 'static,
+        reinstall: impl Fn() -> Result<(), InstallHookError> + Send + Sync + 'static,
+        on_reinstalled: Option<OnReinstalled>,
+    ) -> Self {
+        Self {
+            interval,
+            verify: Arc::new(verify),
+            reinstall: Arc::new(reinstall),
+            on_reinstalled,
+        }
+    }
+
+    /// Reinstalls the hooks if they are not alive. Returns `true` if they were reinstalled.
+    ///
+    /// Failing to reinstall is not reported, since the next check tries again.
+    fn check(&self) -> bool {
+        if (self.verify)() || (self.reinstall)().is_err() {
+            return false;
+        }
+        if let Some(on_reinstalled) = &self.on_reinstalled {
+            on_reinstalled();
+        }
+        true
+    }
+
+    /// Starts checking on a dedicated thread, so that the check does not wait for the thread
+    /// that receives the events. The checks stop when the returned sender is dropped.
+    pub(crate) fn spawn(&self) -> Sender<()> {
+        let (tx, rx) = mpsc::channel();
+        let watchdog = self.clone();
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = rx.recv_timeout(watchdog.interval) {
+                watchdog.check();
+            }
+        });
+        tx
+    }
+}
+
+impl Debug for HookWatchdog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HookWatchdog")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn dropped_hooks_are_reinstalled() {
+        // Stands in for the OS, which removed the hooks.
+        let is_alive = Arc::new(AtomicBool::new(false));
+        let reinstalls = Arc::new(AtomicU32::new(0));
+        let (tx, rx) = mpsc::channel();
+        let watchdog = {
+            let is_alive = Arc::clone(&is_alive);
+            let is_alive_ = Arc::clone(&is_alive);
+            let reinstalls = Arc::clone(&reinstalls);
+            let tx = std::sync::Mutex::new(tx);
+            HookWatchdog::with_hooks(
+                INTERVAL,
+                move || is_alive.load(Ordering::SeqCst),
+                move || {
+                    reinstalls.fetch_add(1, Ordering::SeqCst);
+                    is_alive_.store(true, Ordering::SeqCst);
+                    Ok(())
+                },
+                Some(Arc::new(move || tx.lock().unwrap().send(()).unwrap())),
+            )
+        };
+
+        let stop = watchdog.spawn();
+        rx.recv_timeout(Duration::from_secs(1)).unwrap();
+        // The hooks are alive after being reinstalled.
+        thread::sleep(INTERVAL * 5);
+        drop(stop);
+        assert_eq!(reinstalls.load(Ordering::SeqCst), 1);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn failed_reinstalls_are_retried_without_callback() {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let called = Arc::new(AtomicBool::new(false));
+        let watchdog = {
+            let attempts = Arc::clone(&attempts);
+            let called = Arc::clone(&called);
+            HookWatchdog::with_hooks(
+                INTERVAL,
+                || false,
+                move || {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    Err(InstallHookError::Os(5))
+                },
+                Some(Arc::new(move || called.store(true, Ordering::SeqCst))),
+            )
+        };
+
+        assert!(!watchdog.check());
+        assert!(!watchdog.check());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        assert!(!called.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,88 @@
+use hookmap_core::event::InstallHookError;
+
+use std::thread;
+use std::time::Duration;
+
+/// How many times installing the hooks is retried when the OS fails to install them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct InstallRetry {
+    retries: u32,
+    delay: Duration,
+}
+
+impl InstallRetry {
+    pub(crate) fn new(retries: u32, delay: Duration) -> Self {
+        Self { retries, delay }
+    }
+
+    /// Calls `install` until it succeeds or the retries run out, waiting `delay` before the
+    /// first retry and twice as long before each following one.
+    ///
+    /// [`InstallHookError::AlreadyInstalled`] is returned at once, since waiting does not
+    /// change it.
+    pub(crate) fn run<T>(
+        self,
+        mut install: impl FnMut() -> Result<T, InstallHookError>,
+    ) -> Result<T, InstallHookError> {
+        let mut delay = self.delay;
+        for _ in 0..self.retries {
+            match install() {
+                Err(InstallHookError::Os(_)) => {
+                    thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                }
+                result => return result,
+            }
+        }
+        install()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    const DELAY: Duration = Duration::from_millis(10);
+
+    fn failing(failures: u32) -> impl FnMut() -> Result<u32, InstallHookError> {
+        let mut attempts = 0;
+        move || {
+            attempts += 1;
+            if attempts <= failures {
+                Err(InstallHookError::Os(5))
+            } else {
+                Ok(attempts)
+            }
+        }
+    }
+
+    #[test]
+    fn install_is_retried_with_backoff() {
+        let started = Instant::now();
+        let result = InstallRetry::new(3, DELAY).run(failing(2));
+        assert_eq!(result, Ok(3));
+        // Waits `DELAY` and then twice as long.
+        assert!(started.elapsed() >= DELAY * 3);
+
+        assert_eq!(
+            InstallRetry::new(2, DELAY).run(failing(3)),
+            Err(InstallHookError::Os(5))
+        );
+        assert_eq!(
+            InstallRetry::default().run(failing(1)),
+            Err(InstallHookError::Os(5))
+        );
+    }
+
+    #[test]
+    fn already_installed_is_not_retried() {
+        let mut attempts = 0;
+        let result = InstallRetry::new(3, DELAY).run(|| -> Result<(), _> {
+            attempts += 1;
+            Err(InstallHookError::AlreadyInstalled)
+        });
+        assert_eq!(result, Err(InstallHookError::AlreadyInstalled));
+        assert_eq!(attempts, 1);
+    }
+}
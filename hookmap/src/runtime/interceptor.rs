@@ -2,19 +2,63 @@
 
 pub use super::event_broker::Filter;
 
+use hookmap_core::button::Button;
 use hookmap_core::event::{ButtonEvent, NativeEventOperation};
 
 use super::event_broker::EventBroker;
+use super::held_modifiers::{self, sides, HeldModifiers};
+use crate::hook::ButtonState;
+use crate::macros::sequence::MODIFIER_LIST;
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
 use std::sync::Arc;
-use std::sync::Mutex;
+use std::sync::{Mutex, MutexGuard, PoisonError};
 
 use once_cell::sync::Lazy;
 
 static BROKER: Lazy<Mutex<EventBroker>> = Lazy::new(Mutex::default);
 
-pub(super) fn publish_event(event: ButtonEvent) -> NativeEventOperation {
-    BROKER.lock().unwrap().publish(event)
+// Whether any interceptor is waiting for an event.
+// This is read on the hook thread without locking `BROKER`.
+static IS_SUBSCRIBED: AtomicBool = AtomicBool::new(false);
+
+// A filter callback may panic while the broker is locked, but the broker is still consistent
+// since subscriptions are only added or removed as a whole.
+fn lock_broker() -> MutexGuard<'static, EventBroker> {
+    BROKER.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Sends `event` to the waiting interceptors with the modifier keys held down in `state`.
+pub(crate) fn publish_event(
+    event: ButtonEvent,
+    is_repeat: bool,
+    state: &impl ButtonState,
+) -> NativeEventOperation {
+    // Most events are published while no interceptor is waiting,
+    // so `BROKER` is not locked in that case.
+    if !is_subscribed() {
+        return NativeEventOperation::Dispatch;
+    }
+    let modifiers = HeldModifiers::capture(state);
+    let mut broker = lock_broker();
+    let operation = broker.publish(event, is_repeat, modifiers);
+    IS_SUBSCRIBED.store(!broker.is_empty(), Ordering::SeqCst);
+    operation
+}
+
+pub(super) fn is_subscribed() -> bool {
+    IS_SUBSCRIBED.load(Ordering::SeqCst)
+}
+
+pub(crate) fn subscribe_once(
+    filter: &Arc<Filter>,
+    native_event_operation: NativeEventOperation,
+) -> Receiver<(ButtonEvent, HeldModifiers)> {
+    let mut broker = lock_broker();
+    let rx = broker.subscribe_once(Arc::clone(filter), native_event_operation);
+    IS_SUBSCRIBED.store(true, Ordering::SeqCst);
+    rx
 }
 
 /// Set the hook that receives input events;
@@ -81,12 +125,9 @@ impl Interceptor {
     /// println!("{:?}", event);
     /// ```
     pub fn get(&self) -> ButtonEvent {
-        let rx = BROKER
-            .lock()
-            .unwrap()
-            .subscribe_once(Arc::clone(&self.filter), self.native_event_operation);
+        let rx = subscribe_once(&self.filter, self.native_event_operation);
 
-        rx.recv().unwrap()
+        rx.recv().unwrap().0
     }
 
     /// Captures events with an iterator.
@@ -111,6 +152,12 @@ impl Interceptor {
     }
 }
 
+/// An iterator over the captured events, created by [`Interceptor::iter`].
+///
+/// After an event is yielded, [`event_modifiers`] returns the modifier keys held down when
+/// it was dispatched.
+///
+/// [`event_modifiers`]: crate::event_modifiers
 pub struct Iter {
     filter: Arc<Filter>,
     native_event_operation: NativeEventOperation,
@@ -120,11 +167,261 @@ impl Iterator for Iter {
     type Item = ButtonEvent;
 
     fn next(&mut self) -> Option<ButtonEvent> {
-        let rx = BROKER
-            .lock()
-            .unwrap()
-            .subscribe_once(Arc::clone(&self.filter), self.native_event_operation);
+        let rx = subscribe_once(&self.filter, self.native_event_operation);
+
+        let (event, modifiers) = rx.recv().ok()?;
+        held_modifiers::set_event_modifiers(modifiers);
+        Some(event)
+    }
+}
+
+/// Adds [`while_held`] and [`without`] to iterators over [`ButtonEvent`]s,
+/// such as [`Interceptor::iter`].
+///
+/// [`while_held`]: ModifierFilterExt::while_held
+/// [`without`]: ModifierFilterExt::without
+pub trait ModifierFilterExt: Iterator<Item = ButtonEvent> + Sized {
+    /// Yields only the events that occur while `modifier` is held down.
+    ///
+    /// Whether `modifier` is held down is taken from [`event_modifiers`] after each event
+    /// is pulled from this iterator, which [`Interceptor::iter`] sets to the modifier keys
+    /// held down when the event was dispatched. So the events of `modifier` need not reach
+    /// this iterator, and they are not yielded when they do. [`Button::Shift`],
+    /// [`Button::Ctrl`], [`Button::Alt`] and [`Button::Super`] match either side.
+    ///
+    /// [`event_modifiers`]: crate::event_modifiers
+    ///
+    /// # Panics
+    ///
+    /// Panics if `modifier` is not a modifier key.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hookmap::prelude::*;
+    ///
+    /// let keys: Vec<Button> = Interceptor::dispatch(Filter::new())
+    ///     .iter()
+    ///     .while_held(Button::Ctrl)
+    ///     .without(Button::Shift)
+    ///     .filter(|e| e.action == ButtonAction::Press)
+    ///     .take(3)
+    ///     .map(|e| e.target)
+    ///     .collect();
+    /// ```
+    ///
+    fn while_held(self, modifier: Button) -> ModifierFilter<Self> {
+        ModifierFilter::new(self).while_held(modifier)
+    }
+
+    /// Yields only the events that occur while `modifier` is not held down.
+    /// See [`while_held`](ModifierFilterExt::while_held) for how the state is taken.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `modifier` is not a modifier key.
+    fn without(self, modifier: Button) -> ModifierFilter<Self> {
+        ModifierFilter::new(self).without(modifier)
+    }
+}
+
+impl<I: Iterator<Item = ButtonEvent>> ModifierFilterExt for I {}
+
+/// An iterator that yields events only while its modifier conditions hold.
+///
+/// Created by [`ModifierFilterExt::while_held`] and [`ModifierFilterExt::without`].
+/// Chained calls add conditions to the same iterator, so every condition is checked
+/// against the same state regardless of the order of the calls.
+#[derive(Debug, Clone)]
+pub struct ModifierFilter<I> {
+    iter: I,
+    // Pairs of a modifier and whether it must be held down.
+    conditions: Vec<(Button, bool)>,
+}
+
+impl<I: Iterator<Item = ButtonEvent>> ModifierFilter<I> {
+    fn new(iter: I) -> Self {
+        Self {
+            iter,
+            conditions: Vec::new(),
+        }
+    }
+
+    /// Also requires `modifier` to be held down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `modifier` is not a modifier key.
+    #[must_use]
+    pub fn while_held(self, modifier: Button) -> Self {
+        self.condition(modifier, true)
+    }
+
+    /// Also requires `modifier` not to be held down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `modifier` is not a modifier key.
+    #[must_use]
+    pub fn without(self, modifier: Button) -> Self {
+        self.condition(modifier, false)
+    }
+
+    fn condition(mut self, modifier: Button, held: bool) -> Self {
+        assert!(
+            MODIFIER_LIST.contains(&sides(modifier)[0]),
+            "{modifier:?} is not a modifier key"
+        );
+        self.conditions.push((modifier, held));
+        self
+    }
+
+    fn is_modifier(&self, button: Button) -> bool {
+        self.conditions
+            .iter()
+            .any(|&(modifier, _)| sides(modifier).contains(&button))
+    }
+
+    fn is_satisfied(&self, modifiers: HeldModifiers) -> bool {
+        self.conditions
+            .iter()
+            .all(|&(modifier, held)| modifiers.contains(modifier) == held)
+    }
+}
+
+impl<I: Iterator<Item = ButtonEvent>> Iterator for ModifierFilter<I> {
+    type Item = ButtonEvent;
+
+    fn next(&mut self) -> Option<ButtonEvent> {
+        loop {
+            let event = self.iter.next()?;
+            if !self.is_modifier(event.target)
+                && self.is_satisfied(held_modifiers::event_modifiers())
+            {
+                return Some(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::FakeButtonState;
+    use hookmap_core::button::ButtonAction;
+
+    fn event(target: Button, action: ButtonAction) -> ButtonEvent {
+        ButtonEvent::new(target, action)
+    }
+
+    fn press(target: Button) -> ButtonEvent {
+        event(target, ButtonAction::Press)
+    }
+
+    fn release(target: Button) -> ButtonEvent {
+        event(target, ButtonAction::Release)
+    }
+
+    // Sets `event_modifiers` for each event as `Iter` does.
+    fn dispatched<const N: usize>(events: [ButtonEvent; N]) -> impl Iterator<Item = ButtonEvent> {
+        let state = FakeButtonState::held(&[]);
+        events.into_iter().inspect(move |&event| {
+            state.record(event);
+            held_modifiers::set_event_modifiers(HeldModifiers::capture(&state));
+        })
+    }
+
+    fn targets(events: impl Iterator<Item = ButtonEvent>) -> Vec<Button> {
+        events
+            .filter(|e| e.action == ButtonAction::Press)
+            .map(|e| e.target)
+            .collect()
+    }
+
+    #[test]
+    fn while_held_yields_events_only_while_modifier_is_held() {
+        let events = [
+            press(Button::A),
+            press(Button::LShift),
+            press(Button::B),
+            release(Button::LShift),
+            press(Button::C),
+        ];
+        assert_eq!(
+            targets(dispatched(events).while_held(Button::LShift)),
+            [Button::B]
+        );
+        assert_eq!(
+            targets(dispatched(events).without(Button::LShift)),
+            [Button::A, Button::C]
+        );
+    }
+
+    #[test]
+    fn either_side_matches_generic_modifier() {
+        let events = [
+            press(Button::RCtrl),
+            press(Button::A),
+            press(Button::LCtrl),
+            release(Button::RCtrl),
+            press(Button::B),
+            release(Button::LCtrl),
+            press(Button::C),
+        ];
+        assert_eq!(
+            targets(dispatched(events).while_held(Button::Ctrl)),
+            [Button::A, Button::B]
+        );
+    }
+
+    #[test]
+    fn chained_conditions_track_every_modifier() {
+        let events = [
+            press(Button::LShift),
+            press(Button::A),
+            press(Button::LCtrl),
+            press(Button::B),
+            release(Button::LShift),
+            press(Button::C),
+            release(Button::LCtrl),
+            press(Button::D),
+        ];
+        let expected = [Button::C];
+        assert_eq!(
+            targets(
+                dispatched(events)
+                    .while_held(Button::LCtrl)
+                    .without(Button::LShift)
+            ),
+            expected
+        );
+        assert_eq!(
+            targets(
+                dispatched(events)
+                    .without(Button::LShift)
+                    .while_held(Button::LCtrl)
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn modifiers_are_taken_from_the_dispatch_time_state() {
+        let events = [
+            press(Button::LShift),
+            press(Button::A),
+            release(Button::LShift),
+            press(Button::B),
+        ];
+        // The events of the modifier are not received, e.g. while the interceptor was not
+        // waiting for an event.
+        let received = dispatched(events).filter(|e| e.target != Button::LShift);
+        assert_eq!(targets(received.while_held(Button::Shift)), [Button::A]);
+    }
 
-        rx.recv().ok()
+    #[test]
+    #[should_panic(expected = "is not a modifier key")]
+    fn non_modifier_panics() {
+        let _ = dispatched([]).while_held(Button::A);
     }
 }
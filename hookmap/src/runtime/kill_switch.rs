@@ -0,0 +1,36 @@
+use hookmap_core::button::{Button, ButtonAction};
+use hookmap_core::event::ButtonEvent;
+
+use crate::button_set::ButtonSet;
+use crate::hook::ButtonState;
+
+/// A combination of buttons that stops the runtime.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct KillSwitch(ButtonSet);
+
+impl KillSwitch {
+    pub(crate) fn new(buttons: impl IntoIterator<Item = Button>) -> Self {
+        let buttons: ButtonSet = buttons.into_iter().collect();
+        assert!(
+            buttons.iter().next().is_some(),
+            "The kill switch requires at least one button."
+        );
+        KillSwitch(buttons)
+    }
+
+    pub(crate) fn buttons(&self) -> ButtonSet {
+        self.0
+    }
+
+    /// Returns `true` if `event` presses the last button of the combination.
+    /// Events generated by this program never trigger the kill switch.
+    pub(crate) fn is_triggered(&self, event: ButtonEvent, state: &impl ButtonState) -> bool {
+        event.action == ButtonAction::Press
+            && !event.injected
+            && self.0.contains(event.target)
+            && self
+                .0
+                .iter()
+                .all(|button| button == event.target || state.is_pressed(button))
+    }
+}
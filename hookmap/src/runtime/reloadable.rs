@@ -0,0 +1,45 @@
+use crate::button_set::ButtonSet;
+use crate::hook::HookStorage;
+
+use std::sync::{PoisonError, RwLock, RwLockReadGuard};
+
+/// The hooks and the buttons they handle, which are replaced together.
+#[derive(Debug, Default)]
+pub(crate) struct Loaded<T> {
+    pub(crate) storage: T,
+    pub(crate) handled_buttons: ButtonSet,
+}
+
+/// The hooks of a runtime, which can be replaced while it is running.
+///
+/// The hooks for each event are fetched while holding the read lock, so they are either those
+/// before a replacement or those after it, never a mix of both.
+#[derive(Debug, Default)]
+pub(crate) struct ReloadableStorage<T>(RwLock<Loaded<T>>);
+
+impl<T: HookStorage> ReloadableStorage<T> {
+    #[cfg(test)]
+    pub(crate) fn new(storage: T) -> Self {
+        Self(RwLock::new(Self::load(storage)))
+    }
+
+    fn load(storage: T) -> Loaded<T> {
+        Loaded {
+            handled_buttons: storage.handled_buttons(),
+            storage,
+        }
+    }
+
+    // Hooks are not run while holding the lock, so the hooks are consistent even if
+    // a thread panicked while holding it.
+    pub(crate) fn read(&self) -> RwLockReadGuard<'_, Loaded<T>> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Replaces the hooks, waiting for the events being dispatched. Returns the old hooks.
+    pub(crate) fn replace(&self, storage: T) -> T {
+        let loaded = Self::load(storage);
+        let mut current = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        std::mem::replace(&mut *current, loaded).storage
+    }
+}
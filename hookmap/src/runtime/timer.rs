@@ -0,0 +1,275 @@
+use super::event_queue::WorkerPool;
+use super::{EventQueue, OverflowPolicy};
+
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Instant;
+
+type Task = Box<dyn FnOnce() + Send>;
+
+// Tasks with the same deadline are run in the order they were scheduled.
+type TaskKey = (Instant, u64);
+
+#[derive(Default)]
+struct Queue {
+    tasks: BTreeMap<TaskKey, Task>,
+    next_id: u64,
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    queue: Mutex<Queue>,
+    wake: Condvar,
+}
+
+impl Shared {
+    fn run(&self) {
+        let mut queue = self.queue.lock().unwrap();
+        while !queue.closed {
+            let now = Instant::now();
+            match queue.tasks.keys().next().copied() {
+                Some(key) if key.0 <= now => {
+                    let task = queue.tasks.remove(&key).unwrap();
+                    drop(queue);
+                    // A panicking task must not stop the tasks of the other timing features.
+                    let _ = panic::catch_unwind(AssertUnwindSafe(task));
+                    queue = self.queue.lock().unwrap();
+                }
+                Some((deadline, _)) => {
+                    queue = self.wake.wait_timeout(queue, deadline - now).unwrap().0;
+                }
+                None => queue = self.wake.wait(queue).unwrap(),
+            }
+        }
+    }
+}
+
+/// Runs tasks at their deadlines on a single thread.
+///
+/// Timing features share one thread instead of spawning a sleeping thread for each event.
+/// Tasks should return quickly since they delay the following tasks.
+pub(crate) struct Timer {
+    shared: Arc<Shared>,
+}
+
+impl Timer {
+    pub(crate) fn new() -> Self {
+        let shared = Arc::<Shared>::default();
+        let shared_ = Arc::clone(&shared);
+        thread::spawn(move || shared_.run());
+        Self { shared }
+    }
+
+    /// Schedules `task` to be run at `deadline`, or as soon as possible if it has passed.
+    pub(crate) fn schedule(
+        &self,
+        deadline: Instant,
+        task: impl FnOnce() + Send + 'static,
+    ) -> ScheduledTask {
+        let mut queue = self.shared.queue.lock().unwrap();
+        let key = (deadline, queue.next_id);
+        queue.next_id += 1;
+        queue.tasks.insert(key, Box::new(task));
+        self.shared.wake.notify_one();
+        ScheduledTask {
+            shared: Arc::clone(&self.shared),
+            key,
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().closed = true;
+        self.shared.wake.notify_one();
+    }
+}
+
+/// A task scheduled by [`Timer::schedule`].
+pub(crate) struct ScheduledTask {
+    shared: Arc<Shared>,
+    key: TaskKey,
+}
+
+impl ScheduledTask {
+    /// Cancels the task. Returns `false` if the task has already been run or cancelled.
+    pub(crate) fn cancel(&self) -> bool {
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.tasks.remove(&self.key).is_some()
+    }
+}
+
+static TIMER: Lazy<Timer> = Lazy::new(Timer::new);
+
+/// Schedules `task` on the timer shared by all timing features.
+pub(crate) fn schedule(deadline: Instant, task: impl FnOnce() + Send + 'static) -> ScheduledTask {
+    TIMER.schedule(deadline, task)
+}
+
+static CALLBACKS: Lazy<WorkerPool> = Lazy::new(|| EventQueue::default().pool(EventQueue::WORKERS));
+
+/// Runs the callback of a timing feature on a worker thread, so that a slow callback does not
+/// delay the tasks of the other timing features.
+pub(crate) fn spawn_callback(callback: impl FnOnce() + Send + 'static) {
+    CALLBACKS.push(callback, OverflowPolicy::Block);
+}
+
+/// The task of a timing feature that is rescheduled or cancelled as its state changes.
+///
+/// This is meant to be kept in the mutex guarding the state of the feature. The task may
+/// already be running, waiting for that mutex, when it is rescheduled or cancelled, so each
+/// scheduling gets a new generation, and the task must call [`PendingTask::take`] with its
+/// generation to tell whether it is still the pending one.
+#[derive(Default)]
+pub(crate) struct PendingTask {
+    generation: u64,
+    task: Option<ScheduledTask>,
+}
+
+impl PendingTask {
+    /// Cancels the pending task, and schedules `task` to be run with its generation at
+    /// `deadline` on the shared timer.
+    pub(crate) fn schedule(&mut self, deadline: Instant, task: impl FnOnce(u64) + Send + 'static) {
+        self.cancel();
+        let generation = self.generation;
+        self.task = Some(schedule(deadline, move || task(generation)));
+    }
+
+    /// Cancels the pending task. Returns `true` if there was one.
+    pub(crate) fn cancel(&mut self) -> bool {
+        self.generation += 1;
+        match self.task.take() {
+            Some(task) => {
+                task.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns `true` if the task of `generation` is the pending one, which is then no longer
+    /// pending.
+    pub(crate) fn take(&mut self, generation: u64) -> bool {
+        generation == self.generation && self.task.take().is_some()
+    }
+}
+
+/// Returns a callback for the output of a timing feature, and the receiver of the values it
+/// is called with.
+#[cfg(test)]
+pub(crate) fn recorder<T: Send + 'static>() -> (
+    impl Fn(T) + Send + Sync + 'static,
+    std::sync::mpsc::Receiver<T>,
+) {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let tx = Mutex::new(tx);
+    (move |value| tx.lock().unwrap().send(value).unwrap(), rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn tasks_run_in_deadline_order() {
+        let timer = Timer::new();
+        let (tx, rx) = mpsc::channel();
+        let now = Instant::now();
+        for (i, delay) in [30, 10, 20, 10].into_iter().enumerate() {
+            let tx = tx.clone();
+            timer.schedule(now + Duration::from_millis(delay), move || {
+                tx.send(i).unwrap();
+            });
+        }
+        let order: Vec<_> = rx.iter().take(4).collect();
+        assert_eq!(order, [1, 3, 2, 0]);
+    }
+
+    #[test]
+    fn tasks_run_after_a_panicking_task() {
+        let timer = Timer::new();
+        let (tx, rx) = mpsc::channel();
+        let now = Instant::now();
+        timer.schedule(now, || panic!("panicking task"));
+        timer.schedule(now + Duration::from_millis(10), move || {
+            tx.send(()).unwrap();
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok(()));
+    }
+
+    #[test]
+    fn cancelled_tasks_are_not_run() {
+        let timer = Timer::new();
+        let (tx, rx) = mpsc::channel();
+        let now = Instant::now();
+        let tx_ = tx.clone();
+        let cancelled = timer.schedule(now + Duration::from_millis(20), move || {
+            tx_.send("cancelled").unwrap();
+        });
+        let kept = timer.schedule(now + Duration::from_millis(40), move || {
+            tx.send("kept").unwrap();
+        });
+        assert!(cancelled.cancel());
+        assert!(!cancelled.cancel());
+
+        assert_eq!(rx.recv(), Ok("kept"));
+        assert!(rx.recv().is_err());
+        assert!(!kept.cancel());
+    }
+
+    #[test]
+    fn slow_callbacks_do_not_delay_the_timer() {
+        let timer = Timer::new();
+        let (resume_tx, resume_rx) = mpsc::channel::<()>();
+        let resume_rx = Mutex::new(resume_rx);
+        let (tx, rx) = mpsc::channel();
+        let now = Instant::now();
+        timer.schedule(now, move || {
+            spawn_callback(move || {
+                let _ = resume_rx.lock().unwrap().recv();
+            })
+        });
+        timer.schedule(now + Duration::from_millis(10), move || {
+            tx.send(()).unwrap();
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok(()));
+        drop(resume_tx);
+    }
+
+    #[test]
+    fn only_the_latest_pending_task_is_taken() {
+        let pending = Arc::new(Mutex::new(PendingTask::default()));
+        let (output, rx) = recorder();
+        let output = Arc::new(output);
+        let task = |id| {
+            let pending = Arc::clone(&pending);
+            let output = Arc::clone(&output);
+            move |generation| output((id, pending.lock().unwrap().take(generation)))
+        };
+        let now = Instant::now();
+
+        // A rescheduled task is cancelled.
+        let mut guard = pending.lock().unwrap();
+        guard.schedule(now + Duration::from_millis(20), task(1));
+        guard.schedule(now + Duration::from_millis(20), task(2));
+        drop(guard);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok((2, true)));
+        assert!(!pending.lock().unwrap().cancel());
+
+        // A task rescheduled after the timer has started running it is not taken.
+        let mut guard = pending.lock().unwrap();
+        guard.schedule(Instant::now(), task(3));
+        thread::sleep(Duration::from_millis(20));
+        guard.schedule(Instant::now(), task(4));
+        drop(guard);
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok((3, false)));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(1)), Ok((4, true)));
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+    }
+}
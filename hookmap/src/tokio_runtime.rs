@@ -0,0 +1,191 @@
+//! Delivers events to async tasks on a [Tokio] runtime.
+//!
+//! The hook of the OS must be installed on a thread that runs a message loop, so
+//! [`TokioRuntime::spawn_install`] installs it on a dedicated thread. The events are sent from
+//! that thread to the tasks of the Tokio runtime with [`tokio::sync::mpsc`].
+//!
+//! # Thread boundary
+//!
+//! The callbacks created by [`TokioRuntime::process`] run on the Tokio runtime, after the
+//! event has been handled on the hook thread. Therefore:
+//!
+//! * Whether the event is blocked is decided by the registration, not by the callback.
+//!   For example, [`Registrar::on_press`] blocks the event regardless of how long the
+//!   callback takes.
+//! * [`event_modifiers`] and other thread-local state of the hook thread are not available
+//!   in the callback. Copy them out in a synchronous callback if needed.
+//! * Each callback handles the events one at a time, in the order the [`Process`] receives
+//!   them. Spawn a task in the callback to handle them concurrently. The [`Process`] runs on
+//!   a worker thread like other callbacks, and the workers may swap the events, so register
+//!   it with [`Registrar::on_press_sync`] or [`Registrar::on_release_sync`] to receive the
+//!   events in the order they occurred.
+//!
+//! [Tokio]: https://tokio.rs
+//! [`Registrar::on_press`]: crate::hotkey::Registrar::on_press
+//! [`Registrar::on_press_sync`]: crate::hotkey::Registrar::on_press_sync
+//! [`Registrar::on_release_sync`]: crate::hotkey::Registrar::on_release_sync
+//! [`event_modifiers`]: crate::event_modifiers
+
+use crate::device::InstallHookError;
+use crate::hotkey::{Hotkey, Process};
+
+use std::future::Future;
+use std::thread::{self, JoinHandle};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+
+/// Runs async callbacks of hotkeys on a Tokio runtime.
+///
+/// # Examples
+///
+/// ```no_run
+/// use hookmap::prelude::*;
+/// use hookmap::tokio_runtime::TokioRuntime;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let runtime = TokioRuntime::current();
+///     let mut hotkey = Hotkey::new();
+///     hotkey
+///         .register(Context::default())
+///         .on_press(
+///             Button::A,
+///             runtime.process(|e: ButtonEvent| async move {
+///                 tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+///                 println!("{:?} was pressed a second ago", e.target);
+///             }),
+///         );
+///     runtime.spawn_install(hotkey);
+///     std::future::pending::<()>().await;
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct TokioRuntime {
+    handle: Handle,
+}
+
+impl TokioRuntime {
+    /// Creates a [`TokioRuntime`] that runs the callbacks on the runtime of `handle`.
+    pub fn new(handle: Handle) -> Self {
+        Self { handle }
+    }
+
+    /// Creates a [`TokioRuntime`] that runs the callbacks on the current Tokio runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is not called within a Tokio runtime.
+    pub fn current() -> Self {
+        Self::new(Handle::current())
+    }
+
+    /// Creates a [`Process`] that runs the future returned by `callback` on the Tokio runtime.
+    ///
+    /// The [`Process`] only sends the event to the task, so it returns immediately on the
+    /// hook thread.
+    pub fn process<E, F, Fut>(&self, callback: F) -> Process<E>
+    where
+        E: Send + 'static,
+        F: Fn(E) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        self.handle.spawn(async move {
+            while let Some(event) = rx.recv().await {
+                callback(event).await;
+            }
+        });
+        Process::from(move |event| {
+            // The task has stopped only if the Tokio runtime has shut down.
+            let _ = tx.send(event);
+        })
+    }
+
+    /// Installs `hotkey` on a dedicated thread.
+    ///
+    /// The returned handle yields the result of [`Hotkey::try_install`].
+    pub fn spawn_install(&self, hotkey: Hotkey) -> JoinHandle<Result<(), InstallHookError>> {
+        thread::spawn(move || hotkey.try_install())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{Button, ButtonAction, ButtonEvent};
+    use crate::hotkey::Context;
+    use hookmap_core::event::Event;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn callbacks_run_on_tokio_in_order() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let runtime = TokioRuntime::current();
+        let mut hotkey = Hotkey::new();
+        hotkey.register(Context::default()).on_press(
+            Button::F20,
+            runtime.process(move |e: ButtonEvent| {
+                let tx = tx.clone();
+                async move {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    tx.send(e.action).unwrap();
+                }
+            }),
+        );
+
+        // Stands in for the hook thread, which dispatches the events of the OS.
+        let events = [
+            ButtonAction::Press,
+            ButtonAction::Release,
+            ButtonAction::Press,
+        ]
+        .map(|action| Event::Button(ButtonEvent::new(Button::F20, action)));
+        thread::spawn(move || hotkey.dispatch_all(events))
+            .join()
+            .unwrap();
+
+        assert_eq!(rx.recv().await, Some(ButtonAction::Press));
+        assert_eq!(rx.recv().await, Some(ButtonAction::Press));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn sync_registrations_keep_the_order_of_events() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let runtime = TokioRuntime::current();
+        let process = runtime.process(move |e: ButtonEvent| {
+            let tx = tx.clone();
+            async move { tx.send((e.target, e.action)).unwrap() }
+        });
+        let mut hotkey = Hotkey::new();
+        hotkey
+            .register(Context::default())
+            .on_press_sync(Button::F20, process.clone())
+            .on_release_sync(Button::F20, process.clone())
+            .on_press_sync(Button::F21, process);
+
+        // Stands in for the hook thread, which dispatches the events of the OS.
+        let expected: Vec<_> = (0..100)
+            .flat_map(|_| {
+                [
+                    (Button::F20, ButtonAction::Press),
+                    (Button::F21, ButtonAction::Press),
+                    (Button::F20, ButtonAction::Release),
+                ]
+            })
+            .collect();
+        let events: Vec<_> = expected
+            .iter()
+            .map(|&(target, action)| Event::Button(ButtonEvent::new(target, action)))
+            .collect();
+        thread::spawn(move || hotkey.dispatch_all(events))
+            .join()
+            .unwrap();
+
+        for expected in expected {
+            assert_eq!(rx.recv().await, Some(expected));
+        }
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -4,23 +4,48 @@ use crate::macros::button_arg::ButtonArg;
 use crate::macros::sequence::Sequence;
 use crate::prelude::*;
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Whether an alt-tab binding is holding its modifier down.
+#[derive(Debug, Default)]
+struct AltTabState(AtomicBool);
+
+impl AltTabState {
+    /// Returns `true` if the modifier should be pressed.
+    fn start(&self) -> bool {
+        !self.0.swap(true, Ordering::SeqCst)
+    }
+
+    /// Returns `true` if the modifier should be released.
+    fn finish(&self) -> bool {
+        self.0.swap(false, Ordering::SeqCst)
+    }
+}
+
 fn bind_alt_tab_inner(
     hotkey: &mut Hotkey,
     context: &Context,
     alt: impl Into<ButtonArg> + Clone,
     tab: impl Into<ButtonArg> + Clone,
+    modifier: Button,
     tab_seq: Sequence,
 ) {
-    hotkey
-        .register(context.clone())
-        .on_release(&alt, move |_| seq!(LAlt up).send());
+    let state = Arc::new(AltTabState::default());
+
+    let state_ = Arc::clone(&state);
+    hotkey.register(context.clone()).on_release(&alt, move |_| {
+        if state_.finish() {
+            modifier.release();
+        }
+    });
 
     hotkey
         .register(Context::new().merge(context).modifiers(alt))
         .disable(&tab)
         .on_press(tab, move |_| {
-            if Button::LAlt.is_released() {
-                seq!(LAlt down).send();
+            if state.start() {
+                modifier.press();
             }
             tab_seq.send();
         });
@@ -48,7 +73,34 @@ pub fn alt_tab(
     alt: impl Clone + Into<ButtonArg>,
     tab: impl Into<ButtonArg> + Clone,
 ) {
-    bind_alt_tab_inner(hotkey, context, alt, tab, seq!(Tab));
+    bind_alt_tab_inner(hotkey, context, alt, tab, Button::LAlt, seq!(Tab));
+}
+
+/// Alt-Tab hotkey that holds `modifier` instead of the left Alt key.
+///
+/// # Arguments
+///
+/// * `alt` - A button that act like Alt key.
+/// * `tab` - A button that act like tab key.
+/// * `modifier` - A button to be held down while switching windows.
+///
+/// # Example
+///
+/// ```
+/// use hookmap::prelude::*;
+///
+/// let mut hotkey = Hotkey::new();
+/// utils::alt_tab_with_modifier(&mut hotkey, &Context::new(), Button::A, Button::T, Button::RAlt);
+/// ```
+///
+pub fn alt_tab_with_modifier(
+    hotkey: &mut Hotkey,
+    context: &Context,
+    alt: impl Into<ButtonArg> + Clone,
+    tab: impl Into<ButtonArg> + Clone,
+    modifier: Button,
+) {
+    bind_alt_tab_inner(hotkey, context, alt, tab, modifier, seq!(Tab));
 }
 
 /// Shift-Alt-Tab hotkey.
@@ -73,5 +125,479 @@ pub fn shift_alt_tab(
     alt: impl Into<ButtonArg> + Clone,
     tab: impl Into<ButtonArg> + Clone,
 ) {
-    bind_alt_tab_inner(hotkey, context, alt, tab, seq!(with(LShift), Tab));
+    bind_alt_tab_inner(
+        hotkey,
+        context,
+        alt,
+        tab,
+        Button::LAlt,
+        seq!(with(LShift), Tab),
+    );
+}
+
+/// Shift-Alt-Tab hotkey that holds `modifier` instead of the left Alt key.
+///
+/// # Arguments
+///
+/// * `alt` - A button that act like Alt key.
+/// * `tab` - A button that act like tab key.
+/// * `modifier` - A button to be held down while switching windows.
+///
+/// # Example
+///
+/// ```
+/// use hookmap::prelude::*;
+///
+/// let mut hotkey = Hotkey::new();
+/// utils::shift_alt_tab_with_modifier(&mut hotkey, &Context::new(), Button::A, Button::R, Button::RAlt);
+/// ```
+///
+pub fn shift_alt_tab_with_modifier(
+    hotkey: &mut Hotkey,
+    context: &Context,
+    alt: impl Into<ButtonArg> + Clone,
+    tab: impl Into<ButtonArg> + Clone,
+    modifier: Button,
+) {
+    bind_alt_tab_inner(hotkey, context, alt, tab, modifier, seq!(with(LShift), Tab));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum StickyMode {
+    #[default]
+    Released,
+    Armed,
+    Used(Button),
+    Locked,
+}
+
+#[derive(Debug, Default)]
+struct StickyModifierState(Mutex<StickyMode>);
+
+impl StickyModifierState {
+    /// Returns the action to be performed on the modifier when the trigger is tapped.
+    fn tap_trigger(&self) -> Option<ButtonAction> {
+        let mut mode = self.0.lock().unwrap();
+        let (next, action) = match *mode {
+            StickyMode::Released => (StickyMode::Armed, Some(ButtonAction::Press)),
+            StickyMode::Armed | StickyMode::Used(_) => (StickyMode::Locked, None),
+            StickyMode::Locked => (StickyMode::Released, Some(ButtonAction::Release)),
+        };
+        *mode = next;
+        action
+    }
+
+    /// Returns the action to be performed on the modifier when other button is pressed or released.
+    fn other_button(&self, event: &ButtonEvent) -> Option<ButtonAction> {
+        let mut mode = self.0.lock().unwrap();
+        match (*mode, event.action) {
+            (StickyMode::Armed, ButtonAction::Press) => {
+                *mode = StickyMode::Used(event.target);
+                None
+            }
+            (StickyMode::Used(button), ButtonAction::Release) if button == event.target => {
+                *mode = StickyMode::Released;
+                Some(ButtonAction::Release)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Sticky modifier hotkey.
+///
+/// Tapping `trigger` holds `modifier` down until the next button is released.
+/// Tapping `trigger` twice locks `modifier` until `trigger` is tapped again.
+///
+/// # Arguments
+///
+/// * `trigger` - A button that arms the modifier.
+/// * `modifier` - A button to be held down.
+///
+/// # Example
+///
+/// ```
+/// use hookmap::prelude::*;
+///
+/// let mut hotkey = Hotkey::new();
+/// utils::sticky_modifier(&mut hotkey, &Context::new(), Button::RCtrl, Button::LShift);
+/// ```
+///
+pub fn sticky_modifier(hotkey: &mut Hotkey, context: &Context, trigger: Button, modifier: Button) {
+    let state = Arc::new(StickyModifierState::default());
+
+    // The state is updated in the order of events, so that the modifier applies to exactly
+    // the next button, even if it is pressed right after the trigger.
+    let state_ = Arc::clone(&state);
+    hotkey
+        .register(
+            Context::new()
+                .merge(context)
+                .native_event_operation(NativeEventOperation::Block),
+        )
+        .on_press_sync(trigger, move |_| match state_.tap_trigger() {
+            Some(ButtonAction::Press) => modifier.press(),
+            Some(ButtonAction::Release) => modifier.release(),
+            None => {}
+        })
+        .disable(trigger);
+    hotkey
+        .register(Context::new())
+        .observe_buttons(move |event| {
+            if event.target != trigger
+                && event.target != modifier
+                && state.other_button(&event) == Some(ButtonAction::Release)
+            {
+                modifier.release();
+            }
+        });
+}
+
+/// Returns the volume key and the number of times to click it for the wheel rotation.
+fn volume_clicks(delta: i32) -> (Button, usize) {
+    let button = if delta < 0 {
+        Button::VolumeDown
+    } else {
+        Button::VolumeUp
+    };
+    (button, delta.unsigned_abs() as usize)
+}
+
+/// Changes the volume by rotating the mouse wheel while `modifier` is pressed.
+///
+/// The native wheel event is blocked while `modifier` is pressed.
+///
+/// # Arguments
+///
+/// * `modifier` - A button to be held down to change the volume.
+///
+/// # Example
+///
+/// ```
+/// use hookmap::prelude::*;
+///
+/// let mut hotkey = Hotkey::new();
+/// utils::wheel_volume(&mut hotkey, &Context::new(), Button::RCtrl);
+/// ```
+///
+pub fn wheel_volume(hotkey: &mut Hotkey, context: &Context, modifier: Button) {
+    hotkey
+        .register(
+            Context::new()
+                .merge(context)
+                .modifiers(modifier)
+                .native_event_operation(NativeEventOperation::Block),
+        )
+        .mouse_wheel(|event: WheelEvent| {
+            let (button, count) = volume_clicks(event.delta);
+            (0..count).for_each(|_| button.click());
+        });
+}
+
+/// Freezes the mouse cursor while `modifier` is pressed, such as for precise drawing.
+///
+/// The native cursor events are blocked while `modifier` is pressed, so the cursor does not
+/// move. The mouse buttons and the wheel still work.
+///
+/// # Arguments
+///
+/// * `modifier` - A button to be held down to freeze the cursor.
+///
+/// # Example
+///
+/// ```
+/// use hookmap::prelude::*;
+///
+/// let mut hotkey = Hotkey::new();
+/// utils::cursor_lock(&mut hotkey, &Context::new(), Button::Space);
+/// ```
+///
+pub fn cursor_lock(hotkey: &mut Hotkey, context: &Context, modifier: Button) {
+    hotkey
+        .register(
+            Context::new()
+                .merge(context)
+                .modifiers(modifier)
+                .native_event_operation(NativeEventOperation::Block),
+        )
+        .mouse_cursor_sync(|_| {});
+}
+
+/// Scales cursor movements, carrying the fractions of pixels over to the next movement so
+/// that slow movements are not lost.
+#[derive(Debug)]
+struct CursorScaler {
+    factor: f64,
+    remainder: Mutex<(f64, f64)>,
+}
+
+impl CursorScaler {
+    fn new(factor: f64) -> Self {
+        Self {
+            factor,
+            remainder: Mutex::new((0.0, 0.0)),
+        }
+    }
+
+    fn scale(&self, (dx, dy): (i32, i32)) -> (i32, i32) {
+        let mut remainder = self.remainder.lock().unwrap();
+        let x = f64::from(dx) * self.factor + remainder.0;
+        let y = f64::from(dy) * self.factor + remainder.1;
+        let (x_out, y_out) = (x.trunc(), y.trunc());
+        *remainder = (x - x_out, y - y_out);
+        (x_out as i32, y_out as i32)
+    }
+}
+
+/// Moves the mouse cursor at `factor` times the speed while `modifier` is pressed, such as
+/// `0.25` for precise pointing.
+///
+/// The native cursor events are blocked and re-emitted after scaling. Fractions of pixels are
+/// accumulated, so slow movements still move the cursor. As with [`Registrar::transform_cursor`],
+/// the cursor events emitted by this are not scaled again, and the scaling is disabled if they
+/// feed back endlessly.
+///
+/// [`Registrar::transform_cursor`]: crate::hotkey::Registrar::transform_cursor
+///
+/// # Arguments
+///
+/// * `modifier` - A button to be held down to scale the movements.
+/// * `factor` - A multiplier of the movements.
+///
+/// # Example
+///
+/// ```
+/// use hookmap::prelude::*;
+///
+/// let mut hotkey = Hotkey::new();
+/// utils::precision_mode(&mut hotkey, &Context::new(), Button::Space, 0.25);
+/// ```
+///
+pub fn precision_mode(hotkey: &mut Hotkey, context: &Context, modifier: Button, factor: f64) {
+    let scaler = CursorScaler::new(factor);
+    hotkey
+        .register(
+            Context::new()
+                .merge(context)
+                .modifiers(modifier)
+                .native_event_operation(NativeEventOperation::Block),
+        )
+        .transform_cursor(move |delta| scaler.scale(delta));
+}
+
+#[derive(Debug, Default)]
+struct PushToTalkState(AtomicBool);
+
+impl PushToTalkState {
+    /// Returns `true` if the emitted button should be pressed.
+    /// Key repeats of the trigger return `false`.
+    fn press_trigger(&self) -> bool {
+        !self.0.swap(true, Ordering::SeqCst)
+    }
+
+    /// Returns `true` if the emitted button should be released.
+    fn release_trigger(&self) -> bool {
+        self.0.swap(false, Ordering::SeqCst)
+    }
+
+    /// Returns `true` if the emitted button is held down by the trigger.
+    fn is_held(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Push-to-talk hotkey.
+///
+/// While `trigger` is held down, `emit` is held down, e.g. to talk in a voice chat app that
+/// listens to `emit`. Key repeats of `trigger` and input generated by this program are
+/// ignored. If `trigger` is already held down when the hotkey is installed, its release is
+/// passed on to the OS so that it is not left pressed.
+///
+/// The press and the release are handled in the order of events, and the release is
+/// handled even if the modifiers of `context` are released first, so `emit` is never left
+/// held down.
+///
+/// # Arguments
+///
+/// * `trigger` - A button to be held down to talk.
+/// * `emit` - A button the voice chat app listens to.
+///
+/// # Example
+///
+/// ```
+/// use hookmap::prelude::*;
+///
+/// let mut hotkey = Hotkey::new();
+/// utils::push_to_talk(&mut hotkey, &Context::new(), Button::SideButton1, Button::F13);
+/// ```
+///
+pub fn push_to_talk(hotkey: &mut Hotkey, context: &Context, trigger: Button, emit: Button) {
+    let state = Arc::new(PushToTalkState::default());
+
+    let (state_, state__) = (Arc::clone(&state), Arc::clone(&state));
+    hotkey
+        .register(Context::new().merge(context))
+        .register_held(
+            trigger,
+            move || state.is_held(),
+            move |e: ButtonEvent| {
+                if !e.injected && state_.press_trigger() {
+                    emit.press();
+                }
+            },
+            move |e: ButtonEvent| {
+                if !e.injected && state__.release_trigger() {
+                    emit.release();
+                }
+            },
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_button_event(target: Button, action: ButtonAction) -> ButtonEvent {
+        ButtonEvent::new(target, action)
+    }
+
+    #[test]
+    fn alt_tab_holds_modifier_until_finished() {
+        let state = AltTabState::default();
+        assert!(state.start());
+        assert!(!state.start());
+        assert!(state.finish());
+        assert!(!state.finish());
+        assert!(state.start());
+    }
+
+    #[test]
+    fn alt_tab_states_are_independent() {
+        let alt_tab = AltTabState::default();
+        let shift_alt_tab = AltTabState::default();
+        let is_held = |state: &AltTabState| state.0.load(Ordering::SeqCst);
+
+        // Pressing and releasing through one leaves the other released.
+        assert!(alt_tab.start());
+        assert!(is_held(&alt_tab));
+        assert!(!is_held(&shift_alt_tab));
+        assert!(alt_tab.finish());
+        assert!(!is_held(&shift_alt_tab));
+
+        // Finishing one leaves the other held.
+        assert!(shift_alt_tab.start());
+        assert!(alt_tab.start());
+        assert!(alt_tab.finish());
+        assert!(is_held(&shift_alt_tab));
+        assert!(!is_held(&alt_tab));
+        assert!(shift_alt_tab.finish());
+    }
+
+    #[test]
+    fn wheel_notches_are_mapped_to_volume_clicks() {
+        assert_eq!(volume_clicks(1), (Button::VolumeUp, 1));
+        assert_eq!(volume_clicks(3), (Button::VolumeUp, 3));
+        assert_eq!(volume_clicks(-2), (Button::VolumeDown, 2));
+        assert_eq!(volume_clicks(0).1, 0);
+    }
+
+    #[test]
+    fn cursor_movements_are_scaled() {
+        let scaler = CursorScaler::new(0.5);
+        assert_eq!(scaler.scale((4, -6)), (2, -3));
+        assert_eq!(scaler.scale((0, 0)), (0, 0));
+
+        let scaler = CursorScaler::new(2.0);
+        assert_eq!(scaler.scale((3, -1)), (6, -2));
+    }
+
+    #[test]
+    fn cursor_scaler_accumulates_fractions() {
+        let scaler = CursorScaler::new(0.25);
+        let moves: Vec<_> = (0..8).map(|_| scaler.scale((1, -1))).collect();
+        assert_eq!(
+            moves,
+            [
+                (0, 0),
+                (0, 0),
+                (0, 0),
+                (1, -1),
+                (0, 0),
+                (0, 0),
+                (0, 0),
+                (1, -1)
+            ]
+        );
+
+        // Movements back and forth cancel out the fraction.
+        let scaler = CursorScaler::new(0.5);
+        assert_eq!(scaler.scale((1, 0)), (0, 0));
+        assert_eq!(scaler.scale((-1, 0)), (0, 0));
+        assert_eq!(scaler.scale((1, 0)), (0, 0));
+        assert_eq!(scaler.scale((1, 0)), (1, 0));
+    }
+
+    #[test]
+    fn push_to_talk_pairs_press_and_release() {
+        let state = PushToTalkState::default();
+        assert!(state.press_trigger());
+        assert!(!state.press_trigger());
+        assert!(state.release_trigger());
+
+        assert!(state.press_trigger());
+        assert!(state.release_trigger());
+    }
+
+    #[test]
+    fn push_to_talk_trigger_held_at_install() {
+        let state = PushToTalkState::default();
+        assert!(!state.release_trigger());
+        assert!(state.press_trigger());
+        assert!(state.release_trigger());
+    }
+
+    #[test]
+    fn sticky_modifier_is_released_after_next_button() {
+        let state = StickyModifierState::default();
+        assert_eq!(state.tap_trigger(), Some(ButtonAction::Press));
+
+        let press = create_button_event(Button::A, ButtonAction::Press);
+        let release = create_button_event(Button::A, ButtonAction::Release);
+        assert_eq!(state.other_button(&press), None);
+        assert_eq!(state.other_button(&release), Some(ButtonAction::Release));
+
+        let press = create_button_event(Button::B, ButtonAction::Press);
+        let release = create_button_event(Button::B, ButtonAction::Release);
+        assert_eq!(state.other_button(&press), None);
+        assert_eq!(state.other_button(&release), None);
+    }
+
+    #[test]
+    fn sticky_modifier_ignores_release_of_other_button() {
+        let state = StickyModifierState::default();
+        state.tap_trigger();
+
+        let release = create_button_event(Button::B, ButtonAction::Release);
+        assert_eq!(state.other_button(&release), None);
+
+        let press = create_button_event(Button::A, ButtonAction::Press);
+        assert_eq!(state.other_button(&press), None);
+        assert_eq!(state.other_button(&release), None);
+    }
+
+    #[test]
+    fn sticky_modifier_is_locked_by_double_tap() {
+        let state = StickyModifierState::default();
+        assert_eq!(state.tap_trigger(), Some(ButtonAction::Press));
+        assert_eq!(state.tap_trigger(), None);
+
+        for button in [Button::A, Button::B] {
+            let press = create_button_event(button, ButtonAction::Press);
+            let release = create_button_event(button, ButtonAction::Release);
+            assert_eq!(state.other_button(&press), None);
+            assert_eq!(state.other_button(&release), None);
+        }
+
+        assert_eq!(state.tap_trigger(), Some(ButtonAction::Release));
+    }
 }